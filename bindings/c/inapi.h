@@ -36,6 +36,20 @@ extern char *geterr();
  */
 typedef void Host;
 
+/**
+ * @brief Create a new Host connected to localhost.
+ * @param path Optional path to the data file for this host, or null.
+ * @return A new Host struct, or null on error.
+ *
+ * #### Usage Example
+ *
+ * @code
+ * Host *host = host_local("hosts/localhost.json");
+ * assert(host);
+ * @endcode
+ */
+extern Host *host_local(const char *path);
+
 /**
  * @brief Create a new Host connected to the endpoint specified in the
  *        data file. This function expects to find the following keys
@@ -206,6 +220,57 @@ typedef struct _CommandResult {
  */
 extern Command *command_new(const char *cmd_str);
 
+/**
+ * @brief Set an environment variable for the command.
+ * @param cmd The command object.
+ * @param key The variable's name.
+ * @param value The variable's value.
+ * @return Return code - zero on success, non-zero on error.
+ */
+extern uint8_t command_set_env(Command *cmd, const char *key, const char *value);
+
+/**
+ * @brief Set the directory the command runs in.
+ * @param cmd The command object.
+ * @param cwd The working directory.
+ * @return Return code - zero on success, non-zero on error.
+ */
+extern uint8_t command_set_cwd(Command *cmd, const char *cwd);
+
+/**
+ * @brief Run the command as another user.
+ * @param cmd The command object.
+ * @param user The user's name.
+ * @return Return code - zero on success, non-zero on error.
+ */
+extern uint8_t command_set_user(Command *cmd, const char *user);
+
+/**
+ * @brief Write a string to the command's stdin.
+ * @param cmd The command object.
+ * @param input The input string.
+ * @return Return code - zero on success, non-zero on error.
+ */
+extern uint8_t command_set_stdin(Command *cmd, const char *input);
+
+/**
+ * @brief Kill the command if it runs for too long. A killed command
+ * exits with code 124.
+ * @param cmd The command object.
+ * @param timeout Seconds the command may run for.
+ * @return Return code - zero on success, non-zero on error.
+ */
+extern uint8_t command_set_timeout(Command *cmd, uint32_t timeout);
+
+/**
+ * @brief Fail unless the command exits with one of the given codes.
+ * @param cmd The command object.
+ * @param codes Array of accepted exit codes.
+ * @param codes_len Number of codes in the array.
+ * @return Return code - zero on success, non-zero on error.
+ */
+extern uint8_t command_set_expected_codes(Command *cmd, const int32_t *codes, size_t codes_len);
+
 /**
  * @brief Send request to the Agent to run your shell command.
  * @param cmd The command object.
@@ -524,6 +589,11 @@ enum Providers {
     Pkg,
     Ports,
     Yum,
+    Zypper,
+    Pacman,
+    Apk,
+    Snap,
+    Flatpak,
 };
 
 /**
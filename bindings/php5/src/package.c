@@ -52,6 +52,11 @@ void inapi_init_package(TSRMLS_D) {
     zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_PKG", 12, 5 TSRMLS_CC);
     zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_PORTS", 14, 6 TSRMLS_CC);
     zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_YUM", 12, 7 TSRMLS_CC);
+    zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_ZYPPER", 15, 8 TSRMLS_CC);
+    zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_PACMAN", 15, 9 TSRMLS_CC);
+    zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_APK", 12, 10 TSRMLS_CC);
+    zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_SNAP", 13, 11 TSRMLS_CC);
+    zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_FLATPAK", 16, 12 TSRMLS_CC);
 }
 
 zend_object_value create_php_package(zend_class_entry *class_type TSRMLS_DC) {
@@ -419,6 +419,11 @@ PHP_MINIT_FUNCTION(inapi)
     zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_PKG", 12, 5 TSRMLS_CC);
     zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_PORTS", 14, 6 TSRMLS_CC);
     zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_YUM", 12, 7 TSRMLS_CC);
+    zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_ZYPPER", 15, 8 TSRMLS_CC);
+    zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_PACMAN", 15, 9 TSRMLS_CC);
+    zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_APK", 12, 10 TSRMLS_CC);
+    zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_SNAP", 13, 11 TSRMLS_CC);
+    zend_declare_class_constant_long(inapi_ce_package, "PROVIDER_FLATPAK", 16, 12 TSRMLS_CC);
 
     INIT_CLASS_ENTRY(ce_package_ex, "Intecture\\PackageException", NULL);
     inapi_ce_package_ex = zend_register_internal_class_ex(&ce_package_ex, zend_exception_get_default());
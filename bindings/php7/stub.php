@@ -381,6 +381,31 @@ class Package
      */
     const PROVIDER_YUM = 7;
 
+    /**
+     * Use the Zypper provider.
+     */
+    const PROVIDER_ZYPPER = 8;
+
+    /**
+     * Use the Pacman provider.
+     */
+    const PROVIDER_PACMAN = 9;
+
+    /**
+     * Use the Apk provider.
+     */
+    const PROVIDER_APK = 10;
+
+    /**
+     * Use the Snap provider.
+     */
+    const PROVIDER_SNAP = 11;
+
+    /**
+     * Use the Flatpak provider.
+     */
+    const PROVIDER_FLATPAK = 12;
+
     /**
      * Create a new Package.
      *
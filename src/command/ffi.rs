@@ -8,10 +8,11 @@
 
 //! FFI interface for Command
 
+use error::{seterr, Error};
 use ffi_helpers::Leaky;
 use host::Host;
-use libc::{c_char, uint8_t};
-use std::convert;
+use libc::{c_char, int32_t, size_t, uint32_t, uint8_t};
+use std::{convert, slice};
 use std::ffi::CString;
 use std::panic::catch_unwind;
 use super::{Command, CommandResult};
@@ -39,6 +40,56 @@ pub extern "C" fn command_new(cmd_ptr: *const c_char) -> *mut Command {
     Box::into_raw(Box::new(Command::new(cmd_string)))
 }
 
+#[no_mangle]
+pub extern "C" fn command_set_env(cmd_ptr: *mut Command, key_ptr: *const c_char, value_ptr: *const c_char) -> uint8_t {
+    let mut cmd = Leaky::new(tryrc!(boxptr!(cmd_ptr, "Command pointer")));
+    let key = tryrc!(ptrtostr!(key_ptr, "environment variable name"));
+    let value = tryrc!(ptrtostr!(value_ptr, "environment variable value"));
+
+    cmd.env.insert(key.into(), value.into());
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn command_set_cwd(cmd_ptr: *mut Command, cwd_ptr: *const c_char) -> uint8_t {
+    let mut cmd = Leaky::new(tryrc!(boxptr!(cmd_ptr, "Command pointer")));
+    cmd.cwd = Some(tryrc!(ptrtostr!(cwd_ptr, "working directory string")).into());
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn command_set_user(cmd_ptr: *mut Command, user_ptr: *const c_char) -> uint8_t {
+    let mut cmd = Leaky::new(tryrc!(boxptr!(cmd_ptr, "Command pointer")));
+    cmd.user = Some(tryrc!(ptrtostr!(user_ptr, "user string")).into());
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn command_set_stdin(cmd_ptr: *mut Command, stdin_ptr: *const c_char) -> uint8_t {
+    let mut cmd = Leaky::new(tryrc!(boxptr!(cmd_ptr, "Command pointer")));
+    cmd.stdin = Some(tryrc!(ptrtostr!(stdin_ptr, "stdin string")).into());
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn command_set_timeout(cmd_ptr: *mut Command, timeout: uint32_t) -> uint8_t {
+    let mut cmd = Leaky::new(tryrc!(boxptr!(cmd_ptr, "Command pointer")));
+    cmd.timeout = Some(timeout);
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn command_set_expected_codes(cmd_ptr: *mut Command, codes_ptr: *const int32_t, codes_len: size_t) -> uint8_t {
+    let mut cmd = Leaky::new(tryrc!(boxptr!(cmd_ptr, "Command pointer")));
+    if codes_ptr.is_null() {
+        seterr(Error::NullPtr("exit codes array"));
+        return 1;
+    }
+
+    cmd.expected_codes = unsafe { slice::from_raw_parts(codes_ptr, codes_len as usize) }.to_vec();
+    0
+}
+
 #[no_mangle]
 pub extern "C" fn command_exec(cmd_ptr: *mut Command, host_ptr: *mut Host) -> *mut Ffi__CommandResult {
     let cmd = Leaky::new(trynull!(readptr!(cmd_ptr, "Command pointer")));
@@ -71,13 +122,10 @@ pub extern "C" fn command_result_free(result_ptr: *mut Ffi__CommandResult) -> ui
 #[cfg(test)]
 mod tests {
     use command::CommandResult;
-    #[cfg(feature = "remote-run")]
     use czmq::{ZMsg, ZSys};
     use error::ERRMSG;
-    #[cfg(feature = "remote-run")]
     use host::ffi::host_close;
     use host::Host;
-    #[cfg(feature = "remote-run")]
     use std::{str, thread};
     use std::ffi::CStr;
     use std::ffi::CString;
@@ -103,9 +151,31 @@ mod tests {
         assert_eq!(unsafe { CStr::from_ptr(ERRMSG).to_str().unwrap() }, "Received null when we expected a command string pointer");
     }
 
-    #[cfg(feature = "local-run")]
     #[test]
-    fn test_exec() {
+    fn test_set_opts() {
+        let cmd = command_new(CString::new("make").unwrap().into_raw());
+        assert_eq!(command_set_env(cmd, CString::new("CC").unwrap().as_ptr(), CString::new("clang").unwrap().as_ptr()), 0);
+        assert_eq!(command_set_cwd(cmd, CString::new("/usr/src").unwrap().as_ptr()), 0);
+        assert_eq!(command_set_user(cmd, CString::new("build").unwrap().as_ptr()), 0);
+        assert_eq!(command_set_stdin(cmd, CString::new("y").unwrap().as_ptr()), 0);
+        assert_eq!(command_set_timeout(cmd, 30), 0);
+        let codes = [0, 2];
+        assert_eq!(command_set_expected_codes(cmd, codes.as_ptr(), codes.len()), 0);
+        assert_eq!(command_set_expected_codes(cmd, ptr::null(), 0), 1);
+
+        let command = unsafe { &*cmd };
+        assert_eq!(command.env.get("CC").unwrap(), "clang");
+        assert_eq!(command.cwd, Some("/usr/src".into()));
+        assert_eq!(command.user, Some("build".into()));
+        assert_eq!(command.stdin, Some("y".into()));
+        assert_eq!(command.timeout, Some(30));
+        assert_eq!(command.expected_codes, vec![0, 2]);
+
+        assert_eq!(command_free(cmd), 0);
+    }
+
+    #[test]
+    fn test_exec_local() {
         let path: Option<String> = None;
         let host = Box::into_raw(Box::new(Host::local(path).unwrap()));
 
@@ -120,7 +190,6 @@ mod tests {
         unsafe { Box::from_raw(host) };
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_exec() {
         ZSys::init();
@@ -16,18 +16,14 @@
 //!
 //! ```no_run
 //! # use inapi::Host;
-#![cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! ```
 //!
 //! Now run your command and get the result:
 //!
 //! ```no_run
 //! # use inapi::{Command, Host};
-#![cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let cmd = Command::new("whoami");
 //! let result = cmd.exec(&mut host).unwrap();
 //! println!("Exit: {}, Stdout: {}, Stderr: {}", result.exit_code, result.stdout, result.stderr);
@@ -36,21 +32,52 @@
 //! If all goes well, this will output:
 //!
 //! > Exit: 0, Stdout: <agent_runtime_user>, Stderr:
+//!
+//! Options such as the environment, working directory and a timeout
+//! are set with the builder methods, rather than by building them
+//! into the command string:
+//!
+//! ```no_run
+//! # use inapi::{Command, Host};
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! let cmd = Command::new("make install")
+//!     .cwd("/usr/local/src/app")
+//!     .env("PREFIX", "/opt/app")
+//!     .timeout(600)
+//!     .expected_codes(&[0]);
+//! let result = cmd.exec(&mut host).unwrap();
+//! ```
 
 pub mod ffi;
 
-use error::Result;
+use error::{Error, Result};
 use host::Host;
+use outcome::Outcome;
+use serde_json::{to_value, Map, Value};
+use std::collections::BTreeMap;
 use target::Target;
 
 /// Reusable container for sending commands to managed hosts.
+#[derive(Clone, Debug, PartialEq)]
 pub struct Command {
     /// The shell command
-    cmd: String,
+    pub cmd: String,
+    /// Environment variables set for the command
+    pub env: BTreeMap<String, String>,
+    /// Directory the command runs in
+    pub cwd: Option<String>,
+    /// User the command runs as
+    pub user: Option<String>,
+    /// Input written to the command's stdin, which is otherwise empty
+    pub stdin: Option<String>,
+    /// Seconds the command may run for before it is killed
+    pub timeout: Option<u32>,
+    /// Exit codes that count as success, or empty to accept any
+    pub expected_codes: Vec<i32>,
 }
 
 /// Result attributes returned from the managed host.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CommandResult {
     /// Exit code for the shell command's process
     pub exit_code: i32,
@@ -72,55 +99,214 @@ impl Command {
     pub fn new(cmd: &str) -> Command {
         Command {
             cmd: cmd.to_string(),
+            env: BTreeMap::new(),
+            cwd: None,
+            user: None,
+            stdin: None,
+            timeout: None,
+            expected_codes: Vec::new(),
         }
     }
 
+    /// Set an environment variable for the command.
+    pub fn env(mut self, key: &str, value: &str) -> Command {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the directory the command runs in.
+    pub fn cwd(mut self, path: &str) -> Command {
+        self.cwd = Some(path.into());
+        self
+    }
+
+    /// Run the command as another user. The Agent, or the user that
+    /// the Host connects as, must be allowed to switch user.
+    pub fn user(mut self, user: &str) -> Command {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Write `input` to the command's stdin.
+    pub fn stdin(mut self, input: &str) -> Command {
+        self.stdin = Some(input.into());
+        self
+    }
+
+    /// Kill the command if it runs for longer than `secs` seconds. A
+    /// killed command exits with code 124, like `timeout(1)`.
+    pub fn timeout(mut self, secs: u32) -> Command {
+        self.timeout = Some(secs);
+        self
+    }
+
+    /// Fail unless the command exits with one of `codes`. By default
+    /// any exit code is accepted.
+    pub fn expected_codes(mut self, codes: &[i32]) -> Command {
+        self.expected_codes = codes.to_vec();
+        self
+    }
+
     /// Execute command on shell.
     ///
     /// Command structs are reusable accross multiple hosts, which is
     /// helpful if you are configuring a group of servers
     /// simultaneously.
     ///
+    /// The command runs even if the Host is in check mode, as commands
+    /// are also used to query the host. Use `run()` for commands that
+    /// change the host.
+    ///
     /// # Examples
     ///
     /// ```no_run
     /// # use inapi::{Command, Host};
     /// let cmd = Command::new("whoami");
     ///
-    #[cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-    #[cfg_attr(feature = "local-run", doc = "let mut web1 = Host::local(path).unwrap();")]
-    #[cfg_attr(feature = "remote-run", doc = "let mut web1 = Host::connect(\"data/hosts/web1.json\").unwrap();")]
+    /// let mut web1 = Host::connect("data/hosts/web1.json").unwrap();
     /// let w1_result = cmd.exec(&mut web1).unwrap();
     ///
-    #[cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-    #[cfg_attr(feature = "local-run", doc = "let mut web2 = Host::local(path).unwrap();")]
-    #[cfg_attr(feature = "remote-run", doc = "let mut web2 = Host::connect(\"data/hosts/web2.json\").unwrap();")]
+    /// let mut web2 = Host::connect("data/hosts/web2.json").unwrap();
     /// let w2_result = cmd.exec(&mut web2).unwrap();
     /// ```
-    #[allow(unused_variables)]
     pub fn exec(&self, host: &mut Host) -> Result<CommandResult> {
-        Target::exec(host, &self.cmd)
+        let result = try!(Target::exec(host, self));
+
+        if !self.expected_codes.is_empty() && !self.expected_codes.contains(&result.exit_code) {
+            return Err(Error::Generic(format!("Command `{}` exited with unexpected code {}: {}", self.cmd, result.exit_code, result.stderr)));
+        }
+
+        Ok(result)
+    }
+
+    /// Execute a command that changes the host, recording an Outcome
+    /// with its result. In check mode the command is not run, and is
+    /// reported as a change.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use inapi::{Command, Host};
+    /// # let mut host = Host::connect("hosts/myhost.json").unwrap();
+    /// let outcome = Command::new("systemctl daemon-reload").run(&mut host).unwrap();
+    /// ```
+    pub fn run(&self, host: &mut Host) -> Result<Outcome> {
+        let outcome = Outcome::changed(&format!("command:{}", self.cmd), "exec");
+        if host.check_mode() {
+            return host.record(Ok(()), outcome);
+        }
+
+        match self.exec(host) {
+            Ok(result) => host.record(Ok(()), outcome.command_result(result)),
+            Err(e) => host.record(Err(e), outcome),
+        }
+    }
+
+    /// Get the options sent to the Agent alongside the command, or
+    /// None if there are none.
+    #[doc(hidden)]
+    pub fn opts(&self) -> Option<Value> {
+        let mut opts: Map<String, Value> = Map::new();
+        if !self.env.is_empty() {
+            opts.insert("env".into(), to_value(&self.env));
+        }
+        if let Some(ref cwd) = self.cwd {
+            opts.insert("cwd".into(), to_value(cwd));
+        }
+        if let Some(ref user) = self.user {
+            opts.insert("user".into(), to_value(user));
+        }
+        if let Some(ref stdin) = self.stdin {
+            opts.insert("stdin".into(), to_value(stdin));
+        }
+        if let Some(timeout) = self.timeout {
+            opts.insert("timeout".into(), to_value(timeout));
+        }
+
+        if opts.is_empty() { None } else { Some(Value::Object(opts)) }
+    }
+
+    /// Create a Command from a command string and the options sent
+    /// to the Agent.
+    #[doc(hidden)]
+    pub fn from_opts(cmd: &str, opts: &Value) -> Result<Command> {
+        let mut command = Command::new(cmd);
+
+        if let Some(env) = opts.find("env") {
+            for (key, value) in try!(env.as_object().ok_or(Error::HostResponse)) {
+                command.env.insert(key.clone(), try!(value.as_str().ok_or(Error::HostResponse)).into());
+            }
+        }
+        command.cwd = opts.find("cwd").and_then(|v| v.as_str()).map(|v| v.into());
+        command.user = opts.find("user").and_then(|v| v.as_str()).map(|v| v.into());
+        command.stdin = opts.find("stdin").and_then(|v| v.as_str()).map(|v| v.into());
+        command.timeout = opts.find("timeout").and_then(|v| v.as_u64()).map(|v| v as u32);
+
+        Ok(command)
+    }
+
+    /// Build a single shell command that applies the options itself,
+    /// for hosts where they cannot be passed to the process directly.
+    #[doc(hidden)]
+    pub fn script(&self) -> String {
+        if self.opts().is_none() {
+            return self.cmd.clone();
+        }
+
+        let mut script = String::new();
+        if let Some(ref cwd) = self.cwd {
+            script.push_str(&format!("cd {} && ", quote(cwd)));
+        }
+        if let Some(ref input) = self.stdin {
+            script.push_str(&format!("printf '%s' {} | ", quote(input)));
+        }
+        if !self.env.is_empty() {
+            script.push_str("env ");
+            for (key, value) in &self.env {
+                script.push_str(&quote(&format!("{}={}", key, value)));
+                script.push(' ');
+            }
+        }
+        if let Some(secs) = self.timeout {
+            script.push_str(&format!("timeout -k 5 {} ", secs));
+        }
+        script.push_str(&format!("sh -c {}", quote(&self.cmd)));
+
+        match self.user {
+            Some(ref user) => format!("su -s /bin/sh {} -c {}", quote(user), quote(&script)),
+            None => script,
+        }
     }
 }
 
 pub trait CommandTarget {
-    fn exec(host: &mut Host, cmd: &str) -> Result<CommandResult>;
+    fn exec(host: &mut Host, cmd: &Command) -> Result<CommandResult>;
+}
+
+/// Quote a string for use as a single shell word.
+///
+/// # Examples
+///
+/// ```
+/// # use inapi::command::quote;
+/// assert_eq!(quote("it's"), "'it'\\''s'");
+/// ```
+pub fn quote(s: &str) -> String {
+    format!("'{}'", s.replace("'", "'\\''"))
 }
 
 #[cfg(test)]
 mod tests {
     use Host;
-    #[cfg(feature = "remote-run")]
     use czmq::{ZMsg, ZSys};
-    #[cfg(feature = "local-run")]
+    use host::transport::MemoryTransport;
+    use serde_json;
     use std::{process, str};
-    #[cfg(feature = "remote-run")]
     use std::thread;
     use super::*;
 
-    #[cfg(feature = "local-run")]
     #[test]
-    fn test_exec() {
+    fn test_exec_local() {
         let path: Option<String> = None;
         let mut host = Host::local(path).unwrap();
         let cmd = Command::new("whoami");
@@ -133,7 +319,60 @@ mod tests {
         assert_eq!(result.stderr, str::from_utf8(&output.stderr).unwrap().trim().to_string());
     }
 
-    #[cfg(feature = "remote-run")]
+    #[test]
+    fn test_exec_local_opts() {
+        let mut host = Host::local(None::<String>).unwrap();
+
+        let cmd = Command::new("cat; echo \"$FOO\"; pwd").env("FOO", "bar").cwd("/").stdin("in\n");
+        assert_eq!(cmd.exec(&mut host).unwrap().stdout, "in\nbar\n/");
+
+        assert_eq!(Command::new("sleep 5").timeout(1).exec(&mut host).unwrap().exit_code, 124);
+
+        assert!(Command::new("exit 3").expected_codes(&[0, 1]).exec(&mut host).is_err());
+        assert!(Command::new("exit 3").expected_codes(&[3]).exec(&mut host).is_ok());
+    }
+
+    #[test]
+    fn test_opts() {
+        assert_eq!(Command::new("whoami").opts(), None);
+        assert_eq!(Command::new("whoami").script(), "whoami");
+
+        let cmd = Command::new("echo $FOO").env("FOO", "bar").cwd("/tmp").timeout(5);
+        assert_eq!(cmd.script(), "cd '/tmp' && env 'FOO=bar' timeout -k 5 5 sh -c 'echo $FOO'");
+
+        let cmd = cmd.user("www").stdin("in");
+        assert_eq!(Command::from_opts(&cmd.cmd, &cmd.opts().unwrap()).unwrap(), cmd);
+    }
+
+    #[test]
+    fn test_exec_opts() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        Command::new("make").cwd("/usr/src").exec(&mut host).unwrap();
+
+        let request = &transport.requests()[0];
+        assert_eq!(request[1], "make");
+        let opts: Value = serde_json::from_str(&request[2]).unwrap();
+        assert_eq!(opts.find("cwd").and_then(|v| v.as_str()), Some("/usr/src"));
+    }
+
+    #[test]
+    fn test_run() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "1", "", "failed"]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        host.set_check_mode(true);
+        assert!(Command::new("reboot").run(&mut host).unwrap().is_changed());
+        assert!(transport.requests().is_empty());
+
+        host.set_check_mode(false);
+        assert!(Command::new("reboot").run(&mut host).unwrap().is_failed());
+        assert_eq!(transport.requests().len(), 1);
+    }
+
     #[test]
     fn test_exec() {
         ZSys::init();
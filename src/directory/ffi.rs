@@ -142,22 +142,15 @@ pub extern "C" fn directory_free(dir_ptr: *mut Directory) -> uint8_t {
 
 #[cfg(test)]
 mod tests {
-    #[cfg(feature = "remote-run")]
     use czmq::{ZMsg, ZSys};
     use directory::DirectoryOpts;
-    #[cfg(feature = "remote-run")]
     use host::ffi::host_close;
-    #[cfg(feature = "remote-run")]
     use host::Host;
-    #[cfg(feature = "remote-run")]
     use std::ffi::{CStr, CString};
-    #[cfg(feature = "remote-run")]
     use std::ptr;
-    #[cfg(feature = "remote-run")]
     use std::path::Path;
     use std::str;
     use super::*;
-    #[cfg(feature = "remote-run")]
     use std::thread;
 
     #[test]
@@ -177,7 +170,6 @@ mod tests {
         assert!(found);
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_new_ok() {
         ZSys::init();
@@ -203,7 +195,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_new_fail() {
         ZSys::init();
@@ -228,7 +219,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_exists() {
         ZSys::init();
@@ -264,7 +254,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_create() {
         ZSys::init();
@@ -299,7 +288,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_delete() {
         ZSys::init();
@@ -331,7 +319,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_get_owner() {
         ZSys::init();
@@ -374,7 +361,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_set_owner() {
         ZSys::init();
@@ -409,7 +395,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_get_mode() {
         ZSys::init();
@@ -445,7 +430,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_set_mode() {
         ZSys::init();
@@ -15,18 +15,14 @@
 //!
 //! ```no_run
 //! # use inapi::Host;
-#![cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! ```
 //!
 //! Now you can manage a directory on your managed host.
 //!
 //! ```no_run
 //! # use inapi::{Host, Directory, DirectoryOpts};
-#![cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let dir = Directory::new(&mut host, "/path/to/dir").unwrap();
 //! dir.create(&mut host, Some(&vec![DirectoryOpts::DoRecursive])).unwrap();
 //! dir.set_owner(&mut host, "MyUser", "MyGroup").unwrap();
@@ -36,8 +32,12 @@
 pub mod ffi;
 
 use error::{Error, Result};
-use file::FileOwner;
+use file::{self, FileOwner, FileTarget};
 use host::Host;
+use outcome::Outcome;
+use std::collections::BTreeMap;
+use std::{fs, io};
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use target::Target;
 
@@ -47,6 +47,58 @@ pub enum DirectoryOpts {
     DoRecursive,
 }
 
+/// Options for controlling directory syncs.
+pub enum DirectorySyncOpts {
+    /// Delete files and directories on the managed host that do not
+    /// exist in the local directory.
+    DoPurge,
+}
+
+/// The type of a directory entry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntryType {
+    File,
+    Directory,
+    Symlink,
+    /// Sockets, FIFOs, devices etc.
+    Other,
+}
+
+impl ToString for EntryType {
+    fn to_string(&self) -> String {
+        match *self {
+            EntryType::File => "file",
+            EntryType::Directory => "directory",
+            EntryType::Symlink => "symlink",
+            EntryType::Other => "other",
+        }.to_string()
+    }
+}
+
+impl<'a> From<&'a str> for EntryType {
+    fn from(entry_type: &str) -> EntryType {
+        match entry_type {
+            "file" => EntryType::File,
+            "directory" => EntryType::Directory,
+            "symlink" => EntryType::Symlink,
+            _ => EntryType::Other,
+        }
+    }
+}
+
+/// A file, directory or link within a directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirectoryEntry {
+    /// Absolute path to the entry on managed host
+    pub path: PathBuf,
+    /// Entry type. Symlinks are not followed.
+    pub entry_type: EntryType,
+    /// Size in bytes
+    pub size: u64,
+    /// Last modification time in seconds since the Unix epoch
+    pub mtime: i64,
+}
+
 /// Container for operating on a directory.
 pub struct Directory {
     /// Absolute path to directory on managed host
@@ -60,9 +112,7 @@ impl Directory {
     ///
     /// ```no_run
     /// # use inapi::{Directory, Host};
-    #[cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-    #[cfg_attr(feature = "local-run", doc = "let mut host = Host::local(path).unwrap();")]
-    #[cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+    /// # let mut host = Host::connect("hosts/myhost.json").unwrap();
     /// let directory = Directory::new(&mut host, "/path/to/dir");
     /// ```
     pub fn new<P: AsRef<Path>>(host: &mut Host, path: P) -> Result<Directory> {
@@ -80,8 +130,37 @@ impl Directory {
         Target::directory_exists(host, &self.path)
     }
 
+    /// List the directory's immediate entries.
+    pub fn list(&self, host: &mut Host) -> Result<Vec<DirectoryEntry>> {
+        Target::directory_list(host, &self.path, Some(1))
+    }
+
+    /// Recursively list the directory's entries, descending at most
+    /// `max_depth` levels (or without limit if None). A `max_depth` of
+    /// 0 lists nothing. Symlinks to directories are not followed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use inapi::{Directory, EntryType, Host};
+    /// # let mut host = Host::connect("hosts/myhost.json").unwrap();
+    /// let dir = Directory::new(&mut host, "/var/log").unwrap();
+    /// for entry in dir.walk(&mut host, Some(2)).unwrap() {
+    ///     if entry.entry_type == EntryType::File {
+    ///         println!("{} {}", entry.path.display(), entry.size);
+    ///     }
+    /// }
+    /// ```
+    pub fn walk(&self, host: &mut Host, max_depth: Option<u32>) -> Result<Vec<DirectoryEntry>> {
+        if max_depth == Some(0) {
+            return Ok(Vec::new());
+        }
+
+        Target::directory_list(host, &self.path, max_depth)
+    }
+
     /// Create the directory.
-    pub fn create(&self, host: &mut Host, options: Option<&[DirectoryOpts]>) -> Result<()> {
+    pub fn create(&self, host: &mut Host, options: Option<&[DirectoryOpts]>) -> Result<Outcome> {
         let mut recursive = false;
 
         if let Some(opts) = options {
@@ -92,11 +171,17 @@ impl Directory {
             }
         }
 
-        Target::directory_create(host, &self.path, recursive)
+        let outcome = if try!(self.exists(host)) {
+            Outcome::unchanged(&self.resource(), "create")
+        } else {
+            Outcome::changed(&self.resource(), "create")
+        };
+
+        host.apply(outcome, |h| Target::directory_create(h, &self.path, recursive))
     }
 
     /// Delete the directory.
-    pub fn delete(&self, host: &mut Host, options: Option<&[DirectoryOpts]>) -> Result<()> {
+    pub fn delete(&self, host: &mut Host, options: Option<&[DirectoryOpts]>) -> Result<Outcome> {
         let mut recursive = false;
 
         if let Some(opts) = options {
@@ -107,15 +192,26 @@ impl Directory {
             }
         }
 
-        Target::directory_delete(host, &self.path, recursive)
+        let outcome = if try!(self.exists(host)) {
+            Outcome::changed(&self.resource(), "delete")
+        } else {
+            Outcome::unchanged(&self.resource(), "delete")
+        };
+
+        host.apply(outcome, |h| Target::directory_delete(h, &self.path, recursive))
     }
 
     /// Move the directory to a new path.
-    pub fn mv<P: AsRef<Path>>(&mut self, host: &mut Host, new_path: P) -> Result<()> {
+    pub fn mv<P: AsRef<Path>>(&mut self, host: &mut Host, new_path: P) -> Result<Outcome> {
         let new_path = new_path.as_ref().to_owned();
-        try!(Target::directory_mv(host, &self.path, &new_path));
-        self.path = new_path;
-        Ok(())
+        let outcome = Outcome::changed(&self.resource(), "mv").values(self.path.display(), new_path.display());
+        let outcome = try!(host.apply(outcome, |h| Target::directory_mv(h, &self.path, &new_path)));
+
+        if !host.check_mode() {
+            self.path = new_path;
+        }
+
+        Ok(outcome)
     }
 
     /// Get the directory's owner.
@@ -124,8 +220,17 @@ impl Directory {
     }
 
     // Set the directory's owner.
-    pub fn set_owner(&self, host: &mut Host, user: &str, group: &str) -> Result<()> {
-        Target::directory_set_owner(host, &self.path, user, group)
+    pub fn set_owner(&self, host: &mut Host, user: &str, group: &str) -> Result<Outcome> {
+        let owner = try!(self.get_owner(host));
+        let before = format!("{}:{}", owner.user_name, owner.group_name);
+        let after = format!("{}:{}", user, group);
+        let outcome = if before == after {
+            Outcome::unchanged(&self.resource(), "set_owner")
+        } else {
+            Outcome::changed(&self.resource(), "set_owner")
+        }.values(before, after);
+
+        host.apply(outcome, |h| Target::directory_set_owner(h, &self.path, user, group))
     }
 
     /// Get the directory's permissions mask.
@@ -134,11 +239,222 @@ impl Directory {
     }
 
     /// Set the directory's permissions mask.
-    pub fn set_mode(&self, host: &mut Host, mode: u16) -> Result<()> {
-        Target::directory_set_mode(host, &self.path, mode)
+    pub fn set_mode(&self, host: &mut Host, mode: u16) -> Result<Outcome> {
+        let current = try!(self.get_mode(host));
+        let outcome = if current == mode {
+            Outcome::unchanged(&self.resource(), "set_mode")
+        } else {
+            Outcome::changed(&self.resource(), "set_mode")
+        }.values(current, mode);
+
+        host.apply(outcome, |h| Target::directory_set_mode(h, &self.path, mode))
+    }
+
+    /// Recursively upload a local directory to this directory.
+    ///
+    /// Only files whose checksums differ from the managed host's
+    /// copies are sent. A file on the managed host where the local
+    /// copy is a directory (or vice versa) is deleted and replaced.
+    /// Returns an Outcome for each file or directory
+    /// that changed, followed by an Outcome for the sync as a whole.
+    /// Fails if `local_path` is not a directory.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use inapi::{Directory, DirectorySyncOpts, Host};
+    /// # let mut host = Host::connect("hosts/myhost.json").unwrap();
+    /// let dir = Directory::new(&mut host, "/var/www/static").unwrap();
+    /// dir.sync(&mut host, "build/static", Some(&[DirectorySyncOpts::DoPurge])).unwrap();
+    /// ```
+    pub fn sync<P: AsRef<Path>>(&self, host: &mut Host, local_path: P, options: Option<&[DirectorySyncOpts]>) -> Result<Vec<Outcome>> {
+        let mut purge = false;
+
+        if let Some(opts) = options {
+            for opt in opts {
+                match opt {
+                    &DirectorySyncOpts::DoPurge => purge = true,
+                }
+            }
+        }
+
+        // An empty source would purge everything on the managed host
+        match fs::metadata(local_path.as_ref()) {
+            Ok(ref meta) if meta.is_dir() => (),
+            Ok(_) => return Err(Error::Generic(format!("{} is not a directory", local_path.as_ref().display()))),
+            Err(e) => return Err(Error::Generic(format!("Could not sync from {}: {}", local_path.as_ref().display(), e))),
+        }
+
+        let local = try!(checksums(local_path.as_ref()));
+        let remote = try!(Target::directory_checksums(host, &self.path));
+        let mut outcomes = Vec::new();
+
+        if !try!(self.exists(host)) {
+            outcomes.push(try!(self.create(host, Some(&[DirectoryOpts::DoRecursive]))));
+        }
+
+        // Remote directories that have been deleted, along with their
+        // contents.
+        let mut deleted: Vec<&str> = Vec::new();
+
+        // BTreeMap keys are sorted, so parent directories are created
+        // before their contents.
+        for (rel_path, hash) in &local {
+            let remote_path = self.path.join(rel_path);
+
+            match *hash {
+                None => match remote.get(rel_path) {
+                    Some(&None) => (),
+                    remote_hash => {
+                        // A file is in the way of the directory
+                        if let Some(&Some(_)) = remote_hash {
+                            let outcome = Outcome::changed(&format!("file:{}", remote_path.display()), "delete");
+                            outcomes.push(try!(host.apply(outcome, |h| Target::file_delete(h, &remote_path))));
+                        }
+
+                        let outcome = Outcome::changed(&format!("directory:{}", remote_path.display()), "create");
+                        outcomes.push(try!(host.apply(outcome, |h| Target::directory_create(h, &remote_path, false))));
+                    }
+                },
+                Some(ref hash) => {
+                    // A directory is in the way of the file
+                    if let Some(&None) = remote.get(rel_path) {
+                        deleted.push(rel_path);
+                        let outcome = Outcome::changed(&format!("directory:{}", remote_path.display()), "delete");
+                        outcomes.push(try!(host.apply(outcome, |h| Target::directory_delete(h, &remote_path, true))));
+                    }
+
+                    let remote_hash = remote.get(rel_path).and_then(|h| h.as_ref());
+                    if remote_hash != Some(hash) {
+                        let mut outcome = Outcome::changed(&format!("file:{}", remote_path.display()), "upload");
+                        outcome.before = remote_hash.cloned();
+                        outcome.after = Some(hash.clone());
+
+                        let local_file = try!(fs::File::open(local_path.as_ref().join(rel_path)));
+                        outcomes.push(try!(host.apply(outcome, |h| Target::file_upload_file(h, &remote_path, local_file, None))));
+                    }
+                }
+            }
+        }
+
+        if purge {
+            for (rel_path, hash) in remote.iter().filter(|&(p, _)| !local.contains_key(p)) {
+                // Deleting a directory also deletes its contents
+                if deleted.iter().any(|p| Path::new(rel_path).starts_with(p)) {
+                    continue;
+                }
+
+                let remote_path = self.path.join(rel_path);
+                let outcome = match *hash {
+                    None => {
+                        deleted.push(rel_path);
+                        let outcome = Outcome::changed(&format!("directory:{}", remote_path.display()), "delete");
+                        try!(host.apply(outcome, |h| Target::directory_delete(h, &remote_path, true)))
+                    },
+                    Some(_) => {
+                        let outcome = Outcome::changed(&format!("file:{}", remote_path.display()), "delete");
+                        try!(host.apply(outcome, |h| Target::file_delete(h, &remote_path)))
+                    }
+                };
+                outcomes.push(outcome);
+            }
+        }
+
+        let outcome = if outcomes.is_empty() {
+            Outcome::unchanged(&self.resource(), "sync")
+        } else {
+            let mut outcome = Outcome::changed(&self.resource(), "sync");
+            outcome.after = Some(format!("{} changes", outcomes.len()));
+            outcome
+        };
+        outcomes.push(try!(host.record(Ok(()), outcome)));
+
+        Ok(outcomes)
+    }
+
+    fn resource(&self) -> String {
+        format!("directory:{}", self.path.display())
     }
 }
 
+/// List the entries beneath `path`, descending at most `max_depth`
+/// levels.
+#[doc(hidden)]
+pub fn list<P: AsRef<Path>>(path: P, max_depth: Option<u32>) -> Result<Vec<DirectoryEntry>> {
+    let mut entries = Vec::new();
+    if max_depth != Some(0) {
+        try!(walk_entries(path.as_ref(), 1, max_depth, &mut entries));
+    }
+    Ok(entries)
+}
+
+fn walk_entries(path: &Path, depth: u32, max_depth: Option<u32>, entries: &mut Vec<DirectoryEntry>) -> Result<()> {
+    let mut dir_entries = try!(try!(fs::read_dir(path)).collect::<io::Result<Vec<_>>>());
+    dir_entries.sort_by_key(|e| e.file_name());
+
+    for entry in dir_entries {
+        let meta = try!(fs::symlink_metadata(entry.path()));
+        let file_type = meta.file_type();
+        let entry_type = if file_type.is_symlink() {
+            EntryType::Symlink
+        } else if file_type.is_dir() {
+            EntryType::Directory
+        } else if file_type.is_file() {
+            EntryType::File
+        } else {
+            EntryType::Other
+        };
+
+        entries.push(DirectoryEntry {
+            path: entry.path(),
+            entry_type: entry_type,
+            size: meta.len(),
+            mtime: meta.mtime(),
+        });
+
+        if entry_type == EntryType::Directory && max_depth.map_or(true, |max| depth < max) {
+            try!(walk_entries(&entry.path(), depth + 1, max_depth, entries));
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the SHA256 checksums of every file beneath `path`, keyed by
+/// their `/` separated paths relative to `path`. Directories are
+/// included with a checksum of None. Symlinks to directories, and
+/// anything else that is not a file, are skipped.
+///
+/// If `path` does not exist, the map is empty.
+#[doc(hidden)]
+pub fn checksums<P: AsRef<Path>>(path: P) -> Result<BTreeMap<String, Option<String>>> {
+    let mut map = BTreeMap::new();
+    if fs::metadata(path.as_ref()).is_ok() {
+        try!(walk_checksums(path.as_ref(), "", &mut map));
+    }
+    Ok(map)
+}
+
+fn walk_checksums(path: &Path, prefix: &str, map: &mut BTreeMap<String, Option<String>>) -> Result<()> {
+    for entry in try!(fs::read_dir(path)) {
+        let entry = try!(entry);
+        let name = try!(entry.file_name().into_string().or(Err(Error::Generic("Path is not valid UTF-8".into()))));
+        let rel_path = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+
+        // Symlinks to files are synced as copies of their targets
+        let file_type = try!(entry.file_type());
+        if file_type.is_dir() {
+            map.insert(rel_path.clone(), None);
+            try!(walk_checksums(&entry.path(), &rel_path, map));
+        } else if file_type.is_file() || fs::metadata(entry.path()).map(|m| m.is_file()).unwrap_or(false) {
+            let mut fh = try!(fs::File::open(entry.path()));
+            map.insert(rel_path, Some(try!(file::checksum(&mut fh))));
+        }
+    }
+
+    Ok(())
+}
+
 pub trait DirectoryTarget<P: AsRef<Path>> {
     fn directory_is_directory(host: &mut Host, path: P) -> Result<bool>;
     fn directory_exists(host: &mut Host, path: P) -> Result<bool>;
@@ -149,27 +465,174 @@ pub trait DirectoryTarget<P: AsRef<Path>> {
     fn directory_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()>;
     fn directory_get_mode(host: &mut Host, path: P) -> Result<u16>;
     fn directory_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()>;
+    fn directory_checksums(host: &mut Host, path: P) -> Result<BTreeMap<String, Option<String>>>;
+    fn directory_list(host: &mut Host, path: P, max_depth: Option<u32>) -> Result<Vec<DirectoryEntry>>;
 }
 
 #[cfg(test)]
 mod tests {
     use Host;
-    #[cfg(feature = "remote-run")]
     use czmq::{ZMsg, ZSys};
     use super::*;
-    #[cfg(feature = "remote-run")]
     use std::thread;
+    use std::fs;
+    use std::io::Write;
+    use tempdir::TempDir;
 
-    #[cfg(feature = "local-run")]
     #[test]
-    fn test_new_ok() {
+    fn test_new_ok_local() {
         let path: Option<String> = None;
         let mut host = Host::local(path).unwrap();
         let dir = Directory::new(&mut host, "/path/to/dir");
         assert!(dir.is_ok());
     }
 
-    #[cfg(feature = "remote-run")]
+    #[test]
+    fn test_create_check_mode() {
+        let tempdir = TempDir::new("test_create_check_mode").unwrap();
+        let path = tempdir.path().join("new_dir");
+
+        let mut host = Host::local(None::<String>).unwrap();
+        host.set_check_mode(true);
+
+        let dir = Directory::new(&mut host, &path).unwrap();
+        assert!(dir.create(&mut host, None).unwrap().is_changed());
+        assert!(!path.exists());
+        assert_eq!(host.report().changed(), 1);
+    }
+
+    #[test]
+    fn test_sync() {
+        let src = TempDir::new("test_sync_src").unwrap();
+        fs::create_dir(src.path().join("css")).unwrap();
+        fs::File::create(src.path().join("css/site.css")).unwrap().write_all(b"body {}").unwrap();
+        fs::File::create(src.path().join("index.html")).unwrap().write_all(b"<html>").unwrap();
+
+        let dest = TempDir::new("test_sync_dest").unwrap();
+        fs::create_dir(dest.path().join("old")).unwrap();
+        fs::File::create(dest.path().join("old/stale.js")).unwrap();
+        fs::File::create(dest.path().join("index.html")).unwrap().write_all(b"<html>").unwrap();
+
+        let mut host = Host::local(None::<String>).unwrap();
+        let dir = Directory::new(&mut host, dest.path()).unwrap();
+
+        // css, css/site.css and old are changed; index.html is identical
+        let outcomes = dir.sync(&mut host, src.path(), Some(&[DirectorySyncOpts::DoPurge])).unwrap();
+        assert_eq!(outcomes.len(), 4);
+        assert!(outcomes.last().unwrap().is_changed());
+        assert!(dest.path().join("css/site.css").exists());
+        assert!(!dest.path().join("old").exists());
+
+        let outcomes = dir.sync(&mut host, src.path(), None).unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(!outcomes[0].is_changed());
+    }
+
+    #[test]
+    fn test_sync_replace() {
+        let src = TempDir::new("test_sync_replace_src").unwrap();
+        fs::create_dir(src.path().join("a")).unwrap();
+        fs::File::create(src.path().join("a/x")).unwrap();
+        fs::File::create(src.path().join("b")).unwrap().write_all(b"b").unwrap();
+        fs::create_dir(src.path().join("c")).unwrap();
+        ::std::os::unix::fs::symlink(src.path().join("c"), src.path().join("d")).unwrap();
+
+        let dest = TempDir::new("test_sync_replace_dest").unwrap();
+        fs::File::create(dest.path().join("a")).unwrap();
+        fs::create_dir(dest.path().join("b")).unwrap();
+        fs::File::create(dest.path().join("b/y")).unwrap();
+
+        let mut host = Host::local(None::<String>).unwrap();
+        let dir = Directory::new(&mut host, dest.path()).unwrap();
+
+        dir.sync(&mut host, src.path(), Some(&[DirectorySyncOpts::DoPurge])).unwrap();
+        assert!(dest.path().join("a/x").is_file());
+        assert!(dest.path().join("b").is_file());
+        assert!(dest.path().join("c").is_dir());
+        assert!(!dest.path().join("d").exists());
+    }
+
+    #[test]
+    fn test_sync_missing_source() {
+        let dest = TempDir::new("test_sync_missing_source").unwrap();
+        fs::File::create(dest.path().join("a")).unwrap();
+
+        let mut host = Host::local(None::<String>).unwrap();
+        let dir = Directory::new(&mut host, dest.path()).unwrap();
+
+        assert!(dir.sync(&mut host, dest.path().join("missing"), Some(&[DirectorySyncOpts::DoPurge])).is_err());
+        assert!(dir.sync(&mut host, dest.path().join("a"), Some(&[DirectorySyncOpts::DoPurge])).is_err());
+        assert!(dest.path().join("a").is_file());
+    }
+
+    #[test]
+    fn test_walk() {
+        let tempdir = TempDir::new("test_walk").unwrap();
+        fs::create_dir_all(tempdir.path().join("a/b")).unwrap();
+        fs::File::create(tempdir.path().join("a/b/c")).unwrap().write_all(b"abc").unwrap();
+        fs::File::create(tempdir.path().join("d")).unwrap();
+
+        let mut host = Host::local(None::<String>).unwrap();
+        let dir = Directory::new(&mut host, tempdir.path()).unwrap();
+
+        let entries = dir.list(&mut host).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].entry_type, EntryType::Directory);
+        assert_eq!(entries[1].path, tempdir.path().join("d"));
+
+        assert!(dir.walk(&mut host, Some(0)).unwrap().is_empty());
+        assert!(list(tempdir.path(), Some(0)).unwrap().is_empty());
+        assert_eq!(dir.walk(&mut host, Some(2)).unwrap().len(), 3);
+
+        let entries = dir.walk(&mut host, None).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[2].entry_type, EntryType::File);
+        assert_eq!(entries[2].size, 3);
+    }
+
+    #[test]
+    fn test_list() {
+        ZSys::init();
+
+        let (client, mut server) = ZSys::create_pipe().unwrap();
+
+        let agent_mock = thread::spawn(move || {
+            let req = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("directory::is_directory", req.popstr().unwrap().unwrap());
+
+            let rep = ZMsg::new();
+            rep.addstr("Ok").unwrap();
+            rep.addstr("1").unwrap();
+            rep.send(&mut server).unwrap();
+
+            let req = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("directory::list", req.popstr().unwrap().unwrap());
+            assert_eq!("/var/log", req.popstr().unwrap().unwrap());
+            assert_eq!("1", req.popstr().unwrap().unwrap());
+
+            let rep = ZMsg::new();
+            rep.addstr("Ok").unwrap();
+            rep.addstr("/var/log/messages").unwrap();
+            rep.addstr("file").unwrap();
+            rep.addstr("1024").unwrap();
+            rep.addstr("1490000000").unwrap();
+            rep.send(&mut server).unwrap();
+        });
+
+        let mut host = Host::test_new(None, Some(client), None, None);
+
+        let dir = Directory::new(&mut host, "/var/log").unwrap();
+        let entries = dir.list(&mut host).unwrap();
+        assert_eq!(entries, vec![DirectoryEntry {
+            path: PathBuf::from("/var/log/messages"),
+            entry_type: EntryType::File,
+            size: 1024,
+            mtime: 1490000000,
+        }]);
+
+        agent_mock.join().unwrap();
+    }
+
     #[test]
     fn test_new_ok() {
         ZSys::init();
@@ -207,7 +670,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_exists() {
         ZSys::init();
@@ -242,7 +704,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_create() {
         ZSys::init();
@@ -259,6 +720,15 @@ mod tests {
             rep.addstr("1").unwrap();
             rep.send(&mut server).unwrap();
 
+            let req = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("directory::exists", req.popstr().unwrap().unwrap());
+            assert_eq!("/path/to/dir", req.popstr().unwrap().unwrap());
+
+            let rep = ZMsg::new();
+            rep.addstr("Ok").unwrap();
+            rep.addstr("0").unwrap();
+            rep.send(&mut server).unwrap();
+
             let req = ZMsg::recv(&mut server).unwrap();
             assert_eq!("directory::create", req.popstr().unwrap().unwrap());
             assert_eq!("/path/to/dir", req.popstr().unwrap().unwrap());
@@ -270,12 +740,11 @@ mod tests {
         let mut host = Host::test_new(None, Some(client), None, None);
 
         let dir = Directory::new(&mut host, "/path/to/dir").unwrap();
-        assert!(dir.create(&mut host, Some(&vec![DirectoryOpts::DoRecursive])).is_ok());
+        assert!(dir.create(&mut host, Some(&vec![DirectoryOpts::DoRecursive])).unwrap().is_changed());
 
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_delete() {
         ZSys::init();
@@ -292,6 +761,15 @@ mod tests {
             rep.addstr("1").unwrap();
             rep.send(&mut server).unwrap();
 
+            let req = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("directory::exists", req.popstr().unwrap().unwrap());
+            assert_eq!("/path/to/dir", req.popstr().unwrap().unwrap());
+
+            let rep = ZMsg::new();
+            rep.addstr("Ok").unwrap();
+            rep.addstr("1").unwrap();
+            rep.send(&mut server).unwrap();
+
             let req = ZMsg::recv(&mut server).unwrap();
             assert_eq!("directory::delete", req.popstr().unwrap().unwrap());
             assert_eq!("/path/to/dir", req.popstr().unwrap().unwrap());
@@ -303,12 +781,11 @@ mod tests {
         let mut host = Host::test_new(None, Some(client), None, None);
 
         let dir = Directory::new(&mut host, "/path/to/dir").unwrap();
-        assert!(dir.delete(&mut host, None).is_ok());
+        assert!(dir.delete(&mut host, None).unwrap().is_changed());
 
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_mv() {
         ZSys::init();
@@ -341,7 +818,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_get_owner() {
         ZSys::init();
@@ -385,7 +861,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_set_owner() {
         ZSys::init();
@@ -402,6 +877,18 @@ mod tests {
             rep.addstr("1").unwrap();
             rep.send(&mut server).unwrap();
 
+            let req = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("directory::get_owner", req.popstr().unwrap().unwrap());
+            assert_eq!("/path/to/dir", req.popstr().unwrap().unwrap());
+
+            let rep = ZMsg::new();
+            rep.addstr("Ok").unwrap();
+            rep.addstr("root").unwrap();
+            rep.addstr("0").unwrap();
+            rep.addstr("root").unwrap();
+            rep.addstr("0").unwrap();
+            rep.send(&mut server).unwrap();
+
             let req = ZMsg::recv(&mut server).unwrap();
             assert_eq!("directory::set_owner", req.popstr().unwrap().unwrap());
             assert_eq!("/path/to/dir", req.popstr().unwrap().unwrap());
@@ -415,12 +902,11 @@ mod tests {
 
         let dir = Directory::new(&mut host, "/path/to/dir");
         assert!(dir.is_ok());
-        assert!(dir.unwrap().set_owner(&mut host, "user", "group").is_ok());
+        assert!(dir.unwrap().set_owner(&mut host, "user", "group").unwrap().is_changed());
 
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_get_mode() {
         ZSys::init();
@@ -456,7 +942,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_set_mode() {
         ZSys::init();
@@ -473,6 +958,15 @@ mod tests {
             rep.addstr("1").unwrap();
             rep.send(&mut server).unwrap();
 
+            let req = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("directory::get_mode", req.popstr().unwrap().unwrap());
+            assert_eq!("/path/to/dir", req.popstr().unwrap().unwrap());
+
+            let rep = ZMsg::new();
+            rep.addstr("Ok").unwrap();
+            rep.addstr("644").unwrap();
+            rep.send(&mut server).unwrap();
+
             let req = ZMsg::recv(&mut server).unwrap();
             assert_eq!("directory::set_mode", req.popstr().unwrap().unwrap());
             assert_eq!("/path/to/dir", req.popstr().unwrap().unwrap());
@@ -485,7 +979,7 @@ mod tests {
 
         let dir = Directory::new(&mut host, "/path/to/dir");
         assert!(dir.is_ok());
-        assert!(dir.unwrap().set_mode(&mut host, 755).is_ok());
+        assert!(dir.unwrap().set_mode(&mut host, 755).unwrap().is_changed());
 
         agent_mock.join().unwrap();
     }
@@ -6,18 +6,18 @@
 // https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
 // modified, or distributed except according to those terms.
 
-#[cfg(feature = "remote-run")]
 use czmq;
 use libc::c_char;
 use mustache;
 use regex;
 use rustc_serialize::json;
 use serde_json;
+#[cfg(feature = "ssh")]
+use ssh2;
 use std::{convert, error, ffi, fmt, io, num, ptr, result, str, string};
 use std::any::Any;
 use std::ffi::CString;
 use zdaemon;
-#[cfg(feature = "remote-run")]
 use zfilexfer;
 
 pub type Result<T> = result::Result<T, Error>;
@@ -42,27 +42,20 @@ pub extern "C" fn geterr() -> *const c_char {
 pub enum Error {
     /// An error string returned from the host's Intecture Agent
     Agent(String),
-    #[cfg(feature = "remote-run")]
     /// An error string returned from the host's Intecture Auth
     Auth(String),
-    #[cfg(feature = "remote-run")]
     /// Payload build failure
     BuildFailed(String),
-    #[cfg(feature = "remote-run")]
     /// Payload run failure
     RunFailed(String),
-    #[cfg(feature = "remote-run")]
     /// CZMQ error
     Czmq(czmq::Error),
-    #[cfg(feature = "remote-run")]
     /// Message frames missing in the response from host's Intecture Agent
     Frame(MissingFrame),
     /// Generic error string
     Generic(String),
-    #[cfg(feature = "remote-run")]
     /// Cannot run command on disconnected host
     HostDisconnected,
-    #[cfg(feature = "remote-run")]
     /// Invalid response from host
     HostResponse,
     /// Invalid file descriptor
@@ -87,13 +80,17 @@ pub enum Error {
     Regex(regex::Error),
     /// Serde JSON error
     SerdeJson(serde_json::Error),
+    #[cfg(feature = "ssh")]
+    /// SSH error
+    Ssh(ssh2::Error),
     /// Cast str
     StrFromUtf8(str::Utf8Error),
     /// Cast String
     StringFromUtf8(string::FromUtf8Error),
+    /// Host did not respond in time
+    Timeout,
     // ZDaemon error
     ZDaemon(zdaemon::Error),
-    #[cfg(feature = "remote-run")]
     /// ZFileXfer error
     ZFileXfer(zfilexfer::Error),
 }
@@ -104,20 +101,13 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::Agent(ref e) => write!(f, "Agent error: {}", e),
-            #[cfg(feature = "remote-run")]
             Error::Auth(ref e) => write!(f, "Auth error: {}", e),
-            #[cfg(feature = "remote-run")]
             Error::BuildFailed(ref e) => write!(f, "Failed to build payload: {}", e),
-            #[cfg(feature = "remote-run")]
             Error::RunFailed(ref e) => write!(f, "Failed to run payload: {}", e),
-            #[cfg(feature = "remote-run")]
             Error::Czmq(ref e) => write!(f, "CZMQ error: {}", e),
-            #[cfg(feature = "remote-run")]
             Error::Frame(ref e) => write!(f, "Missing frame {} in message: {}", e.order, e.name),
             Error::Generic(ref e) => write!(f, "Error: {}", e),
-            #[cfg(feature = "remote-run")]
             Error::HostDisconnected => write!(f, "Cannot run command while host is disconnected"),
-            #[cfg(feature = "remote-run")]
             Error::HostResponse => write!(f, "Invalid response from host"),
             Error::InvalidFileDescriptor => write!(f, "Invalid file descriptor"),
             Error::Io(ref e) => write!(f, "IO error: {}", e),
@@ -130,10 +120,12 @@ impl fmt::Display for Error {
             Error::QueryParser(ref e) => write!(f, "Query parser error: {}", e),
             Error::Regex(ref e) => write!(f, "Regex error: {}", e),
             Error::SerdeJson(ref e) => write!(f, "Serde JSON error: {}", e),
+            #[cfg(feature = "ssh")]
+            Error::Ssh(ref e) => write!(f, "SSH error: {}", e),
             Error::StrFromUtf8(ref e) => write!(f, "Convert from UTF8 slice to str error: {}", e),
             Error::StringFromUtf8(ref e) => write!(f, "Convert from UTF8 slice to String error: {}", e),
+            Error::Timeout => write!(f, "Timed out waiting for host to respond"),
             Error::ZDaemon(ref e) => write!(f, "ZDaemon error: {}", e),
-            #[cfg(feature = "remote-run")]
             Error::ZFileXfer(ref e) => write!(f, "ZFileXfer error: {}", e),
         }
     }
@@ -143,20 +135,13 @@ impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
             Error::Agent(ref e) => e,
-            #[cfg(feature = "remote-run")]
             Error::Auth(ref e) => e,
-            #[cfg(feature = "remote-run")]
             Error::BuildFailed(ref e) => e,
-            #[cfg(feature = "remote-run")]
             Error::RunFailed(ref e) => e,
-            #[cfg(feature = "remote-run")]
             Error::Czmq(ref e) => e.description(),
-            #[cfg(feature = "remote-run")]
             Error::Frame(_) => "The Agent's reply was missing a part ('frame') of the expected message",
             Error::Generic(ref e) => e,
-            #[cfg(feature = "remote-run")]
             Error::HostDisconnected => "Cannot run command on disconnected host",
-            #[cfg(feature = "remote-run")]
             Error::HostResponse => "Invalid response from host",
             Error::InvalidFileDescriptor => "Invalid file descriptor",
             Error::Io(ref e) => e.description(),
@@ -169,10 +154,12 @@ impl error::Error for Error {
             Error::QueryParser(ref e) => e,
             Error::Regex(ref e) => e.description(),
             Error::SerdeJson(ref e) => e.description(),
+            #[cfg(feature = "ssh")]
+            Error::Ssh(ref e) => e.description(),
             Error::StrFromUtf8(ref e) => e.description(),
             Error::StringFromUtf8(ref e) => e.description(),
+            Error::Timeout => "Timed out waiting for host to respond",
             Error::ZDaemon(ref e) => e.description(),
-            #[cfg(feature = "remote-run")]
             Error::ZFileXfer(ref e) => e.description(),
         }
     }
@@ -191,7 +178,6 @@ impl convert::From<Box<Any + Send>> for Error {
     }
 }
 
-#[cfg(feature = "remote-run")]
 impl convert::From<czmq::Error> for Error {
     fn from(err: czmq::Error) -> Error {
         Error::Czmq(err)
@@ -216,7 +202,6 @@ impl convert::From<json::DecoderError> for Error {
     }
 }
 
-#[cfg(feature = "remote-run")]
 impl convert::From<MissingFrame> for Error {
     fn from(err: MissingFrame) -> Error {
         Error::Frame(err)
@@ -229,6 +214,18 @@ impl convert::From<mustache::Error> for Error {
     }
 }
 
+#[cfg(feature = "ssh")]
+impl convert::From<ssh2::Error> for Error {
+    fn from(err: ssh2::Error) -> Error {
+        // LIBSSH2_ERROR_TIMEOUT
+        if err.code() == -9 {
+            Error::Timeout
+        } else {
+            Error::Ssh(err)
+        }
+    }
+}
+
 impl convert::From<regex::Error> for Error {
     fn from(err: regex::Error) -> Error {
         Error::Regex(err)
@@ -271,21 +268,18 @@ impl convert::From<zdaemon::Error> for Error {
     }
 }
 
-#[cfg(feature = "remote-run")]
 impl convert::From<zfilexfer::Error> for Error {
     fn from(err: zfilexfer::Error) -> Error {
         Error::ZFileXfer(err)
     }
 }
 
-#[cfg(feature = "remote-run")]
 #[derive(Debug)]
 pub struct MissingFrame {
     name: String,
     order: u8,
 }
 
-#[cfg(feature = "remote-run")]
 impl MissingFrame {
     pub fn new(name: &str, order: u8) -> MissingFrame {
         MissingFrame {
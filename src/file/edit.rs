@@ -0,0 +1,196 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Idempotent edits to the lines of a file.
+
+use error::{Error, Result};
+use regex::Regex;
+
+/// An edit to apply to a file with `File::edit()`.
+///
+/// Each edit is idempotent: applying it to a file that is already
+/// in the desired state leaves the file untouched.
+pub enum FileEdit {
+    /// Ensure the line is present, appending it if missing.
+    LinePresent(String),
+    /// Remove every occurrence of the line.
+    LineAbsent(String),
+    /// Replace the first line matching the regex pattern (first value)
+    /// with the line (second value), removing any other matching
+    /// lines. If no lines match and the line is missing, it is
+    /// appended.
+    Regex(String, String),
+    /// Remove every line matching the regex pattern.
+    RegexAbsent(String),
+    /// Ensure a block of lines is present between the markers
+    /// `# BEGIN <name>` and `# END <name>`, where the name is the
+    /// first value and the block content is the second. A begin
+    /// marker without an end marker is an error.
+    Block(String, String),
+    /// Remove the named block, including its markers.
+    BlockAbsent(String),
+}
+
+/// Apply an edit to the contents of a file.
+///
+/// Returns the new contents, or None if the contents are already in
+/// the desired state. Files with CRLF line endings keep them.
+pub fn apply(content: &str, edit: &FileEdit) -> Result<Option<String>> {
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let lines: Vec<&str> = content.lines().collect();
+    let mut new_lines: Vec<String> = Vec::new();
+
+    match *edit {
+        FileEdit::LinePresent(ref line) => {
+            new_lines.extend(lines.iter().map(|l| l.to_string()));
+            if !lines.contains(&line.as_str()) {
+                new_lines.push(line.clone());
+            }
+        },
+        FileEdit::LineAbsent(ref line) => {
+            new_lines.extend(lines.iter().filter(|l| **l != line.as_str()).map(|l| l.to_string()));
+        },
+        FileEdit::Regex(ref pattern, ref line) => {
+            let regex = try!(Regex::new(pattern));
+            let mut matched = false;
+            for l in &lines {
+                if regex.is_match(l) {
+                    if !matched {
+                        matched = true;
+                        new_lines.push(line.clone());
+                    }
+                } else {
+                    new_lines.push(l.to_string());
+                }
+            }
+            if !matched && !lines.contains(&line.as_str()) {
+                new_lines.push(line.clone());
+            }
+        },
+        FileEdit::RegexAbsent(ref pattern) => {
+            let regex = try!(Regex::new(pattern));
+            new_lines.extend(lines.iter().filter(|l| !regex.is_match(l)).map(|l| l.to_string()));
+        },
+        FileEdit::Block(ref name, ref block) => {
+            let (begin, end) = markers(name);
+            match try!(find_block(&lines, &begin, &end)) {
+                Some((start, finish)) => {
+                    new_lines.extend(lines[..start + 1].iter().map(|l| l.to_string()));
+                    new_lines.extend(block.lines().map(|l| l.to_string()));
+                    new_lines.extend(lines[finish..].iter().map(|l| l.to_string()));
+                },
+                None => {
+                    new_lines.extend(lines.iter().map(|l| l.to_string()));
+                    new_lines.push(begin);
+                    new_lines.extend(block.lines().map(|l| l.to_string()));
+                    new_lines.push(end);
+                }
+            }
+        },
+        FileEdit::BlockAbsent(ref name) => {
+            let (begin, end) = markers(name);
+            match try!(find_block(&lines, &begin, &end)) {
+                Some((start, finish)) => {
+                    new_lines.extend(lines[..start].iter().map(|l| l.to_string()));
+                    new_lines.extend(lines[finish + 1..].iter().map(|l| l.to_string()));
+                },
+                None => new_lines.extend(lines.iter().map(|l| l.to_string())),
+            }
+        },
+    }
+
+    if new_lines == lines {
+        Ok(None)
+    } else {
+        let mut new_content = new_lines.join(newline);
+        if !new_content.is_empty() {
+            new_content.push_str(newline);
+        }
+        Ok(Some(new_content))
+    }
+}
+
+fn markers(name: &str) -> (String, String) {
+    (format!("# BEGIN {}", name), format!("# END {}", name))
+}
+
+/// Find the line indexes of a block's begin and end markers.
+fn find_block(lines: &[&str], begin: &str, end: &str) -> Result<Option<(usize, usize)>> {
+    let start = match lines.iter().position(|l| *l == begin) {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+
+    match lines[start..].iter().position(|l| *l == end) {
+        Some(i) => Ok(Some((start, start + i))),
+        None => Err(Error::Generic(format!("Found \"{}\" without \"{}\"", begin, end))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_present() {
+        let edit = FileEdit::LinePresent("net.ipv4.ip_forward = 1".into());
+        assert_eq!(apply("a\nb\n", &edit).unwrap().unwrap(), "a\nb\nnet.ipv4.ip_forward = 1\n");
+        assert!(apply("a\nnet.ipv4.ip_forward = 1", &edit).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_line_absent() {
+        let edit = FileEdit::LineAbsent("b".into());
+        assert_eq!(apply("a\nb\nc\nb\n", &edit).unwrap().unwrap(), "a\nc\n");
+        assert!(apply("a\nc\n", &edit).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_regex() {
+        let edit = FileEdit::Regex("^127\\.0\\.0\\.1\\s".into(), "127.0.0.1 localhost web1".into());
+        assert_eq!(apply("127.0.0.1 localhost\n::1 localhost\n", &edit).unwrap().unwrap(),
+                   "127.0.0.1 localhost web1\n::1 localhost\n");
+        assert!(apply("127.0.0.1 localhost web1\n", &edit).unwrap().is_none());
+        assert_eq!(apply("127.0.0.1 a\n::1 a\n127.0.0.1 b\n", &edit).unwrap().unwrap(),
+                   "127.0.0.1 localhost web1\n::1 a\n");
+        assert_eq!(apply("", &edit).unwrap().unwrap(), "127.0.0.1 localhost web1\n");
+        assert!(apply("", &FileEdit::Regex("(".into(), "".into())).is_err());
+    }
+
+    #[test]
+    fn test_regex_absent() {
+        let edit = FileEdit::RegexAbsent("^#".into());
+        assert_eq!(apply("# comment\na\n", &edit).unwrap().unwrap(), "a\n");
+        assert!(apply("a\n", &edit).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_block() {
+        let edit = FileEdit::Block("web".into(), "a\nb".into());
+        assert_eq!(apply("x\n", &edit).unwrap().unwrap(), "x\n# BEGIN web\na\nb\n# END web\n");
+        assert_eq!(apply("x\n# BEGIN web\nold\n# END web\ny\n", &edit).unwrap().unwrap(),
+                   "x\n# BEGIN web\na\nb\n# END web\ny\n");
+        assert!(apply("x\n# BEGIN web\na\nb\n# END web\n", &edit).unwrap().is_none());
+        assert!(apply("x\n# BEGIN web\nold\n", &edit).is_err());
+    }
+
+    #[test]
+    fn test_block_absent() {
+        let edit = FileEdit::BlockAbsent("web".into());
+        assert_eq!(apply("x\n# BEGIN web\na\n# END web\ny\n", &edit).unwrap().unwrap(), "x\ny\n");
+        assert!(apply("x\ny\n", &edit).unwrap().is_none());
+        assert!(apply("x\n# BEGIN web\na\n", &edit).is_err());
+    }
+
+    #[test]
+    fn test_crlf() {
+        let edit = FileEdit::LinePresent("c".into());
+        assert_eq!(apply("a\r\nb\r\n", &edit).unwrap().unwrap(), "a\r\nb\r\nc\r\n");
+        assert!(apply("a\r\nc\r\n", &edit).unwrap().is_none());
+    }
+}
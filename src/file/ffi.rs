@@ -8,18 +8,13 @@
 
 //! FFI interface for File
 
-#[cfg(feature = "remote-run")]
 use error;
 use ffi_helpers::Leaky;
 use host::Host;
-use libc::{c_char, int8_t, int16_t, uint8_t, uint16_t, uint64_t};
-#[cfg(feature = "remote-run")]
-use libc::c_int;
+use libc::{c_char, c_int, int8_t, int16_t, uint8_t, uint16_t, uint64_t};
 use std::{convert, ptr};
 use std::ffi::CString;
-#[cfg(feature = "remote-run")]
 use std::fs;
-#[cfg(feature = "remote-run")]
 use std::os::unix::io::FromRawFd;
 use std::panic::catch_unwind;
 use super::*;
@@ -86,7 +81,6 @@ pub extern "C" fn file_exists(file_ptr: *const File, host_ptr: *const Host) -> i
     }
 }
 
-#[cfg(feature = "remote-run")]
 #[no_mangle]
 pub extern "C" fn file_upload(file_ptr: *const File,
                               host_ptr: *const Host,
@@ -105,7 +99,6 @@ pub extern "C" fn file_upload(file_ptr: *const File,
     0
 }
 
-#[cfg(feature = "remote-run")]
 #[no_mangle]
 pub extern "C" fn file_upload_file(file_ptr: *const File,
                                    host_ptr: *const Host,
@@ -214,19 +207,14 @@ pub extern "C" fn file_free(file_ptr: *mut File) -> uint8_t {
 
 #[cfg(test)]
 mod tests {
-    #[cfg(feature = "remote-run")]
     use czmq::{ZMsg, ZSys};
     use file::FileOwner;
-    #[cfg(feature = "remote-run")]
     use host::ffi::host_close;
-    #[cfg(feature = "remote-run")]
     use host::Host;
     use std::ffi::{CStr, CString};
-    #[cfg(feature = "remote-run")]
     use std::path::Path;
     use std::str;
     use super::*;
-    #[cfg(feature = "remote-run")]
     use std::thread;
     use zfilexfer::FileOptions;
 
@@ -262,7 +250,6 @@ mod tests {
         assert_eq!(ffi_owner.group_gid, 456);
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_new_ok() {
         ZSys::init();
@@ -288,7 +275,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_new_fail() {
         ZSys::init();
@@ -313,7 +299,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_exists() {
         ZSys::init();
@@ -349,7 +334,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_delete() {
         ZSys::init();
@@ -381,7 +365,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_get_owner() {
         ZSys::init();
@@ -424,7 +407,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_set_owner() {
         ZSys::init();
@@ -463,7 +445,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_get_mode() {
         ZSys::init();
@@ -499,7 +480,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_set_mode() {
         ZSys::init();
@@ -15,43 +15,60 @@
 //!
 //! ```no_run
 //! # use inapi::Host;
-#![cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! ```
 //!
 //! Now you can manage a file on your managed host.
 //!
 //! ```no_run
 //! # use inapi::{Host, File, FileOptions};
-#![cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let file = File::new(&mut host, "/path/to/destination_file").unwrap();
-#![cfg_attr(feature = "remote-run", doc = " file.upload(&mut host, \"/path/to/local_file\", None);")]
+//! file.upload(&mut host, "/path/to/local_file", None);
 //! file.set_owner(&mut host, "MyUser", "MyGroup").unwrap();
 //! file.set_mode(&mut host, 644).unwrap();
 //!
-#![cfg_attr(feature = "remote-run", doc = " // Now let's upload another file and backup the original")]
-#![cfg_attr(feature = "remote-run", doc = " file.upload(&mut host, \"/path/to/new_file\", Some(&vec![FileOptions::BackupExisting(\"_bk\".to_string())])).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "")]
-#![cfg_attr(feature = "remote-run", doc = " // Your remote path now has two entries:")]
-#![cfg_attr(feature = "remote-run", doc = " // \"/path/to/destination_file\" and \"/path/to/destination_file_bk\"")]
+//! // Now let's upload another file and backup the original
+//! file.upload(&mut host, "/path/to/new_file", Some(&vec![FileOptions::BackupExisting("_bk".to_string())])).unwrap();
+//!
+//! // Your remote path now has two entries:
+//! // "/path/to/destination_file" and "/path/to/destination_file_bk"
+//! ```
+//!
+//! Alternatively, describe the file's desired state and let
+//! `ensure()` apply only what differs. Content is compared by
+//! checksum, so an identical file is never transferred.
+//!
+//! ```no_run
+//! # use inapi::{Host, File, FileContent, FileSpec};
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! let file = File::new(&mut host, "/etc/motd").unwrap();
+//! let spec = FileSpec::new()
+//!     .content(FileContent::Text("Welcome!\n".into()))
+//!     .owner("root", "root")
+//!     .mode(644);
+//! let outcomes = file.ensure(&mut host, spec).unwrap();
 //! ```
 
+pub mod edit;
 pub mod ffi;
 
+pub use self::edit::FileEdit;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use directory::EntryType;
 use error::Result;
 use host::Host;
-#[cfg(feature = "remote-run")]
-use host::HostSendRecv;
 use error::Error;
-#[cfg(feature = "remote-run")]
+use outcome::Outcome;
 use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::process;
 use target::Target;
-#[cfg(feature = "remote-run")]
-use zfilexfer;
+use tempfile::tempfile;
+use zfilexfer::FileOptions;
 
 /// Owner's user and group for a file.
 #[derive(Debug)]
@@ -66,6 +83,102 @@ pub struct FileOwner {
     pub group_gid: u64,
 }
 
+/// Metadata for a file on a managed host.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileMetadata {
+    /// File type. Symlinks are not followed.
+    pub file_type: EntryType,
+    /// Size in bytes
+    pub size: u64,
+    /// Permissions mask, e.g. 644
+    pub mode: u16,
+    /// Owner's UID
+    pub uid: u64,
+    /// Owner's GID
+    pub gid: u64,
+    /// Last access time in seconds since the Unix epoch
+    pub atime: i64,
+    /// Last modification time in seconds since the Unix epoch
+    pub mtime: i64,
+    /// Last status change time in seconds since the Unix epoch
+    pub ctime: i64,
+    /// Inode number
+    pub inode: u64,
+    /// Number of hard links
+    pub nlink: u64,
+    /// SELinux security context, if SELinux is available
+    pub selinux_context: Option<String>,
+    /// SHA256 checksum of the contents of a regular file
+    pub checksum: Option<String>,
+}
+
+/// Source of a file's desired content.
+pub enum FileContent {
+    /// Path to a file on the local machine
+    Path(PathBuf),
+    /// An open file handle, e.g. a rendered `Template`
+    File(fs::File),
+    /// Literal file contents
+    Text(String),
+}
+
+impl FileContent {
+    /// Get the content's checksum and a handle to read it from.
+    fn open(self) -> Result<(String, fs::File)> {
+        let mut file = match self {
+            FileContent::Path(path) => try!(fs::File::open(path)),
+            FileContent::File(file) => file,
+            FileContent::Text(text) => {
+                let mut file = try!(tempfile());
+                try!(file.write_all(text.as_bytes()));
+                file
+            },
+        };
+
+        try!(file.seek(SeekFrom::Start(0)));
+        let sum = try!(checksum(&mut file));
+        try!(file.seek(SeekFrom::Start(0)));
+
+        Ok((sum, file))
+    }
+}
+
+/// Desired state of a file, applied with `File::ensure()`.
+pub struct FileSpec {
+    content: Option<FileContent>,
+    owner: Option<(String, String)>,
+    mode: Option<u16>,
+}
+
+impl FileSpec {
+    /// Create an empty FileSpec, which leaves the file untouched.
+    pub fn new() -> FileSpec {
+        FileSpec {
+            content: None,
+            owner: None,
+            mode: None,
+        }
+    }
+
+    /// Set the file's desired content.
+    pub fn content(mut self, content: FileContent) -> FileSpec {
+        self.content = Some(content);
+        self
+    }
+
+    /// Set the file's desired owner.
+    pub fn owner(mut self, user: &str, group: &str) -> FileSpec {
+        self.owner = Some((user.into(), group.into()));
+        self
+    }
+
+    /// Set the file's desired permissions mask.
+    pub fn mode(mut self, mode: u16) -> FileSpec {
+        self.mode = Some(mode);
+        self
+    }
+}
+
 /// Container for operating on a file.
 pub struct File {
     /// Absolute path to file on managed host
@@ -79,9 +192,7 @@ impl File {
     ///
     /// ```no_run
     /// # use inapi::{File, Host};
-    #[cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-    #[cfg_attr(feature = "local-run", doc = "let mut host = Host::local(path).unwrap();")]
-    #[cfg_attr(feature = "remote-run", doc = "let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+    /// let mut host = Host::connect("hosts/myhost.json").unwrap();
     /// let file = File::new(&mut host, "/path/to/file");
     /// ```
     pub fn new<P: AsRef<Path>>(host: &mut Host, path: P) -> Result<File> {
@@ -99,37 +210,193 @@ impl File {
         Target::file_exists(host, &self.path)
     }
 
-    #[cfg(feature = "remote-run")]
     /// Upload a file to the managed host.
-    pub fn upload<P: AsRef<Path>>(&self, host: &mut Host, local_path: P, options: Option<&[zfilexfer::FileOptions]>) -> Result<()> {
-        let mut file = try!(zfilexfer::File::open(&local_path, options));
-        host.send_fs_file(&mut file, &self.path)
+    pub fn upload<P: AsRef<Path>>(&self, host: &mut Host, local_path: P, options: Option<&[FileOptions]>) -> Result<Outcome> {
+        let file = try!(fs::File::open(local_path));
+        self.upload_file(host, file, options)
     }
 
-    #[cfg(feature = "remote-run")]
     /// Upload a file handle to the managed host.
-    pub fn upload_file(&self, host: &mut Host, file: fs::File, options: Option<&[zfilexfer::FileOptions]>) -> Result<()> {
-        let mut zfile = try!(zfilexfer::File::open_file(file, options));
-        host.send_fs_file(&mut zfile, &self.path)
+    pub fn upload_file(&self, host: &mut Host, file: fs::File, options: Option<&[FileOptions]>) -> Result<Outcome> {
+        let outcome = Outcome::changed(&self.resource(), "upload");
+        host.apply(outcome, |h| Target::file_upload_file(h, &self.path, file, options))
+    }
+
+    /// Download the file from the managed host to a local path.
+    ///
+    /// The file is downloaded beside `local_path` and renamed into
+    /// place once complete, so a failed download leaves any existing
+    /// file at `local_path` untouched.
+    pub fn download<P: AsRef<Path>>(&self, host: &mut Host, local_path: P) -> Result<()> {
+        let mut tmp_path = local_path.as_ref().as_os_str().to_owned();
+        tmp_path.push(format!(".{}.tmp", process::id()));
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let result = fs::File::create(&tmp_path)
+            .map_err(Error::from)
+            .and_then(|mut file| Target::file_download(host, &self.path, &mut file))
+            .and_then(|_| fs::rename(&tmp_path, local_path.as_ref()).map_err(Error::from));
+
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+
+        result
+    }
+
+    /// Read the file's contents into a String.
+    pub fn read_to_string(&self, host: &mut Host) -> Result<String> {
+        let mut buf = Vec::new();
+        try!(Target::file_download(host, &self.path, &mut buf));
+        Ok(try!(String::from_utf8(buf)))
+    }
+
+    /// Apply an idempotent edit to the file's lines, creating the
+    /// file if it does not exist.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use inapi::{File, FileEdit, Host};
+    /// # let mut host = Host::connect("hosts/myhost.json").unwrap();
+    /// let file = File::new(&mut host, "/etc/sysctl.conf").unwrap();
+    /// file.edit(&mut host, FileEdit::LinePresent("net.ipv4.ip_forward = 1".into())).unwrap();
+    /// ```
+    pub fn edit(&self, host: &mut Host, edit: FileEdit) -> Result<Outcome> {
+        let changed = Outcome::changed(&self.resource(), "edit");
+        let unchanged = Outcome::unchanged(&self.resource(), "edit");
+
+        // Preview the edit against a copy of the file's contents
+        if host.check_mode() {
+            let content = if try!(self.exists(host)) {
+                try!(self.read_to_string(host))
+            } else {
+                String::new()
+            };
+
+            return match try!(edit::apply(&content, &edit)) {
+                Some(_) => host.record(Ok(()), changed),
+                None => host.record(Ok(()), unchanged),
+            };
+        }
+
+        match Target::file_edit(host, &self.path, &edit) {
+            Ok(true) => host.record(Ok(()), changed),
+            Ok(false) => host.record(Ok(()), unchanged),
+            Err(e) => host.record(Err(e), changed),
+        }
+    }
+
+    /// Get the SHA256 checksum of the file's contents, or None if
+    /// the file does not exist.
+    pub fn checksum(&self, host: &mut Host) -> Result<Option<String>> {
+        Target::file_hash(host, &self.path)
+    }
+
+    /// Bring the file into the state described by `spec`.
+    ///
+    /// Only attributes that differ from the file's current state are
+    /// applied, and content is only transferred when its checksum
+    /// differs from the file on the managed host. Returns an Outcome
+    /// for each attribute in `spec`.
+    ///
+    /// Returns an error if the file does not exist and `spec` has no
+    /// content to create it with.
+    pub fn ensure(&self, host: &mut Host, spec: FileSpec) -> Result<Vec<Outcome>> {
+        let mut outcomes = Vec::new();
+        let mut exists = try!(self.exists(host));
+
+        if !exists && spec.content.is_none() {
+            return Err(Error::Generic(format!("Cannot ensure {} as it does not exist and no content was given", self.path.display())));
+        }
+
+        if let Some(content) = spec.content {
+            let (checksum, file) = try!(content.open());
+            let current = try!(self.checksum(host));
+
+            let mut outcome = if current.as_ref() == Some(&checksum) {
+                Outcome::unchanged(&self.resource(), "content")
+            } else {
+                Outcome::changed(&self.resource(), "content")
+            };
+            outcome.before = current;
+            outcome.after = Some(checksum);
+
+            outcomes.push(try!(host.apply(outcome, |h| Target::file_upload_file(h, &self.path, file, None))));
+            exists = exists || !host.check_mode();
+        }
+
+        // In check mode a file that would have been uploaded does not
+        // exist yet, so there is nothing to compare against.
+        if !exists {
+            if let Some((user, group)) = spec.owner {
+                let mut outcome = Outcome::changed(&self.resource(), "set_owner");
+                outcome.after = Some(format!("{}:{}", user, group));
+                outcomes.push(try!(host.record(Ok(()), outcome)));
+            }
+            if let Some(mode) = spec.mode {
+                let mut outcome = Outcome::changed(&self.resource(), "set_mode");
+                outcome.after = Some(mode.to_string());
+                outcomes.push(try!(host.record(Ok(()), outcome)));
+            }
+            return Ok(outcomes);
+        }
+
+        if let Some((user, group)) = spec.owner {
+            outcomes.push(try!(self.set_owner(host, &user, &group)));
+        }
+
+        if let Some(mode) = spec.mode {
+            outcomes.push(try!(self.set_mode(host, mode)));
+        }
+
+        Ok(outcomes)
     }
 
     /// Delete the file.
-    pub fn delete(&self, host: &mut Host) -> Result<()> {
-        Target::file_delete(host, &self.path)
+    pub fn delete(&self, host: &mut Host) -> Result<Outcome> {
+        let outcome = if try!(self.exists(host)) {
+            Outcome::changed(&self.resource(), "delete")
+        } else {
+            Outcome::unchanged(&self.resource(), "delete")
+        };
+
+        host.apply(outcome, |h| Target::file_delete(h, &self.path))
     }
 
     /// Move the file to a new path.
-    pub fn mv<P: AsRef<Path>>(&mut self, host: &mut Host, new_path: P) -> Result<()> {
+    pub fn mv<P: AsRef<Path>>(&mut self, host: &mut Host, new_path: P) -> Result<Outcome> {
         let new_path = new_path.as_ref().to_owned();
-        try!(Target::file_mv(host, &self.path, &new_path));
-        self.path = new_path;
-        Ok(())
+        let outcome = Outcome::changed(&self.resource(), "mv").values(self.path.display(), new_path.display());
+        let outcome = try!(host.apply(outcome, |h| Target::file_mv(h, &self.path, &new_path)));
+
+        if !host.check_mode() {
+            self.path = new_path;
+        }
+
+        Ok(outcome)
     }
 
     /// Copy the file to a new path.
-    pub fn copy<P: AsRef<Path>>(&self, host: &mut Host, new_path: P) -> Result<()> {
+    pub fn copy<P: AsRef<Path>>(&self, host: &mut Host, new_path: P) -> Result<Outcome> {
         let new_path = new_path.as_ref().to_owned();
-        Target::file_copy(host, &self.path, &new_path)
+        let outcome = Outcome::changed(&self.resource(), "copy").values(self.path.display(), new_path.display());
+        host.apply(outcome, |h| Target::file_copy(h, &self.path, &new_path))
+    }
+
+    /// Get the file's metadata in a single call.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use inapi::{File, Host};
+    /// # let mut host = Host::connect("hosts/myhost.json").unwrap();
+    /// let file = File::new(&mut host, "/etc/hosts").unwrap();
+    /// let meta = file.metadata(&mut host).unwrap();
+    /// println!("{} bytes, modified {}", meta.size, meta.mtime);
+    /// ```
+    pub fn metadata(&self, host: &mut Host) -> Result<FileMetadata> {
+        Target::file_metadata(host, &self.path)
     }
 
     /// Get the file's owner.
@@ -138,8 +405,17 @@ impl File {
     }
 
     /// Set the file's owner.
-    pub fn set_owner(&self, host: &mut Host, user: &str, group: &str) -> Result<()> {
-        Target::file_set_owner(host, &self.path, user, group)
+    pub fn set_owner(&self, host: &mut Host, user: &str, group: &str) -> Result<Outcome> {
+        let owner = try!(self.get_owner(host));
+        let before = format!("{}:{}", owner.user_name, owner.group_name);
+        let after = format!("{}:{}", user, group);
+        let outcome = if before == after {
+            Outcome::unchanged(&self.resource(), "set_owner")
+        } else {
+            Outcome::changed(&self.resource(), "set_owner")
+        }.values(before, after);
+
+        host.apply(outcome, |h| Target::file_set_owner(h, &self.path, user, group))
     }
 
     /// Get the file's permissions mask.
@@ -148,11 +424,39 @@ impl File {
     }
 
     /// Set the file's permissions mask.
-    pub fn set_mode(&self, host: &mut Host, mode: u16) -> Result<()> {
-        Target::file_set_mode(host, &self.path, mode)
+    pub fn set_mode(&self, host: &mut Host, mode: u16) -> Result<Outcome> {
+        let current = try!(self.get_mode(host));
+        let outcome = if current == mode {
+            Outcome::unchanged(&self.resource(), "set_mode")
+        } else {
+            Outcome::changed(&self.resource(), "set_mode")
+        }.values(current, mode);
+
+        host.apply(outcome, |h| Target::file_set_mode(h, &self.path, mode))
+    }
+
+    fn resource(&self) -> String {
+        format!("file:{}", self.path.display())
     }
 }
 
+/// Calculate the SHA256 checksum of `reader`'s contents.
+#[doc(hidden)]
+pub fn checksum<R: Read>(reader: &mut R) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 8192];
+
+    loop {
+        let len = try!(reader.read(&mut buf));
+        if len == 0 {
+            break;
+        }
+        hasher.input(&buf[..len]);
+    }
+
+    Ok(hasher.result_str())
+}
+
 pub trait FileTarget<P: AsRef<Path>> {
     fn file_is_file(host: &mut Host, path: P) -> Result<bool>;
     fn file_exists(host: &mut Host, path: P) -> Result<bool>;
@@ -163,18 +467,24 @@ pub trait FileTarget<P: AsRef<Path>> {
     fn file_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()>;
     fn file_get_mode(host: &mut Host, path: P) -> Result<u16>;
     fn file_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()>;
+    fn file_hash(host: &mut Host, path: P) -> Result<Option<String>>;
+    fn file_upload_file(host: &mut Host, path: P, file: fs::File, options: Option<&[FileOptions]>) -> Result<()>;
+    fn file_download(host: &mut Host, path: P, writer: &mut Write) -> Result<()>;
+    fn file_edit(host: &mut Host, path: P, edit: &FileEdit) -> Result<bool>;
+    fn file_metadata(host: &mut Host, path: P) -> Result<FileMetadata>;
 }
 
 #[cfg(test)]
 mod tests {
-    #[cfg(feature = "remote-run")]
     use czmq::{ZMsg, ZSys};
     use host::Host;
-    #[cfg(feature = "remote-run")]
+    use outcome::Status;
+    use std::fs;
+    use std::io::{Read, Write};
     use std::thread;
     use super::*;
+    use tempdir::TempDir;
 
-    #[cfg(feature = "local-run")]
     #[test]
     fn test_new_ok() {
         let path: Option<String> = None;
@@ -183,7 +493,203 @@ mod tests {
         assert!(file.is_ok());
     }
 
-    #[cfg(feature = "remote-run")]
+    #[test]
+    fn test_checksum() {
+        let (sum, _) = FileContent::Text("Hello".into()).open().unwrap();
+        assert_eq!(sum, "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969");
+    }
+
+    #[test]
+    fn test_read_to_string() {
+        let tempdir = TempDir::new("test_file_read_to_string").unwrap();
+        let path = tempdir.path().join("file");
+        fs::File::create(&path).unwrap().write_all(b"Hello").unwrap();
+
+        let mut host = Host::local(None::<String>).unwrap();
+        let file = File::new(&mut host, &path).unwrap();
+        assert_eq!(file.read_to_string(&mut host).unwrap(), "Hello");
+
+        let download = tempdir.path().join("download");
+        assert!(file.download(&mut host, &download).is_ok());
+
+        let mut content = String::new();
+        fs::File::open(&download).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "Hello");
+
+        // A failed download leaves the existing file in place
+        fs::remove_file(&path).unwrap();
+        assert!(file.download(&mut host, &download).is_err());
+        assert_eq!(fs::read_dir(tempdir.path()).unwrap().count(), 1);
+
+        let mut content = String::new();
+        fs::File::open(&download).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "Hello");
+    }
+
+    #[test]
+    fn test_metadata() {
+        let tempdir = TempDir::new("test_file_metadata").unwrap();
+        let path = tempdir.path().join("file");
+        fs::File::create(&path).unwrap().write_all(b"Hello").unwrap();
+
+        let mut host = Host::local(None::<String>).unwrap();
+        let file = File::new(&mut host, &path).unwrap();
+        file.set_mode(&mut host, 640).unwrap();
+
+        let meta = file.metadata(&mut host).unwrap();
+        assert_eq!(meta.file_type, EntryType::File);
+        assert_eq!(meta.size, 5);
+        assert_eq!(meta.mode, 640);
+        assert_eq!(meta.nlink, 1);
+        assert_eq!(meta.checksum.unwrap(), "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969");
+    }
+
+    #[test]
+    fn test_ensure_local() {
+        let tempdir = TempDir::new("test_file_ensure").unwrap();
+        let path = tempdir.path().join("file");
+
+        let mut host = Host::local(None::<String>).unwrap();
+        let file = File::new(&mut host, &path).unwrap();
+
+        let outcomes = file.ensure(&mut host, FileSpec::new().content(FileContent::Text("Hello".into())).mode(600)).unwrap();
+        assert!(outcomes.iter().all(|o| o.status == Status::Changed));
+
+        let mut content = String::new();
+        fs::File::open(&path).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "Hello");
+        assert_eq!(file.get_mode(&mut host).unwrap(), 600);
+
+        let outcomes = file.ensure(&mut host, FileSpec::new().content(FileContent::Text("Hello".into())).mode(600)).unwrap();
+        assert!(outcomes.iter().all(|o| o.status == Status::Unchanged));
+    }
+
+    #[test]
+    fn test_ensure_missing_no_content() {
+        let tempdir = TempDir::new("test_file_ensure_missing").unwrap();
+        let path = tempdir.path().join("file");
+
+        let mut host = Host::local(None::<String>).unwrap();
+        let file = File::new(&mut host, &path).unwrap();
+
+        assert!(file.ensure(&mut host, FileSpec::new().mode(600)).is_err());
+        assert!(!path.exists());
+        assert!(host.report().outcomes.is_empty());
+    }
+
+    #[test]
+    fn test_ensure() {
+        ZSys::init();
+
+        let (client, mut server) = ZSys::create_pipe().unwrap();
+
+        let agent_mock = thread::spawn(move || {
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("file::is_file", msg.popstr().unwrap().unwrap());
+            assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("1").unwrap();
+            reply.send(&mut server).unwrap();
+
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("file::exists", msg.popstr().unwrap().unwrap());
+            assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("1").unwrap();
+            reply.send(&mut server).unwrap();
+
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("file::hash", msg.popstr().unwrap().unwrap());
+            assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969").unwrap();
+            reply.send(&mut server).unwrap();
+
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("file::get_mode", msg.popstr().unwrap().unwrap());
+            assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("644").unwrap();
+            reply.send(&mut server).unwrap();
+        });
+
+        let mut host = Host::test_new(None, Some(client), None, None);
+
+        let file = File::new(&mut host, "/tmp/test").unwrap();
+        let outcomes = file.ensure(&mut host, FileSpec::new().content(FileContent::Text("Hello".into())).mode(644)).unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|o| o.status == Status::Unchanged));
+
+        agent_mock.join().unwrap();
+    }
+
+    #[test]
+    fn test_edit() {
+        ZSys::init();
+
+        let (client, mut server) = ZSys::create_pipe().unwrap();
+
+        let agent_mock = thread::spawn(move || {
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("file::is_file", msg.popstr().unwrap().unwrap());
+            assert_eq!("/etc/hosts", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("1").unwrap();
+            reply.send(&mut server).unwrap();
+
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("file::edit", msg.popstr().unwrap().unwrap());
+            assert_eq!("/etc/hosts", msg.popstr().unwrap().unwrap());
+            assert_eq!("regex", msg.popstr().unwrap().unwrap());
+            assert_eq!("^127\\.0\\.0\\.1", msg.popstr().unwrap().unwrap());
+            assert_eq!("127.0.0.1 localhost", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("0").unwrap();
+            reply.send(&mut server).unwrap();
+        });
+
+        let mut host = Host::test_new(None, Some(client), None, None);
+
+        let file = File::new(&mut host, "/etc/hosts").unwrap();
+        let outcome = file.edit(&mut host, FileEdit::Regex("^127\\.0\\.0\\.1".into(), "127.0.0.1 localhost".into())).unwrap();
+        assert_eq!(outcome.status, Status::Unchanged);
+
+        agent_mock.join().unwrap();
+    }
+
+    #[test]
+    fn test_edit_local() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let tempdir = TempDir::new("test_file_edit_local").unwrap();
+        let path = tempdir.path().join("file");
+        let link = tempdir.path().join("link");
+        fs::File::create(&path).unwrap().write_all(b"a\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&path, &link).unwrap();
+
+        let mut host = Host::local(None::<String>).unwrap();
+        let file = File::new(&mut host, &link).unwrap();
+        assert!(file.edit(&mut host, FileEdit::LinePresent("b".into())).unwrap().is_changed());
+
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read_dir(tempdir.path()).unwrap().count(), 2);
+        assert_eq!(file.read_to_string(&mut host).unwrap(), "a\nb\n");
+    }
+
     #[test]
     fn test_new() {
         ZSys::init();
@@ -219,7 +725,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_exists() {
         ZSys::init();
@@ -254,7 +759,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_delete() {
         ZSys::init();
@@ -271,6 +775,15 @@ mod tests {
             reply.addstr("1").unwrap();
             reply.send(&mut server).unwrap();
 
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("file::exists", msg.popstr().unwrap().unwrap());
+            assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("1").unwrap();
+            reply.send(&mut server).unwrap();
+
             let msg = ZMsg::recv(&mut server).unwrap();
             assert_eq!("file::delete", msg.popstr().unwrap().unwrap());
             assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
@@ -281,12 +794,11 @@ mod tests {
         let mut host = Host::test_new(None, Some(client), None, None);
 
         let file = File::new(&mut host, "/tmp/test").unwrap();
-        assert!(file.delete(&mut host).is_ok());
+        assert!(file.delete(&mut host).unwrap().is_changed());
 
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_mv() {
         ZSys::init();
@@ -319,7 +831,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_copy() {
         ZSys::init();
@@ -352,7 +863,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_get_owner() {
         ZSys::init();
@@ -394,7 +904,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_set_owner() {
         ZSys::init();
@@ -411,6 +920,18 @@ mod tests {
             reply.addstr("1").unwrap();
             reply.send(&mut server).unwrap();
 
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("file::get_owner", msg.popstr().unwrap().unwrap());
+            assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("root").unwrap();
+            reply.addstr("0").unwrap();
+            reply.addstr("root").unwrap();
+            reply.addstr("0").unwrap();
+            reply.send(&mut server).unwrap();
+
             let msg = ZMsg::recv(&mut server).unwrap();
             assert_eq!("file::set_owner", msg.popstr().unwrap().unwrap());
             assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
@@ -423,12 +944,14 @@ mod tests {
         let mut host = Host::test_new(None, Some(client), None, None);
 
         let file = File::new(&mut host, "/tmp/test").unwrap();
-        assert!(file.set_owner(&mut host, "user", "group").is_ok());
+        let outcome = file.set_owner(&mut host, "user", "group").unwrap();
+        assert!(outcome.is_changed());
+        assert_eq!(outcome.before.unwrap(), "root:root");
+        assert_eq!(outcome.after.unwrap(), "user:group");
 
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_get_mode() {
         ZSys::init();
@@ -463,7 +986,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_set_mode() {
         ZSys::init();
@@ -480,6 +1002,15 @@ mod tests {
             reply.addstr("1").unwrap();
             reply.send(&mut server).unwrap();
 
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("file::get_mode", msg.popstr().unwrap().unwrap());
+            assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("755").unwrap();
+            reply.send(&mut server).unwrap();
+
             let msg = ZMsg::recv(&mut server).unwrap();
             assert_eq!("file::set_mode", msg.popstr().unwrap().unwrap());
             assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
@@ -491,7 +1022,51 @@ mod tests {
         let mut host = Host::test_new(None, Some(client), None, None);
 
         let file = File::new(&mut host, "/tmp/test").unwrap();
-        assert!(file.set_mode(&mut host, 644).is_ok());
+        assert!(file.set_mode(&mut host, 644).unwrap().is_changed());
+        assert_eq!(host.report().changed(), 1);
+
+        agent_mock.join().unwrap();
+    }
+
+    #[test]
+    fn test_set_mode_check_mode() {
+        ZSys::init();
+
+        let (client, mut server) = ZSys::create_pipe().unwrap();
+
+        let agent_mock = thread::spawn(move || {
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("file::is_file", msg.popstr().unwrap().unwrap());
+            assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("1").unwrap();
+            reply.send(&mut server).unwrap();
+
+            for _ in 0..2 {
+                let msg = ZMsg::recv(&mut server).unwrap();
+                assert_eq!("file::get_mode", msg.popstr().unwrap().unwrap());
+                assert_eq!("/tmp/test", msg.popstr().unwrap().unwrap());
+
+                let reply = ZMsg::new();
+                reply.addstr("Ok").unwrap();
+                reply.addstr("755").unwrap();
+                reply.send(&mut server).unwrap();
+            }
+        });
+
+        let mut host = Host::test_new(None, Some(client), None, None);
+        host.set_check_mode(true);
+
+        let file = File::new(&mut host, "/tmp/test").unwrap();
+        assert_eq!(file.set_mode(&mut host, 755).unwrap().status, Status::Unchanged);
+        assert_eq!(file.set_mode(&mut host, 644).unwrap().status, Status::Changed);
+
+        let report = host.report();
+        assert!(report.check_mode);
+        assert_eq!(report.unchanged(), 1);
+        assert_eq!(report.changed(), 1);
 
         agent_mock.join().unwrap();
     }
@@ -0,0 +1,176 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The primitive for managing groups on a managed host.
+//!
+//! # Examples
+//!
+//! Initialise a new Host using your managed host's IP address and
+//! port number:
+//!
+//! ```no_run
+//! # use inapi::Host;
+//! let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! ```
+//!
+//! Now create a group with a fixed GID:
+//!
+//! ```no_run
+//! # use inapi::{Group, Host};
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! let group = Group::new("www");
+//! group.create(&mut host, Some(2000)).unwrap();
+//! ```
+
+use error::{Error, Result};
+use host::Host;
+use outcome::Outcome;
+use target::Target;
+
+/// Attributes of an existing group.
+#[derive(Debug, PartialEq)]
+pub struct GroupInfo {
+    /// Group name
+    pub name: String,
+    /// Group GID
+    pub gid: u64,
+    /// Names of users that are members of the group
+    pub members: Vec<String>,
+}
+
+/// Container for operating on a group.
+pub struct Group {
+    /// Group name
+    name: String,
+}
+
+impl Group {
+    /// Create a new Group struct.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use inapi::Group;
+    /// let group = Group::new("www");
+    /// ```
+    pub fn new(name: &str) -> Group {
+        Group {
+            name: name.to_string(),
+        }
+    }
+
+    /// Check if the group exists.
+    pub fn exists(&self, host: &mut Host) -> Result<bool> {
+        Ok(try!(self.info(host)).is_some())
+    }
+
+    /// Get the group's attributes, or None if the group does not exist.
+    pub fn info(&self, host: &mut Host) -> Result<Option<GroupInfo>> {
+        Target::group_get(host, &self.name)
+    }
+
+    /// Create the group if it does not exist. If `gid` is None, the
+    /// OS picks the next available GID.
+    pub fn create(&self, host: &mut Host, gid: Option<u64>) -> Result<Outcome> {
+        let outcome = if try!(self.exists(host)) {
+            Outcome::unchanged(&self.resource(), "create")
+        } else {
+            let mut outcome = Outcome::changed(&self.resource(), "create");
+            outcome.after = gid.map(|g| format!("gid={}", g));
+            outcome
+        };
+
+        host.apply(outcome, |h| Target::group_create(h, &self.name, gid))
+    }
+
+    /// Change the group's GID.
+    pub fn set_gid(&self, host: &mut Host, gid: u64) -> Result<Outcome> {
+        let info = match try!(self.info(host)) {
+            Some(info) => info,
+            None => return Err(Error::Generic(format!("Group {} does not exist", self.name))),
+        };
+
+        let outcome = if info.gid == gid {
+            Outcome::unchanged(&self.resource(), "set_gid")
+        } else {
+            Outcome::changed(&self.resource(), "set_gid").values(info.gid, gid)
+        };
+
+        host.apply(outcome, |h| Target::group_modify(h, &self.name, gid))
+    }
+
+    /// Delete the group if it exists.
+    pub fn delete(&self, host: &mut Host) -> Result<Outcome> {
+        let outcome = if try!(self.exists(host)) {
+            Outcome::changed(&self.resource(), "delete")
+        } else {
+            Outcome::unchanged(&self.resource(), "delete")
+        };
+
+        host.apply(outcome, |h| Target::group_delete(h, &self.name))
+    }
+
+    fn resource(&self) -> String {
+        format!("group:{}", self.name)
+    }
+}
+
+pub trait GroupTarget {
+    fn group_get(host: &mut Host, name: &str) -> Result<Option<GroupInfo>>;
+    fn group_create(host: &mut Host, name: &str, gid: Option<u64>) -> Result<()>;
+    fn group_modify(host: &mut Host, name: &str, gid: u64) -> Result<()>;
+    fn group_delete(host: &mut Host, name: &str) -> Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use czmq::{ZMsg, ZSys};
+    use host::Host;
+    use std::thread;
+    use super::*;
+
+    #[test]
+    fn test_create() {
+        ZSys::init();
+
+        let (client, mut server) = ZSys::create_pipe().unwrap();
+
+        let agent_mock = thread::spawn(move || {
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("group::get", msg.popstr().unwrap().unwrap());
+            assert_eq!("www", msg.popstr().unwrap().unwrap());
+
+            server.send_str("Ok").unwrap();
+
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("group::create", msg.popstr().unwrap().unwrap());
+            assert_eq!("www", msg.popstr().unwrap().unwrap());
+            assert_eq!("2000", msg.popstr().unwrap().unwrap());
+
+            server.send_str("Ok").unwrap();
+
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("group::get", msg.popstr().unwrap().unwrap());
+            assert_eq!("www", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("2000").unwrap();
+            reply.addstr("").unwrap();
+            reply.send(&mut server).unwrap();
+        });
+
+        let mut host = Host::test_new(None, Some(client), None, None);
+
+        let group = Group::new("www");
+        assert!(group.create(&mut host, Some(2000)).unwrap().is_changed());
+        assert!(!group.create(&mut host, Some(2000)).unwrap().is_changed());
+
+        agent_mock.join().unwrap();
+    }
+}
@@ -11,7 +11,6 @@
 use error::{Error, self};
 use ffi_helpers::{Ffi__Array, Leaky};
 use libc::{c_char, int8_t};
-#[cfg(feature = "remote-run")]
 use libc::{uint8_t, uint32_t};
 use serde_json::Value;
 use std::{mem, ptr};
@@ -32,7 +31,6 @@ pub enum Ffi__DataType {
     Object,
 }
 
-#[cfg(feature = "local-run")]
 #[no_mangle]
 pub extern "C" fn host_local(path_ptr: *const c_char) -> *mut Host {
     let path = if path_ptr.is_null() {
@@ -45,7 +43,6 @@ pub extern "C" fn host_local(path_ptr: *const c_char) -> *mut Host {
     Box::into_raw(Box::new(host))
 }
 
-#[cfg(feature = "remote-run")]
 #[no_mangle]
 pub extern "C" fn host_connect(path_ptr: *const c_char) -> *mut Host {
     let path = trynull!(ptrtostr!(path_ptr, "path string"));
@@ -53,7 +50,6 @@ pub extern "C" fn host_connect(path_ptr: *const c_char) -> *mut Host {
     Box::into_raw(Box::new(host))
 }
 
-#[cfg(feature = "remote-run")]
 #[no_mangle]
 pub extern "C" fn host_connect_endpoint(hostname_ptr: *const c_char,
                                         api_port: uint32_t,
@@ -63,7 +59,6 @@ pub extern "C" fn host_connect_endpoint(hostname_ptr: *const c_char,
     Box::into_raw(Box::new(host))
 }
 
-#[cfg(feature = "remote-run")]
 #[no_mangle]
 pub extern "C" fn host_connect_payload(api_endpoint_ptr: *const c_char, file_endpoint_ptr: *const c_char) -> *mut Host {
     let api_endpoint = trynull!(ptrtostr!(api_endpoint_ptr, "api endpoint string"));
@@ -79,7 +74,6 @@ pub extern "C" fn host_data(host_ptr: *mut Host) -> *const c_void {
     data_ref as *const c_void
 }
 
-#[cfg(feature = "remote-run")]
 #[no_mangle]
 pub extern "C" fn host_close(host_ptr: *mut Host) -> uint8_t {
     tryrc!(boxptr!(host_ptr, "Host pointer"));
@@ -241,18 +235,11 @@ mod tests {
     use ffi_helpers::Ffi__Array;
     use host::Host;
     use libc::c_char;
-    #[cfg(feature = "remote-run")]
     use serde_json;
     use std::ffi::CString;
-    #[cfg(feature = "local-run")]
-    use std::fs::File;
-    #[cfg(feature = "local-run")]
-    use std::io::Write;
     use std::os::raw::c_void;
     use std::ptr;
     use super::*;
-    #[cfg(feature = "local-run")]
-    use tempdir::TempDir;
 
     #[test]
     fn test_open_get_value() {
@@ -357,10 +344,8 @@ mod tests {
         check_array(a1);
     }
 
-    #[cfg(feature = "local-run")]
     fn check_array(v: Vec<&str>) {
         let mut iter = v.into_iter();
-        assert_eq!(iter.next().unwrap(), "_telemetry");
         assert_eq!(iter.next().unwrap(), "array");
         assert_eq!(iter.next().unwrap(), "bool");
         assert_eq!(iter.next().unwrap(), "f64");
@@ -370,44 +355,6 @@ mod tests {
         assert_eq!(iter.next().unwrap(), "u64");
     }
 
-    #[cfg(feature = "remote-run")]
-    fn check_array(v: Vec<&str>) {
-        let mut iter = v.into_iter();
-        assert_eq!(iter.next().unwrap(), "array");
-        assert_eq!(iter.next().unwrap(), "bool");
-        assert_eq!(iter.next().unwrap(), "f64");
-        assert_eq!(iter.next().unwrap(), "i64");
-        assert_eq!(iter.next().unwrap(), "obj");
-        assert_eq!(iter.next().unwrap(), "string");
-        assert_eq!(iter.next().unwrap(), "u64");
-    }
-
-    #[cfg(feature = "local-run")]
-    fn create_host() -> *mut Host {
-        let td = TempDir::new("test_data_ffi").unwrap();
-        let mut path = td.path().to_owned();
-        path.push("data.json");
-
-        let mut fh = File::create(&path).unwrap();
-        fh.write_all(b"{
-            \"bool\": true,
-            \"i64\": -5,
-            \"u64\": 10,
-            \"f64\": 1.2,
-            \"string\": \"abc\",
-            \"array\": [
-                123,
-                \"def\"
-            ],
-            \"obj\": {
-                \"a\": \"b\"
-            }
-        }").unwrap();
-
-        Box::into_raw(Box::new(Host::local(Some(&path)).unwrap()))
-    }
-
-    #[cfg(feature = "remote-run")]
     fn create_host() -> *mut Host {
         let v = serde_json::from_str("{
             \"bool\": true,
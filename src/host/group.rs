@@ -0,0 +1,258 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Run operations against many hosts concurrently.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use inapi::{Host, HostGroup, Package};
+//! let (mut group, errors) = HostGroup::connect(vec!["hosts/web1.json", "hosts/web2.json"], 10);
+//! assert!(errors.is_empty());
+//! group.add("localhost", Host::local(None::<String>).unwrap());
+//!
+//! let results = group.run(|host| {
+//!     let mut package = try!(Package::new(host, "nginx", None));
+//!     package.install(host)
+//! });
+//!
+//! for (name, result) in results {
+//!     if let Err(e) = result {
+//!         println!("{} failed: {}", name, e);
+//!     }
+//! }
+//! ```
+
+use error::{Error, Result};
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::channel;
+use std::thread;
+use super::{ConnectOptions, Host};
+use super::inventory::Inventory;
+
+/// A named set of Hosts that operations are run against
+/// concurrently.
+pub struct HostGroup {
+    /// Hosts and their names, in the order they were added
+    hosts: Vec<(String, Host)>,
+    /// Maximum number of hosts to operate on at once
+    parallelism: usize,
+}
+
+impl HostGroup {
+    /// Create an empty HostGroup that operates on at most
+    /// `parallelism` hosts at once.
+    pub fn new(parallelism: usize) -> HostGroup {
+        HostGroup {
+            hosts: Vec::new(),
+            parallelism: if parallelism == 0 { 1 } else { parallelism },
+        }
+    }
+
+    /// Connect to the hosts described by each data file in `paths`,
+    /// with at most `parallelism` connections in flight at once.
+    ///
+    /// Hosts that connect successfully are named after their data
+    /// files' paths. Hosts that fail to connect are returned with
+    /// their errors.
+    pub fn connect<P: AsRef<Path>>(paths: Vec<P>, parallelism: usize) -> (HostGroup, Vec<(String, Error)>) {
+        let names: Vec<String> = paths.iter().map(|p| p.as_ref().to_string_lossy().into_owned()).collect();
+        let results = run_parallel(names.clone(), parallelism, |name| ((), Host::connect(&name)));
+
+        Self::collect(names, results, parallelism)
+    }
+
+    /// Connect to the inventory hosts that match `pattern`, with at
+    /// most `parallelism` connections in flight at once.
+    ///
+    /// Hosts that connect successfully are named after their
+    /// inventory names. Hosts that fail to connect are returned with
+    /// their errors.
+    pub fn connect_inventory(inventory: &Inventory, pattern: &str, parallelism: usize) -> Result<(HostGroup, Vec<(String, Error)>)> {
+        Self::connect_inventory_with(inventory, pattern, parallelism, &ConnectOptions::default())
+    }
+
+    /// Connect to the inventory hosts that match `pattern`, using the
+    /// given timeouts and retry policy for each host.
+    pub fn connect_inventory_with(inventory: &Inventory, pattern: &str, parallelism: usize, opts: &ConnectOptions) -> Result<(HostGroup, Vec<(String, Error)>)> {
+        let names: Vec<String> = try!(inventory.select_hosts(pattern)).into_iter().map(|h| h.to_string()).collect();
+        let inventory = Arc::new(inventory.clone());
+        let opts = *opts;
+        let results = run_parallel(names.clone(), parallelism, move |name| ((), Host::connect_inventory_with(&inventory, &name, &opts)));
+
+        Ok(Self::collect(names, results, parallelism))
+    }
+
+    /// Add a Host to the group.
+    pub fn add(&mut self, name: &str, host: Host) {
+        self.hosts.push((name.to_string(), host));
+    }
+
+    /// Remove a Host from the group.
+    pub fn remove(&mut self, name: &str) -> Option<Host> {
+        match self.hosts.iter().position(|&(ref n, _)| n == name) {
+            Some(i) => Some(self.hosts.remove(i).1),
+            None => None,
+        }
+    }
+
+    /// Get the names of the hosts in the group.
+    pub fn names(&self) -> Vec<&str> {
+        self.hosts.iter().map(|&(ref n, _)| n.as_str()).collect()
+    }
+
+    /// Get the number of hosts in the group.
+    pub fn len(&self) -> usize {
+        self.hosts.len()
+    }
+
+    /// Check whether the group has no hosts.
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+
+    /// Get a Host by name.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Host> {
+        self.hosts.iter_mut().find(|&&mut (ref n, _)| n == name).map(|&mut (_, ref mut h)| h)
+    }
+
+    /// Run `f` against every host, with at most `parallelism` hosts
+    /// running at once.
+    ///
+    /// Returns each host's name and result, in the order the hosts
+    /// were added. A panic in `f` is returned as an error for that
+    /// host and does not affect the others. Every host stays in the
+    /// group, including those whose `f` panicked.
+    pub fn run<F, T>(&mut self, f: F) -> Vec<(String, Result<T>)>
+        where F: Fn(&mut Host) -> Result<T> + Send + Sync + 'static,
+              T: Send + 'static
+    {
+        let (names, hosts): (Vec<String>, Vec<Host>) = self.hosts.drain(..).unzip();
+        let results = run_parallel(hosts, self.parallelism, move |mut host| {
+            // Catch panics here, rather than in run_parallel, so that
+            // the Host is not lost when `f` panics.
+            let result = match panic::catch_unwind(AssertUnwindSafe(|| f(&mut host))) {
+                Ok(result) => result,
+                Err(e) => Err(Error::from(e)),
+            };
+            (Some(host), result)
+        });
+
+        let mut output = Vec::new();
+        for (name, (host, result)) in names.into_iter().zip(results) {
+            if let Some(host) = host {
+                self.hosts.push((name.clone(), host));
+            }
+            output.push((name, result));
+        }
+
+        output
+    }
+
+    fn collect(names: Vec<String>, results: Vec<((), Result<Host>)>, parallelism: usize) -> (HostGroup, Vec<(String, Error)>) {
+        let mut group = HostGroup::new(parallelism);
+        let mut errors = Vec::new();
+        for (name, (_, result)) in names.into_iter().zip(results) {
+            match result {
+                Ok(host) => group.add(&name, host),
+                Err(e) => errors.push((name, e)),
+            }
+        }
+
+        (group, errors)
+    }
+}
+
+/// Run `f` over every item on a pool of at most `parallelism`
+/// threads, returning the results in the order of `items`.
+fn run_parallel<I, O, R, F>(items: Vec<I>, parallelism: usize, f: F) -> Vec<(O, Result<R>)>
+    where I: Send + 'static,
+          O: Send + 'static + Default,
+          R: Send + 'static,
+          F: Fn(I) -> (O, Result<R>) + Send + Sync + 'static
+{
+    let len = items.len();
+    let queue = Arc::new(Mutex::new(items.into_iter().enumerate().collect::<VecDeque<_>>()));
+    let f = Arc::new(f);
+    let (tx, rx) = channel();
+
+    let workers: Vec<_> = (0..parallelism.max(1).min(len)).map(|_| {
+        let queue = queue.clone();
+        let f = f.clone();
+        let tx = tx.clone();
+
+        thread::spawn(move || {
+            loop {
+                let next = queue.lock().unwrap().pop_front();
+                let (index, item) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+
+                let result = match panic::catch_unwind(AssertUnwindSafe(|| f(item))) {
+                    Ok(result) => result,
+                    Err(e) => (O::default(), Err(Error::from(e))),
+                };
+
+                if tx.send((index, result)).is_err() {
+                    break;
+                }
+            }
+        })
+    }).collect();
+    drop(tx);
+
+    let mut results: Vec<Option<(O, Result<R>)>> = (0..len).map(|_| None).collect();
+    for (index, result) in rx {
+        results[index] = Some(result);
+    }
+
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    results.into_iter().map(|r| r.unwrap_or((O::default(), Err(Error::Generic("Worker thread exited unexpectedly".into()))))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use error::Error;
+    use Host;
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let mut group = HostGroup::new(2);
+        assert!(group.is_empty());
+        for name in &["web1", "web2", "web3"] {
+            group.add(name, test_host());
+        }
+        assert_eq!(group.len(), 3);
+        assert!(!group.is_empty());
+
+        let results = group.run(|_| Ok(1));
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|&(_, ref r)| *r.as_ref().unwrap() == 1));
+        assert_eq!(group.names(), vec!["web1", "web2", "web3"]);
+
+        let results = group.run(|_| -> Result<()> { panic!("{}", "boom") });
+        assert_eq!(results[0].0, "web1");
+        match results[0].1 {
+            Err(Error::Generic(ref e)) => assert_eq!(e, "boom"),
+            _ => panic!("Expected panic to be returned as an error"),
+        }
+        assert_eq!(group.names(), vec!["web1", "web2", "web3"]);
+    }
+
+    fn test_host() -> Host {
+        Host::test_new(None, None, None, None)
+    }
+}
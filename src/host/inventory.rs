@@ -0,0 +1,369 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Parser for Intecture inventory files.
+//!
+//! An inventory names your hosts, points each at its data file and
+//! arranges them into groups. Groups can contain hosts and other
+//! groups, and can carry data of their own:
+//!
+//! ```json
+//! {
+//!     "hosts": {
+//!         "web1": "hosts/web1.json",
+//!         "web2": "hosts/web2.json",
+//!         "db1": "hosts/db1.json"
+//!     },
+//!     "groups": {
+//!         "web": {
+//!             "hosts": [ "web1", "web2" ],
+//!             "data": { "nginx": { "workers": 4 } }
+//!         },
+//!         "prod": {
+//!             "children": [ "web" ],
+//!             "hosts": [ "db1" ],
+//!             "data": { "environment": "production" }
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Data file paths are relative to the `data/` directory, as with
+//! `Host::connect()`. When a host's data is loaded, group data is
+//! merged beneath it using the usual data file precedence rules: a
+//! host's own data file beats the data of its groups, and a group's
+//! data beats the data of the groups that contain it.
+//!
+//! # Patterns
+//!
+//! Hosts are selected using patterns made up of host names, group
+//! names and the special group `all`, separated by colons:
+//!
+//! - `web:db` - hosts in either `web` or `db`
+//! - `web:&prod` - hosts in `web` that are also in `prod`
+//! - `web:!web3` - hosts in `web` except `web3`
+//!
+//! Plain terms are combined first, then intersected with `&` terms,
+//! then `!` terms are removed.
+
+use error::{Error, Result};
+use serde_json::{self, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use super::data;
+
+/// A set of hosts and groups loaded from an inventory file.
+#[derive(Clone, Debug)]
+pub struct Inventory {
+    /// Host names and their data file paths
+    hosts: BTreeMap<String, PathBuf>,
+    /// Group names and their definitions
+    groups: BTreeMap<String, InventoryGroup>,
+}
+
+#[derive(Clone, Debug)]
+struct InventoryGroup {
+    /// Names of hosts that are direct members of the group
+    hosts: Vec<String>,
+    /// Names of groups nested inside the group
+    children: Vec<String>,
+    /// Data shared by every host in the group
+    data: Value,
+}
+
+impl Inventory {
+    /// Load an inventory file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Inventory> {
+        let mut fh = try!(fs::File::open(path.as_ref()));
+        let value: Value = try!(serde_json::from_reader(&mut fh));
+        Self::from_value(&value)
+    }
+
+    /// Create an inventory from a parsed inventory file.
+    pub fn from_value(value: &Value) -> Result<Inventory> {
+        let mut hosts = BTreeMap::new();
+        if let Some(h) = value.find("hosts") {
+            let h = try!(needobj!(h));
+            for (name, path) in h {
+                hosts.insert(name.clone(), PathBuf::from(try!(needstr!(path))));
+            }
+        }
+
+        let mut groups = BTreeMap::new();
+        if let Some(g) = value.find("groups") {
+            let g = try!(needobj!(g));
+            for (name, group) in g {
+                if name == "all" {
+                    return Err(Error::Generic("Group name `all` is reserved".into()));
+                }
+
+                groups.insert(name.clone(), InventoryGroup {
+                    hosts: try!(str_array(group, "hosts")),
+                    children: try!(str_array(group, "children")),
+                    data: group.find("data").cloned().unwrap_or(Value::Null),
+                });
+            }
+        }
+
+        let inventory = Inventory {
+            hosts: hosts,
+            groups: groups,
+        };
+
+        for (name, group) in &inventory.groups {
+            for host in &group.hosts {
+                if !inventory.hosts.contains_key(host) {
+                    return Err(Error::Generic(format!("Group {} contains unknown host {}", name, host)));
+                }
+            }
+
+            for child in &group.children {
+                if !inventory.groups.contains_key(child) {
+                    return Err(Error::Generic(format!("Group {} contains unknown group {}", name, child)));
+                }
+            }
+
+            // Fail early on cyclic groups, rather than whenever a
+            // host in the cycle is loaded.
+            try!(inventory.depth(name, &mut Vec::new()));
+        }
+
+        Ok(inventory)
+    }
+
+    /// Get the names of all hosts in the inventory.
+    pub fn hosts(&self) -> Vec<&str> {
+        self.hosts.keys().map(|h| h.as_str()).collect()
+    }
+
+    /// Get the path to a host's data file.
+    pub fn host_path(&self, host: &str) -> Result<&Path> {
+        match self.hosts.get(host) {
+            Some(p) => Ok(p.as_path()),
+            None => Err(Error::Generic(format!("Unknown host {}", host))),
+        }
+    }
+
+    /// Get the names of the groups a host belongs to, including
+    /// groups that contain those groups. Groups are ordered from
+    /// least to most specific.
+    pub fn groups_of(&self, host: &str) -> Result<Vec<&str>> {
+        try!(self.host_path(host));
+
+        let mut found = BTreeSet::new();
+        for (name, group) in &self.groups {
+            if group.hosts.iter().any(|h| h == host) {
+                self.ancestors(name, &mut found);
+            }
+        }
+
+        let mut groups = Vec::new();
+        for name in found {
+            groups.push((try!(self.depth(name, &mut Vec::new())), name));
+        }
+        groups.sort();
+
+        Ok(groups.into_iter().map(|(_, name)| name).collect())
+    }
+
+    /// Get the names of the hosts that match a pattern.
+    pub fn select_hosts(&self, pattern: &str) -> Result<Vec<&str>> {
+        let mut include = BTreeSet::new();
+        let mut intersect = Vec::new();
+        let mut exclude = BTreeSet::new();
+
+        for term in pattern.split(':').map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            if term.starts_with('&') {
+                intersect.push(try!(self.resolve(&term[1..])));
+            } else if term.starts_with('!') {
+                exclude.extend(try!(self.resolve(&term[1..])));
+            } else {
+                include.extend(try!(self.resolve(term)));
+            }
+        }
+
+        for set in intersect {
+            include = include.intersection(&set).cloned().collect();
+        }
+
+        Ok(include.difference(&exclude).cloned().collect())
+    }
+
+    /// Get the data file paths of the hosts that match a pattern.
+    pub fn select(&self, pattern: &str) -> Result<Vec<&Path>> {
+        let mut paths = Vec::new();
+        for host in try!(self.select_hosts(pattern)) {
+            paths.push(try!(self.host_path(host)));
+        }
+        Ok(paths)
+    }
+
+    /// Load a host's data file, merged with the data of its groups.
+    pub fn data(&self, host: &str) -> Result<Value> {
+        self.merge_data(host, Value::Null)
+    }
+
+    /// Load a host's data file and its groups' data, merging them on
+    /// top of `last_value`.
+    #[doc(hidden)]
+    pub fn merge_data(&self, host: &str, mut last_value: Value) -> Result<Value> {
+        let value = try!(data::open(try!(self.host_path(host))));
+
+        for group in try!(self.groups_of(host)) {
+            let group_data = self.groups.get(group).unwrap().data.clone();
+            if !group_data.is_null() {
+                last_value = try!(data::merge(group_data, last_value));
+            }
+        }
+
+        data::merge(value, last_value)
+    }
+
+    fn resolve(&self, term: &str) -> Result<BTreeSet<&str>> {
+        let mut hosts = BTreeSet::new();
+
+        if term == "all" {
+            hosts.extend(self.hosts.keys().map(|h| h.as_str()));
+        }
+        else if let Some(group) = self.groups.get(term) {
+            hosts.extend(group.hosts.iter().map(|h| h.as_str()));
+            for child in &group.children {
+                hosts.extend(try!(self.resolve(child)));
+            }
+        }
+        else if let Some((name, _)) = self.hosts.iter().find(|&(h, _)| h == term) {
+            hosts.insert(name.as_str());
+        }
+        else {
+            return Err(Error::Generic(format!("Unknown host or group {}", term)));
+        }
+
+        Ok(hosts)
+    }
+
+    // Add a group and every group containing it to `found`.
+    fn ancestors<'a>(&'a self, group: &'a str, found: &mut BTreeSet<&'a str>) {
+        if found.insert(group) {
+            for (name, g) in &self.groups {
+                if g.children.iter().any(|c| c == group) {
+                    self.ancestors(name, found);
+                }
+            }
+        }
+    }
+
+    // Get the number of levels of groups above a group.
+    fn depth<'a>(&'a self, group: &'a str, path: &mut Vec<&'a str>) -> Result<usize> {
+        if path.contains(&group) {
+            return Err(Error::Generic(format!("Group {} contains itself", group)));
+        }
+
+        path.push(group);
+        let mut depth = 0;
+        for (name, g) in &self.groups {
+            if g.children.iter().any(|c| c == group) {
+                depth = depth.max(try!(self.depth(name, path)) + 1);
+            }
+        }
+        path.pop();
+
+        Ok(depth)
+    }
+}
+
+fn str_array(value: &Value, key: &str) -> Result<Vec<String>> {
+    match value.find(key) {
+        Some(a) => {
+            let mut v = Vec::new();
+            for s in try!(needarray!(a)) {
+                v.push(try!(needstr!(s)).to_string());
+            }
+            Ok(v)
+        },
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{self, Value};
+    use std::fs;
+    use std::io::Write;
+    use std::path::Path;
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_select() {
+        let inventory = Inventory::from_value(&inventory_value("/tmp")).unwrap();
+
+        assert_eq!(inventory.select_hosts("all").unwrap(), vec!["db1", "web1", "web2", "web3"]);
+        assert_eq!(inventory.select_hosts("web:!web3").unwrap(), vec!["web1", "web2"]);
+        assert_eq!(inventory.select_hosts("web:&prod").unwrap(), vec!["web1", "web2"]);
+        assert_eq!(inventory.select_hosts("db1:web3").unwrap(), vec!["db1", "web3"]);
+        assert_eq!(inventory.select("prod:!web").unwrap(), vec![Path::new("/tmp/db1.json")]);
+        assert!(inventory.select_hosts("web:!nope").is_err());
+
+        assert_eq!(inventory.groups_of("web1").unwrap(), vec!["frontend", "prod", "web"]);
+        assert_eq!(inventory.groups_of("web3").unwrap(), vec!["frontend", "web"]);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let value: Value = serde_json::from_str("{
+            \"groups\": {
+                \"a\": { \"children\": [ \"b\" ] },
+                \"b\": { \"children\": [ \"a\" ] }
+            }
+        }").unwrap();
+        assert!(Inventory::from_value(&value).is_err());
+    }
+
+    #[test]
+    fn test_data() {
+        let tempdir = TempDir::new("inventory_test").unwrap();
+        let path = tempdir.path().to_str().unwrap();
+
+        let mut fh = fs::File::create(tempdir.path().join("web1.json")).unwrap();
+        fh.write_all(b"{ \"hostname\": \"web1\", \"workers\": 8 }").unwrap();
+
+        let inventory = Inventory::from_value(&inventory_value(path)).unwrap();
+        let data = inventory.data("web1").unwrap();
+
+        assert_eq!(data.find("hostname").unwrap().as_str(), Some("web1"));
+        assert_eq!(data.find("workers").unwrap().as_u64(), Some(8));
+        assert_eq!(data.find("role").unwrap().as_str(), Some("web"));
+        assert_eq!(data.find("environment").unwrap().as_str(), Some("production"));
+    }
+
+    fn inventory_value(path: &str) -> Value {
+        serde_json::from_str(&format!("{{
+            \"hosts\": {{
+                \"web1\": \"{0}/web1.json\",
+                \"web2\": \"{0}/web2.json\",
+                \"web3\": \"{0}/web3.json\",
+                \"db1\": \"{0}/db1.json\"
+            }},
+            \"groups\": {{
+                \"web\": {{
+                    \"hosts\": [ \"web1\", \"web2\", \"web3\" ],
+                    \"data\": {{ \"workers\": 4, \"role\": \"web\" }}
+                }},
+                \"frontend\": {{
+                    \"children\": [ \"web\" ],
+                    \"data\": {{ \"role\": \"frontend\" }}
+                }},
+                \"prod\": {{
+                    \"hosts\": [ \"web1\", \"web2\", \"db1\" ],
+                    \"data\": {{ \"environment\": \"production\", \"role\": \"prod\" }}
+                }}
+            }}
+        }}", path)).unwrap()
+    }
+}
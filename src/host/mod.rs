@@ -12,9 +12,7 @@
 //!
 //! ```no_run
 //! # use inapi::{Command, Host};
-#![cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! let mut host = Host::connect("hosts/myhost.json").unwrap();
 //!
 //! let cmd = Command::new("whoami");
 //! let result = cmd.exec(&mut host).unwrap();
@@ -23,51 +21,72 @@
 #[macro_use]
 pub mod data;
 pub mod ffi;
+pub mod group;
+pub mod inventory;
+pub mod options;
+pub mod privilege;
 pub mod telemetry;
+pub mod transport;
 
+pub use self::options::ConnectOptions;
+pub use self::privilege::{Become, BecomeMethod};
 pub use self::telemetry::TelemetryTarget;
+pub use self::transport::{Message, Transport};
 
-#[cfg(feature = "remote-run")]
-use czmq::{ZCert, ZMsg, ZSock, SocketType};
-#[cfg(feature = "remote-run")]
+use czmq::{ZSock, SocketType};
 use error::Error;
 use error::Result;
-#[cfg(feature = "remote-run")]
+use outcome::{self, Outcome, Report};
 use serde_json;
 use serde_json::Value;
-#[cfg(feature = "remote-run")]
+use std::fs;
+use std::io::Write;
 use std::mem;
 use std::path::Path;
-use std::rc::Rc;
-#[cfg(feature = "remote-run")]
-use zfilexfer;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+#[cfg(feature = "ssh")]
+use self::transport::{SshAuth, SshHostKey, SshTransport};
+use self::transport::{Container, ContainerTransport, ZmqTransport};
+use zfilexfer::FileOptions;
 
-#[cfg(feature = "local-run")]
-/// Representation of a managed host.
-pub struct Host {
-    /// Data for host, comprising data files and telemetry
-    data: Rc<Value>,
-}
-
-#[cfg(feature = "remote-run")]
 /// Representation of a managed host.
+///
+/// A Host either runs primitives against the machine it was created
+/// on (see `Host::local()`), or sends them to a remote host over a
+/// Transport (see `Host::connect()`). Both kinds of Host can be used
+/// side by side in the same program.
 pub struct Host {
     /// Hostname or IP of managed host
     pub hostname: String,
-    /// API socket
-    api_sock: Option<ZSock>,
-    /// File transfer socket
-    file_sock: Option<ZSock>,
+    /// Connection to the host's Agent, or None for the local machine
+    transport: Option<Box<Transport>>,
+    /// Connection timeouts and retry policy
+    options: ConnectOptions,
+    /// When the Agent last replied to a request
+    last_reply: Instant,
     /// Data for host, comprising data files and telemetry
-    data: Rc<Value>,
+    data: Arc<Value>,
+    /// Report changes instead of applying them
+    check_mode: bool,
+    /// User to run operations as, or None for the connecting user
+    escalation: Option<Become>,
+    /// Outcomes of operations run against this host
+    report: Arc<Mutex<Report>>,
 }
 
 impl Host {
-    #[cfg(feature = "local-run")]
     /// Create a new Host connected to localhost.
     pub fn local<P: AsRef<Path>>(path: Option<P>) -> Result<Host> {
         let mut me = Host {
-            data: Rc::new(Value::Null),
+            hostname: "localhost".into(),
+            transport: None,
+            options: ConnectOptions::default(),
+            last_reply: Instant::now(),
+            data: Arc::new(Value::Null),
+            check_mode: false,
+            escalation: None,
+            report: Arc::new(Mutex::new(Report::new())),
         };
 
         let telemetry = try!(telemetry::Telemetry::init(&mut me));
@@ -75,66 +94,164 @@ impl Host {
         match path {
             Some(p) => {
                 let value = try!(data::open(p));
-                me.data = Rc::new(try!(data::merge(value, telemetry)));
+                me.data = Arc::new(try!(data::merge(value, telemetry)));
             },
-            None => me.data = Rc::new(telemetry),
+            None => me.data = Arc::new(telemetry),
         }
 
         Ok(me)
     }
 
-    #[cfg(feature = "remote-run")]
     /// Create a new Host connected to the endpoint specified in the
     /// data file. This function expects to find the following keys
     /// in the root namespace: "hostname", "api_port", "file_port".
+    ///
+    /// Hosts that do not run an Agent can be managed over SSH instead
+    /// by setting "transport" to "ssh" (requires the `ssh` feature).
+    /// The "ssh" namespace may then set "port", "user" (defaults to
+    /// root) and either "key" and "passphrase", or "password".
+    /// Otherwise the local SSH agent is used to log in. The host's key
+    /// must be in "known_hosts" (defaults to `~/.ssh/known_hosts`), or
+    /// match the SHA-1 fingerprint in "host_key". Setting
+    /// "check_host_key" to false skips the check, which lets anyone
+    /// between this machine and the host pose as it.
+    ///
+    /// Chroots and containers on the local machine can be managed by
+    /// setting "transport" to "chroot", "nsenter", "docker" or
+    /// "podman", and "container" to the root directory, process ID
+    /// or container name respectively.
     pub fn connect<P: AsRef<Path>>(path: P) -> Result<Host> {
+        Self::connect_with(path, &ConnectOptions::default())
+    }
+
+    /// Create a new Host connected to the endpoint specified in the
+    /// data file, using the given timeouts and retry policy.
+    pub fn connect_with<P: AsRef<Path>>(path: P, opts: &ConnectOptions) -> Result<Host> {
         let value = try!(data::open(path.as_ref()));
-        let mut me = try!(Self::connect_endpoint(try!(needstr!(value => "/hostname")),
-                                                 try!(needu64!(value => "/api_port")) as u32,
-                                                 try!(needu64!(value => "/file_port")) as u32));
+        let mut me = try!(Self::connect_data(&value, opts));
+        let telemetry = me.take_data();
+        me.data = Arc::new(try!(data::merge(value, telemetry)));
 
-        let mut telemetry = Rc::new(Value::Null);
-        mem::swap(&mut telemetry, &mut me.data);
-        // We can use unwrap() here safely as we can guarantee that
-        // there is only one strong reference to telemetry.
-        me.data = Rc::new(try!(data::merge(value, Rc::try_unwrap(telemetry).unwrap())));
+        Ok(me)
+    }
+
+    /// Create a new Host connected to the endpoint specified in the
+    /// host's inventory data. The host's data file is merged with
+    /// the data of the groups it belongs to.
+    pub fn connect_inventory(inventory: &inventory::Inventory, host: &str) -> Result<Host> {
+        Self::connect_inventory_with(inventory, host, &ConnectOptions::default())
+    }
+
+    /// Create a new Host connected to the endpoint specified in the
+    /// host's inventory data, using the given timeouts and retry
+    /// policy.
+    pub fn connect_inventory_with(inventory: &inventory::Inventory, host: &str, opts: &ConnectOptions) -> Result<Host> {
+        let value = try!(inventory.data(host));
+        let mut me = try!(Self::connect_data(&value, opts));
+        let telemetry = me.take_data();
+        me.data = Arc::new(try!(inventory.merge_data(host, telemetry)));
 
         Ok(me)
     }
 
-    #[cfg(feature = "remote-run")]
+    fn connect_data(value: &Value, opts: &ConnectOptions) -> Result<Host> {
+        match wantstr!(value => "/transport") {
+            None | Some("zmq") => Self::connect_endpoint_with(try!(needstr!(value => "/hostname")),
+                                                             try!(needu64!(value => "/api_port")) as u32,
+                                                             try!(needu64!(value => "/file_port")) as u32,
+                                                             opts),
+            Some("ssh") => Self::connect_ssh(value, opts),
+            Some("chroot") => Self::container(Container::Chroot(try!(needstr!(value => "/container")).into())),
+            Some("nsenter") => Self::container(Container::Nsenter(try!(needu64!(value => "/container")) as u32)),
+            Some("docker") => Self::container(Container::Docker(try!(needstr!(value => "/container")).into())),
+            Some("podman") => Self::container(Container::Podman(try!(needstr!(value => "/container")).into())),
+            Some(t) => Err(Error::Generic(format!("Unknown transport {}", t))),
+        }
+    }
+
+    #[cfg(feature = "ssh")]
+    fn connect_ssh(value: &Value, opts: &ConnectOptions) -> Result<Host> {
+        let hostname = try!(needstr!(value => "/hostname"));
+        let port = wantu64!(value => "/ssh/port").unwrap_or(22) as u16;
+        let user = wantstr!(value => "/ssh/user").unwrap_or("root");
+        let auth = if let Some(key) = wantstr!(value => "/ssh/key") {
+            SshAuth::KeyFile(key.into(), wantstr!(value => "/ssh/passphrase").map(|p| p.to_string()))
+        } else if let Some(password) = wantstr!(value => "/ssh/password") {
+            SshAuth::Password(password.into())
+        } else {
+            SshAuth::Agent
+        };
+        let host_key = if wantbool!(value => "/ssh/check_host_key") == Some(false) {
+            SshHostKey::Insecure
+        } else if let Some(fingerprint) = wantstr!(value => "/ssh/host_key") {
+            SshHostKey::Fingerprint(fingerprint.into())
+        } else {
+            SshHostKey::KnownHosts(wantstr!(value => "/ssh/known_hosts").map(|p| p.into()))
+        };
+
+        opts.retry(|| {
+            let transport = try!(SshTransport::connect(hostname, port, user, &auth, &host_key, opts));
+            let mut me = Self::from_transport(hostname, Box::new(transport));
+            me.options = *opts;
+            me.data = Arc::new(try!(telemetry::Telemetry::init(&mut me)));
+            Ok(me)
+        })
+    }
+
+    #[cfg(not(feature = "ssh"))]
+    fn connect_ssh(_: &Value, _: &ConnectOptions) -> Result<Host> {
+        Err(Error::Generic("The SSH transport requires the `ssh` feature".into()))
+    }
+
+    fn take_data(&mut self) -> Value {
+        let mut data = Arc::new(Value::Null);
+        mem::swap(&mut data, &mut self.data);
+        // We can use unwrap() here safely as we can guarantee that
+        // there is only one strong reference to data.
+        Arc::try_unwrap(data).unwrap()
+    }
+
     /// Create a new Host connected to the specified endpoint. Note
     /// that this function does not load any user data.
     pub fn connect_endpoint(hostname: &str, api_port: u32, file_port: u32) -> Result<Host> {
-        let user_cert = try!(ZCert::load("user.crt"));
-        let server_cert = try!(Self::lookup_server_cert(hostname, &user_cert));
-
-        let mut api_sock = ZSock::new(SocketType::REQ);
-        user_cert.apply(&mut api_sock);
-        api_sock.set_curve_serverkey(server_cert.public_txt());
-        api_sock.set_sndtimeo(Some(1800000));
-        api_sock.set_rcvtimeo(Some(1800000));
-        try!(api_sock.connect(&format!("tcp://{}:{}", hostname, api_port)));
-
-        let mut file_sock = ZSock::new(SocketType::DEALER);
-        user_cert.apply(&mut file_sock);
-        file_sock.set_curve_serverkey(server_cert.public_txt());
-        file_sock.set_sndtimeo(Some(1800000));
-        file_sock.set_rcvtimeo(Some(1800000));
-        try!(file_sock.connect(&format!("tcp://{}:{}", hostname, file_port)));
+        Self::connect_endpoint_with(hostname, api_port, file_port, &ConnectOptions::default())
+    }
 
-        let mut me = Host {
-            hostname: hostname.into(),
-            api_sock: Some(api_sock),
-            file_sock: Some(file_sock),
-            data: Rc::new(Value::Null),
+    /// Create a new Host connected to the specified endpoint, using
+    /// the given timeouts and retry policy. Connection attempts that
+    /// time out are retried; other errors are returned immediately.
+    pub fn connect_endpoint_with(hostname: &str, api_port: u32, file_port: u32, opts: &ConnectOptions) -> Result<Host> {
+        opts.retry(|| {
+            let transport = try!(ZmqTransport::connect(hostname, api_port, file_port, opts));
+
+            // The Agent's first reply doubles as the connection
+            // handshake, so it gets the connect timeout.
+            let mut me = Self::from_transport(hostname, Box::new(transport));
+            me.options = opts.request_timeout(opts.connect_timeout);
+            me.data = Arc::new(try!(telemetry::Telemetry::init(&mut me)));
+            me.options = *opts;
+
+            Ok(me)
+        })
+    }
+
+    /// Create a new Host that runs primitives inside a chroot or
+    /// container on the local machine. Note that this function does
+    /// not load any user data.
+    pub fn container(container: Container) -> Result<Host> {
+        let hostname = match container {
+            Container::Chroot(ref root) => root.to_string_lossy().into_owned(),
+            Container::Nsenter(pid) => pid.to_string(),
+            Container::Docker(ref id) | Container::Podman(ref id) => id.clone(),
         };
-        me.data = Rc::new(try!(telemetry::Telemetry::init(&mut me)));
+
+        let transport = try!(ContainerTransport::new(container));
+        let mut me = Self::from_transport(&hostname, Box::new(transport));
+        me.data = Arc::new(try!(telemetry::Telemetry::init(&mut me)));
 
         Ok(me)
     }
 
-    #[cfg(feature = "remote-run")]
     /// Create a new Host specifically for use inside a payload.
     pub fn connect_payload(api_endpoint: &str, file_endpoint: &str) -> Result<Host> {
         let api_sock = ZSock::new(SocketType::DEALER);
@@ -146,12 +263,26 @@ impl Host {
         let data_json = try!(api_sock.recv_str()).unwrap();
         let data = try!(serde_json::from_str(&data_json));
 
-        Ok(Host {
-            hostname: "payload".into(),
-            api_sock: Some(api_sock),
-            file_sock: Some(file_sock),
-            data: Rc::new(data),
-        })
+        let mut me = Self::from_transport("payload", Box::new(ZmqTransport::new(Some(api_sock), Some(file_sock))));
+        me.data = Arc::new(data);
+
+        Ok(me)
+    }
+
+    /// Create a new Host that talks to its Agent over the given
+    /// Transport. Note that this function does not load any data,
+    /// including telemetry.
+    pub fn from_transport(hostname: &str, transport: Box<Transport>) -> Host {
+        Host {
+            hostname: hostname.into(),
+            transport: Some(transport),
+            options: ConnectOptions::default(),
+            last_reply: Instant::now(),
+            data: Arc::new(Value::Null),
+            check_mode: false,
+            escalation: None,
+            report: Arc::new(Mutex::new(Report::new())),
+        }
     }
 
     /// Get data for Host.
@@ -160,105 +291,216 @@ impl Host {
     }
 
     /// Get a reference counted version of data for Host.
-    pub fn data_owned(&self) -> Rc<Value> {
+    pub fn data_owned(&self) -> Arc<Value> {
         self.data.clone()
     }
 
-    #[cfg(feature = "remote-run")]
-    fn lookup_server_cert(hostname: &str, user_cert: &ZCert) -> Result<ZCert> {
-        let auth_cert = try!(ZCert::load("auth.crt"));
+    /// Enable or disable check mode.
+    ///
+    /// While in check mode, primitives report the changes they
+    /// would make to the host instead of applying them. Read-only
+    /// operations, such as `File::get_mode()`, are still run.
+    pub fn set_check_mode(&mut self, enabled: bool) {
+        self.check_mode = enabled;
+    }
 
-        let mut auth_sock = ZSock::new(SocketType::REQ);
-        user_cert.apply(&mut auth_sock);
-        auth_sock.set_curve_serverkey(auth_cert.public_txt());
-        auth_sock.set_sndtimeo(Some(10000));
-        auth_sock.set_rcvtimeo(Some(10000));
-        try!(auth_sock.connect(&format!("tcp://{}:{}", ::PROJECT_CONFIG.auth_server, ::PROJECT_CONFIG.auth_api_port)));
+    /// Check whether the Host is in check mode.
+    pub fn check_mode(&self) -> bool {
+        self.check_mode
+    }
 
-        // Get server cert from Auth server
-        let msg = ZMsg::new();
-        try!(msg.addstr("cert::lookup"));
-        try!(msg.addstr(hostname));
-        try!(msg.send(&mut auth_sock));
+    /// Run commands, file writes and edits, `chown`/`chmod`, links,
+    /// users, groups, services and package operations as another user,
+    /// or None to run them as the user that the Host connects as. See
+    /// `Become` for the limits on passwords.
+    pub fn set_become(&mut self, escalation: Option<Become>) {
+        self.escalation = escalation;
+    }
 
-        let reply = try!(ZMsg::recv(&mut auth_sock));
+    /// Get the user that operations are run as, if it has been set.
+    pub fn escalation(&self) -> Option<&Become> {
+        self.escalation.as_ref()
+    }
 
-        if reply.size() != 2 {
-            return Err(Error::HostResponse);
+    /// Run `f` with operations run as another user, then restore the
+    /// Host's previous setting.
+    pub fn with_become<F, T>(&mut self, escalation: Become, f: F) -> T
+        where F: FnOnce(&mut Host) -> T
+    {
+        let previous = mem::replace(&mut self.escalation, Some(escalation));
+        let result = f(self);
+        self.escalation = previous;
+        result
+    }
+
+    /// Get a report of every operation run against the host.
+    pub fn report(&self) -> Report {
+        let mut report = self.report.lock().unwrap().clone();
+        report.check_mode = self.check_mode;
+        report
+    }
+
+    #[doc(hidden)]
+    pub fn record(&self, result: Result<()>, outcome: Outcome) -> Result<Outcome> {
+        outcome::record(&mut self.report.lock().unwrap(), result, outcome)
+    }
+
+    /// Run `f` to apply a change, unless the Outcome says there is
+    /// nothing to change or the Host is in check mode, then record
+    /// the Outcome.
+    #[doc(hidden)]
+    pub fn apply<F>(&mut self, outcome: Outcome, f: F) -> Result<Outcome>
+        where F: FnOnce(&mut Host) -> Result<()>
+    {
+        let result = if outcome.is_changed() && !self.check_mode {
+            f(self)
+        } else {
+            Ok(())
+        };
+        self.record(result, outcome)
+    }
+
+    /// Check whether the Host runs primitives against the local
+    /// machine, rather than over a Transport.
+    pub fn is_local(&self) -> bool {
+        self.transport.is_none()
+    }
+
+    /// Check that the Agent is responding. The Agent must reply
+    /// within the connect timeout. Local Hosts always respond.
+    pub fn ping(&mut self) -> Result<()> {
+        if self.is_local() {
+            return Ok(());
         }
 
-        match try!(reply.popstr().unwrap().or(Err(Error::HostResponse))).as_ref() {
-            "Ok" => {
-                let pk = try!(reply.popstr().unwrap().or(Err(Error::HostResponse)));
-                Ok(try!(ZCert::from_txt(&pk, "0000000000000000000000000000000000000000")))
-            },
-            "Err" => Err(Error::Auth(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))))),
-            _ => Err(Error::HostResponse),
+        let msg = Message::new();
+        try!(msg.addstr("ping"));
+        try!(try!(self.transport()).send(msg));
+
+        let timeout = self.options.connect_timeout;
+        let reply = try!(self.recv_api(timeout));
+        Self::extract_header(&reply)
+    }
+
+    /// Re-establish the connection to the Agent, discarding any
+    /// request that is still waiting for a reply.
+    ///
+    /// This happens automatically before the next request when a
+    /// request times out. Hosts created with `connect_payload()` or
+    /// with a Transport that does not support reconnecting fail with
+    /// `Error::HostDisconnected`.
+    pub fn reconnect(&mut self) -> Result<()> {
+        try!(try!(self.transport()).reconnect());
+        self.last_reply = Instant::now();
+        Ok(())
+    }
+
+    // Restore a Host whose connection was dropped after a timeout, and
+    // ping a Host that has been idle for longer than the heartbeat
+    // interval, reconnecting if the Agent does not answer.
+    fn keepalive(&mut self) -> Result<()> {
+        if !try!(self.transport()).is_connected() {
+            return self.reconnect();
+        }
+
+        if let Some(interval) = self.options.heartbeat {
+            let idle = self.last_reply.elapsed();
+            if idle.as_secs() * 1000 + (idle.subsec_nanos() / 1000000) as u64 >= interval as u64 {
+                match self.ping() {
+                    Err(Error::Timeout) => return self.reconnect(),
+                    // Agents that predate `ping` reply with an error,
+                    // which still shows that they are alive.
+                    Err(Error::Agent(_)) => return Ok(()),
+                    result => return result,
+                }
+            }
         }
+
+        Ok(())
+    }
+
+    fn recv_api(&mut self, timeout: u32) -> Result<Message> {
+        let result = try!(self.transport()).recv(timeout);
+        if result.is_ok() {
+            self.last_reply = Instant::now();
+        }
+        result
     }
 
-    #[cfg(all(test, feature = "remote-run"))]
+    // Get the Host's Transport. Local Hosts have no Transport, so
+    // they cannot send requests.
+    fn transport(&mut self) -> Result<&mut Box<Transport>> {
+        self.transport.as_mut().ok_or(Error::HostDisconnected)
+    }
+
+    #[cfg(test)]
     pub fn test_new(hostname: Option<String>, api_sock: Option<ZSock>, file_sock: Option<ZSock>, data: Option<Value>) -> Host {
-        let host = Host {
-            hostname: hostname.unwrap_or(String::new()),
-            api_sock: api_sock,
-            file_sock: file_sock,
-            data: match data {
-                Some(d) => Rc::new(d),
-                None => Rc::new(Value::Null),
-            },
-        };
+        let mut host = Self::from_transport(&hostname.unwrap_or(String::new()), Box::new(ZmqTransport::new(api_sock, file_sock)));
+        if let Some(d) = data {
+            host.data = Arc::new(d);
+        }
 
         host
     }
 }
 
-#[cfg(feature = "remote-run")]
 pub trait HostSendRecv {
-    fn send(&mut self, msg: ZMsg) -> Result<()>;
-    fn send_file(&mut self, msg: ZMsg) -> Result<()>;
-    fn send_fs_file<P: AsRef<Path>>(&mut self, file: &mut zfilexfer::File, remote_path: P) -> Result<()>;
-    fn recv(&mut self, min: usize, max: Option<usize>) -> Result<ZMsg>;
-    fn recv_raw(&mut self) -> Result<ZMsg>;
-    fn recv_file_raw(&mut self) -> Result<ZMsg>;
-    fn extract_header(msg: &ZMsg) -> Result<()>;
+    fn send(&mut self, msg: Message) -> Result<()>;
+    fn send_file(&mut self, msg: Message) -> Result<()>;
+    fn upload_file<P: AsRef<Path>>(&mut self, file: fs::File, remote_path: P, options: Option<&[FileOptions]>) -> Result<()>;
+    fn recv_fs_file<P: AsRef<Path>>(&mut self, remote_path: P, writer: &mut Write) -> Result<()>;
+    fn recv(&mut self, min: usize, max: Option<usize>) -> Result<Message>;
+    fn recv_raw(&mut self) -> Result<Message>;
+    fn recv_file_raw(&mut self) -> Result<Message>;
+    fn extract_header(msg: &Message) -> Result<()>;
 }
 
-#[cfg(feature = "remote-run")]
 impl HostSendRecv for Host {
-    fn send(&mut self, msg: ZMsg) -> Result<()> {
-        if self.api_sock.is_none() {
-            return Err(Error::HostDisconnected);
-        }
-
-        try!(msg.send(self.api_sock.as_mut().unwrap()));
-        Ok(())
+    fn send(&mut self, msg: Message) -> Result<()> {
+        try!(self.keepalive());
+        try!(self.transport()).send(msg)
     }
 
-    fn send_file(&mut self, msg: ZMsg) -> Result<()> {
-        if self.file_sock.is_none() {
-            return Err(Error::HostDisconnected);
-        }
+    fn send_file(&mut self, msg: Message) -> Result<()> {
+        try!(self.transport()).send_file(msg)
+    }
 
-        try!(msg.send(self.file_sock.as_mut().unwrap()));
-        Ok(())
+    fn upload_file<P: AsRef<Path>>(&mut self, file: fs::File, remote_path: P, options: Option<&[FileOptions]>) -> Result<()> {
+        try!(self.transport()).upload(file, remote_path.as_ref(), options)
     }
 
-    fn send_fs_file<P: AsRef<Path>>(&mut self, file: &mut zfilexfer::File, remote_path: P) -> Result<()> {
-        if self.file_sock.is_none() {
-            return Err(Error::HostDisconnected);
+    fn recv_fs_file<P: AsRef<Path>>(&mut self, remote_path: P, writer: &mut Write) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("DOWNLOAD"));
+        try!(msg.addstr(remote_path.as_ref().to_str().unwrap()));
+        try!(self.send_file(msg));
+
+        // The agent replies with the number of chunks, followed by
+        // one message per chunk: [index, data].
+        let reply = try!(self.recv_file_raw());
+        try!(Self::extract_header(&reply));
+        let chunks = try!(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))).parse::<u64>());
+
+        for index in 0..chunks {
+            let reply = try!(self.recv_file_raw());
+            try!(Self::extract_header(&reply));
+
+            if reply.size() != 2 || try!(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))).parse::<u64>()) != index {
+                return Err(Error::HostResponse);
+            }
+
+            match reply.popstr() {
+                Some(Ok(chunk)) => try!(writer.write_all(chunk.as_bytes())),
+                Some(Err(bytes)) => try!(writer.write_all(&bytes)),
+                None => return Err(Error::HostResponse),
+            }
         }
 
-        try!(file.send(self.file_sock.as_mut().unwrap(), remote_path));
         Ok(())
     }
 
-    fn recv(&mut self, min: usize, max: Option<usize>) -> Result<ZMsg> {
-        if self.api_sock.is_none() {
-            return Err(Error::HostDisconnected);
-        }
-
-        let msg = try!(ZMsg::recv(self.api_sock.as_mut().unwrap()));
+    fn recv(&mut self, min: usize, max: Option<usize>) -> Result<Message> {
+        let msg = try!(self.recv_raw());
         try!(Self::extract_header(&msg));
 
         // Check msg size
@@ -269,23 +511,17 @@ impl HostSendRecv for Host {
         }
     }
 
-    fn recv_raw(&mut self) -> Result<ZMsg> {
-        if self.api_sock.is_none() {
-            return Err(Error::HostDisconnected);
-        }
-
-        Ok(try!(ZMsg::recv(self.api_sock.as_mut().unwrap())))
+    fn recv_raw(&mut self) -> Result<Message> {
+        let timeout = self.options.request_timeout;
+        self.recv_api(timeout)
     }
 
-    fn recv_file_raw(&mut self) -> Result<ZMsg> {
-        if self.file_sock.is_none() {
-            return Err(Error::HostDisconnected);
-        }
-
-        Ok(try!(ZMsg::recv(self.file_sock.as_mut().unwrap())))
+    fn recv_file_raw(&mut self) -> Result<Message> {
+        let timeout = self.options.request_timeout;
+        try!(self.transport()).recv_file(timeout)
     }
 
-    fn extract_header(msg: &ZMsg) -> Result<()> {
+    fn extract_header(msg: &Message) -> Result<()> {
         if msg.size() == 0 {
             return Err(Error::HostResponse);
         }
@@ -304,16 +540,16 @@ impl HostSendRecv for Host {
     }
 }
 
-#[cfg(feature = "remote-run")]
 #[cfg(test)]
 mod tests {
+    use command::Command;
     use czmq::{ZMsg, ZSock, SocketType, ZSys};
     use serde_json;
     use std::fs;
     use std::thread;
     use super::*;
+    use super::transport::MemoryTransport;
     use tempdir::TempDir;
-    use zfilexfer::File;
 
     #[test]
     fn test_connect_payload() {
@@ -332,7 +568,7 @@ mod tests {
         let mut host = Host::connect_payload(&api_endpoint, "inproc://file_endpoint").unwrap();
         assert_eq!(host.data().find("key"), Some(&serde_json::to_value("value")));
 
-        let msg = ZMsg::new();
+        let msg = Message::new();
         msg.addstr("test").unwrap();
         host.send(msg).unwrap();
 
@@ -348,7 +584,7 @@ mod tests {
         let mut host1 = Host::test_new(None, Some(client), None, None);
         let mut host2 = Host::test_new(None, Some(server), None, None);
 
-        let msg = ZMsg::new();
+        let msg = Message::new();
         msg.addstr("Ok").unwrap();
         msg.addstr("moo").unwrap();
         msg.addstr("cow").unwrap();
@@ -358,18 +594,83 @@ mod tests {
         assert_eq!(reply.popstr().unwrap().unwrap(), "moo");
         assert_eq!(reply.popstr().unwrap().unwrap(), "cow");
 
-        let msg = ZMsg::new();
+        let msg = Message::new();
         msg.addstr("No header").unwrap();
         host2.send(msg).unwrap();
 
         assert!(host1.recv(0, None).is_err());
-        let msg = ZMsg::new();
+        let msg = Message::new();
         msg.addstr("Err").unwrap();
         host1.send(msg).unwrap();
 
         assert!(host2.recv(0, None).is_err());
     }
 
+    #[test]
+    fn test_heartbeat() {
+        let _ = ::_MOCK_ENV.init();
+
+        let (client, mut server) = ZSys::create_pipe().unwrap();
+
+        let agent_mock = thread::spawn(move || {
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("ping", msg.popstr().unwrap().unwrap());
+            server.send_str("Ok").unwrap();
+
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("moo", msg.popstr().unwrap().unwrap());
+            server.send_str("Ok").unwrap();
+        });
+
+        let mut host = Host::test_new(None, Some(client), None, None);
+        host.options = ConnectOptions::new().heartbeat(0);
+
+        let msg = Message::new();
+        msg.addstr("moo").unwrap();
+        host.send(msg).unwrap();
+        host.recv(0, Some(0)).unwrap();
+
+        agent_mock.join().unwrap();
+    }
+
+    #[test]
+    fn test_heartbeat_reconnect() {
+        let transport = MemoryTransport::new();
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        host.options = ConnectOptions::new().heartbeat(0);
+
+        // The ping times out, so the Host reconnects before sending
+        let msg = Message::new();
+        msg.addstr("cow").unwrap();
+        host.send(msg).unwrap();
+        assert_eq!(transport.reconnects(), 1);
+
+        transport.reply(&["Ok", "moo"]);
+        let reply = host.recv(1, Some(1)).unwrap();
+        assert_eq!(reply.popstr().unwrap().unwrap(), "moo");
+        assert_eq!(transport.requests(), vec![vec!["ping".to_string()], vec!["cow".to_string()]]);
+    }
+
+    #[test]
+    fn test_recv_timeout() {
+        let _ = ::_MOCK_ENV.init();
+
+        let (client, _server) = ZSys::create_pipe().unwrap();
+
+        let mut host = Host::test_new(None, Some(client), None, None);
+        host.options = ConnectOptions::new().request_timeout(10);
+
+        match host.recv(0, None) {
+            Err(Error::Timeout) => (),
+            _ => panic!("Expected recv to time out"),
+        }
+
+        match host.recv(0, None) {
+            Err(Error::HostDisconnected) => (),
+            _ => panic!("Expected host to be disconnected after timeout"),
+        }
+    }
+
     #[test]
     fn test_send_file() {
         let _ = ::_MOCK_ENV.init();
@@ -377,7 +678,6 @@ mod tests {
         let tempdir = TempDir::new("host_test_send_file").unwrap();
         let path = format!("{}/file.txt", tempdir.path().to_str().unwrap());
         fs::File::create(&path).unwrap();
-        let mut file = File::open(&path, None).unwrap();
 
         let (client, mut server) = ZSys::create_pipe().unwrap();
         client.set_rcvtimeo(Some(500));
@@ -392,8 +692,101 @@ mod tests {
         });
 
         let mut host = Host::test_new(None, None, Some(client), None);
-        assert!(host.send_fs_file(&mut file, &path).is_ok());
+        assert!(host.upload_file(fs::File::open(&path).unwrap(), &path, None).is_ok());
 
         handle.join().unwrap();
     }
+
+    #[test]
+    fn test_recv_fs_file() {
+        let _ = ::_MOCK_ENV.init();
+
+        let (client, mut server) = ZSys::create_pipe().unwrap();
+        client.set_rcvtimeo(Some(500));
+        server.set_rcvtimeo(Some(500));
+
+        let handle = thread::spawn(move|| {
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!(msg.popstr().unwrap().unwrap(), "DOWNLOAD");
+            assert_eq!(msg.popstr().unwrap().unwrap(), "/path/to/file");
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("2").unwrap();
+            reply.send(&mut server).unwrap();
+
+            for (index, chunk) in vec!["Hello, ", "world!"].into_iter().enumerate() {
+                let reply = ZMsg::new();
+                reply.addstr("Ok").unwrap();
+                reply.addstr(&index.to_string()).unwrap();
+                reply.addstr(chunk).unwrap();
+                reply.send(&mut server).unwrap();
+            }
+        });
+
+        let mut host = Host::test_new(None, None, Some(client), None);
+        let mut buf = Vec::new();
+        assert!(host.recv_fs_file("/path/to/file", &mut buf).is_ok());
+        assert_eq!(buf, b"Hello, world!");
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_from_transport() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Err", "Unknown endpoint"]);
+        transport.reply(&["Ok", "moo"]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        host.options = ConnectOptions::new().heartbeat(0);
+
+        let msg = Message::new();
+        msg.addstr("cow").unwrap();
+        host.send(msg).unwrap();
+        let reply = host.recv(1, Some(1)).unwrap();
+        assert_eq!(reply.popstr().unwrap().unwrap(), "moo");
+
+        assert_eq!(transport.requests(), vec![vec!["ping".to_string()], vec!["cow".to_string()]]);
+    }
+
+    #[test]
+    fn test_local_and_remote() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "remote", ""]);
+
+        let mut local = Host::local(None::<String>).unwrap();
+        let mut remote = Host::from_transport("test", Box::new(transport.clone()));
+        assert!(local.is_local());
+        assert!(!remote.is_local());
+
+        let cmd = Command::new("echo local");
+        assert_eq!(cmd.exec(&mut local).unwrap().stdout, "local");
+        assert_eq!(cmd.exec(&mut remote).unwrap().stdout, "remote");
+        assert_eq!(transport.requests().len(), 1);
+
+        let msg = Message::new();
+        msg.addstr("cow").unwrap();
+        assert!(local.send(msg).is_err());
+        assert!(local.ping().is_ok());
+    }
+
+    #[test]
+    fn test_become() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "www", ""]);
+        transport.reply(&["Ok", "0", "", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        host.set_become(Some(Become::sudo()));
+
+        let cmd = Command::new("whoami");
+        assert_eq!(host.with_become(Become::doas().user("www"), |h| cmd.exec(h)).unwrap().stdout, "www");
+        assert_eq!(host.escalation(), Some(&Become::sudo()));
+        cmd.exec(&mut host).unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests[0], vec!["command::exec", "doas -n -u 'www' sh -c 'whoami'"]);
+        assert_eq!(requests[1], vec!["command::exec", "sudo -n -u 'root' -- sh -c 'whoami'"]);
+    }
 }
@@ -0,0 +1,147 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Options for connecting to a remote host.
+
+use error::{Error, Result};
+use std::thread;
+use std::time::Duration;
+
+/// Timeouts and retry policy used when connecting to, and sending
+/// requests to, a remote host. All durations are in milliseconds.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use inapi::{ConnectOptions, Host};
+/// let opts = ConnectOptions::new()
+///     .connect_timeout(5000)
+///     .request_timeout(60000)
+///     .retries(3)
+///     .backoff(500)
+///     .heartbeat(60000);
+/// let mut host = Host::connect_with("hosts/myhost.json", &opts).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConnectOptions {
+    /// Time allowed for the Auth server and Agent to answer while
+    /// connecting
+    pub connect_timeout: u32,
+    /// Time allowed for the Agent to answer each request
+    pub request_timeout: u32,
+    /// Number of times to retry a connection that timed out
+    pub retries: u32,
+    /// Delay before the first retry, which doubles for each
+    /// subsequent retry
+    pub backoff: u32,
+    /// How long a connection may sit idle before the Agent is pinged
+    /// ahead of the next request
+    pub heartbeat: Option<u32>,
+}
+
+impl ConnectOptions {
+    /// Create a new ConnectOptions with the default policy: a 10
+    /// second connect timeout, a 30 minute request timeout, no
+    /// retries and no heartbeat.
+    pub fn new() -> ConnectOptions {
+        ConnectOptions {
+            connect_timeout: 10000,
+            request_timeout: 1800000,
+            retries: 0,
+            backoff: 1000,
+            heartbeat: None,
+        }
+    }
+
+    /// Set the connect timeout.
+    pub fn connect_timeout(mut self, ms: u32) -> ConnectOptions {
+        self.connect_timeout = ms;
+        self
+    }
+
+    /// Set the per-request timeout.
+    pub fn request_timeout(mut self, ms: u32) -> ConnectOptions {
+        self.request_timeout = ms;
+        self
+    }
+
+    /// Set the number of connection retries.
+    pub fn retries(mut self, retries: u32) -> ConnectOptions {
+        self.retries = retries;
+        self
+    }
+
+    /// Set the delay before the first retry.
+    pub fn backoff(mut self, ms: u32) -> ConnectOptions {
+        self.backoff = ms;
+        self
+    }
+
+    /// Ping the Agent before sending a request if the connection has
+    /// been idle for at least `ms`. If the Agent does not answer, the
+    /// Host reconnects before sending the request.
+    pub fn heartbeat(mut self, ms: u32) -> ConnectOptions {
+        self.heartbeat = Some(ms);
+        self
+    }
+
+    /// Run `f` until it succeeds, fails with an error other than a
+    /// timeout, or runs out of retries.
+    #[doc(hidden)]
+    pub fn retry<T, F>(&self, mut f: F) -> Result<T>
+        where F: FnMut() -> Result<T>
+    {
+        let mut delay = self.backoff as u64;
+        let mut attempt = 0;
+
+        loop {
+            match f() {
+                Err(Error::Timeout) if attempt < self.retries => {
+                    thread::sleep(Duration::from_millis(delay));
+                    delay *= 2;
+                    attempt += 1;
+                },
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for ConnectOptions {
+    fn default() -> ConnectOptions {
+        ConnectOptions::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use error::Error;
+    use super::*;
+
+    #[test]
+    fn test_retry() {
+        let opts = ConnectOptions::new().retries(2).backoff(1);
+
+        let mut attempts = 0;
+        let result: Result<()> = opts.retry(|| { attempts += 1; Err(Error::Timeout) });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        let mut attempts = 0;
+        let result: Result<()> = opts.retry(|| { attempts += 1; Err(Error::HostResponse) });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let mut attempts = 0;
+        let result = opts.retry(|| {
+            attempts += 1;
+            if attempts < 2 { Err(Error::Timeout) } else { Ok(attempts) }
+        });
+        assert_eq!(result.unwrap(), 2);
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Options for running operations as another user.
+
+use command::quote;
+
+/// The tool used to switch user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BecomeMethod {
+    /// `sudo -u <user>`
+    Sudo,
+    /// `doas -u <user>`
+    Doas,
+    /// `su <user>`
+    Su,
+}
+
+/// Runs commands, file writes and edits, `chown`/`chmod`, links,
+/// users, groups, services and package operations as another user,
+/// usually root.
+///
+/// Without a password, the host must allow the switch without one
+/// (e.g. with `NOPASSWD` in sudoers), otherwise the operation fails
+/// rather than waiting at a prompt. `su` always prompts, so it needs a
+/// password.
+///
+/// A password can only be given for local Hosts, where the prompt is
+/// answered on a pty. The remote transports do not allocate a pty, so
+/// operations on a remote Host with a password fail.
+///
+/// # Examples
+///
+/// Escalate every operation on a Host:
+///
+/// ```no_run
+/// # use inapi::{Become, Host};
+/// let mut host = Host::connect("hosts/myhost.json").unwrap();
+/// host.set_become(Some(Become::sudo()));
+/// ```
+///
+/// Or escalate a single call:
+///
+/// ```no_run
+/// # use inapi::{Become, Command, Host};
+/// let mut host = Host::local(None::<String>).unwrap();
+/// let cmd = Command::new("whoami");
+/// let result = host.with_become(Become::doas().user("www").password("secret"), |h| cmd.exec(h)).unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Become {
+    /// Tool used to switch user
+    pub method: BecomeMethod,
+    /// User to run operations as
+    pub user: String,
+    /// Password to answer the tool's prompt with
+    pub password: Option<String>,
+}
+
+impl Become {
+    /// Create a new Become that uses `method` to run operations as
+    /// root.
+    pub fn new(method: BecomeMethod) -> Become {
+        Become {
+            method: method,
+            user: "root".into(),
+            password: None,
+        }
+    }
+
+    /// Become root with `sudo`.
+    pub fn sudo() -> Become {
+        Become::new(BecomeMethod::Sudo)
+    }
+
+    /// Become root with `doas`.
+    pub fn doas() -> Become {
+        Become::new(BecomeMethod::Doas)
+    }
+
+    /// Become root with `su`.
+    pub fn su() -> Become {
+        Become::new(BecomeMethod::Su)
+    }
+
+    /// Set the user to run operations as.
+    pub fn user(mut self, user: &str) -> Become {
+        self.user = user.into();
+        self
+    }
+
+    /// Set the password used to answer the prompt.
+    pub fn password(mut self, password: &str) -> Become {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Wrap a shell command so that it runs as the target user.
+    pub fn wrap(&self, cmd: &str) -> String {
+        // Without a password, fail instead of prompting
+        let prompt = if self.password.is_some() { "" } else { "-n " };
+
+        match self.method {
+            BecomeMethod::Sudo => format!("sudo {}-u {} -- sh -c {}", prompt, quote(&self.user), quote(cmd)),
+            BecomeMethod::Doas => format!("doas {}-u {} sh -c {}", prompt, quote(&self.user), quote(cmd)),
+            BecomeMethod::Su => format!("su {} -c {}", quote(&self.user), quote(cmd)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(Become::sudo().wrap("id -u"), "sudo -n -u 'root' -- sh -c 'id -u'");
+        assert_eq!(Become::doas().user("www").password("secret").wrap("echo 'hi'"),
+                   "doas -u 'www' sh -c 'echo '\\''hi'\\'''");
+        assert_eq!(Become::su().wrap("whoami"), "su 'root' -c 'whoami'");
+    }
+}
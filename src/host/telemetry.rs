@@ -8,12 +8,10 @@
 
 use error::Result;
 use host::Host;
-#[cfg(feature = "local-run")]
 use serde_json::{to_value, Map};
 use serde_json::Value;
 use target::Target;
 
-#[cfg(feature = "local-run")]
 #[derive(Debug, RustcEncodable)]
 pub struct Telemetry {
     pub cpu: Cpu,
@@ -24,11 +22,7 @@ pub struct Telemetry {
     pub os: Os,
 }
 
-#[cfg(feature = "remote-run")]
-pub struct Telemetry;
-
 impl Telemetry {
-    #[cfg(feature = "local-run")]
     pub fn new(cpu: Cpu, fs: Vec<FsMount>, hostname: &str, memory: u64, net: Vec<Netif>, os: Os) -> Telemetry {
         Telemetry {
             cpu: cpu,
@@ -40,25 +34,23 @@ impl Telemetry {
         }
     }
 
-    #[cfg(feature = "local-run")]
     pub fn init(host: &mut Host) -> Result<Value> {
         let t = try!(Target::telemetry_init(host));
 
-        // Make sure telemetry is namespaced
+        // The Agent namespaces its own telemetry, so only local
+        // telemetry needs namespacing here.
+        if !host.is_local() {
+            return Ok(t);
+        }
+
         let mut t_map: Map<String, Value> = Map::new();
         t_map.insert("_telemetry".into(), t);
         Ok(to_value(t_map))
     }
 
-    #[cfg(feature = "remote-run")]
-    pub fn init(host: &mut Host) -> Result<Value> {
-        Ok(try!(Target::telemetry_init(host)))
-    }
-
     // XXX While Macros 1.1 are unstable, we can't use Serde to
     // convert Telemetry => Value, so we have to roll our own.
     // (https://github.com/rust-lang/rust/issues/35900)
-    #[cfg(feature = "local-run")]
     pub fn into_value(self) -> Value {
         let mut cpu: Map<String, Value> = Map::new();
         cpu.insert("vendor".into(), to_value(self.cpu.vendor));
@@ -125,7 +117,6 @@ pub trait TelemetryTarget {
     fn telemetry_init(host: &mut Host) -> Result<Value>;
 }
 
-#[cfg(feature = "local-run")]
 #[derive(Debug, RustcEncodable)]
 pub struct Cpu {
     pub vendor: String,
@@ -133,7 +124,6 @@ pub struct Cpu {
     pub cores: u32,
 }
 
-#[cfg(feature = "local-run")]
 impl Cpu {
     pub fn new(vendor: &str, brand_string: &str, cores: u32) -> Cpu {
         Cpu {
@@ -144,7 +134,6 @@ impl Cpu {
     }
 }
 
-#[cfg(feature = "local-run")]
 #[derive(Debug, RustcEncodable)]
 pub struct FsMount {
     pub filesystem: String,
@@ -155,7 +144,6 @@ pub struct FsMount {
     pub capacity: f32,
 }
 
-#[cfg(feature = "local-run")]
 #[derive(Debug, RustcEncodable)]
 pub struct Netif {
     pub interface: String,
@@ -165,21 +153,18 @@ pub struct Netif {
     pub status: Option<NetifStatus>,
 }
 
-#[cfg(feature = "local-run")]
 #[derive(Debug, RustcEncodable, PartialEq)]
 pub enum NetifStatus {
     Active,
     Inactive,
 }
 
-#[cfg(feature = "local-run")]
 #[derive(Debug, RustcEncodable)]
 pub struct NetifIPv4 {
     pub address: String,
     pub netmask: String,
 }
 
-#[cfg(feature = "local-run")]
 #[derive(Debug, RustcEncodable)]
 pub struct NetifIPv6 {
     pub address: String,
@@ -187,7 +172,6 @@ pub struct NetifIPv6 {
     pub scopeid: Option<String>,
 }
 
-#[cfg(feature = "local-run")]
 #[derive(Debug, RustcEncodable)]
 pub struct Os {
     pub arch: String,
@@ -199,7 +183,6 @@ pub struct Os {
     pub version_patch: u32,
 }
 
-#[cfg(feature = "local-run")]
 impl Os {
     pub fn new(arch: &str, family: &str, platform: &str, version_str: &str, version_maj: u32, version_min: u32, version_patch: u32) -> Os {
         Os {
@@ -216,10 +199,8 @@ impl Os {
 
 #[cfg(test)]
 mod tests {
-    #[cfg(feature = "local-run")]
     use Host;
 
-    #[cfg(feature = "local-run")]
     #[test]
     fn test_telemetry_init() {
         let path: Option<String> = None;
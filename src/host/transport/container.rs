@@ -0,0 +1,311 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Container transport, for chroots and containers on the local
+//! machine.
+//!
+//! `ContainerTransport` answers requests itself. Commands are run
+//! inside the container with `chroot`, `nsenter`, `docker exec` or
+//! `podman exec`, and file operations are mapped onto the container's
+//! root directory, as seen from the local machine. Symlinks are
+//! resolved within that root, as they would be inside the container.
+
+use error::{Error, Result};
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str;
+use super::{Message, Transport};
+use super::shell::{self, Shell};
+use zfilexfer::FileOptions;
+
+/// Most symlinks followed when resolving a path, as for Linux's
+/// ELOOP
+const MAX_SYMLINKS: u32 = 40;
+
+/// A chroot or container that a Host can run primitives in.
+#[derive(Clone, Debug)]
+pub enum Container {
+    /// A root directory, entered with `chroot`
+    Chroot(PathBuf),
+    /// The namespaces of a running process, entered with `nsenter`
+    Nsenter(u32),
+    /// A running Docker container, by name or ID
+    Docker(String),
+    /// A running Podman container, by name or ID
+    Podman(String),
+}
+
+/// Transport that runs primitives inside a chroot or container on the
+/// local machine.
+///
+/// Most containers can only be entered by root, so the process using
+/// this transport usually needs to run as root too.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use inapi::{Container, ContainerTransport, Host};
+/// let transport = ContainerTransport::new(Container::Chroot("/mnt/image".into())).unwrap();
+/// let mut host = Host::from_transport("image", Box::new(transport));
+/// ```
+pub struct ContainerTransport {
+    /// Container that commands are run in
+    container: Container,
+    /// The container's root directory on the local machine
+    root: PathBuf,
+    /// Reply to the last API request
+    reply: Option<Message>,
+    /// Replies to the last file channel request
+    file_replies: VecDeque<Message>,
+}
+
+impl ContainerTransport {
+    /// Create a transport for a chroot or container. Docker and
+    /// Podman containers must be running.
+    pub fn new(container: Container) -> Result<ContainerTransport> {
+        let root = match container {
+            Container::Chroot(ref root) => root.clone(),
+            Container::Nsenter(pid) => proc_root(pid),
+            Container::Docker(ref id) => proc_root(try!(container_pid("docker", id))),
+            Container::Podman(ref id) => proc_root(try!(container_pid("podman", id))),
+        };
+
+        if !root.is_dir() {
+            return Err(Error::Generic(format!("Container root {} is not a directory", root.display())));
+        }
+
+        Ok(ContainerTransport {
+            container: container,
+            root: root,
+            reply: None,
+            file_replies: VecDeque::new(),
+        })
+    }
+
+    // Map a path inside the container to a path on the local machine.
+    // Symlinks are resolved against the container's root, so that an
+    // absolute symlink or ".." cannot lead out of the container. The
+    // last component is only resolved if `follow` is true.
+    fn local_path(&self, path: &Path, follow: bool) -> Result<PathBuf> {
+        let mut resolved = PathBuf::new();
+        let mut pending: Vec<OsString> = path.iter().rev().map(|c| c.to_owned()).collect();
+        let mut symlinks = 0;
+
+        while let Some(component) = pending.pop() {
+            if component == "/" {
+                resolved = PathBuf::new();
+            } else if component == ".." {
+                resolved.pop();
+            } else if component != "." {
+                let candidate = resolved.join(&component);
+                let local = self.root.join(&candidate);
+
+                let is_symlink = fs::symlink_metadata(&local).map(|m| m.file_type().is_symlink()).unwrap_or(false);
+                if is_symlink && (follow || !pending.is_empty()) {
+                    symlinks += 1;
+                    if symlinks > MAX_SYMLINKS {
+                        return Err(Error::Generic(format!("Too many levels of symbolic links in {}", path.display())));
+                    }
+
+                    let target = try!(fs::read_link(&local));
+                    pending.extend(target.iter().rev().map(|c| c.to_owned()));
+                } else {
+                    resolved = candidate;
+                }
+            }
+        }
+
+        Ok(self.root.join(resolved))
+    }
+}
+
+impl Shell for ContainerTransport {
+    fn exec(&mut self, cmd: &str) -> Result<(i32, String, String)> {
+        let mut command = match self.container {
+            Container::Chroot(ref root) => {
+                let mut c = process::Command::new("chroot");
+                c.arg(root);
+                c
+            },
+            Container::Nsenter(pid) => {
+                let mut c = process::Command::new("nsenter");
+                c.args(&["-t", &pid.to_string(), "-m", "-u", "-i", "-n", "-p"]);
+                c
+            },
+            Container::Docker(ref id) => {
+                let mut c = process::Command::new("docker");
+                c.args(&["exec", id]);
+                c
+            },
+            Container::Podman(ref id) => {
+                let mut c = process::Command::new("podman");
+                c.args(&["exec", id]);
+                c
+            },
+        };
+
+        let output = try!(command.args(&["/bin/sh", "-c", cmd]).output());
+
+        Ok((output.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+
+    fn stat(&mut self, path: &Path) -> Result<Option<bool>> {
+        match fs::metadata(try!(self.local_path(path, true))) {
+            Ok(meta) => Ok(Some(meta.is_dir())),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn rename(&mut self, path: &Path, new_path: &Path) -> Result<()> {
+        try!(fs::rename(try!(self.local_path(path, false)), try!(self.local_path(new_path, false))));
+        Ok(())
+    }
+
+    fn unlink(&mut self, path: &Path) -> Result<()> {
+        try!(fs::remove_file(try!(self.local_path(path, false))));
+        Ok(())
+    }
+
+    fn read(&mut self, path: &Path) -> Result<Option<Vec<u8>>> {
+        let mut fh = match fs::File::open(try!(self.local_path(path, true))) {
+            Ok(fh) => fh,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut contents = Vec::new();
+        try!(fh.read_to_end(&mut contents));
+        Ok(Some(contents))
+    }
+
+    fn write(&mut self, path: &Path, contents: &mut Read) -> Result<()> {
+        let mut fh = try!(fs::File::create(try!(self.local_path(path, true))));
+        try!(io::copy(contents, &mut fh));
+        Ok(())
+    }
+}
+
+impl Transport for ContainerTransport {
+    fn send(&mut self, msg: Message) -> Result<()> {
+        self.reply = Some(try!(shell::handle(self, msg)));
+        Ok(())
+    }
+
+    fn recv(&mut self, _: u32) -> Result<Message> {
+        self.reply.take().ok_or(Error::HostResponse)
+    }
+
+    fn send_file(&mut self, msg: Message) -> Result<()> {
+        self.file_replies = try!(shell::handle_file(self, msg));
+        Ok(())
+    }
+
+    fn recv_file(&mut self, _: u32) -> Result<Message> {
+        self.file_replies.pop_front().ok_or(Error::HostResponse)
+    }
+
+    fn upload(&mut self, file: fs::File, remote_path: &Path, options: Option<&[FileOptions]>) -> Result<()> {
+        shell::upload(self, file, remote_path, options)
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+}
+
+fn proc_root(pid: u32) -> PathBuf {
+    PathBuf::from(format!("/proc/{}/root", pid))
+}
+
+// Get the PID of a running Docker or Podman container's init process.
+fn container_pid(runtime: &str, id: &str) -> Result<u32> {
+    let output = try!(process::Command::new(runtime).args(&["inspect", "-f", "{{.State.Pid}}", id]).output());
+    if !output.status.success() {
+        return Err(Error::Generic(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+
+    match try!(str::from_utf8(&output.stdout).or(Err(Error::HostResponse))).trim().parse::<u32>() {
+        Ok(0) => Err(Error::Generic(format!("Container {} is not running", id))),
+        Ok(pid) => Ok(pid),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::fs::symlink;
+    use std::path::Path;
+    use super::*;
+    use super::super::{Message, Transport};
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_chroot_files() {
+        let tempdir = TempDir::new("test_chroot_files").unwrap();
+        fs::create_dir(tempdir.path().join("etc")).unwrap();
+        let mut fh = fs::File::create(tempdir.path().join("etc/motd")).unwrap();
+        fh.write_all(b"Hello").unwrap();
+
+        let mut transport = ContainerTransport::new(Container::Chroot(tempdir.path().to_owned())).unwrap();
+
+        transport.send(Message::from(&["file::is_file", "/etc/motd"][..])).unwrap();
+        let reply = transport.recv(0).unwrap();
+        assert_eq!(reply.popstr().unwrap().unwrap(), "Ok");
+        assert_eq!(reply.popstr().unwrap().unwrap(), "1");
+
+        transport.send(Message::from(&["file::mv", "/etc/motd", "/etc/issue"][..])).unwrap();
+        assert_eq!(transport.recv(0).unwrap().popstr().unwrap().unwrap(), "Ok");
+        assert!(tempdir.path().join("etc/issue").exists());
+
+        let upload = tempdir.path().join("upload");
+        let mut fh = fs::File::create(&upload).unwrap();
+        fh.write_all(b"World").unwrap();
+        transport.upload(fs::File::open(&upload).unwrap(), Path::new("/etc/motd"), None).unwrap();
+
+        transport.send_file(Message::from(&["DOWNLOAD", "/etc/motd"][..])).unwrap();
+        let header = transport.recv_file(0).unwrap();
+        assert_eq!(header.popstr().unwrap().unwrap(), "Ok");
+        assert_eq!(header.popstr().unwrap().unwrap(), "1");
+        let chunk = transport.recv_file(0).unwrap();
+        chunk.popstr().unwrap().unwrap();
+        chunk.popstr().unwrap().unwrap();
+        assert_eq!(chunk.popstr().unwrap().unwrap(), "World");
+
+        transport.send(Message::from(&["bogus::request", "/etc/motd"][..])).unwrap();
+        assert_eq!(transport.recv(0).unwrap().popstr().unwrap().unwrap(), "Err");
+    }
+
+    #[test]
+    fn test_chroot_symlinks() {
+        let tempdir = TempDir::new("test_chroot_symlinks").unwrap();
+        fs::create_dir(tempdir.path().join("etc")).unwrap();
+        fs::File::create(tempdir.path().join("etc/passwd")).unwrap().write_all(b"chroot").unwrap();
+        symlink("/etc", tempdir.path().join("abs")).unwrap();
+        symlink("../../etc/passwd", tempdir.path().join("etc/rel")).unwrap();
+        symlink("loop", tempdir.path().join("loop")).unwrap();
+
+        let transport = ContainerTransport::new(Container::Chroot(tempdir.path().to_owned())).unwrap();
+        let passwd = tempdir.path().join("etc/passwd");
+
+        assert_eq!(transport.local_path(Path::new("/abs/passwd"), true).unwrap(), passwd);
+        assert_eq!(transport.local_path(Path::new("/etc/rel"), true).unwrap(), passwd);
+        assert_eq!(transport.local_path(Path::new("/../../etc/passwd"), true).unwrap(), passwd);
+        assert_eq!(transport.local_path(Path::new("/etc/rel"), false).unwrap(), tempdir.path().join("etc/rel"));
+        assert_eq!(transport.local_path(Path::new("/abs/missing"), true).unwrap(), tempdir.path().join("etc/missing"));
+        assert!(transport.local_path(Path::new("/loop"), true).is_err());
+    }
+}
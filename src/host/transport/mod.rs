@@ -0,0 +1,281 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The wire layer between a Host and its Agent.
+//!
+//! A Host talks to its Agent by exchanging multi-part Messages over a
+//! `Transport`. The API channel is strictly request/reply: each
+//! request is followed by exactly one reply. The file channel carries
+//! file transfers, which may span several replies.
+//!
+//! The default transport is `ZmqTransport`, which speaks ZeroMQ with
+//! CURVE encryption. `ContainerTransport` runs primitives inside a
+//! chroot or container on the local machine and, with the `ssh`
+//! feature, `SshTransport` manages hosts that do not run an Agent.
+//! `MemoryTransport` replays canned replies and records requests, for
+//! testing code that uses a Host without a running Agent.
+
+pub mod container;
+#[doc(hidden)]
+pub mod shell;
+#[cfg(feature = "ssh")]
+pub mod ssh;
+pub mod zmq;
+
+pub use self::container::{Container, ContainerTransport};
+#[cfg(feature = "ssh")]
+pub use self::ssh::{SshAuth, SshHostKey, SshTransport};
+pub use self::zmq::ZmqTransport;
+
+use error::{Error, Result};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::result;
+use std::sync::{Arc, Mutex};
+use zfilexfer::FileOptions;
+
+/// A multi-part message, where each part ("frame") is a string or
+/// raw bytes.
+#[derive(Debug, Default)]
+pub struct Message {
+    frames: RefCell<VecDeque<Vec<u8>>>,
+}
+
+impl Message {
+    /// Create an empty Message.
+    pub fn new() -> Message {
+        Message::default()
+    }
+
+    /// Append a string frame.
+    pub fn addstr(&self, frame: &str) -> Result<()> {
+        self.addbytes(frame.as_bytes())
+    }
+
+    /// Append a binary frame.
+    pub fn addbytes(&self, frame: &[u8]) -> Result<()> {
+        self.frames.borrow_mut().push_back(frame.to_vec());
+        Ok(())
+    }
+
+    /// Remove the first frame. Frames that are not valid UTF-8 are
+    /// returned as raw bytes.
+    pub fn popstr(&self) -> Option<result::Result<String, Vec<u8>>> {
+        self.frames.borrow_mut().pop_front().map(|f| String::from_utf8(f).map_err(|e| e.into_bytes()))
+    }
+
+    /// Remove the first frame as raw bytes.
+    pub fn popbytes(&self) -> Option<Vec<u8>> {
+        self.frames.borrow_mut().pop_front()
+    }
+
+    /// Get the number of frames.
+    pub fn size(&self) -> usize {
+        self.frames.borrow().len()
+    }
+}
+
+impl<'a> From<&'a [&'a str]> for Message {
+    fn from(frames: &'a [&'a str]) -> Message {
+        Message {
+            frames: RefCell::new(frames.iter().map(|f| f.as_bytes().to_vec()).collect()),
+        }
+    }
+}
+
+/// A connection to a host's Agent.
+pub trait Transport: Send {
+    /// Send a request over the API channel.
+    fn send(&mut self, msg: Message) -> Result<()>;
+
+    /// Wait up to `timeout` milliseconds for a reply on the API
+    /// channel, returning `Error::Timeout` if none arrives.
+    fn recv(&mut self, timeout: u32) -> Result<Message>;
+
+    /// Send a request over the file channel.
+    fn send_file(&mut self, msg: Message) -> Result<()>;
+
+    /// Wait up to `timeout` milliseconds for a reply on the file
+    /// channel, returning `Error::Timeout` if none arrives.
+    fn recv_file(&mut self, timeout: u32) -> Result<Message>;
+
+    /// Stream a local file to `remote_path` over the file channel.
+    fn upload(&mut self, file: fs::File, remote_path: &Path, options: Option<&[FileOptions]>) -> Result<()>;
+
+    /// Check whether the API channel can carry another request.
+    fn is_connected(&self) -> bool;
+
+    /// Re-establish both channels, discarding any request that is
+    /// still waiting for a reply.
+    fn reconnect(&mut self) -> Result<()> {
+        Err(Error::HostDisconnected)
+    }
+}
+
+/// An in-memory Transport that records requests and answers them
+/// with canned replies.
+///
+/// Clones share the same state, so a test can keep a clone to script
+/// replies and inspect requests after handing the original to a Host.
+/// Like a ZmqTransport, a request that times out leaves the transport
+/// disconnected until it is reconnected.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use inapi::{Host, MemoryTransport};
+/// let transport = MemoryTransport::new();
+/// transport.reply(&["Ok", "0", "hello", ""]);
+///
+/// let mut host = Host::from_transport("test", Box::new(transport.clone()));
+/// // ...run a Command against host...
+///
+/// assert_eq!(transport.requests()[0][0], "command::exec");
+/// ```
+#[derive(Clone, Default)]
+pub struct MemoryTransport {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    requests: Vec<Vec<String>>,
+    replies: VecDeque<Vec<String>>,
+    file_requests: Vec<Vec<String>>,
+    file_replies: VecDeque<Vec<String>>,
+    uploads: Vec<(PathBuf, Vec<u8>)>,
+    disconnected: bool,
+    reconnects: usize,
+}
+
+impl MemoryTransport {
+    /// Create a MemoryTransport with no canned replies.
+    pub fn new() -> MemoryTransport {
+        MemoryTransport::default()
+    }
+
+    /// Queue a reply on the API channel.
+    pub fn reply(&self, frames: &[&str]) {
+        self.state.lock().unwrap().replies.push_back(frames.iter().map(|f| f.to_string()).collect());
+    }
+
+    /// Queue a reply on the file channel.
+    pub fn reply_file(&self, frames: &[&str]) {
+        self.state.lock().unwrap().file_replies.push_back(frames.iter().map(|f| f.to_string()).collect());
+    }
+
+    /// Get the requests sent over the API channel.
+    pub fn requests(&self) -> Vec<Vec<String>> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Get the requests sent over the file channel.
+    pub fn file_requests(&self) -> Vec<Vec<String>> {
+        self.state.lock().unwrap().file_requests.clone()
+    }
+
+    /// Get the remote paths and contents of uploaded files.
+    pub fn uploads(&self) -> Vec<(PathBuf, Vec<u8>)> {
+        self.state.lock().unwrap().uploads.clone()
+    }
+
+    /// Get the number of times the transport was reconnected.
+    pub fn reconnects(&self) -> usize {
+        self.state.lock().unwrap().reconnects
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&mut self, msg: Message) -> Result<()> {
+        self.state.lock().unwrap().requests.push(frames(msg));
+        Ok(())
+    }
+
+    fn recv(&mut self, _: u32) -> Result<Message> {
+        let mut state = self.state.lock().unwrap();
+        match state.replies.pop_front() {
+            Some(reply) => Ok(to_message(reply)),
+            None => {
+                state.disconnected = true;
+                Err(Error::Timeout)
+            },
+        }
+    }
+
+    fn send_file(&mut self, msg: Message) -> Result<()> {
+        self.state.lock().unwrap().file_requests.push(frames(msg));
+        Ok(())
+    }
+
+    fn recv_file(&mut self, _: u32) -> Result<Message> {
+        match self.state.lock().unwrap().file_replies.pop_front() {
+            Some(reply) => Ok(to_message(reply)),
+            None => Err(Error::Timeout),
+        }
+    }
+
+    fn upload(&mut self, mut file: fs::File, remote_path: &Path, _: Option<&[FileOptions]>) -> Result<()> {
+        let mut contents = Vec::new();
+        try!(file.read_to_end(&mut contents));
+        self.state.lock().unwrap().uploads.push((remote_path.to_path_buf(), contents));
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        !self.state.lock().unwrap().disconnected
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.disconnected = false;
+        state.reconnects += 1;
+        Ok(())
+    }
+}
+
+fn frames(msg: Message) -> Vec<String> {
+    let mut frames = Vec::new();
+    while let Some(frame) = msg.popbytes() {
+        frames.push(String::from_utf8_lossy(&frame).into_owned());
+    }
+    frames
+}
+
+fn to_message(frames: Vec<String>) -> Message {
+    Message {
+        frames: RefCell::new(frames.into_iter().map(|f| f.into_bytes()).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use error::Error;
+    use super::*;
+
+    #[test]
+    fn test_memory_transport() {
+        let mut transport = MemoryTransport::new();
+        let observer = transport.clone();
+        observer.reply(&["Ok", "1"]);
+
+        transport.send(Message::from(&["file::exists", "/tmp"][..])).unwrap();
+        let reply = transport.recv(0).unwrap();
+        assert_eq!(reply.size(), 2);
+        assert_eq!(reply.popstr().unwrap().unwrap(), "Ok");
+
+        match transport.recv(0) {
+            Err(Error::Timeout) => (),
+            _ => panic!("Expected recv to time out with no replies queued"),
+        }
+
+        assert_eq!(observer.requests(), vec![vec!["file::exists".to_string(), "/tmp".to_string()]]);
+    }
+}
@@ -0,0 +1,862 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Request handling for transports that manage a host without an
+//! Agent.
+//!
+//! Rather than forwarding requests to an Agent, these transports
+//! answer them by running shell commands on the host and accessing
+//! its files directly. The commands need a POSIX shell with GNU
+//! coreutils or BusyBox (`stat -c`, `find`, `sha256sum`), and the
+//! shadow utilities (`useradd`, `groupadd`, etc.) or FreeBSD's `pw` to
+//! manage users and groups. Unknown requests fail with `Error::Agent`.
+//!
+//! Escalated operations use the same commands, run as the target
+//! user, to answer requests on any Host.
+
+use command::{quote, Command};
+use error::{Error, Result};
+use file::{self, FileEdit};
+use serde_json::{self, to_value, Map, Value};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process;
+use super::Message;
+use zfilexfer::FileOptions;
+
+/// Size of the chunks a file is downloaded in
+const CHUNK_SIZE: usize = 65536;
+
+/// The operations an agentless transport needs to answer requests.
+pub trait Shell {
+    /// Run a command with `sh`, returning its exit code, stdout and
+    /// stderr.
+    fn exec(&mut self, cmd: &str) -> Result<(i32, String, String)>;
+
+    /// Get whether a path is a directory, or None if it does not
+    /// exist.
+    fn stat(&mut self, path: &Path) -> Result<Option<bool>>;
+
+    /// Move a file or directory.
+    fn rename(&mut self, path: &Path, new_path: &Path) -> Result<()>;
+
+    /// Delete a file.
+    fn unlink(&mut self, path: &Path) -> Result<()>;
+
+    /// Read a file, or None if it does not exist.
+    fn read(&mut self, path: &Path) -> Result<Option<Vec<u8>>>;
+
+    /// Create or overwrite a file.
+    fn write(&mut self, path: &Path, contents: &mut Read) -> Result<()>;
+}
+
+/// Answer an API request. Timeouts are returned as errors, while any
+/// other error becomes an "Err" reply.
+pub fn handle<S: Shell>(shell: &mut S, msg: Message) -> Result<Message> {
+    let reply = Message::new();
+    match dispatch(shell, msg) {
+        Ok(frames) => {
+            try!(reply.addstr("Ok"));
+            for frame in frames {
+                try!(reply.addstr(&frame));
+            }
+        },
+        Err(Error::Timeout) => return Err(Error::Timeout),
+        Err(e) => {
+            try!(reply.addstr("Err"));
+            try!(reply.addstr(&match e {
+                Error::Agent(msg) => msg,
+                e => e.to_string(),
+            }));
+        },
+    }
+    Ok(reply)
+}
+
+/// Answer an API request, returning the frames of a successful reply.
+pub fn request<S: Shell>(shell: &mut S, frames: &[&str]) -> Result<Vec<String>> {
+    dispatch(shell, Message::from(frames))
+}
+
+/// Answer a file channel request, returning every reply.
+pub fn handle_file<S: Shell>(shell: &mut S, msg: Message) -> Result<VecDeque<Message>> {
+    match download(shell, msg) {
+        Ok(replies) => Ok(replies),
+        Err(e) => {
+            let reply = Message::new();
+            try!(reply.addstr("Err"));
+            try!(reply.addstr(&e.to_string()));
+            Ok(vec![reply].into_iter().collect())
+        },
+    }
+}
+
+/// Write a local file to `remote_path`.
+pub fn upload<S: Shell>(shell: &mut S, mut file: fs::File, remote_path: &Path, options: Option<&[FileOptions]>) -> Result<()> {
+    if let Some(opts) = options {
+        for opt in opts {
+            if let FileOptions::BackupExisting(ref suffix) = *opt {
+                if try!(shell.stat(remote_path)).is_some() {
+                    let mut backup = remote_path.as_os_str().to_owned();
+                    backup.push(suffix);
+                    try!(shell.rename(remote_path, Path::new(&backup)));
+                }
+            }
+        }
+    }
+
+    shell.write(remote_path, &mut file)
+}
+
+fn dispatch<S: Shell>(shell: &mut S, msg: Message) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    while let Some(frame) = msg.popstr() {
+        args.push(try!(frame.or(Err(Error::HostResponse))));
+    }
+    if args.is_empty() {
+        return Err(Error::HostResponse);
+    }
+
+    let name = args.remove(0);
+    let arg = |i: usize| args.get(i).map(|a| a.as_str()).ok_or(Error::HostResponse);
+
+    match name.as_ref() {
+        "ping" => Ok(vec![]),
+        "command::exec" => {
+            let cmd = match args.get(1) {
+                Some(opts) => try!(Command::from_opts(try!(arg(0)), &try!(serde_json::from_str(opts)))),
+                None => Command::new(try!(arg(0))),
+            };
+            let (code, stdout, stderr) = try!(shell.exec(&cmd.script()));
+            Ok(vec![code.to_string(), stdout, stderr])
+        },
+        "directory::is_directory" => Ok(vec![flag(try!(shell.stat(Path::new(try!(arg(0))))) == Some(true))]),
+        "directory::exists" | "file::exists" => Ok(vec![flag(try!(shell.stat(Path::new(try!(arg(0))))).is_some())]),
+        "file::is_file" => Ok(vec![flag(try!(shell.stat(Path::new(try!(arg(0))))) == Some(false))]),
+        "directory::create" => {
+            let cmd = format!("mkdir{} {}", if try!(arg(1)) == "1" { " -p" } else { "" }, quote(try!(arg(0))));
+            run(shell, &cmd)
+        },
+        "directory::delete" => {
+            let cmd = format!("{} {}", if try!(arg(1)) == "1" { "rm -rf" } else { "rmdir" }, quote(try!(arg(0))));
+            run(shell, &cmd)
+        },
+        "directory::mv" | "file::mv" => {
+            try!(shell.rename(Path::new(try!(arg(0))), Path::new(try!(arg(1)))));
+            Ok(vec![])
+        },
+        "file::delete" => {
+            try!(shell.unlink(Path::new(try!(arg(0)))));
+            Ok(vec![])
+        },
+        "file::copy" => {
+            let cmd = format!("cp -p {} {}", quote(try!(arg(0))), quote(try!(arg(1))));
+            run(shell, &cmd)
+        },
+        "directory::get_owner" | "file::get_owner" => {
+            let owner = try!(run(shell, &format!("stat -c '%U %u %G %g' {}", quote(try!(arg(0))))));
+            let owner: Vec<String> = owner.concat().split_whitespace().map(|s| s.to_string()).collect();
+            if owner.len() == 4 { Ok(owner) } else { Err(Error::HostResponse) }
+        },
+        "directory::set_owner" | "file::set_owner" => {
+            let cmd = format!("chown {}:{} {}", quote(try!(arg(1))), quote(try!(arg(2))), quote(try!(arg(0))));
+            run(shell, &cmd)
+        },
+        "directory::get_mode" | "file::get_mode" => run(shell, &format!("stat -c %a {}", quote(try!(arg(0))))),
+        "directory::set_mode" | "file::set_mode" => {
+            let cmd = format!("chmod {} {}", quote(try!(arg(1))), quote(try!(arg(0))));
+            run(shell, &cmd)
+        },
+        "file::hash" => match try!(shell.read(Path::new(try!(arg(0))))) {
+            Some(contents) => Ok(vec![try!(file::checksum(&mut &contents[..]))]),
+            None => Ok(vec![]),
+        },
+        "file::edit" => {
+            let edit = try!(parse_edit(&args[1..]));
+            Ok(vec![flag(try!(edit_file(shell, try!(arg(0)), &edit)))])
+        },
+        "file::metadata" => metadata(shell, try!(arg(0))),
+        "directory::list" => list(shell, try!(arg(0)), try!(arg(1))),
+        "directory::checksums" => checksums(shell, try!(arg(0))),
+        "link::read" => run(shell, &format!("[ -L {0} ] || exit 0; readlink {0}", quote(try!(arg(0))))),
+        "link::create" => run(shell, &format!("ln -sfn {} {}", quote(try!(arg(1))), quote(try!(arg(0))))),
+        "link::create_hard" => run(shell, &format!("ln -f {} {}", quote(try!(arg(1))), quote(try!(arg(0))))),
+        "link::is_hard" => {
+            // Exits 1 if the path is missing, a symlink or not the same
+            // file, and 2 if the target is missing.
+            let cmd = format!("[ -e {1} ] || {{ echo 'No such file or directory' >&2; exit 2; }}; [ ! -L {0} ] && [ {0} -ef {1} ]",
+                              quote(try!(arg(0))), quote(try!(arg(1))));
+            match try!(shell.exec(&cmd)) {
+                (0, _, _) => Ok(vec![flag(true)]),
+                (1, _, _) => Ok(vec![flag(false)]),
+                (_, _, stderr) => Err(Error::Agent(stderr)),
+            }
+        },
+        "link::delete" => {
+            try!(shell.unlink(Path::new(try!(arg(0)))));
+            Ok(vec![])
+        },
+        "user::get" => user_get(shell, try!(arg(0))),
+        "user::create" | "user::modify" => {
+            let mut opts = String::new();
+            for pair in args[1..].chunks(2) {
+                if pair.len() != 2 {
+                    return Err(Error::HostResponse);
+                }
+
+                let opt = match pair[0].as_ref() {
+                    "uid" => "-u",
+                    "gid" => "-g",
+                    "shell" => "-s",
+                    "home" => "-m -d",
+                    "groups" => "-G",
+                    _ => return Err(Error::HostResponse),
+                };
+                opts.push_str(&format!(" {} {}", opt, quote(&pair[1])));
+            }
+            let tool = if name == "user::create" { "useradd" } else { "usermod" };
+            run(shell, &account_cmd(tool, try!(arg(0)), &opts))
+        },
+        "user::delete" => run(shell, &account_cmd("userdel", try!(arg(0)), "")),
+        "group::get" => {
+            let entry = match try!(account_entry(shell, "group", try!(arg(0)))) {
+                Some(entry) => entry,
+                None => return Ok(vec![]),
+            };
+
+            // name:password:gid:members
+            let fields: Vec<&str> = entry.split(':').collect();
+            if fields.len() < 4 {
+                return Err(Error::Generic(format!("Invalid group entry for group {}", try!(arg(0)))));
+            }
+            Ok(vec![fields[2].into(), fields[3].into()])
+        },
+        "group::create" => {
+            let opts = match try!(arg(1)) {
+                "" => String::new(),
+                gid => format!(" -g {}", quote(gid)),
+            };
+            run(shell, &account_cmd("groupadd", try!(arg(0)), &opts))
+        },
+        "group::modify" => run(shell, &account_cmd("groupmod", try!(arg(0)), &format!(" -g {}", quote(try!(arg(1)))))),
+        "group::delete" => run(shell, &account_cmd("groupdel", try!(arg(0)), "")),
+        "package::default_provider" => default_provider(shell),
+        "service::action" => service_action(shell, try!(arg(0)), try!(arg(1))),
+        "telemetry" => Ok(vec![try!(telemetry(shell)).to_string()]),
+        _ => Err(Error::Agent(format!("{} is not supported without an Agent", name))),
+    }
+}
+
+// Run a command, failing if it exits with a non-zero code.
+fn run<S: Shell>(shell: &mut S, cmd: &str) -> Result<Vec<String>> {
+    let (code, stdout, stderr) = try!(shell.exec(cmd));
+    if code == 0 {
+        Ok(if stdout.is_empty() { vec![] } else { vec![stdout] })
+    } else {
+        Err(Error::Agent(stderr))
+    }
+}
+
+fn parse_edit(args: &[String]) -> Result<FileEdit> {
+    let arg = |i: usize| args.get(i).cloned().ok_or(Error::HostResponse);
+
+    Ok(match try!(arg(0)).as_ref() {
+        "line_present" => FileEdit::LinePresent(try!(arg(1))),
+        "line_absent" => FileEdit::LineAbsent(try!(arg(1))),
+        "regex" => FileEdit::Regex(try!(arg(1)), try!(arg(2))),
+        "regex_absent" => FileEdit::RegexAbsent(try!(arg(1))),
+        "block" => FileEdit::Block(try!(arg(1)), try!(arg(2))),
+        "block_absent" => FileEdit::BlockAbsent(try!(arg(1))),
+        _ => return Err(Error::HostResponse),
+    })
+}
+
+/// Apply an edit to a file, writing the result beside the file and
+/// renaming it into place. Returns whether the file changed.
+pub fn edit_file<S: Shell>(shell: &mut S, path: &str, edit: &FileEdit) -> Result<bool> {
+    // Edit the target of a symlink rather than replacing the link
+    let path = match try!(shell.exec(&format!("readlink -f {}", quote(path)))) {
+        (0, ref real_path, _) if !real_path.is_empty() => real_path.clone(),
+        _ => path.to_string(),
+    };
+
+    let content = match try!(shell.read(Path::new(&path))) {
+        Some(content) => try!(String::from_utf8(content)),
+        None => String::new(),
+    };
+    let new_content = match try!(file::edit::apply(&content, edit)) {
+        Some(new_content) => new_content,
+        None => return Ok(false),
+    };
+
+    // Copy the file first to keep its mode and owner, as overwriting
+    // a file keeps them.
+    let tmp_path = format!("{}.{}.tmp", path, process::id());
+    let result = run(shell, &format!("[ ! -e {0} ] || cp -p {0} {1}", quote(&path), quote(&tmp_path)))
+        .and_then(|_| shell.write(Path::new(&tmp_path), &mut new_content.as_bytes()))
+        .and_then(|_| shell.rename(Path::new(&tmp_path), Path::new(&path)));
+    if result.is_err() {
+        let _ = shell.unlink(Path::new(&tmp_path));
+    }
+    try!(result);
+
+    Ok(true)
+}
+
+// Get the frames of a FileMetadata: type, size, mode, uid, gid, atime,
+// mtime, ctime, inode, nlink, SELinux context and checksum.
+fn metadata<S: Shell>(shell: &mut S, path: &str) -> Result<Vec<String>> {
+    let stat = try!(run(shell, &format!("stat -c '%F|%s|%a|%u|%g|%X|%Y|%Z|%i|%h' {}", quote(path)))).concat();
+    let mut frames: Vec<String> = stat.split('|').map(|f| f.to_string()).collect();
+    if frames.len() != 10 {
+        return Err(Error::HostResponse);
+    }
+    frames[0] = entry_type(&frames[0]).into();
+
+    // Hosts without SELinux have no context
+    match try!(shell.exec(&format!("stat -c %C {} 2>/dev/null", quote(path)))) {
+        (0, ref context, _) if context != "?" => frames.push(context.clone()),
+        _ => frames.push(String::new()),
+    }
+
+    let checksum = if frames[0] == "file" {
+        match try!(shell.read(Path::new(path))) {
+            Some(contents) => try!(file::checksum(&mut &contents[..])),
+            None => String::new(),
+        }
+    } else {
+        String::new()
+    };
+    frames.push(checksum);
+
+    Ok(frames)
+}
+
+// List the entries beneath a directory as groups of path, type, size
+// and mtime frames, in the same order as a local listing.
+fn list<S: Shell>(shell: &mut S, path: &str, max_depth: &str) -> Result<Vec<String>> {
+    let depth = match max_depth {
+        "" => String::new(),
+        "0" => return Ok(vec![]),
+        d => format!(" -maxdepth {}", try!(d.parse::<u32>())),
+    };
+    let cmd = format!("cd {} && find . -mindepth 1{} -exec stat -c '%F|%s|%Y|%n' {{}} +", quote(path), depth);
+
+    let mut entries = Vec::new();
+    for line in try!(run(shell, &cmd)).concat().lines() {
+        let cols: Vec<&str> = line.splitn(4, '|').collect();
+        if cols.len() != 4 {
+            return Err(Error::HostResponse);
+        }
+        entries.push((Path::new(path).join(relative(cols[3])), entry_type(cols[0]), cols[1].to_string(), cols[2].to_string()));
+    }
+
+    // Paths compare by component, which puts each directory's
+    // contents straight after it.
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut frames = Vec::new();
+    for (entry_path, entry_type, size, mtime) in entries {
+        frames.push(try!(entry_path.to_str().ok_or(Error::Generic("Path is not valid UTF-8".into()))).to_string());
+        frames.push(entry_type.into());
+        frames.push(size);
+        frames.push(mtime);
+    }
+    Ok(frames)
+}
+
+// Get the checksums of every file beneath a directory as pairs of
+// relative path and checksum frames, where directories have an empty
+// checksum. Like a local directory, symlinks to directories and
+// anything else that is not a file are skipped.
+fn checksums<S: Shell>(shell: &mut S, path: &str) -> Result<Vec<String>> {
+    if try!(shell.stat(Path::new(path))).is_none() {
+        return Ok(vec![]);
+    }
+
+    let mut frames = Vec::new();
+    for dir in try!(run(shell, &format!("cd {} && find . -mindepth 1 -type d", quote(path)))).concat().lines() {
+        frames.push(relative(dir).to_string());
+        frames.push(String::new());
+    }
+
+    // macOS has `shasum` rather than `sha256sum`, which prints the
+    // same format.
+    let cmd = format!("if command -v sha256sum >/dev/null; then h=sha256sum; else h='shasum -a 256'; fi; \
+                       cd {} && find . -mindepth 1 \\( -type f -o \\( -type l -exec test -f {{}} \\; \\) \\) -exec $h {{}} +", quote(path));
+    for line in try!(run(shell, &cmd)).concat().lines() {
+        // GNU sha256sum escapes names with backslashes or newlines,
+        // and marks their lines with a leading backslash.
+        let (line, escaped) = if line.starts_with('\\') { (&line[1..], true) } else { (line, false) };
+        let mut cols = line.splitn(2, "  ");
+        let (hash, name) = match (cols.next(), cols.next()) {
+            (Some(hash), Some(name)) => (hash, name),
+            _ => return Err(Error::HostResponse),
+        };
+
+        let name = relative(name);
+        frames.push(if escaped { name.replace("\\n", "\n").replace("\\\\", "\\") } else { name.to_string() });
+        frames.push(hash.to_string());
+    }
+
+    Ok(frames)
+}
+
+// Strip the "./" that `find .` prefixes paths with.
+fn relative(path: &str) -> &str {
+    if path.starts_with("./") { &path[2..] } else { path }
+}
+
+// Map the file types printed by `stat -c %F` to the Agent's names.
+fn entry_type(file_type: &str) -> &'static str {
+    match file_type {
+        "regular file" | "regular empty file" => "file",
+        "directory" => "directory",
+        "symbolic link" => "symlink",
+        _ => "other",
+    }
+}
+
+// Get the frames of a UserInfo: uid, gid, home, shell and groups.
+fn user_get<S: Shell>(shell: &mut S, name: &str) -> Result<Vec<String>> {
+    let entry = match try!(account_entry(shell, "passwd", name)) {
+        Some(entry) => entry,
+        None => return Ok(vec![]),
+    };
+
+    // name:password:uid:gid:gecos:home:shell
+    let fields: Vec<&str> = entry.split(':').collect();
+    if fields.len() < 7 {
+        return Err(Error::Generic(format!("Invalid passwd entry for user {}", name)));
+    }
+
+    // The first group is the user's primary group
+    let groups = try!(run(shell, &format!("id -Gn {}", quote(name)))).concat();
+    let groups: Vec<&str> = groups.split_whitespace().skip(1).collect();
+
+    Ok(vec![fields[2].into(), fields[3].into(), fields[5].into(), fields[6].into(), groups.join(",")])
+}
+
+// Build a shadow utilities command, or its `pw` equivalent on hosts
+// without them, e.g. FreeBSD. `opts` is appended to the command.
+fn account_cmd(tool: &str, name: &str, opts: &str) -> String {
+    format!("if command -v {0} >/dev/null; then {0}{2} {1}; else pw {0} {1}{2}; fi", tool, quote(name), opts)
+}
+
+// Get a user or group's entry from `getent`, or from /etc on hosts
+// without it, e.g. Alpine.
+fn account_entry<S: Shell>(shell: &mut S, db: &str, name: &str) -> Result<Option<String>> {
+    let cmd = format!("if command -v getent >/dev/null; then getent {0} {1}; else awk -F: -v n={1} '$1 == n' /etc/{0}; fi", db, quote(name));
+    let (code, stdout, _) = try!(shell.exec(&cmd));
+
+    Ok(match stdout.lines().next() {
+        Some(entry) if code == 0 => Some(entry.to_string()),
+        _ => None,
+    })
+}
+
+// Find the package provider to use when none is given, by looking for
+// each provider's binary in order of preference.
+fn default_provider<S: Shell>(shell: &mut S) -> Result<Vec<String>> {
+    let providers = [("apk", "Apk"), ("apt-get", "Apt"), ("dnf", "Dnf"), ("yum", "Yum"), ("zypper", "Zypper"),
+                     ("pacman", "Pacman"), ("pkg", "Pkg"), ("brew", "Homebrew"), ("port", "Macports")];
+    let bins: Vec<&str> = providers.iter().map(|&(bin, _)| bin).collect();
+
+    let cmd = format!("for b in {}; do command -v $b >/dev/null && echo $b && exit 0; done; exit 1", bins.join(" "));
+    let (code, stdout, _) = try!(shell.exec(&cmd));
+
+    match providers.iter().find(|&&(bin, _)| code == 0 && bin == stdout) {
+        Some(&(_, provider)) => Ok(vec![provider.into()]),
+        None => Err(Error::Generic("No package providers are available".into())),
+    }
+}
+
+fn service_action<S: Shell>(shell: &mut S, name: &str, action: &str) -> Result<Vec<String>> {
+    let systemd = try!(shell.exec("command -v systemctl")).0 == 0;
+    let status = if systemd {
+        format!("systemctl is-active {}", quote(name))
+    } else {
+        format!("service {} status", quote(name))
+    };
+
+    if action == "start" || action == "stop" {
+        let running = try!(shell.exec(&status)).0 == 0;
+        if running == (action == "start") {
+            return Ok(vec![]);
+        }
+    }
+
+    let cmd = if systemd {
+        format!("systemctl {} {}", quote(action), quote(name))
+    } else {
+        format!("service {} {}", quote(name), quote(action))
+    };
+    let (code, stdout, stderr) = try!(shell.exec(&cmd));
+    Ok(vec![code.to_string(), stdout, stderr])
+}
+
+fn telemetry<S: Shell>(shell: &mut S) -> Result<Value> {
+    let (_, hostname, _) = try!(shell.exec("hostname -f 2>/dev/null || hostname"));
+    let (_, arch, _) = try!(shell.exec("uname -m"));
+    let (_, os_release, _) = try!(shell.exec("cat /etc/os-release"));
+    let (_, cpuinfo, _) = try!(shell.exec("cat /proc/cpuinfo"));
+    let (_, cores, _) = try!(shell.exec("nproc"));
+    let (_, meminfo, _) = try!(shell.exec("cat /proc/meminfo"));
+    let (_, df, _) = try!(shell.exec("df -Pk"));
+
+    let (platform, family, version_str) = parse_os_release(&os_release);
+    let (maj, min, patch) = parse_version(&version_str);
+
+    let mut cpu: Map<String, Value> = Map::new();
+    cpu.insert("vendor".into(), to_value(cpuinfo_field(&cpuinfo, "vendor_id")));
+    cpu.insert("brand_string".into(), to_value(cpuinfo_field(&cpuinfo, "model name")));
+    cpu.insert("cores".into(), to_value(cores.trim().parse::<u32>().unwrap_or(1)));
+
+    let mut os: Map<String, Value> = Map::new();
+    os.insert("arch".into(), to_value(arch));
+    os.insert("family".into(), to_value(family));
+    os.insert("platform".into(), to_value(platform));
+    os.insert("version_str".into(), to_value(version_str));
+    os.insert("version_maj".into(), to_value(maj));
+    os.insert("version_min".into(), to_value(min));
+    os.insert("version_patch".into(), to_value(patch));
+
+    let mut telemetry: Map<String, Value> = Map::new();
+    telemetry.insert("cpu".into(), to_value(cpu));
+    telemetry.insert("fs".into(), to_value(parse_df(&df)));
+    telemetry.insert("hostname".into(), to_value(hostname));
+    telemetry.insert("memory".into(), to_value(parse_meminfo(&meminfo)));
+    telemetry.insert("net".into(), Value::Array(Vec::new()));
+    telemetry.insert("os".into(), to_value(os));
+
+    // Match the namespacing of the Agent's telemetry
+    let mut t_map: Map<String, Value> = Map::new();
+    t_map.insert("_telemetry".into(), to_value(telemetry));
+    Ok(to_value(t_map))
+}
+
+fn download<S: Shell>(shell: &mut S, msg: Message) -> Result<VecDeque<Message>> {
+    if msg.popstr().map(|f| f.ok()) != Some(Some("DOWNLOAD".into())) {
+        return Err(Error::HostResponse);
+    }
+    let path = try!(try!(msg.popstr().ok_or(Error::HostResponse)).or(Err(Error::HostResponse)));
+
+    let contents = match try!(shell.read(Path::new(&path))) {
+        Some(c) => c,
+        None => return Err(Error::Generic(format!("{} does not exist", path))),
+    };
+
+    let mut replies = VecDeque::new();
+    let chunks: Vec<&[u8]> = contents.chunks(CHUNK_SIZE).collect();
+    let header = Message::new();
+    try!(header.addstr("Ok"));
+    try!(header.addstr(&chunks.len().to_string()));
+    replies.push_back(header);
+
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let reply = Message::new();
+        try!(reply.addstr("Ok"));
+        try!(reply.addstr(&index.to_string()));
+        try!(reply.addbytes(chunk));
+        replies.push_back(reply);
+    }
+
+    Ok(replies)
+}
+
+fn flag(b: bool) -> String {
+    if b { "1".into() } else { "0".into() }
+}
+
+fn cpuinfo_field(cpuinfo: &str, field: &str) -> String {
+    cpuinfo.lines()
+           .find(|l| l.split(':').next().map(|k| k.trim()) == Some(field))
+           .and_then(|l| l.splitn(2, ':').nth(1))
+           .map(|v| v.trim().to_string())
+           .unwrap_or_default()
+}
+
+/// Get the platform, family and version from /etc/os-release.
+pub fn parse_os_release(os_release: &str) -> (String, String, String) {
+    let mut id = String::new();
+    let mut id_like = String::new();
+    let mut version = String::new();
+
+    for line in os_release.lines() {
+        let mut kv = line.splitn(2, '=');
+        let key = kv.next().unwrap_or("").trim();
+        let value = kv.next().unwrap_or("").trim().trim_matches('"').to_string();
+        match key {
+            "ID" => id = value,
+            "ID_LIKE" => id_like = value,
+            "VERSION_ID" => version = value,
+            _ => (),
+        }
+    }
+
+    let family = if id == "debian" || id_like.split_whitespace().any(|i| i == "debian") {
+        "debian"
+    } else if ["centos", "fedora", "rhel"].contains(&id.as_str()) || id_like.split_whitespace().any(|i| i == "rhel" || i == "fedora") {
+        "redhat"
+    } else {
+        "linux"
+    };
+
+    (id, family.into(), version)
+}
+
+/// Split a version into its major, minor and patch numbers.
+pub fn parse_version(version: &str) -> (u32, u32, u32) {
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().unwrap_or(0));
+    (parts.next().unwrap_or(0), parts.next().unwrap_or(0), parts.next().unwrap_or(0))
+}
+
+// Get total memory in bytes from /proc/meminfo.
+fn parse_meminfo(meminfo: &str) -> u64 {
+    meminfo.lines()
+           .find(|l| l.starts_with("MemTotal:"))
+           .and_then(|l| l.split_whitespace().nth(1))
+           .and_then(|kb| kb.parse::<u64>().ok())
+           .map(|kb| kb * 1024)
+           .unwrap_or(0)
+}
+
+// Parse the output of `df -Pk` into the Agent's fs telemetry.
+fn parse_df(df: &str) -> Vec<Value> {
+    let mut mounts = Vec::new();
+
+    for line in df.lines().skip(1) {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        if cols.len() < 6 {
+            continue;
+        }
+
+        let mut map: Map<String, Value> = Map::new();
+        map.insert("filesystem".into(), to_value(cols[0]));
+        map.insert("mountpoint".into(), to_value(cols[5..].join(" ")));
+        map.insert("size".into(), to_value(cols[1].parse::<u64>().unwrap_or(0)));
+        map.insert("used".into(), to_value(cols[2].parse::<u64>().unwrap_or(0)));
+        map.insert("available".into(), to_value(cols[3].parse::<u64>().unwrap_or(0)));
+        map.insert("capacity".into(), to_value(cols[4].trim_right_matches('%').parse::<f32>().unwrap_or(0.0) / 100.0));
+        mounts.push(to_value(map));
+    }
+
+    mounts
+}
+
+#[cfg(test)]
+mod tests {
+    use directory;
+    use error::Result;
+    use std::collections::BTreeMap;
+    use std::{fs, process};
+    use std::io::{self, Read, Write};
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use std::path::Path;
+    use super::*;
+    use super::super::Message;
+    use tempdir::TempDir;
+
+    // Answers requests on the local machine, as a remote host would.
+    struct LocalShell;
+
+    impl Shell for LocalShell {
+        fn exec(&mut self, cmd: &str) -> Result<(i32, String, String)> {
+            let output = try!(process::Command::new("sh").arg("-c").arg(cmd).output());
+            Ok((output.status.code().unwrap_or(-1),
+                String::from_utf8_lossy(&output.stdout).trim_right().to_string(),
+                String::from_utf8_lossy(&output.stderr).trim_right().to_string()))
+        }
+
+        fn stat(&mut self, path: &Path) -> Result<Option<bool>> {
+            match fs::metadata(path) {
+                Ok(meta) => Ok(Some(meta.is_dir())),
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        }
+
+        fn rename(&mut self, path: &Path, new_path: &Path) -> Result<()> {
+            Ok(try!(fs::rename(path, new_path)))
+        }
+
+        fn unlink(&mut self, path: &Path) -> Result<()> {
+            Ok(try!(fs::remove_file(path)))
+        }
+
+        fn read(&mut self, path: &Path) -> Result<Option<Vec<u8>>> {
+            let mut contents = Vec::new();
+            match fs::File::open(path) {
+                Ok(mut fh) => { try!(fh.read_to_end(&mut contents)); },
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+            Ok(Some(contents))
+        }
+
+        fn write(&mut self, path: &Path, contents: &mut Read) -> Result<()> {
+            let mut fh = try!(fs::File::create(path));
+            try!(io::copy(contents, &mut fh));
+            Ok(())
+        }
+    }
+
+    // Send a request, returning the reply's frames after "Ok".
+    fn request(frames: &[&str]) -> Vec<String> {
+        let reply = handle(&mut LocalShell, Message::from(frames)).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = reply.popstr() {
+            frames.push(frame.unwrap());
+        }
+        assert_eq!(frames.remove(0), "Ok", "{:?}", frames);
+        frames
+    }
+
+    #[test]
+    fn test_file_edit() {
+        let tempdir = TempDir::new("test_shell_file_edit").unwrap();
+        let path = tempdir.path().join("file");
+        let link = tempdir.path().join("link");
+        fs::File::create(&path).unwrap().write_all(b"a\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&path, &link).unwrap();
+
+        let link = link.to_str().unwrap();
+        assert_eq!(request(&["file::edit", link, "line_present", "b"]), vec!["1"]);
+        assert_eq!(request(&["file::edit", link, "line_present", "b"]), vec!["0"]);
+
+        let mut content = String::new();
+        fs::File::open(&path).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "a\nb\n");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(fs::symlink_metadata(link).unwrap().file_type().is_symlink());
+    }
+
+    #[test]
+    fn test_file_metadata() {
+        let tempdir = TempDir::new("test_shell_file_metadata").unwrap();
+        let path = tempdir.path().join("file");
+        fs::File::create(&path).unwrap().write_all(b"Hello").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let frames = request(&["file::metadata", path.to_str().unwrap()]);
+        assert_eq!(frames.len(), 12);
+        assert_eq!(&frames[..3], &["file", "5", "640"]);
+        assert_eq!(frames[11], "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969");
+
+        let frames = request(&["file::metadata", tempdir.path().to_str().unwrap()]);
+        assert_eq!(frames[0], "directory");
+        assert_eq!(frames[11], "");
+    }
+
+    #[test]
+    fn test_directory_list() {
+        let tempdir = TempDir::new("test_shell_directory_list").unwrap();
+        fs::create_dir_all(tempdir.path().join("a/b")).unwrap();
+        fs::File::create(tempdir.path().join("a/b/c")).unwrap().write_all(b"abc").unwrap();
+        fs::File::create(tempdir.path().join("a-d")).unwrap();
+        symlink(tempdir.path().join("a"), tempdir.path().join("e")).unwrap();
+
+        let path = tempdir.path().to_str().unwrap();
+        for depth in &[None, Some(0), Some(2)] {
+            let mut frames = Vec::new();
+            for entry in directory::list(path, *depth).unwrap() {
+                frames.push(entry.path.to_str().unwrap().to_string());
+                frames.push(entry.entry_type.to_string());
+                frames.push(entry.size.to_string());
+                frames.push(entry.mtime.to_string());
+            }
+            assert_eq!(request(&["directory::list", path, &depth.map(|d| d.to_string()).unwrap_or_default()]), frames);
+        }
+    }
+
+    #[test]
+    fn test_directory_checksums() {
+        let tempdir = TempDir::new("test_shell_directory_checksums").unwrap();
+        fs::create_dir_all(tempdir.path().join("a/b")).unwrap();
+        fs::File::create(tempdir.path().join("a/b/c")).unwrap().write_all(b"abc").unwrap();
+        fs::File::create(tempdir.path().join("d")).unwrap();
+        symlink(tempdir.path().join("a"), tempdir.path().join("e")).unwrap();
+        symlink(tempdir.path().join("d"), tempdir.path().join("f")).unwrap();
+
+        let path = tempdir.path().to_str().unwrap();
+        let frames = request(&["directory::checksums", path]);
+        let mut checksums = BTreeMap::new();
+        for pair in frames.chunks(2) {
+            checksums.insert(pair[0].clone(), if pair[1].is_empty() { None } else { Some(pair[1].clone()) });
+        }
+        assert_eq!(checksums, directory::checksums(path).unwrap());
+
+        assert!(request(&["directory::checksums", "/path/to/nowhere"]).is_empty());
+    }
+
+    #[test]
+    fn test_links() {
+        let tempdir = TempDir::new("test_shell_links").unwrap();
+        let target = tempdir.path().join("target");
+        fs::File::create(&target).unwrap();
+
+        let target = target.to_str().unwrap();
+        let link = tempdir.path().join("link");
+        let link = link.to_str().unwrap();
+        let hard = tempdir.path().join("hard");
+        let hard = hard.to_str().unwrap();
+
+        assert!(request(&["link::read", link]).is_empty());
+        request(&["link::create", link, target]);
+        assert_eq!(request(&["link::read", link]), vec![target]);
+
+        assert_eq!(request(&["link::is_hard", hard, target]), vec!["0"]);
+        request(&["link::create_hard", hard, target]);
+        assert_eq!(request(&["link::is_hard", hard, target]), vec!["1"]);
+        assert_eq!(request(&["link::is_hard", link, target]), vec!["0"]);
+
+        request(&["link::delete", link]);
+        assert!(fs::symlink_metadata(link).is_err());
+    }
+
+    #[test]
+    fn test_user_get() {
+        assert_eq!(request(&["user::get", "root"])[..2], ["0", "0"]);
+        assert!(request(&["user::get", "no-such-user"]).is_empty());
+        assert_eq!(request(&["group::get", "root"])[0], "0");
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let reply = handle(&mut LocalShell, Message::from(&["bogus::request"][..])).unwrap();
+        assert_eq!(reply.popstr().unwrap().unwrap(), "Err");
+    }
+
+    #[test]
+    fn test_parse_os_release() {
+        let os_release = "NAME=\"Ubuntu\"\nID=ubuntu\nID_LIKE=debian\nVERSION_ID=\"16.04\"\n";
+        assert_eq!(parse_os_release(os_release), ("ubuntu".into(), "debian".into(), "16.04".into()));
+        assert_eq!(parse_version("16.04"), (16, 4, 0));
+
+        let os_release = "ID=\"centos\"\nID_LIKE=\"rhel fedora\"\nVERSION_ID=\"7\"\n";
+        assert_eq!(parse_os_release(os_release), ("centos".into(), "redhat".into(), "7".into()));
+    }
+
+    #[test]
+    fn test_parse_meminfo() {
+        assert_eq!(parse_meminfo("MemTotal:        2048 kB\nMemFree:          1024 kB\n"), 2097152);
+    }
+
+    #[test]
+    fn test_parse_df() {
+        let df = "Filesystem     1024-blocks    Used Available Capacity Mounted on\n\
+                  /dev/sda1         10000    2500      7500      25% /\n";
+        let mounts = parse_df(df);
+        assert_eq!(mounts.len(), 1);
+        assert_eq!(mounts[0].find("mountpoint").unwrap().as_str(), Some("/"));
+        assert_eq!(mounts[0].find("used").unwrap().as_u64(), Some(2500));
+    }
+}
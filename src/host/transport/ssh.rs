@@ -0,0 +1,416 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! SSH transport, for hosts that do not run an Agent.
+//!
+//! `SshTransport` answers requests itself by running shell commands
+//! over SSH and accessing files over SFTP. The host's key is checked
+//! before logging in, so that credentials are never sent to a machine
+//! posing as the host.
+
+use error::{Error, Result};
+use host::ConnectOptions;
+use ssh2::{self, Channel, CheckResult, HashType, KnownHostFileKind, Session, Sftp};
+use std::collections::VecDeque;
+use std::{env, fs};
+use std::io::{self, Read};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use super::{Message, Transport};
+use super::shell::{self, Shell};
+use zfilexfer::FileOptions;
+
+/// libssh2 error code for an SFTP status, such as a missing file
+const LIBSSH2_ERROR_SFTP_PROTOCOL: i32 = -31;
+/// SFTP status for a missing file
+const LIBSSH2_FX_NO_SUCH_FILE: i32 = 2;
+
+/// Credentials used to log in to a host over SSH.
+#[derive(Clone, Debug)]
+pub enum SshAuth {
+    /// Use the keys held by the local SSH agent
+    Agent,
+    /// Use a private key file, with an optional passphrase
+    KeyFile(PathBuf, Option<String>),
+    /// Use a password
+    Password(String),
+}
+
+/// How the host's key is verified before logging in.
+#[derive(Clone, Debug)]
+pub enum SshHostKey {
+    /// Look the key up in a known_hosts file, defaulting to
+    /// `~/.ssh/known_hosts`. Unknown hosts are refused.
+    KnownHosts(Option<PathBuf>),
+    /// Compare the key's SHA-1 fingerprint, given as hex with or
+    /// without colons
+    Fingerprint(String),
+    /// Accept any key. Anyone between this machine and the host can
+    /// pose as it, so only use this on trusted networks.
+    Insecure,
+}
+
+/// Transport that manages a host over SSH, without an Agent.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use inapi::{ConnectOptions, Host, SshAuth, SshHostKey, SshTransport};
+/// let transport = SshTransport::connect("example.com", 22, "root", &SshAuth::Agent, &SshHostKey::KnownHosts(None), &ConnectOptions::new()).unwrap();
+/// let mut host = Host::from_transport("example.com", Box::new(transport));
+/// ```
+pub struct SshTransport {
+    /// SSH session, or None if the session timed out
+    session: Option<Session>,
+    /// The TCP connection underlying the session, which must outlive
+    /// it
+    _tcp: Option<TcpStream>,
+    /// Reply to the last API request
+    reply: Option<Message>,
+    /// Replies to the last file channel request
+    file_replies: VecDeque<Message>,
+    /// Details needed to log in again
+    hostname: String,
+    port: u16,
+    user: String,
+    auth: SshAuth,
+    host_key: SshHostKey,
+    options: ConnectOptions,
+}
+
+// An SSH session must not be used by more than one thread at a time,
+// but can safely be moved between threads. As every session operation
+// requires `&mut SshTransport`, the session can only be used by the
+// one thread that owns the transport.
+unsafe impl Send for SshTransport {}
+
+impl SshTransport {
+    /// Log in to `hostname` as `user`, once its key has been verified
+    /// with `host_key`.
+    pub fn connect(hostname: &str, port: u16, user: &str, auth: &SshAuth, host_key: &SshHostKey, opts: &ConnectOptions) -> Result<SshTransport> {
+        let mut me = SshTransport {
+            session: None,
+            _tcp: None,
+            reply: None,
+            file_replies: VecDeque::new(),
+            hostname: hostname.into(),
+            port: port,
+            user: user.into(),
+            auth: auth.clone(),
+            host_key: host_key.clone(),
+            options: *opts,
+        };
+        try!(me.login());
+        Ok(me)
+    }
+
+    fn login(&mut self) -> Result<()> {
+        let addr = match try!((self.hostname.as_str(), self.port).to_socket_addrs()).next() {
+            Some(addr) => addr,
+            None => return Err(Error::Generic(format!("Could not resolve {}", self.hostname))),
+        };
+        let tcp = match TcpStream::connect_timeout(&addr, Duration::from_millis(self.options.connect_timeout as u64)) {
+            Ok(tcp) => tcp,
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => return Err(Error::Timeout),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut session = match Session::new() {
+            Some(s) => s,
+            None => return Err(Error::Generic("Could not create SSH session".into())),
+        };
+        session.set_timeout(self.options.connect_timeout);
+        try!(session.handshake(&tcp));
+        try!(self.check_host_key(&session));
+
+        match self.auth {
+            SshAuth::Agent => try!(session.userauth_agent(&self.user)),
+            SshAuth::KeyFile(ref key, ref passphrase) => try!(session.userauth_pubkey_file(&self.user, None, key, passphrase.as_ref().map(|p| p.as_str()))),
+            SshAuth::Password(ref password) => try!(session.userauth_password(&self.user, password)),
+        }
+
+        if !session.authenticated() {
+            return Err(Error::Generic(format!("Could not log in to {} as {}", self.hostname, self.user)));
+        }
+
+        session.set_timeout(self.options.request_timeout);
+        self.session = Some(session);
+        self._tcp = Some(tcp);
+        Ok(())
+    }
+
+    fn check_host_key(&self, session: &Session) -> Result<()> {
+        match self.host_key {
+            SshHostKey::KnownHosts(ref path) => {
+                let path = match *path {
+                    Some(ref path) => path.clone(),
+                    None => match env::var_os("HOME") {
+                        Some(home) => Path::new(&home).join(".ssh/known_hosts"),
+                        None => return Err(Error::Generic("Could not find known_hosts as HOME is not set".into())),
+                    },
+                };
+
+                let mut known_hosts = try!(session.known_hosts());
+                if let Err(e) = known_hosts.read_file(&path, KnownHostFileKind::OpenSSH) {
+                    return Err(Error::Generic(format!("Could not read {}: {}", path.display(), e)));
+                }
+
+                let key = match session.host_key() {
+                    Some((key, _)) => key,
+                    None => return Err(Error::Generic(format!("{} did not send a host key", self.hostname))),
+                };
+                match known_hosts.check_port(&self.hostname, self.port, key) {
+                    CheckResult::Match => Ok(()),
+                    CheckResult::Mismatch => Err(Error::Generic(format!("The host key of {} does not match the key in {}. Another machine may be posing as the host.",
+                                                                        self.hostname, path.display()))),
+                    CheckResult::NotFound => Err(Error::Generic(format!("{} is not in {}. Add its key, or give its fingerprint instead.",
+                                                                        self.hostname, path.display()))),
+                    CheckResult::Failure => Err(Error::Generic(format!("Could not check the host key of {}", self.hostname))),
+                }
+            },
+            SshHostKey::Fingerprint(ref expected) => {
+                let actual = match session.host_key_hash(HashType::Sha1) {
+                    Some(hash) => fingerprint(hash),
+                    None => return Err(Error::Generic(format!("{} did not send a host key", self.hostname))),
+                };
+
+                if actual == expected.replace(':', "").to_lowercase() {
+                    Ok(())
+                } else {
+                    Err(Error::Generic(format!("The host key fingerprint of {} is {}, not {}. Another machine may be posing as the host.",
+                                               self.hostname, actual, expected)))
+                }
+            },
+            SshHostKey::Insecure => Ok(()),
+        }
+    }
+
+    fn session(&self) -> Result<&Session> {
+        self.session.as_ref().ok_or(Error::HostDisconnected)
+    }
+
+    fn sftp(&self) -> Result<Sftp> {
+        Ok(try!(try!(self.session()).sftp()))
+    }
+}
+
+impl Shell for SshTransport {
+    fn exec(&mut self, cmd: &str) -> Result<(i32, String, String)> {
+        let session = try!(self.session());
+        let mut channel = try!(session.channel_session());
+        try!(channel.exec(cmd));
+
+        // Read stdout and stderr together, as the command blocks once
+        // either of them fills the channel's window.
+        session.set_blocking(false);
+        let output = drain(&mut channel, self.options.request_timeout);
+        session.set_blocking(true);
+        let (stdout, stderr) = try!(output);
+        try!(channel.wait_close());
+
+        Ok((try!(channel.exit_status()),
+            String::from_utf8_lossy(&stdout).trim_right().into(),
+            String::from_utf8_lossy(&stderr).trim_right().into()))
+    }
+
+    fn stat(&mut self, path: &Path) -> Result<Option<bool>> {
+        let sftp = try!(self.sftp());
+        match sftp.stat(path) {
+            Ok(stat) => Ok(Some(stat.is_dir())),
+            Err(ref e) if not_found(&sftp, e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn rename(&mut self, path: &Path, new_path: &Path) -> Result<()> {
+        Ok(try!(try!(self.sftp()).rename(path, new_path, None)))
+    }
+
+    fn unlink(&mut self, path: &Path) -> Result<()> {
+        Ok(try!(try!(self.sftp()).unlink(path)))
+    }
+
+    fn read(&mut self, path: &Path) -> Result<Option<Vec<u8>>> {
+        let sftp = try!(self.sftp());
+        let mut fh = match sftp.open(path) {
+            Ok(fh) => fh,
+            Err(ref e) if not_found(&sftp, e) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut contents = Vec::new();
+        try!(fh.read_to_end(&mut contents));
+        Ok(Some(contents))
+    }
+
+    fn write(&mut self, path: &Path, contents: &mut Read) -> Result<()> {
+        let sftp = try!(self.sftp());
+        let mut fh = try!(sftp.create(path));
+        try!(io::copy(contents, &mut fh));
+        Ok(())
+    }
+}
+
+// Format a host key hash as lowercase hex.
+fn fingerprint(hash: &[u8]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Read a channel's stdout and stderr until both reach EOF. The session
+// must be non-blocking.
+fn drain(channel: &mut Channel, timeout: u32) -> Result<(Vec<u8>, Vec<u8>)> {
+    let deadline = Instant::now() + Duration::from_millis(timeout as u64);
+    let mut output = (Vec::new(), Vec::new());
+    let mut eof = (false, false);
+    let mut buf = [0; 8192];
+
+    while !(eof.0 && eof.1) {
+        let mut idle = true;
+
+        if !eof.0 {
+            match try!(read_some(&mut channel.stream(0), &mut buf)) {
+                Some(0) => eof.0 = true,
+                Some(n) => { output.0.extend_from_slice(&buf[..n]); idle = false; },
+                None => (),
+            }
+        }
+        if !eof.1 {
+            match try!(read_some(&mut channel.stderr(), &mut buf)) {
+                Some(0) => eof.1 = true,
+                Some(n) => { output.1.extend_from_slice(&buf[..n]); idle = false; },
+                None => (),
+            }
+        }
+
+        if idle {
+            // A timeout of 0 waits forever, as it does for a session
+            if timeout > 0 && Instant::now() > deadline {
+                return Err(Error::Timeout);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    Ok(output)
+}
+
+// Read from a stream, or None if it would block.
+fn read_some<R: Read>(stream: &mut R, buf: &mut [u8]) -> Result<Option<usize>> {
+    match stream.read(buf) {
+        Ok(n) => Ok(Some(n)),
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Check whether an SFTP error is for a missing file, rather than
+// another SFTP status such as permission denied.
+fn not_found(sftp: &Sftp, err: &ssh2::Error) -> bool {
+    err.code() == LIBSSH2_ERROR_SFTP_PROTOCOL && sftp.last_error().code() == LIBSSH2_FX_NO_SUCH_FILE
+}
+
+impl Transport for SshTransport {
+    fn send(&mut self, msg: Message) -> Result<()> {
+        if self.session.is_none() {
+            return Err(Error::HostDisconnected);
+        }
+
+        match shell::handle(self, msg) {
+            Ok(reply) => {
+                self.reply = Some(reply);
+                Ok(())
+            },
+            // A session that timed out may still be busy with the
+            // request, so it is discarded until the transport
+            // reconnects.
+            Err(Error::Timeout) => {
+                self.session = None;
+                self._tcp = None;
+                Err(Error::Timeout)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    fn recv(&mut self, _: u32) -> Result<Message> {
+        self.reply.take().ok_or(Error::HostResponse)
+    }
+
+    fn send_file(&mut self, msg: Message) -> Result<()> {
+        self.file_replies = try!(shell::handle_file(self, msg));
+        Ok(())
+    }
+
+    fn recv_file(&mut self, _: u32) -> Result<Message> {
+        self.file_replies.pop_front().ok_or(Error::HostResponse)
+    }
+
+    fn upload(&mut self, file: fs::File, remote_path: &Path, options: Option<&[FileOptions]>) -> Result<()> {
+        shell::upload(self, file, remote_path, options)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.session.is_some()
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        self.session = None;
+        self._tcp = None;
+        self.reply = None;
+        self.file_replies.clear();
+        self.login()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Command, Directory, File, FileEdit, Host};
+    use std::{env, fs};
+    use std::io::Write;
+    use super::*;
+    use tempdir::TempDir;
+
+    // Needs an sshd on localhost that accepts the current user's keys
+    // from the SSH agent, e.g. `cargo test --features ssh -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_localhost() {
+        let user = env::var("USER").unwrap();
+        assert!(SshTransport::connect("localhost", 22, &user, &SshAuth::Agent, &SshHostKey::Fingerprint("00".into()), &ConnectOptions::new()).is_err());
+        let transport = SshTransport::connect("localhost", 22, &user, &SshAuth::Agent, &SshHostKey::KnownHosts(None), &ConnectOptions::new()).unwrap();
+        let mut host = Host::from_transport("localhost", Box::new(transport));
+
+        // Output larger than the channel window on both streams
+        let result = Command::new("head -c 1000000 /dev/zero | tr '\\0' a; head -c 1000000 /dev/zero | tr '\\0' b >&2").exec(&mut host).unwrap();
+        assert_eq!(result.stdout.len(), 1000000);
+        assert_eq!(result.stderr.len(), 1000000);
+
+        let tempdir = TempDir::new("test_ssh_localhost").unwrap();
+        fs::create_dir(tempdir.path().join("a")).unwrap();
+        fs::File::create(tempdir.path().join("a/b")).unwrap().write_all(b"a\n").unwrap();
+
+        let file = File::new(&mut host, tempdir.path().join("a/b")).unwrap();
+        assert!(file.edit(&mut host, FileEdit::LinePresent("b".into())).unwrap().is_changed());
+        assert_eq!(file.read_to_string(&mut host).unwrap(), "a\nb\n");
+
+        let dir = Directory::new(&mut host, tempdir.path()).unwrap();
+        assert_eq!(dir.walk(&mut host, None).unwrap().len(), 2);
+
+        // Missing files are not SFTP errors
+        fs::remove_file(tempdir.path().join("a/b")).unwrap();
+        assert!(!file.exists(&mut host).unwrap());
+        assert_eq!(file.checksum(&mut host).unwrap(), None);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(&[0x0a, 0xff, 0x10]), "0aff10");
+    }
+}
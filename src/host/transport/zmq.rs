@@ -0,0 +1,265 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! ZeroMQ transport, encrypted with CURVE.
+
+use czmq::{ZCert, ZMsg, ZPoller, ZSock, SocketType};
+use error::{Error, Result};
+use host::ConnectOptions;
+use std::{cmp, fs, i32};
+use std::path::Path;
+use super::{Message, Transport};
+use zfilexfer::{self, FileOptions};
+
+/// Transport that talks to the Agent over a ZeroMQ REQ socket for
+/// API requests and a DEALER socket for file transfers.
+pub struct ZmqTransport {
+    /// API socket
+    api_sock: Option<ZSock>,
+    /// File transfer socket
+    file_sock: Option<ZSock>,
+    /// Details needed to re-create the sockets, if the transport
+    /// was connected to an endpoint
+    endpoint: Option<Endpoint>,
+}
+
+struct Endpoint {
+    /// Hostname or IP of managed host
+    hostname: String,
+    /// Agent API port
+    api_port: u32,
+    /// Agent file transfer port
+    file_port: u32,
+    /// The Agent's public CURVE key
+    server_key: String,
+    /// Time allowed for sending a message
+    send_timeout: u32,
+}
+
+// ZeroMQ sockets must not be used by more than one thread at a time,
+// but can safely be moved between threads. As every socket operation
+// requires `&mut ZmqTransport`, the sockets can only be used by the
+// one thread that owns the transport.
+unsafe impl Send for ZmqTransport {}
+
+impl ZmqTransport {
+    /// Connect to the Agent on `hostname`, looking up its public key
+    /// from the Auth server.
+    pub fn connect(hostname: &str, api_port: u32, file_port: u32, opts: &ConnectOptions) -> Result<ZmqTransport> {
+        let user_cert = try!(ZCert::load("user.crt"));
+        let server_cert = try!(lookup_server_cert(hostname, &user_cert, opts.connect_timeout));
+
+        let endpoint = Endpoint {
+            hostname: hostname.into(),
+            api_port: api_port,
+            file_port: file_port,
+            server_key: server_cert.public_txt().to_string(),
+            send_timeout: opts.request_timeout,
+        };
+        let (api_sock, file_sock) = try!(open_sockets(&endpoint, &user_cert));
+
+        Ok(ZmqTransport {
+            api_sock: Some(api_sock),
+            file_sock: Some(file_sock),
+            endpoint: Some(endpoint),
+        })
+    }
+
+    /// Wrap already connected sockets. The transport cannot
+    /// reconnect.
+    pub fn new(api_sock: Option<ZSock>, file_sock: Option<ZSock>) -> ZmqTransport {
+        ZmqTransport {
+            api_sock: api_sock,
+            file_sock: file_sock,
+            endpoint: None,
+        }
+    }
+}
+
+impl Transport for ZmqTransport {
+    fn send(&mut self, msg: Message) -> Result<()> {
+        if self.api_sock.is_none() {
+            return Err(Error::HostDisconnected);
+        }
+
+        try!(try!(to_zmsg(msg)).send(self.api_sock.as_mut().unwrap()));
+        Ok(())
+    }
+
+    fn recv(&mut self, timeout: u32) -> Result<Message> {
+        if self.api_sock.is_none() {
+            return Err(Error::HostDisconnected);
+        }
+
+        let result = recv_timeout(self.api_sock.as_mut().unwrap(), timeout);
+
+        // A REQ socket that is still waiting for a reply cannot send
+        // another request, so it is discarded until the transport
+        // reconnects.
+        if let Err(Error::Timeout) = result {
+            self.api_sock = None;
+        }
+
+        result.map(from_zmsg)
+    }
+
+    fn send_file(&mut self, msg: Message) -> Result<()> {
+        if self.file_sock.is_none() {
+            return Err(Error::HostDisconnected);
+        }
+
+        try!(try!(to_zmsg(msg)).send(self.file_sock.as_mut().unwrap()));
+        Ok(())
+    }
+
+    fn recv_file(&mut self, timeout: u32) -> Result<Message> {
+        if self.file_sock.is_none() {
+            return Err(Error::HostDisconnected);
+        }
+
+        recv_timeout(self.file_sock.as_mut().unwrap(), timeout).map(from_zmsg)
+    }
+
+    fn upload(&mut self, file: fs::File, remote_path: &Path, options: Option<&[FileOptions]>) -> Result<()> {
+        if self.file_sock.is_none() {
+            return Err(Error::HostDisconnected);
+        }
+
+        let mut zfile = try!(zfilexfer::File::open_file(file, options));
+        try!(zfile.send(self.file_sock.as_mut().unwrap(), remote_path));
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.api_sock.is_some()
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        let (api_sock, file_sock) = match self.endpoint {
+            Some(ref endpoint) => try!(open_sockets(endpoint, &try!(ZCert::load("user.crt")))),
+            None => return Err(Error::HostDisconnected),
+        };
+
+        self.api_sock = Some(api_sock);
+        self.file_sock = Some(file_sock);
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub fn from_zmsg(msg: ZMsg) -> Message {
+    let message = Message::new();
+    while let Some(frame) = msg.popstr() {
+        // Adding a frame to a Message cannot fail
+        match frame {
+            Ok(s) => message.addstr(&s).unwrap(),
+            Err(b) => message.addbytes(&b).unwrap(),
+        }
+    }
+    message
+}
+
+#[doc(hidden)]
+pub fn to_zmsg(msg: Message) -> Result<ZMsg> {
+    let zmsg = ZMsg::new();
+    while let Some(frame) = msg.popbytes() {
+        try!(zmsg.addbytes(&frame));
+    }
+    Ok(zmsg)
+}
+
+fn open_sockets(endpoint: &Endpoint, user_cert: &ZCert) -> Result<(ZSock, ZSock)> {
+    let mut api_sock = ZSock::new(SocketType::REQ);
+    user_cert.apply(&mut api_sock);
+    api_sock.set_curve_serverkey(&endpoint.server_key);
+    api_sock.set_sndtimeo(Some(sndtimeo(endpoint.send_timeout)));
+    try!(api_sock.connect(&format!("tcp://{}:{}", endpoint.hostname, endpoint.api_port)));
+
+    let mut file_sock = ZSock::new(SocketType::DEALER);
+    user_cert.apply(&mut file_sock);
+    file_sock.set_curve_serverkey(&endpoint.server_key);
+    file_sock.set_sndtimeo(Some(sndtimeo(endpoint.send_timeout)));
+    try!(file_sock.connect(&format!("tcp://{}:{}", endpoint.hostname, endpoint.file_port)));
+
+    Ok((api_sock, file_sock))
+}
+
+fn lookup_server_cert(hostname: &str, user_cert: &ZCert, timeout: u32) -> Result<ZCert> {
+    let auth_cert = try!(ZCert::load("auth.crt"));
+
+    let mut auth_sock = ZSock::new(SocketType::REQ);
+    user_cert.apply(&mut auth_sock);
+    auth_sock.set_curve_serverkey(auth_cert.public_txt());
+    auth_sock.set_sndtimeo(Some(sndtimeo(timeout)));
+    try!(auth_sock.connect(&format!("tcp://{}:{}", ::PROJECT_CONFIG.auth_server, ::PROJECT_CONFIG.auth_api_port)));
+
+    // Get server cert from Auth server
+    let msg = ZMsg::new();
+    try!(msg.addstr("cert::lookup"));
+    try!(msg.addstr(hostname));
+    try!(msg.send(&mut auth_sock));
+
+    let reply = try!(recv_timeout(&mut auth_sock, timeout));
+
+    if reply.size() != 2 {
+        return Err(Error::HostResponse);
+    }
+
+    match try!(reply.popstr().unwrap().or(Err(Error::HostResponse))).as_ref() {
+        "Ok" => {
+            let pk = try!(reply.popstr().unwrap().or(Err(Error::HostResponse)));
+            Ok(try!(ZCert::from_txt(&pk, "0000000000000000000000000000000000000000")))
+        },
+        "Err" => Err(Error::Auth(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))))),
+        _ => Err(Error::HostResponse),
+    }
+}
+
+// ZMQ takes send timeouts as an i32, so clamp longer timeouts rather
+// than letting them wrap to a negative (infinite) timeout.
+fn sndtimeo(ms: u32) -> i32 {
+    cmp::min(ms, i32::MAX as u32) as i32
+}
+
+fn recv_timeout(sock: &mut ZSock, timeout: u32) -> Result<ZMsg> {
+    let mut poller = try!(ZPoller::new());
+    try!(poller.add(sock));
+
+    let ready: Option<ZSock> = poller.wait(Some(timeout));
+    if ready.is_none() {
+        return Err(Error::Timeout);
+    }
+
+    Ok(try!(ZMsg::recv(sock)))
+}
+
+#[cfg(test)]
+mod tests {
+    use czmq::ZMsg;
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        let zmsg = ZMsg::new();
+        zmsg.addstr("moo").unwrap();
+        zmsg.addbytes(&[0, 159, 146, 150]).unwrap();
+
+        let msg = from_zmsg(zmsg);
+        assert_eq!(msg.size(), 2);
+
+        let zmsg = to_zmsg(msg).unwrap();
+        assert_eq!(zmsg.popstr().unwrap().unwrap(), "moo");
+        assert_eq!(zmsg.popstr().unwrap().unwrap_err(), vec![0, 159, 146, 150]);
+    }
+
+    #[test]
+    fn test_sndtimeo() {
+        assert_eq!(sndtimeo(1800000), 1800000);
+        assert_eq!(sndtimeo(u32::max_value()), i32::MAX);
+    }
+}
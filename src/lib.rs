@@ -16,16 +16,22 @@
 //!
 //! ## Communication
 //!
+//! A Host created with `Host::local()` runs primitives directly
+//! against the machine your code is running on. Any other Host sends
+//! them to a managed host, and both kinds of Host can be used in the
+//! same program.
+//!
 //! The API communicates with the Agent service on your managed hosts
 //! via a ZeroMQ "REQ" (REQuest) socket. No other socket types are
 //! supported by the Intecture Agent.
 //!
 //! **Note:** The Agent service must be running when you call any
-//! Intecture API primitives, or the program will hang while it
-//! attempts to connect to a non-existent socket.
+//! Intecture API primitives. If the Agent does not respond within
+//! the timeouts set by `ConnectOptions`, the primitive fails with
+//! `Error::Timeout`.
 
-#[cfg(feature = "remote-run")]
 extern crate czmq;
+extern crate crypto;
 #[macro_use]
 extern crate lazy_static;
 extern crate libc;
@@ -34,6 +40,8 @@ extern crate regex;
 extern crate rustc_serialize;
 extern crate serde;
 extern crate serde_json;
+#[cfg(feature = "ssh")]
+extern crate ssh2;
 #[cfg(test)]
 extern crate tempdir;
 extern crate tempfile;
@@ -47,52 +55,58 @@ mod project;
 pub mod directory;
 pub mod error;
 pub mod file;
+pub mod group;
 #[macro_use]
 mod host;
-#[cfg(all(test, feature = "remote-run"))]
+pub mod link;
+#[cfg(test)]
 mod mock_env;
+pub mod outcome;
 pub mod package;
-#[cfg(feature = "remote-run")]
 mod payload;
 pub mod service;
 mod target;
 pub mod template;
+pub mod user;
 
 pub use command::{Command, CommandResult};
-pub use directory::{Directory, DirectoryOpts};
+pub use directory::{Directory, DirectoryEntry, DirectoryOpts, DirectorySyncOpts, EntryType};
 pub use error::Error;
-pub use file::{File, FileOwner};
+pub use file::{File, FileContent, FileEdit, FileMetadata, FileOwner, FileSpec};
+pub use group::{Group, GroupInfo};
 pub use host::Host;
+pub use host::ConnectOptions;
+pub use host::{Become, BecomeMethod};
+pub use host::transport::{Container, ContainerTransport, Message, MemoryTransport, Transport, ZmqTransport};
+#[cfg(feature = "ssh")]
+pub use host::transport::{SshAuth, SshHostKey, SshTransport};
+pub use host::group::HostGroup;
+pub use host::inventory::Inventory;
 pub use host::data::open as data_open;
 pub use host::ffi::{host_data, get_value, get_value_keys, get_value_type};
-#[cfg(feature = "local-run")]
-pub use host::ffi::host_local;
-#[cfg(feature = "remote-run")]
-pub use host::ffi::{host_connect, host_connect_endpoint, host_connect_payload, host_close};
+pub use host::ffi::{host_local, host_connect, host_connect_endpoint, host_connect_payload, host_close};
+pub use link::Link;
 pub use mustache::{MapBuilder, VecBuilder};
-pub use package::Package;
+pub use outcome::{Outcome, Report, Status};
+pub use package::{CachePolicy, Package, PackageSet, Repository};
 pub use package::providers::{Provider, ProviderFactory, Providers};
-#[cfg(feature = "remote-run")]
 pub use payload::Payload;
-#[cfg(feature = "remote-run")]
 pub use payload::config::Config as PayloadConfig;
-#[cfg(feature = "remote-run")]
 pub use payload::ffi::{payload_new, payload_build, payload_run, payload_free};
 pub use project::{Language, ProjectConfig};
 pub use serde_json::Value;
 pub use service::{Service, ServiceRunnable};
 pub use template::Template;
+pub use user::{User, UserInfo, UserOpts};
 pub use zfilexfer::FileOptions;
 
-#[cfg(feature = "remote-run")]
 use zdaemon::ConfigFile;
 
-#[cfg(all(test, feature = "remote-run"))]
+#[cfg(test)]
 lazy_static! {
     static ref _MOCK_ENV: mock_env::MockEnv = mock_env::MockEnv::new();
 }
 
-#[cfg(feature = "remote-run")]
 lazy_static! {
     static ref PROJECT_CONFIG: project::ProjectConfig = project::ProjectConfig::load("project.json")
                                                                                .expect("Could not load project.json");
@@ -0,0 +1,202 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The primitive for managing symlinks and hard links on a managed
+//! host.
+//!
+//! # Examples
+//!
+//! Initialise a new Host using your managed host's IP address and
+//! port number:
+//!
+//! ```no_run
+//! # use inapi::Host;
+//! let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! ```
+//!
+//! Now point the `current` symlink at a new release:
+//!
+//! ```no_run
+//! # use inapi::{Host, Link};
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! let link = Link::new("/srv/app/current");
+//! link.create(&mut host, "/srv/app/releases/42").unwrap();
+//! ```
+
+use error::{Error, Result};
+use host::Host;
+use outcome::Outcome;
+use std::path::{Path, PathBuf};
+use target::Target;
+
+/// Container for operating on a link.
+pub struct Link {
+    /// Absolute path to the link on managed host
+    path: PathBuf,
+}
+
+impl Link {
+    /// Create a new Link struct.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use inapi::Link;
+    /// let link = Link::new("/srv/app/current");
+    /// ```
+    pub fn new<P: AsRef<Path>>(path: P) -> Link {
+        Link {
+            path: path.as_ref().into(),
+        }
+    }
+
+    /// Check if the path is a symlink.
+    pub fn is_symlink(&self, host: &mut Host) -> Result<bool> {
+        Ok(try!(self.read(host)).is_some())
+    }
+
+    /// Get the path a symlink points to, or None if the path is not a
+    /// symlink.
+    pub fn read(&self, host: &mut Host) -> Result<Option<PathBuf>> {
+        Target::link_read(host, self.path.as_path())
+    }
+
+    /// Make the path a symlink to `target`. An existing symlink that
+    /// points elsewhere is atomically replaced.
+    pub fn create<P: AsRef<Path>>(&self, host: &mut Host, target: P) -> Result<Outcome> {
+        let target = target.as_ref();
+        let outcome = match try!(self.read(host)) {
+            Some(ref current) if current.as_path() == target => Outcome::unchanged(&self.resource(), "create"),
+            Some(current) => Outcome::changed(&self.resource(), "retarget").values(current.display(), target.display()),
+            None => {
+                if try!(Target::file_exists(host, self.path.as_path())) {
+                    return Err(Error::Generic(format!("{} exists and is not a symlink", self.path.display())));
+                }
+
+                let mut outcome = Outcome::changed(&self.resource(), "create");
+                outcome.after = Some(target.display().to_string());
+                outcome
+            }
+        };
+
+        host.apply(outcome, |h| Target::link_create(h, self.path.as_path(), target))
+    }
+
+    /// Make the path a hard link to `target`. An existing file at the
+    /// path that is not already linked to `target` is replaced.
+    pub fn create_hard<P: AsRef<Path>>(&self, host: &mut Host, target: P) -> Result<Outcome> {
+        let target = target.as_ref();
+        let outcome = if try!(Target::link_is_hard(host, self.path.as_path(), target)) {
+            Outcome::unchanged(&self.resource(), "create_hard")
+        } else {
+            let mut outcome = Outcome::changed(&self.resource(), "create_hard");
+            outcome.after = Some(target.display().to_string());
+            outcome
+        };
+
+        host.apply(outcome, |h| Target::link_create_hard(h, self.path.as_path(), target))
+    }
+
+    /// Delete the symlink. The file it points to is left untouched.
+    pub fn delete(&self, host: &mut Host) -> Result<Outcome> {
+        let outcome = match try!(self.read(host)) {
+            Some(current) => {
+                let mut outcome = Outcome::changed(&self.resource(), "delete");
+                outcome.before = Some(current.display().to_string());
+                outcome
+            },
+            None => Outcome::unchanged(&self.resource(), "delete"),
+        };
+
+        host.apply(outcome, |h| Target::link_delete(h, self.path.as_path()))
+    }
+
+    fn resource(&self) -> String {
+        format!("link:{}", self.path.display())
+    }
+}
+
+pub trait LinkTarget<P: AsRef<Path>> {
+    fn link_read(host: &mut Host, path: P) -> Result<Option<PathBuf>>;
+    fn link_create(host: &mut Host, path: P, target: P) -> Result<()>;
+    fn link_create_hard(host: &mut Host, path: P, target: P) -> Result<()>;
+    fn link_is_hard(host: &mut Host, path: P, target: P) -> Result<bool>;
+    fn link_delete(host: &mut Host, path: P) -> Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use czmq::{ZMsg, ZSys};
+    use Host;
+    use std::thread;
+    use std::fs;
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_create_local() {
+        let tempdir = TempDir::new("test_link_create").unwrap();
+        let release1 = tempdir.path().join("release1");
+        let release2 = tempdir.path().join("release2");
+        fs::create_dir(&release1).unwrap();
+        fs::create_dir(&release2).unwrap();
+
+        let mut host = Host::local(None::<String>).unwrap();
+        let link = Link::new(tempdir.path().join("current"));
+
+        assert!(link.create(&mut host, &release1).unwrap().is_changed());
+        assert!(!link.create(&mut host, &release1).unwrap().is_changed());
+        assert_eq!(link.create(&mut host, &release2).unwrap().action, "retarget");
+        assert_eq!(link.read(&mut host).unwrap().unwrap(), release2);
+
+        assert!(link.delete(&mut host).unwrap().is_changed());
+        assert!(!link.is_symlink(&mut host).unwrap());
+        assert!(release2.exists());
+
+        let file = tempdir.path().join("file");
+        fs::File::create(&file).unwrap();
+        let hard = Link::new(tempdir.path().join("hard"));
+        assert!(hard.create_hard(&mut host, &file).unwrap().is_changed());
+        assert!(!hard.create_hard(&mut host, &file).unwrap().is_changed());
+        assert!(Link::new(&file).create(&mut host, &release1).is_err());
+    }
+
+    #[test]
+    fn test_create_remote() {
+        ZSys::init();
+
+        let (client, mut server) = ZSys::create_pipe().unwrap();
+
+        let agent_mock = thread::spawn(move || {
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("link::read", msg.popstr().unwrap().unwrap());
+            assert_eq!("/srv/app/current", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("/srv/app/releases/1").unwrap();
+            reply.send(&mut server).unwrap();
+
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("link::create", msg.popstr().unwrap().unwrap());
+            assert_eq!("/srv/app/current", msg.popstr().unwrap().unwrap());
+            assert_eq!("/srv/app/releases/2", msg.popstr().unwrap().unwrap());
+
+            server.send_str("Ok").unwrap();
+        });
+
+        let mut host = Host::test_new(None, Some(client), None, None);
+
+        let link = Link::new("/srv/app/current");
+        let outcome = link.create(&mut host, "/srv/app/releases/2").unwrap();
+        assert_eq!(outcome.action, "retarget");
+        assert_eq!(outcome.before.unwrap(), "/srv/app/releases/1");
+
+        agent_mock.join().unwrap();
+    }
+}
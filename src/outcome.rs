@@ -0,0 +1,260 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Outcomes of primitive operations and the aggregated run report.
+//!
+//! Every operation that can modify a managed host returns an
+//! `Outcome`, which is also recorded in the Host's `Report`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use inapi::{Host, File};
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! let file = File::new(&mut host, "/path/to/file").unwrap();
+//! let outcome = file.set_mode(&mut host, 644).unwrap();
+//! println!("{} {}: {:?}", outcome.resource, outcome.action, outcome.status);
+//!
+//! println!("{}", host.report().to_json());
+//! ```
+
+use command::CommandResult;
+use error::Result;
+use serde_json::{self, to_value, Value};
+use serde_json::value::Map;
+
+/// Whether an operation changed the state of a managed host.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    /// The host's state was changed (or would have been in check mode)
+    Changed,
+    /// The host was already in the desired state
+    Unchanged,
+    /// The operation failed
+    Failed,
+}
+
+impl ToString for Status {
+    fn to_string(&self) -> String {
+        match *self {
+            Status::Changed => "changed",
+            Status::Unchanged => "unchanged",
+            Status::Failed => "failed",
+        }.to_string()
+    }
+}
+
+/// The result of a single primitive operation.
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    /// The resource operated on, e.g. `file:/etc/hosts`
+    pub resource: String,
+    /// The operation performed, e.g. `set_mode`
+    pub action: String,
+    /// Whether the host's state changed
+    pub status: Status,
+    /// The resource's state before the operation, if known
+    pub before: Option<String>,
+    /// The resource's desired state after the operation, if known
+    pub after: Option<String>,
+    /// Error message for a failed operation
+    pub error: Option<String>,
+    /// Result of the shell command that performed the operation
+    pub result: Option<CommandResult>,
+}
+
+impl Outcome {
+    /// Create an Outcome for an operation that changed state.
+    pub fn changed(resource: &str, action: &str) -> Outcome {
+        Outcome {
+            resource: resource.to_string(),
+            action: action.to_string(),
+            status: Status::Changed,
+            before: None,
+            after: None,
+            error: None,
+            result: None,
+        }
+    }
+
+    /// Create an Outcome for an operation that left state untouched.
+    pub fn unchanged(resource: &str, action: &str) -> Outcome {
+        let mut outcome = Outcome::changed(resource, action);
+        outcome.status = Status::Unchanged;
+        outcome
+    }
+
+    /// Set the before and after values.
+    pub fn values<B: ToString, A: ToString>(mut self, before: B, after: A) -> Outcome {
+        self.before = Some(before.to_string());
+        self.after = Some(after.to_string());
+        self
+    }
+
+    /// Attach the result of the shell command that performed the
+    /// operation. A non-zero exit code marks the Outcome as failed.
+    pub fn command_result(mut self, result: CommandResult) -> Outcome {
+        if result.exit_code != 0 {
+            self.status = Status::Failed;
+        }
+        self.result = Some(result);
+        self
+    }
+
+    /// Whether the operation changed state.
+    pub fn is_changed(&self) -> bool {
+        self.status == Status::Changed
+    }
+
+    /// Whether the operation failed.
+    pub fn is_failed(&self) -> bool {
+        self.status == Status::Failed
+    }
+
+    pub fn into_value(self) -> Value {
+        let mut map: Map<String, Value> = Map::new();
+        map.insert("resource".into(), to_value(self.resource));
+        map.insert("action".into(), to_value(self.action));
+        map.insert("status".into(), to_value(self.status.to_string()));
+        if let Some(before) = self.before {
+            map.insert("before".into(), to_value(before));
+        }
+        if let Some(after) = self.after {
+            map.insert("after".into(), to_value(after));
+        }
+        if let Some(error) = self.error {
+            map.insert("error".into(), to_value(error));
+        }
+        if let Some(result) = self.result {
+            let mut map1: Map<String, Value> = Map::new();
+            map1.insert("exit_code".into(), to_value(result.exit_code));
+            map1.insert("stdout".into(), to_value(result.stdout));
+            map1.insert("stderr".into(), to_value(result.stderr));
+            map.insert("result".into(), to_value(map1));
+        }
+        to_value(map)
+    }
+}
+
+/// Aggregated outcomes of every operation run against a Host.
+#[derive(Clone, Debug)]
+pub struct Report {
+    /// Whether the Host was in check mode
+    pub check_mode: bool,
+    /// Outcomes in the order they were recorded
+    pub outcomes: Vec<Outcome>,
+}
+
+impl Report {
+    #[doc(hidden)]
+    pub fn new() -> Report {
+        Report {
+            check_mode: false,
+            outcomes: Vec::new(),
+        }
+    }
+
+    /// Number of operations that changed state.
+    pub fn changed(&self) -> usize {
+        self.count(Status::Changed)
+    }
+
+    /// Number of operations that left state untouched.
+    pub fn unchanged(&self) -> usize {
+        self.count(Status::Unchanged)
+    }
+
+    /// Number of operations that failed.
+    pub fn failed(&self) -> usize {
+        self.count(Status::Failed)
+    }
+
+    fn count(&self, status: Status) -> usize {
+        self.outcomes.iter().filter(|o| o.status == status).count()
+    }
+
+    pub fn into_value(self) -> Value {
+        let mut summary: Map<String, Value> = Map::new();
+        summary.insert("changed".into(), to_value(self.changed()));
+        summary.insert("unchanged".into(), to_value(self.unchanged()));
+        summary.insert("failed".into(), to_value(self.failed()));
+
+        let mut map: Map<String, Value> = Map::new();
+        map.insert("check_mode".into(), to_value(self.check_mode));
+        map.insert("summary".into(), to_value(summary));
+        map.insert("outcomes".into(), Value::Array(self.outcomes.into_iter().map(|o| o.into_value()).collect()));
+        to_value(map)
+    }
+
+    /// Serialize the report to a JSON string.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.clone().into_value()).unwrap()
+    }
+}
+
+/// Add the outcome of an operation to a report.
+///
+/// If `result` is an error, the Outcome is recorded as failed and
+/// the error is returned.
+#[doc(hidden)]
+pub fn record(report: &mut Report, result: Result<()>, mut outcome: Outcome) -> Result<Outcome> {
+    match result {
+        Ok(_) => {
+            report.outcomes.push(outcome.clone());
+            Ok(outcome)
+        },
+        Err(e) => {
+            outcome.status = Status::Failed;
+            outcome.error = Some(format!("{}", e));
+            report.outcomes.push(outcome);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use command::CommandResult;
+    use error::Error;
+    use serde_json::Value;
+    use super::*;
+
+    #[test]
+    fn test_outcome_command_result() {
+        let outcome = Outcome::changed("package:nginx", "install").command_result(CommandResult {
+            exit_code: 1,
+            stdout: String::new(),
+            stderr: "Not found".into(),
+        });
+        assert!(outcome.is_failed());
+    }
+
+    #[test]
+    fn test_record() {
+        let mut report = Report::new();
+        assert!(record(&mut report, Ok(()), Outcome::changed("file:/tmp/a", "delete")).is_ok());
+        assert!(record(&mut report, Ok(()), Outcome::unchanged("file:/tmp/b", "delete")).is_ok());
+        assert!(record(&mut report, Err(Error::Generic("Nope".into())), Outcome::changed("file:/tmp/c", "delete")).is_err());
+
+        assert_eq!(report.changed(), 1);
+        assert_eq!(report.unchanged(), 1);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.outcomes[2].error, Some("Nope".to_string()));
+    }
+
+    #[test]
+    fn test_report_into_value() {
+        let mut report = Report::new();
+        report.outcomes.push(Outcome::changed("file:/tmp/a", "set_mode").values(755, 644));
+
+        let value = report.into_value();
+        assert_eq!(value.pointer("/summary/changed"), Some(&Value::U64(1)));
+        assert_eq!(value.pointer("/outcomes/0/before"), Some(&Value::String("755".into())));
+        assert_eq!(value.pointer("/outcomes/0/status"), Some(&Value::String("changed".into())));
+    }
+}
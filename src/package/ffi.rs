@@ -27,6 +27,11 @@ pub enum Ffi__Providers {
     Pkg,
     Ports,
     Yum,
+    Zypper,
+    Pacman,
+    Apk,
+    Snap,
+    Flatpak,
 }
 
 impl convert::Into<Option<Providers>> for Ffi__Providers {
@@ -40,6 +45,11 @@ impl convert::Into<Option<Providers>> for Ffi__Providers {
             Ffi__Providers::Pkg => Some(Providers::Pkg),
             Ffi__Providers::Ports => Some(Providers::Ports),
             Ffi__Providers::Yum => Some(Providers::Yum),
+            Ffi__Providers::Zypper => Some(Providers::Zypper),
+            Ffi__Providers::Pacman => Some(Providers::Pacman),
+            Ffi__Providers::Apk => Some(Providers::Apk),
+            Ffi__Providers::Snap => Some(Providers::Snap),
+            Ffi__Providers::Flatpak => Some(Providers::Flatpak),
         }
     }
 }
@@ -69,8 +79,8 @@ pub extern "C" fn package_install(pkg_ptr: *mut Package, host_ptr: *const Host)
     let mut pkg = Leaky::new(trynull!(boxptr!(pkg_ptr, "Package pointer")));
     let mut host = Leaky::new(trynull!(readptr!(host_ptr, "Host pointer")));
 
-    let result = trynull!(pkg.install(&mut host));
-    match result {
+    let outcome = trynull!(pkg.install(&mut host));
+    match outcome.result {
         Some(r) => {
             let ffi_r: Ffi__CommandResult = trynull!(catch_unwind(|| r.into()));
             Box::into_raw(Box::new(ffi_r))
@@ -84,8 +94,8 @@ pub extern "C" fn package_uninstall(pkg_ptr: *mut Package, host_ptr: *const Host
     let mut pkg = Leaky::new(trynull!(boxptr!(pkg_ptr, "Package pointer")));
     let mut host = Leaky::new(trynull!(readptr!(host_ptr, "Host pointer")));
 
-    let result = trynull!(pkg.uninstall(&mut host));
-    match result {
+    let outcome = trynull!(pkg.uninstall(&mut host));
+    match outcome.result {
         Some(r) => {
             let ffi_r: Ffi__CommandResult = trynull!(catch_unwind(|| r.into()));
             Box::into_raw(Box::new(ffi_r))
@@ -102,20 +112,16 @@ pub extern "C" fn package_free(pkg_ptr: *mut Package) -> uint8_t {
 
 #[cfg(test)]
 mod tests {
-    #[cfg(feature = "remote-run")]
     use czmq::{ZMsg, ZSys};
     use Host;
-    #[cfg(feature = "remote-run")]
     use host::ffi::host_close;
     use std::ffi::CString;
     use std::str;
-    #[cfg(feature = "remote-run")]
     use std::thread;
     use super::*;
 
-    #[cfg(feature = "local-run")]
     #[test]
-    fn test_package_new_default() {
+    fn test_package_new_default_local() {
         let path: Option<String> = None;
         let host = Host::local(path).unwrap();
         let name = CString::new("nginx").unwrap().into_raw();
@@ -124,7 +130,6 @@ mod tests {
         assert_eq!(pkg.name, "nginx");
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_package_new_default() {
         ZSys::init();
@@ -168,7 +173,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_package_new_homebrew() {
         ZSys::init();
@@ -206,7 +210,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "local-run")]
     #[test]
     fn test_package_is_installed() {
         let path: Option<String> = None;
@@ -218,7 +221,6 @@ mod tests {
         assert_eq!(package_free(pkg), 0);
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_package_install() {
         ZSys::init();
@@ -272,7 +274,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_package_uninstall() {
         ZSys::init();
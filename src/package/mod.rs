@@ -16,18 +16,14 @@
 //!
 //! ```no_run
 //! # use inapi::Host;
-#![cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! ```
 //!
 //! Now install the package `nginx` using the default provider:
 //!
 //! ```no_run
 //! # use inapi::{Host, Package};
-#![cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let mut package = Package::new(&mut host, "nginx", None).unwrap();
 //! package.install(&mut host);
 //! ```
@@ -38,20 +34,70 @@
 //!
 //! ```no_run
 //! # use inapi::{Host, Package, Providers};
-#![cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let mut package = Package::new(&mut host, "nginx", Some(Providers::Homebrew)).unwrap();
 //! package.install(&mut host);
 //! ```
+//!
+//! To pin a package, install a version that matches a requirement and
+//! hold it there:
+//!
+//! ```no_run
+//! # use inapi::{Host, Package};
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! let mut package = Package::new(&mut host, "nginx", None).unwrap();
+//! package.install_version(&mut host, ">= 1.10, < 1.12").unwrap();
+//! package.hold(&mut host).unwrap();
+//! ```
+//!
+//! Fresh hosts often have an empty or stale metadata cache. Set a
+//! cache policy to refresh it automatically before installing:
+//!
+//! ```no_run
+//! # use inapi::{CachePolicy, Host, Package};
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! let mut package = Package::new(&mut host, "nginx", None).unwrap();
+//! package.set_cache_policy(CachePolicy::OlderThan(60));
+//! package.install(&mut host).unwrap();
+//! ```
+//!
+//! When installing many packages, a `PackageSet` queries and installs
+//! them all with one command each:
+//!
+//! ```no_run
+//! # use inapi::{Host, PackageSet};
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! let mut packages = PackageSet::new(&mut host, &["nginx", "git", "curl"], None).unwrap();
+//! packages.install(&mut host).unwrap();
+//! ```
 
 pub mod ffi;
 pub mod providers;
+mod repository;
+mod set;
+pub mod version;
 
 use command::CommandResult;
-use error::Result;
+use error::{Error, Result};
 use host::Host;
+use outcome::Outcome;
 use self::providers::*;
+pub use self::repository::Repository;
+pub use self::set::PackageSet;
+use std::cmp::Ordering;
+
+/// When to refresh the provider's metadata cache before installing
+/// or upgrading packages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CachePolicy {
+    /// Never refresh automatically
+    Never,
+    /// Refresh before every install
+    Always,
+    /// Refresh if the cache has not been refreshed in this many
+    /// minutes
+    OlderThan(u64),
+}
 
 /// Container for operating on a package.
 pub struct Package {
@@ -61,6 +107,8 @@ pub struct Package {
     provider: Box<Provider + 'static>,
     /// Package installed bool
     installed: bool,
+    /// When to refresh the provider's metadata cache
+    cache_policy: CachePolicy,
 }
 
 impl Package {
@@ -73,9 +121,7 @@ impl Package {
     ///
     /// ```no_run
     /// # use inapi::{Host, Package, Providers};
-    #[cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-    #[cfg_attr(feature = "local-run", doc = "let mut host = Host::local(path).unwrap();")]
-    #[cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+    /// # let mut host = Host::connect("hosts/myhost.json").unwrap();
     /// let pkg = Package::new(&mut host, "nginx", Some(Providers::Yum));
     /// ```
     pub fn new(host: &mut Host, name: &str, providers: Option<Providers>) -> Result<Package> {
@@ -86,6 +132,7 @@ impl Package {
             name: name.to_string(),
             provider: provider,
             installed: installed,
+            cache_policy: CachePolicy::Never,
         })
     }
 
@@ -94,35 +141,215 @@ impl Package {
         self.installed
     }
 
+    /// Set when to refresh the provider's metadata cache before
+    /// installing or upgrading the package. Defaults to
+    /// `CachePolicy::Never`.
+    pub fn set_cache_policy(&mut self, policy: CachePolicy) {
+        self.cache_policy = policy;
+    }
+
+    /// Refresh the provider's metadata cache.
+    pub fn refresh_cache(&self, host: &mut Host) -> Result<Outcome> {
+        refresh_cache(&*self.provider, host, &self.resource())
+    }
+
     /// Install the package.
-    pub fn install(&mut self, host: &mut Host) -> Result<Option<CommandResult>> {
+    pub fn install(&mut self, host: &mut Host) -> Result<Outcome> {
         if self.installed {
-            Ok(None)
-        } else {
-            let result = try!(self.provider.install(host, &self.name));
+            return host.record(Ok(()), Outcome::unchanged(&self.resource(), "install"));
+        }
 
-            if result.exit_code == 0 {
-                self.installed = true;
-            }
+        try!(refresh_stale_cache(&*self.provider, host, self.cache_policy, &self.resource()));
 
-            Ok(Some(result))
+        let outcome = Outcome::changed(&self.resource(), "install");
+        let outcome = try!(run(host, outcome, |h| self.provider.install(h, &self.name)));
+
+        if succeeded(&outcome) {
+            self.installed = true;
         }
+
+        Ok(outcome)
     }
 
     /// Uninstall the package.
-    pub fn uninstall(&mut self, host: &mut Host) -> Result<Option<CommandResult>> {
-        if self.installed {
-            let result = try!(self.provider.uninstall(host, &self.name));
+    pub fn uninstall(&mut self, host: &mut Host) -> Result<Outcome> {
+        if !self.installed {
+            return host.record(Ok(()), Outcome::unchanged(&self.resource(), "uninstall"));
+        }
+
+        let outcome = Outcome::changed(&self.resource(), "uninstall");
+        let outcome = try!(run(host, outcome, |h| self.provider.uninstall(h, &self.name)));
+
+        if succeeded(&outcome) {
+            self.installed = false;
+        }
+
+        Ok(outcome)
+    }
+
+    /// Get the installed version of the package, or None if it is not
+    /// installed.
+    pub fn installed_version(&self, host: &mut Host) -> Result<Option<String>> {
+        self.provider.installed_version(host, &self.name)
+    }
 
-            if result.exit_code == 0 {
-                self.installed = false;
+    /// Get the newest version of the package that the provider can
+    /// install.
+    pub fn candidate_version(&self, host: &mut Host) -> Result<Option<String>> {
+        self.provider.candidate_version(host, &self.name)
+    }
+
+    /// Check whether the package is held at its installed version.
+    pub fn is_held(&self, host: &mut Host) -> Result<bool> {
+        self.provider.is_held(host, &self.name)
+    }
+
+    /// Install a version of the package that matches a requirement,
+    /// upgrading or downgrading any installed version.
+    ///
+    /// The requirement is either an exact version or a range, e.g.
+    /// `>= 1.10, < 1.12` or `1.10.*`. For a range, the newest
+    /// available version that matches is installed. See the
+    /// [`version`](version/index.html) module for the syntax.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use inapi::{Host, Package};
+    /// # let mut host = Host::connect("hosts/myhost.json").unwrap();
+    /// let mut package = Package::new(&mut host, "nginx", None).unwrap();
+    /// package.install_version(&mut host, "1.10.3-1").unwrap();
+    /// ```
+    pub fn install_version(&mut self, host: &mut Host, req: &str) -> Result<Outcome> {
+        let installed = try!(self.provider.installed_version(host, &self.name));
+        if let Some(ref v) = installed {
+            if try!(version::matches(req, v)) {
+                return host.record(Ok(()), Outcome::unchanged(&self.resource(), "install_version").values(v, v));
             }
+        }
 
-            Ok(Some(result))
+        try!(refresh_stale_cache(&*self.provider, host, self.cache_policy, &self.resource()));
+
+        let target = if version::is_range(req) {
+            let versions = try!(self.provider.available_versions(host, &self.name));
+            match try!(version::best_match(req, &versions)) {
+                Some(v) => v.to_string(),
+                None => return Err(Error::Generic(format!("No version of {} matches \"{}\"", self.name, req))),
+            }
         } else {
-            Ok(None)
+            req.to_string()
+        };
+
+        let outcome = Outcome::changed(&self.resource(), "install_version")
+                              .values(installed.as_ref().map_or("absent", |v| v.as_str()), &target);
+        let outcome = try!(run(host, outcome, |h| self.provider.install_version(h, &self.name, &target)));
+
+        if succeeded(&outcome) {
+            self.installed = true;
         }
+
+        Ok(outcome)
+    }
+
+    /// Upgrade the package to the newest version that the provider
+    /// can install. The package must already be installed.
+    pub fn upgrade(&mut self, host: &mut Host) -> Result<Outcome> {
+        let installed = match try!(self.provider.installed_version(host, &self.name)) {
+            Some(v) => v,
+            None => return Err(Error::Generic(format!("Cannot upgrade {} as it is not installed", self.name))),
+        };
+        try!(refresh_stale_cache(&*self.provider, host, self.cache_policy, &self.resource()));
+        let candidate = try!(self.provider.candidate_version(host, &self.name)).unwrap_or_else(|| installed.clone());
+
+        let outcome = if version::compare(&candidate, &installed) == Ordering::Greater {
+            Outcome::changed(&self.resource(), "upgrade")
+        } else {
+            Outcome::unchanged(&self.resource(), "upgrade")
+        }.values(&installed, &candidate);
+
+        run(host, outcome, |h| self.provider.upgrade(h, &self.name))
+    }
+
+    /// Hold the package at its installed version, so that it is not
+    /// upgraded by the provider.
+    pub fn hold(&mut self, host: &mut Host) -> Result<Outcome> {
+        let outcome = if try!(self.provider.is_held(host, &self.name)) {
+            Outcome::unchanged(&self.resource(), "hold")
+        } else {
+            Outcome::changed(&self.resource(), "hold")
+        };
+
+        run(host, outcome, |h| self.provider.hold(h, &self.name))
+    }
+
+    /// Release a held package, so that it can be upgraded again.
+    pub fn unhold(&mut self, host: &mut Host) -> Result<Outcome> {
+        let outcome = if try!(self.provider.is_held(host, &self.name)) {
+            Outcome::changed(&self.resource(), "unhold")
+        } else {
+            Outcome::unchanged(&self.resource(), "unhold")
+        };
+
+        run(host, outcome, |h| self.provider.unhold(h, &self.name))
+    }
+
+    fn resource(&self) -> String {
+        format!("package:{}", self.name)
+    }
+}
+
+// Run a provider command if there is a change to apply and the Host
+// is not in check mode. Returns None if the command was skipped.
+fn exec<F>(host: &mut Host, changed: bool, f: F) -> Result<Option<CommandResult>>
+    where F: FnOnce(&mut Host) -> Result<CommandResult>
+{
+    if !changed || host.check_mode() {
+        return Ok(None);
     }
+
+    f(host).map(Some)
+}
+
+// Run a provider command for a changed Outcome, unless the Host is
+// in check mode, then record the Outcome with the command's result.
+fn run<F>(host: &mut Host, outcome: Outcome, f: F) -> Result<Outcome>
+    where F: FnOnce(&mut Host) -> Result<CommandResult>
+{
+    match exec(host, outcome.is_changed(), f) {
+        Ok(Some(result)) => host.record(Ok(()), outcome.command_result(result)),
+        Ok(None) => host.record(Ok(()), outcome),
+        Err(e) => host.record(Err(e), outcome),
+    }
+}
+
+// Refresh the provider's metadata cache, recording the refresh as an
+// Outcome for `resource`.
+fn refresh_cache(provider: &Provider, host: &mut Host, resource: &str) -> Result<Outcome> {
+    run(host, Outcome::changed(resource, "refresh_cache"), |h| provider.refresh_cache(h))
+}
+
+// Refresh the provider's metadata cache if the policy considers it
+// stale. A cache that has never been refreshed is always stale.
+fn refresh_stale_cache(provider: &Provider, host: &mut Host, policy: CachePolicy, resource: &str) -> Result<()> {
+    let stale = match policy {
+        CachePolicy::Never => false,
+        CachePolicy::Always => true,
+        CachePolicy::OlderThan(minutes) => match try!(provider.cache_age(host)) {
+            Some(age) => age >= minutes * 60,
+            None => true,
+        },
+    };
+
+    if stale {
+        try!(refresh_cache(provider, host, resource));
+    }
+
+    Ok(())
+}
+
+// Whether a provider command ran and exited successfully.
+fn succeeded(outcome: &Outcome) -> bool {
+    outcome.result.as_ref().map_or(false, |r| r.exit_code == 0)
 }
 
 pub trait PackageTarget {
@@ -132,15 +359,13 @@ pub trait PackageTarget {
 #[cfg(test)]
 mod tests {
     use Host;
-    #[cfg(feature = "remote-run")]
     use czmq::{ZMsg, ZSys};
+    use host::transport::MemoryTransport;
+    use outcome::Status;
     use super::*;
-    #[cfg(feature = "remote-run")]
     use super::providers::Providers;
-    #[cfg(feature = "remote-run")]
     use std::thread;
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_new_homebrew() {
         ZSys::init();
@@ -182,16 +407,14 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "local-run")]
     #[test]
-    fn test_new_default() {
+    fn test_new_default_local() {
         let path: Option<String> = None;
         let mut host = Host::local(path).unwrap();
         let pkg = Package::new(&mut host, "nginx", None);
         assert!(pkg.is_ok());
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_new_default() {
         ZSys::init();
@@ -237,4 +460,63 @@ mod tests {
 
         agent_mock.join().unwrap();
     }
+
+    #[test]
+    fn test_install_version_range() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "/usr/bin/apt-get", ""]);
+        transport.reply(&["Ok", "0", "nginx\t\t\t\t\tinstall", ""]);
+        transport.reply(&["Ok", "0", "install ok installed 1.10.3-1", ""]);
+        transport.reply(&["Ok", "0", "nginx | 1.12.1-1 | http://nginx.org/packages/debian stretch/nginx amd64 Packages\nnginx | 1.11.9-1 | http://nginx.org/packages/debian stretch/nginx amd64 Packages", ""]);
+        transport.reply(&["Ok", "0", "", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        let mut pkg = Package::new(&mut host, "nginx", Some(Providers::Apt)).unwrap();
+        let outcome = pkg.install_version(&mut host, ">= 1.11, < 1.12").unwrap();
+        assert_eq!(outcome.status, Status::Changed);
+        assert_eq!(outcome.before, Some("1.10.3-1".into()));
+        assert_eq!(outcome.after, Some("1.11.9-1".into()));
+
+        let requests = transport.requests();
+        assert_eq!(requests[4], vec!["command::exec", "apt-get -y --allow-downgrades install nginx=1.11.9-1"]);
+    }
+
+    #[test]
+    fn test_hold() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "/usr/bin/apt-get", ""]);
+        transport.reply(&["Ok", "0", "nginx\t\t\t\t\tinstall", ""]);
+        transport.reply(&["Ok", "0", "", ""]);
+        transport.reply(&["Ok", "0", "nginx set on hold.", ""]);
+        transport.reply(&["Ok", "0", "nginx", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        let mut pkg = Package::new(&mut host, "nginx", Some(Providers::Apt)).unwrap();
+        assert!(pkg.hold(&mut host).unwrap().is_changed());
+        assert!(!pkg.hold(&mut host).unwrap().is_changed());
+
+        let requests = transport.requests();
+        assert_eq!(requests[3], vec!["command::exec", "apt-mark hold nginx"]);
+    }
+
+    #[test]
+    fn test_install_stale_cache() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "/usr/bin/apt-get", ""]);
+        transport.reply(&["Ok", "1", "", ""]);
+        transport.reply(&["Ok", "0", "1500007200\n1500000000", ""]);
+        transport.reply(&["Ok", "0", "", ""]);
+        transport.reply(&["Ok", "0", "", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        let mut pkg = Package::new(&mut host, "nginx", Some(Providers::Apt)).unwrap();
+        pkg.set_cache_policy(CachePolicy::OlderThan(60));
+        assert!(pkg.install(&mut host).unwrap().is_changed());
+
+        let requests = transport.requests();
+        assert_eq!(requests[2], vec!["command::exec", "date +%s && stat -c %Y /var/lib/apt/lists/partial"]);
+        assert_eq!(requests[3], vec!["command::exec", "apt-get update"]);
+        assert_eq!(requests[4], vec!["command::exec", "apt-get -y install nginx"]);
+        assert_eq!(host.report().outcomes[0].action, "refresh_cache");
+    }
 }
@@ -0,0 +1,223 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Apk package provider
+
+use command::{quote, Command, CommandResult};
+use error::{Error, Result};
+use host::Host;
+use package::version;
+use super::*;
+
+pub struct Apk;
+
+impl Provider for Apk {
+    fn get_providers(&self) -> Providers {
+        Providers::Apk
+    }
+
+    fn is_active(&self, host: &mut Host) -> Result<bool> {
+        let cmd = Command::new("which apk");
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn is_installed(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new(&format!("apk info -e {}", name));
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        // e.g. "nginx-1.12.2-r1 x86_64 {nginx} (BSD-2-Clause) [installed]"
+        let cmd = Command::new(&format!("apk list --installed {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        let prefix = format!("{}-", name);
+        Ok(result.stdout.lines()
+                        .filter_map(|l| l.split_whitespace().next())
+                        .filter(|p| p.starts_with(&prefix))
+                        .map(|p| &p[prefix.len()..])
+                        .find(|v| v.starts_with(|c: char| c.is_digit(10)))
+                        .map(|v| v.to_string()))
+    }
+
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let versions = try!(self.available_versions(host, name));
+        Ok(versions.into_iter().max_by(|a, b| version::compare(a, b)))
+    }
+
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>> {
+        // e.g.
+        // nginx policy:
+        //   1.12.2-r1:
+        //     lib/apk/db/installed
+        //     http://dl-cdn.alpinelinux.org/alpine/v3.7/main
+        let cmd = Command::new(&format!("apk policy {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(Vec::new());
+        }
+
+        Ok(result.stdout.lines()
+                        .skip(1)
+                        .filter(|l| l.starts_with("  ") && !l.starts_with("    ") && l.ends_with(':'))
+                        .map(|l| l.trim().trim_right_matches(':').to_string())
+                        .collect())
+    }
+
+    fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apk add {}", name));
+        cmd.exec(host)
+    }
+
+    // Apk records the constraint in /etc/apk/world, so installing an
+    // exact version also holds the package there.
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apk add {}", quote(&format!("{}={}", name, version))));
+        cmd.exec(host)
+    }
+
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apk upgrade {}", name));
+        cmd.exec(host)
+    }
+
+    fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apk del {}", name));
+        cmd.exec(host)
+    }
+
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>> {
+        let cmd = Command::new("apk info");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        let installed: Vec<&str> = result.stdout.lines().collect();
+        Ok(names.iter().filter(|n| installed.contains(*n)).map(|n| n.to_string()).collect())
+    }
+
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apk add {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apk del {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new(&format!("grep -Eq '^{}(=|~)' /etc/apk/world", name));
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    // A package is held by constraining it to its installed version
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        match try!(self.installed_version(host, name)) {
+            Some(version) => self.install_version(host, name, &version),
+            None => Err(Error::Generic(format!("Cannot hold {} as it is not installed", name))),
+        }
+    }
+
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apk add {}", name));
+        cmd.exec(host)
+    }
+
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new(&format!("grep -q {} /etc/apk/repositories", quote(&format!("^@{} ", name))));
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool> {
+        let cmd = Command::new(&format!("grep -Fxq {} /etc/apk/repositories", quote(&try!(repo_line(repo)))));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(false);
+        }
+
+        match repo.key {
+            Some(_) => file_exists(host, &key_path(&repo.name)),
+            None => Ok(true),
+        }
+    }
+
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        let mut cmds = Vec::new();
+        if let Some(ref key) = repo.key {
+            cmds.push(format!("wget -qO {} {}", quote(&key_path(&repo.name)), quote(key)));
+        }
+        cmds.push(format!("sed -i '/^@{} /d' /etc/apk/repositories", repo.name));
+        cmds.push(format!("printf '%s\\n' {} >> /etc/apk/repositories", quote(&try!(repo_line(repo)))));
+        cmds.push("apk update".into());
+
+        exec_all(host, &cmds)
+    }
+
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        exec_all(host, &[
+            format!("sed -i '/^@{} /d' /etc/apk/repositories", repo.name),
+            format!("rm -f {}", quote(&key_path(&repo.name))),
+            "apk update".into(),
+        ])
+    }
+
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult> {
+        let cmd = Command::new("apk update");
+        cmd.exec(host)
+    }
+
+    // Apk keeps an index for each repository.
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>> {
+        stat_age(host, "stat -c %Y /var/cache/apk/APKINDEX.*.tar.gz")
+    }
+}
+
+fn key_path(name: &str) -> String {
+    format!("/etc/apk/keys/{}.rsa.pub", name)
+}
+
+// Repositories are added with a tag so that they can be found by
+// name, e.g. "@nginx http://nginx.org/packages/alpine/v3.7/main".
+// Packages from a tagged repository are installed as `<name>@<tag>`.
+fn repo_line(repo: &Repository) -> Result<String> {
+    Ok(format!("@{} {}", repo.name, try!(repository_url(repo))))
+}
+
+#[cfg(test)]
+mod tests {
+    use Host;
+    use host::transport::MemoryTransport;
+    use super::*;
+
+    #[test]
+    fn test_versions() {
+        let policy = "nginx policy:\n  1.12.2-r1:\n    lib/apk/db/installed\n    http://dl-cdn.alpinelinux.org/alpine/v3.7/main\n  1.14.0-r0:\n    http://dl-cdn.alpinelinux.org/alpine/edge/main";
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "nginx-1.12.2-r1 x86_64 {nginx} (BSD-2-Clause) [installed]", ""]);
+        transport.reply(&["Ok", "0", policy, ""]);
+        transport.reply(&["Ok", "0", policy, ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        assert_eq!(Apk.installed_version(&mut host, "nginx").unwrap().unwrap(), "1.12.2-r1");
+        assert_eq!(Apk.available_versions(&mut host, "nginx").unwrap(), vec!["1.12.2-r1", "1.14.0-r0"]);
+        assert_eq!(Apk.candidate_version(&mut host, "nginx").unwrap().unwrap(), "1.14.0-r0");
+    }
+}
@@ -8,8 +8,8 @@
 
 //! Apt package provider
 
-use command::{Command, CommandResult};
-use error::Result;
+use command::{quote, Command, CommandResult};
+use error::{Error, Result};
 use host::Host;
 use super::*;
 
@@ -34,13 +34,184 @@ impl Provider for Apt {
         Ok(result.exit_code == 0)
     }
 
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let cmd = Command::new(&format!("dpkg-query -W -f='${{Status}} ${{Version}}' {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        // e.g. "install ok installed 1.10.3-1", or "hold ok installed
+        // 1.10.3-1" for a held package
+        let status: Vec<&str> = result.stdout.split_whitespace().collect();
+        if status.len() == 4 && status[2] == "installed" {
+            Ok(Some(status[3].to_string()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let cmd = Command::new(&format!("apt-cache policy {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        Ok(result.stdout.lines()
+                        .filter_map(|l| l.trim().splitn(2, "Candidate:").nth(1))
+                        .map(|v| v.trim())
+                        .find(|v| *v != "(none)")
+                        .map(|v| v.to_string()))
+    }
+
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>> {
+        let cmd = Command::new(&format!("apt-cache madison {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        // e.g. "nginx | 1.10.3-1 | http://deb.debian.org/debian stretch/main amd64 Packages"
+        let mut versions: Vec<String> = result.stdout.lines()
+                                                     .filter_map(|l| l.split('|').nth(1))
+                                                     .map(|v| v.trim().to_string())
+                                                     .collect();
+        versions.dedup();
+        Ok(versions)
+    }
+
     fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
         let cmd = Command::new(&format!("apt-get -y install {}", name));
         cmd.exec(host)
     }
 
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apt-get -y --allow-downgrades install {}={}", name, version));
+        cmd.exec(host)
+    }
+
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apt-get -y --only-upgrade install {}", name));
+        cmd.exec(host)
+    }
+
     fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
         let cmd = Command::new(&format!("apt-get -y remove {}", name));
         cmd.exec(host)
     }
+
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>> {
+        let cmd = Command::new("dpkg --get-selections");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        // e.g. "nginx\t\t\t\t\tinstall", or "libc6:amd64\t\t\t\tinstall"
+        let installed: Vec<&str> = result.stdout.lines().filter_map(|line| {
+            let mut cols = line.split_whitespace();
+            match (cols.next(), cols.next()) {
+                (Some(pkg), Some("install")) => pkg.split(':').next(),
+                _ => None,
+            }
+        }).collect();
+
+        Ok(names.iter().filter(|n| installed.contains(*n)).map(|n| n.to_string()).collect())
+    }
+
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apt-get -y install {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apt-get -y remove {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new("apt-mark showhold");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        Ok(result.stdout.lines().any(|l| l.trim() == name))
+    }
+
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apt-mark hold {}", name));
+        cmd.exec(host)
+    }
+
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("apt-mark unhold {}", name));
+        cmd.exec(host)
+    }
+
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool> {
+        file_exists(host, &source_path(name))
+    }
+
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool> {
+        if !try!(file_matches(host, &source_path(&repo.name), &try!(source_list(repo)))) {
+            return Ok(false);
+        }
+
+        match repo.key {
+            Some(_) => file_exists(host, &key_path(&repo.name)),
+            None => Ok(true),
+        }
+    }
+
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        let mut cmds = Vec::new();
+        if let Some(ref key) = repo.key {
+            cmds.push(format!("mkdir -p '/etc/apt/keyrings' && curl -fsSL {} -o {}", quote(key), quote(&key_path(&repo.name))));
+        }
+        cmds.push(write_file(&source_path(&repo.name), &try!(source_list(repo))));
+        cmds.push("apt-get update".into());
+
+        exec_all(host, &cmds)
+    }
+
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        exec_all(host, &[
+            format!("rm -f {} {}", quote(&source_path(&repo.name)), quote(&key_path(&repo.name))),
+            "apt-get update".into(),
+        ])
+    }
+
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult> {
+        let cmd = Command::new("apt-get update");
+        cmd.exec(host)
+    }
+
+    // `apt-get update` downloads every index into the partial directory
+    // before moving it into place, which touches the directory.
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>> {
+        stat_age(host, "stat -c %Y /var/lib/apt/lists/partial")
+    }
+}
+
+fn source_path(name: &str) -> String {
+    format!("/etc/apt/sources.list.d/{}.list", name)
+}
+
+fn key_path(name: &str) -> String {
+    format!("/etc/apt/keyrings/{}.asc", name)
+}
+
+// Get the source list for a repository, e.g.
+// "deb [signed-by=/etc/apt/keyrings/nginx.asc] http://nginx.org/packages/debian stretch nginx"
+fn source_list(repo: &Repository) -> Result<String> {
+    let suite = match repo.suite {
+        Some(ref suite) => suite,
+        None => return Err(Error::Generic(format!("Apt repository {} needs a suite, e.g. \"stretch\"", repo.name))),
+    };
+    let components = if repo.components.is_empty() { "main".to_string() } else { repo.components.join(" ") };
+    let options = if repo.key.is_some() { format!("[signed-by={}] ", key_path(&repo.name)) } else { String::new() };
+
+    Ok(format!("deb {}{} {} {}", options, try!(repository_url(repo)), suite, components))
 }
@@ -0,0 +1,232 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Flatpak package provider
+//!
+//! Packages are named by their application ID, e.g. `org.gimp.GIMP`,
+//! and repositories are Flatpak remotes.
+
+use command::{quote, Command, CommandResult};
+use error::{Error, Result};
+use host::Host;
+use super::*;
+
+pub struct Flatpak;
+
+impl Provider for Flatpak {
+    fn get_providers(&self) -> Providers {
+        Providers::Flatpak
+    }
+
+    fn is_active(&self, host: &mut Host) -> Result<bool> {
+        let cmd = Command::new("which flatpak");
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn is_installed(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new(&format!("flatpak info {}", name));
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        // e.g. "Version: 2.10.8"
+        let cmd = Command::new(&format!("flatpak info {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        Ok(result.stdout.lines()
+                        .find(|l| l.trim().starts_with("Version:"))
+                        .and_then(|l| l.splitn(2, ':').nth(1))
+                        .map(|v| v.trim().to_string()))
+    }
+
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let cmd = Command::new(&format!("flatpak search --columns=application,version {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        Ok(result.stdout.lines().filter_map(|line| {
+            let mut cols = line.split('\t');
+            match (cols.next(), cols.next()) {
+                (Some(app), Some(version)) if app.trim() == name && !version.trim().is_empty() => Some(version.trim().to_string()),
+                _ => None,
+            }
+        }).next())
+    }
+
+    // Remotes only offer the newest version of an application.
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>> {
+        Ok(try!(self.candidate_version(host, name)).into_iter().collect())
+    }
+
+    fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("flatpak install -y --noninteractive {}", name));
+        cmd.exec(host)
+    }
+
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult> {
+        if try!(self.candidate_version(host, name)).map_or(true, |v| v != version) {
+            return Err(Error::Generic(format!("Flatpak can only install the newest version of {}", name)));
+        }
+
+        let action = if try!(self.is_installed(host, name)) { "update" } else { "install" };
+        let cmd = Command::new(&format!("flatpak {} -y --noninteractive {}", action, name));
+        cmd.exec(host)
+    }
+
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("flatpak update -y --noninteractive {}", name));
+        cmd.exec(host)
+    }
+
+    fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("flatpak uninstall -y --noninteractive {}", name));
+        cmd.exec(host)
+    }
+
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>> {
+        let cmd = Command::new("flatpak list --columns=application");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        let installed: Vec<&str> = result.stdout.lines().map(|l| l.trim()).collect();
+        Ok(names.iter().filter(|n| installed.contains(*n)).map(|n| n.to_string()).collect())
+    }
+
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("flatpak install -y --noninteractive {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("flatpak uninstall -y --noninteractive {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new("flatpak mask");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        Ok(result.stdout.lines().any(|l| l.trim() == name))
+    }
+
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("flatpak mask {}", name));
+        cmd.exec(host)
+    }
+
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("flatpak mask --remove {}", name));
+        cmd.exec(host)
+    }
+
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool> {
+        Ok(try!(remotes(host)).iter().any(|&(ref n, _)| n == name))
+    }
+
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool> {
+        let url = try!(repository_url(repo));
+        Ok(try!(remotes(host)).iter().any(|&(ref n, ref u)| *n == repo.name && u == url))
+    }
+
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        let url = try!(repository_url(repo));
+        if try!(self.has_repository(host, &repo.name)) {
+            let cmd = Command::new(&format!("flatpak remote-modify --url={} {}", quote(url), repo.name));
+            return cmd.exec(host);
+        }
+
+        match repo.key {
+            Some(ref key) => {
+                let key_path = format!("/tmp/.inapi-{}.gpg", repo.name);
+                exec_all(host, &[
+                    format!("curl -fsSL {} -o {}", quote(key), quote(&key_path)),
+                    format!("flatpak remote-add --gpg-import={} {} {}; status=$?; rm -f {}; exit $status", quote(&key_path), repo.name, quote(url), quote(&key_path)),
+                ])
+            },
+            None => {
+                let cmd = Command::new(&format!("flatpak remote-add {} {}", repo.name, quote(url)));
+                cmd.exec(host)
+            },
+        }
+    }
+
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("flatpak remote-delete {}", repo.name));
+        cmd.exec(host)
+    }
+
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult> {
+        let cmd = Command::new("flatpak update -y --noninteractive --appstream");
+        cmd.exec(host)
+    }
+
+    // Flatpak keeps the appstream data for each remote.
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>> {
+        stat_age(host, "stat -c %Y /var/lib/flatpak/appstream/*/*/.timestamp")
+    }
+}
+
+// Get the name and URL of each configured remote.
+fn remotes(host: &mut Host) -> Result<Vec<(String, String)>> {
+    let cmd = Command::new("flatpak remotes --columns=name,url");
+    let result = try!(cmd.exec(host));
+    if result.exit_code != 0 {
+        return Err(Error::Agent(result.stderr));
+    }
+
+    Ok(result.stdout.lines().filter_map(|line| {
+        let mut cols = line.split('\t');
+        match (cols.next(), cols.next()) {
+            (Some(name), Some(url)) => Some((name.trim().to_string(), url.trim().to_string())),
+            _ => None,
+        }
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use Host;
+    use host::transport::MemoryTransport;
+    use super::*;
+
+    #[test]
+    fn test_versions() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "GIMP - GNU Image Manipulation Program\n\n          Ref: app/org.gimp.GIMP/x86_64/stable\n      Version: 2.10.8\n       Origin: flathub", ""]);
+        transport.reply(&["Ok", "0", "org.gimp.GIMP\t2.10.12\norg.gimp.GIMP.Manual\t2.10", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        assert_eq!(Flatpak.installed_version(&mut host, "org.gimp.GIMP").unwrap().unwrap(), "2.10.8");
+        assert_eq!(Flatpak.candidate_version(&mut host, "org.gimp.GIMP").unwrap().unwrap(), "2.10.12");
+    }
+
+    #[test]
+    fn test_has_repository() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "flathub\thttps://dl.flathub.org/repo/", ""]);
+        transport.reply(&["Ok", "0", "flathub\thttps://dl.flathub.org/repo/", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        assert!(Flatpak.has_repository(&mut host, "flathub").unwrap());
+        assert!(!Flatpak.has_repository(&mut host, "gnome").unwrap());
+    }
+}
@@ -8,10 +8,11 @@
 
 //! Homebrew package provider
 
-use command::{Command, CommandResult};
+use command::{quote, Command, CommandResult};
 use error::{Error, Result};
 use host::Host;
 use regex::Regex;
+use serde_json::{self, Value};
 use super::*;
 
 pub struct Homebrew;
@@ -39,13 +40,135 @@ impl Provider for Homebrew {
         Ok(re.is_match(&result.stdout))
     }
 
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        // e.g. "nginx 1.12.1 1.13.0", listing every installed version
+        let cmd = Command::new(&format!("brew list --versions {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        Ok(result.stdout.split_whitespace().skip(1).last().map(|v| v.to_string()))
+    }
+
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let cmd = Command::new(&format!("brew info --json=v1 {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        let info: Value = try!(serde_json::from_str(&result.stdout));
+        Ok(wantstr!(info => "/0/versions/stable").map(|v| v.to_string()))
+    }
+
+    // Homebrew only provides the current version of a formula. Older
+    // versions are separate formulae, e.g. `postgresql@9.6`.
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>> {
+        Ok(try!(self.candidate_version(host, name)).into_iter().collect())
+    }
+
     fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
         let cmd = Command::new(&format!("brew install {}", name));
         cmd.exec(host)
     }
 
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult> {
+        let cmd = if try!(self.candidate_version(host, name)).map_or(false, |v| v == version) {
+            Command::new(&format!("brew install {}", name))
+        } else {
+            Command::new(&format!("brew install {}@{}", name, version))
+        };
+        cmd.exec(host)
+    }
+
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("brew upgrade {}", name));
+        cmd.exec(host)
+    }
+
     fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
         let cmd = Command::new(&format!("brew uninstall {}", name));
         cmd.exec(host)
     }
+
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>> {
+        let cmd = Command::new("brew list");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        let installed: Vec<&str> = result.stdout.split_whitespace().collect();
+        Ok(names.iter().filter(|n| installed.contains(*n)).map(|n| n.to_string()).collect())
+    }
+
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("brew install {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("brew uninstall {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new("brew list --pinned");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        Ok(result.stdout.lines().any(|l| l.trim() == name))
+    }
+
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("brew pin {}", name));
+        cmd.exec(host)
+    }
+
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("brew unpin {}", name));
+        cmd.exec(host)
+    }
+
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new("brew tap");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        Ok(result.stdout.lines().any(|l| l.trim() == name))
+    }
+
+    // A tap is cloned from its URL when it is added, so an existing
+    // tap is always up to date.
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool> {
+        self.has_repository(host, &repo.name)
+    }
+
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        let cmd = match repo.url {
+            Some(ref url) => Command::new(&format!("brew tap {} {}", repo.name, quote(url))),
+            None => Command::new(&format!("brew tap {}", repo.name)),
+        };
+        cmd.exec(host)
+    }
+
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("brew untap {}", repo.name));
+        cmd.exec(host)
+    }
+
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult> {
+        let cmd = Command::new("brew update");
+        cmd.exec(host)
+    }
+
+    // `brew update` fetches the Homebrew repository.
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>> {
+        stat_age(host, "stat -f %m \"$(brew --repository)/.git/FETCH_HEAD\"")
+    }
 }
@@ -7,10 +7,15 @@
 // modified, or distributed except according to those terms.
 
 //! Macports package provider
+//!
+//! Macports builds the newest version of a port from its ports tree,
+//! but keeps older versions installed and inactive, so installing a
+//! version activates it if it is already installed.
 
-use command::CommandResult;
-use error::Result;
+use command::{Command, CommandResult};
+use error::{Error, Result};
 use host::Host;
+use package::version;
 use super::*;
 
 pub struct Macports;
@@ -20,23 +25,190 @@ impl Provider for Macports {
         Providers::Macports
     }
 
-    #[allow(unused_variables)]
     fn is_active(&self, host: &mut Host) -> Result<bool> {
+        let cmd = Command::new("which port");
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn is_installed(&self, host: &mut Host, name: &str) -> Result<bool> {
+        Ok(try!(installed(host, name)).iter().any(|&(_, active)| active))
+    }
+
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        Ok(try!(installed(host, name)).into_iter().find(|&(_, active)| active).map(|(v, _)| v))
+    }
+
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        // e.g. "1.12.2\t0", which is version 1.12.2, revision 0
+        let cmd = Command::new(&format!("port -q info --line --version --revision {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        let mut cols = result.stdout.split_whitespace();
+        Ok(match (cols.next(), cols.next()) {
+            (Some(version), Some(revision)) => Some(format!("{}_{}", version, revision)),
+            _ => None,
+        })
+    }
+
+    // The ports tree only offers one version of a port, so older
+    // versions are those still installed.
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>> {
+        let mut versions: Vec<String> = try!(installed(host, name)).into_iter().map(|(v, _)| v).collect();
+        if let Some(candidate) = try!(self.candidate_version(host, name)) {
+            versions.push(candidate);
+        }
+
+        versions.sort_by(|a, b| version::compare(a, b));
+        versions.dedup();
+        Ok(versions)
+    }
+
+    fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("port -N install {}", name));
+        cmd.exec(host)
+    }
+
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult> {
+        if try!(installed(host, name)).iter().any(|&(ref v, _)| v == version) {
+            let cmd = Command::new(&format!("port -N activate {} @{}", name, version));
+            return cmd.exec(host);
+        }
+
+        if try!(self.candidate_version(host, name)).map_or(true, |v| v != version) {
+            return Err(Error::Generic(format!("Macports can only install the newest version of {}", name)));
+        }
+
+        self.install(host, name)
+    }
+
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("port -N upgrade {}", name));
+        cmd.exec(host)
+    }
+
+    fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("port -N uninstall {}", name));
+        cmd.exec(host)
+    }
+
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>> {
+        // e.g. "  nginx @1.12.2_0+ssl (active)"
+        let cmd = Command::new("port -q installed active");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        let installed: Vec<&str> = result.stdout.lines().filter_map(|l| l.split_whitespace().next()).collect();
+        Ok(names.iter().filter(|n| installed.contains(*n)).map(|n| n.to_string()).collect())
+    }
+
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("port -N install {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("port -N uninstall {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    // Macports cannot hold ports, so none are ever held.
+    #[allow(unused_variables)]
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool> {
         Ok(false)
     }
 
     #[allow(unused_variables)]
-    fn is_installed(&self, host: &mut Host, name: &str) -> Result<bool> {
-        unimplemented!();
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        Err(Error::Generic("Holding packages is not supported by Macports".into()))
     }
 
     #[allow(unused_variables)]
-    fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
-        unimplemented!();
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        Err(Error::Generic("Holding packages is not supported by Macports".into()))
     }
 
     #[allow(unused_variables)]
-    fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
-        unimplemented!();
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool> {
+        Err(Error::Generic("Repositories are not supported by Macports".into()))
+    }
+
+    #[allow(unused_variables)]
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool> {
+        Err(Error::Generic("Repositories are not supported by Macports".into()))
+    }
+
+    #[allow(unused_variables)]
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        Err(Error::Generic("Repositories are not supported by Macports".into()))
+    }
+
+    #[allow(unused_variables)]
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        Err(Error::Generic("Repositories are not supported by Macports".into()))
+    }
+
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult> {
+        let cmd = Command::new("port -N selfupdate");
+        cmd.exec(host)
+    }
+
+    // Macports keeps an index for each ports tree it syncs.
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>> {
+        stat_age(host, "find /opt/local/var/macports/sources -name PortIndex -exec stat -f %m {} +")
+    }
+}
+
+// Get each installed version of a port and whether it is active, from
+// lines such as "  nginx @1.12.2_0+ssl (active)". Variants are
+// stripped from the version.
+fn installed(host: &mut Host, name: &str) -> Result<Vec<(String, bool)>> {
+    let cmd = Command::new(&format!("port -q installed {}", name));
+    let result = try!(cmd.exec(host));
+    if result.exit_code != 0 {
+        return Ok(Vec::new());
+    }
+
+    Ok(result.stdout.lines().filter_map(|line| {
+        let mut cols = line.split_whitespace();
+        match (cols.next(), cols.next()) {
+            (Some(n), Some(v)) if n == name && v.starts_with('@') => {
+                let version = v[1..].split('+').next().unwrap_or("").to_string();
+                Some((version, cols.next() == Some("(active)")))
+            },
+            _ => None,
+        }
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use Host;
+    use host::transport::MemoryTransport;
+    use super::*;
+
+    #[test]
+    fn test_versions() {
+        let installed = "  nginx @1.10.3_0+ssl\n  nginx @1.12.2_0+ssl (active)\n  nginx-devel @1.13.0_0";
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", installed, ""]);
+        transport.reply(&["Ok", "0", installed, ""]);
+        transport.reply(&["Ok", "0", "1.14.0\t1", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        assert_eq!(Macports.installed_version(&mut host, "nginx").unwrap().unwrap(), "1.12.2_0");
+        assert_eq!(Macports.available_versions(&mut host, "nginx").unwrap(), vec!["1.10.3_0", "1.12.2_0", "1.14.0_1"]);
+
+        // Installed versions are activated rather than rebuilt
+        transport.reply(&["Ok", "0", installed, ""]);
+        transport.reply(&["Ok", "0", "", ""]);
+        Macports.install_version(&mut host, "nginx", "1.10.3_0").unwrap();
+        assert_eq!(transport.requests()[4], vec!["command::exec", "port -N activate nginx @1.10.3_0"]);
     }
 }
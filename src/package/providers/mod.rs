@@ -6,44 +6,60 @@
 // https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
 // modified, or distributed except according to those terms.
 
+pub mod apk;
 pub mod apt;
 pub mod dnf;
+pub mod flatpak;
 pub mod homebrew;
 pub mod macports;
+pub mod pacman;
 pub mod pkg;
 pub mod ports;
+pub mod snap;
 pub mod yum;
+pub mod zypper;
 
-use command::CommandResult;
+use command::{quote, Command, CommandResult};
 use error::{Error, Result};
 use host::Host;
 pub use self::homebrew::Homebrew;
 use std::convert;
 use std::panic;
+use std::path::Path;
 use std::string::ToString;
-use super::PackageTarget;
+use super::{PackageTarget, Repository};
 use target::Target;
 
 pub enum Providers {
+    Apk,
     Apt,
     Dnf,
+    Flatpak,
     Homebrew,
     Macports,
+    Pacman,
     Pkg,
     Ports,
+    Snap,
     Yum,
+    Zypper,
 }
 
 impl ToString for Providers {
     fn to_string(&self) -> String {
         match self {
+            &Providers::Apk => "Apk".to_string(),
             &Providers::Apt => "Apt".to_string(),
             &Providers::Dnf => "Dnf".to_string(),
+            &Providers::Flatpak => "Flatpak".to_string(),
             &Providers::Homebrew => "Homebrew".to_string(),
             &Providers::Macports => "Macports".to_string(),
+            &Providers::Pacman => "Pacman".to_string(),
             &Providers::Pkg => "Pkg".to_string(),
             &Providers::Ports => "Ports".to_string(),
+            &Providers::Snap => "Snap".to_string(),
             &Providers::Yum => "Yum".to_string(),
+            &Providers::Zypper => "Zypper".to_string(),
         }
     }
 }
@@ -51,13 +67,18 @@ impl ToString for Providers {
 impl convert::From<String> for Providers {
     fn from(provider: String) -> Providers {
         match provider.as_ref() {
+            "Apk" => Providers::Apk,
             "Apt" => Providers::Apt,
             "Dnf" => Providers::Dnf,
+            "Flatpak" => Providers::Flatpak,
             "Homebrew" => Providers::Homebrew,
             "Macports" => Providers::Macports,
+            "Pacman" => Providers::Pacman,
             "Pkg" => Providers::Pkg,
             "Ports" => Providers::Ports,
+            "Snap" => Providers::Snap,
             "Yum" => Providers::Yum,
+            "Zypper" => Providers::Zypper,
             _ => panic!("Invalid provider"),
         }
     }
@@ -84,13 +105,18 @@ impl ProviderFactory {
 
     pub fn resolve(providers: Providers) -> Box<Provider + 'static> {
         match providers {
+            Providers::Apk => Box::new(apk::Apk),
             Providers::Apt => Box::new(apt::Apt),
             Providers::Dnf => Box::new(dnf::Dnf),
+            Providers::Flatpak => Box::new(flatpak::Flatpak),
             Providers::Homebrew => Box::new(homebrew::Homebrew),
             Providers::Macports => Box::new(macports::Macports),
+            Providers::Pacman => Box::new(pacman::Pacman),
             Providers::Pkg => Box::new(pkg::Pkg),
             Providers::Ports => Box::new(ports::Ports),
+            Providers::Snap => Box::new(snap::Snap),
             Providers::Yum => Box::new(yum::Yum),
+            Providers::Zypper => Box::new(zypper::Zypper),
         }
     }
 }
@@ -99,6 +125,148 @@ pub trait Provider: panic::UnwindSafe {
     fn get_providers(&self) -> Providers;
     fn is_active(&self, host: &mut Host) -> Result<bool>;
     fn is_installed(&self, host: &mut Host, name: &str) -> Result<bool>;
+    /// Get the installed version, or None if the package is not
+    /// installed.
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>>;
+    /// Get the newest version that the provider can install.
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>>;
+    /// Get every version that the provider can install.
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>>;
     fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult>;
+    /// Install an exact version, upgrading or downgrading any
+    /// installed version.
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult>;
+    /// Upgrade an installed package to the candidate version.
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult>;
     fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult>;
+    /// Get the packages in `names` that are installed, using a single
+    /// query.
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>>;
+    /// Install several packages in one transaction.
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult>;
+    /// Uninstall several packages in one transaction.
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult>;
+    /// Check whether the package is held at its installed version.
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool>;
+    /// Stop the package from being upgraded.
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult>;
+    /// Allow a held package to be upgraded again.
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult>;
+    /// Check whether a repository with this name is configured.
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool>;
+    /// Check whether a repository is configured exactly as given.
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool>;
+    /// Add or replace a repository, then refresh the metadata cache.
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult>;
+    /// Remove a repository, then refresh the metadata cache.
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult>;
+    /// Refresh the provider's package metadata cache.
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult>;
+    /// Get the number of seconds since the metadata cache was last
+    /// refreshed, or None if it has never been refreshed or the
+    /// provider cannot tell.
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>>;
+}
+
+// Get the versions of a package from the output of `yum list` or
+// `dnf list`, e.g. "nginx.x86_64  1:1.12.2-2.el7  epel", in the
+// order they are listed and without the epoch.
+fn rpm_list_versions(output: &str, name: &str) -> Vec<String> {
+    output.lines().filter_map(|line| {
+        let mut cols = line.split_whitespace();
+        match (cols.next(), cols.next()) {
+            (Some(pkg), Some(version)) if pkg.rsplitn(2, '.').nth(1) == Some(name) => {
+                Some(version.splitn(2, ':').last().unwrap().to_string())
+            },
+            _ => None,
+        }
+    }).collect()
+}
+
+// Get the URL of a repository, which every provider except Homebrew
+// needs.
+fn repository_url(repo: &Repository) -> Result<&str> {
+    match repo.url {
+        Some(ref url) => Ok(url.as_str()),
+        None => Err(Error::Generic(format!("Repository {} needs a URL", repo.name))),
+    }
+}
+
+// Get the contents of a `.repo` file for Yum and Dnf.
+fn rpm_repo_file(repo: &Repository) -> Result<String> {
+    let mut lines = vec![
+        format!("[{}]", repo.name),
+        format!("name={}", repo.name),
+        format!("baseurl={}", try!(repository_url(repo))),
+        "enabled=1".to_string(),
+    ];
+
+    match repo.key {
+        Some(ref key) => {
+            lines.push("gpgcheck=1".into());
+            lines.push(format!("gpgkey={}", key));
+        },
+        None => lines.push("gpgcheck=0".into()),
+    }
+
+    Ok(lines.join("\n"))
+}
+
+// Check whether a file on the host exists.
+fn file_exists(host: &mut Host, path: &str) -> Result<bool> {
+    let cmd = Command::new(&format!("test -e {}", quote(path)));
+    let result = try!(cmd.exec(host));
+
+    Ok(result.exit_code == 0)
+}
+
+// Check whether a file on the host contains exactly `content`.
+fn file_matches(host: &mut Host, path: &str, content: &str) -> Result<bool> {
+    let cmd = Command::new(&format!("cat {}", quote(path)));
+    let result = try!(cmd.exec(host));
+
+    Ok(result.exit_code == 0 && result.stdout.trim() == content.trim())
+}
+
+// Get a shell command that writes `content` to a file. Writing with
+// a command rather than uploading the file means that the write is
+// escalated along with the rest of the provider's commands.
+fn write_file(path: &str, content: &str) -> String {
+    let dir = Path::new(path).parent().map_or("/".into(), |p| p.to_string_lossy().into_owned());
+    format!("mkdir -p {} && printf '%s\\n' {} > {}", quote(&dir), quote(content), quote(path))
+}
+
+// Get the age in seconds of the oldest file whose modification time
+// is printed by `stat_cmd`, one per line, or None if there are no
+// such files.
+fn stat_age(host: &mut Host, stat_cmd: &str) -> Result<Option<u64>> {
+    let cmd = Command::new(&format!("date +%s && {}", stat_cmd));
+    let result = try!(cmd.exec(host));
+    if result.exit_code != 0 {
+        return Ok(None);
+    }
+
+    let mut times = result.stdout.lines().filter_map(|l| l.trim().parse::<u64>().ok());
+    let now = match times.next() {
+        Some(now) => now,
+        None => return Ok(None),
+    };
+
+    Ok(times.min().map(|oldest| now.saturating_sub(oldest)))
+}
+
+// Run commands in turn, stopping at the first that fails, and return
+// the result of the last command run.
+fn exec_all(host: &mut Host, cmds: &[String]) -> Result<CommandResult> {
+    let mut last = None;
+    for cmd in cmds {
+        let result = try!(Command::new(cmd).exec(host));
+        let failed = result.exit_code != 0;
+        last = Some(result);
+        if failed {
+            break;
+        }
+    }
+
+    last.ok_or_else(|| Error::Generic("No commands to run".into()))
 }
@@ -0,0 +1,261 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Pacman package provider
+//!
+//! Installs and upgrades only touch the packages they are given, with
+//! `pacman -S`. The sync database is only refreshed by
+//! `refresh_cache` and the cache policy. Arch Linux does not support
+//! partial upgrades, so upgrade the whole system after refreshing the
+//! database, e.g. with `Command::new("pacman -Su --noconfirm")`.
+
+use command::{quote, Command, CommandResult};
+use error::{Error, Result};
+use host::Host;
+use package::version;
+use super::*;
+
+pub struct Pacman;
+
+impl Provider for Pacman {
+    fn get_providers(&self) -> Providers {
+        Providers::Pacman
+    }
+
+    fn is_active(&self, host: &mut Host) -> Result<bool> {
+        let cmd = Command::new("which pacman");
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn is_installed(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new(&format!("pacman -Q {}", name));
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        // e.g. "nginx 1.12.1-1"
+        let cmd = Command::new(&format!("pacman -Q {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        Ok(result.stdout.split_whitespace().nth(1).map(|v| v.to_string()))
+    }
+
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        // e.g. "Version         : 1.12.1-1"
+        let cmd = Command::new(&format!("pacman -Si {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        Ok(result.stdout.lines()
+                        .find(|l| l.starts_with("Version"))
+                        .and_then(|l| l.splitn(2, ':').nth(1))
+                        .map(|v| v.trim().to_string()))
+    }
+
+    // The sync database only offers one version of a package, so
+    // older versions come from the package cache.
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>> {
+        let mut versions = try!(cached_versions(host, name));
+        if let Some(candidate) = try!(self.candidate_version(host, name)) {
+            versions.push(candidate);
+        }
+
+        versions.sort_by(|a, b| version::compare(a, b));
+        versions.dedup();
+        Ok(versions)
+    }
+
+    fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("pacman -S --noconfirm --needed {}", name));
+        cmd.exec(host)
+    }
+
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult> {
+        let cmd = if try!(self.candidate_version(host, name)).map_or(false, |v| v == version) {
+            Command::new(&format!("pacman -S --noconfirm {}", name))
+        } else {
+            Command::new(&format!("pacman -U --noconfirm $(ls /var/cache/pacman/pkg/{}-{}-*.pkg.tar.* | grep -v '\\.sig$')", name, version))
+        };
+        cmd.exec(host)
+    }
+
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("pacman -S --noconfirm {}", name));
+        cmd.exec(host)
+    }
+
+    fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("pacman -R --noconfirm {}", name));
+        cmd.exec(host)
+    }
+
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>> {
+        let cmd = Command::new("pacman -Qq");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        let installed: Vec<&str> = result.stdout.lines().collect();
+        Ok(names.iter().filter(|n| installed.contains(*n)).map(|n| n.to_string()).collect())
+    }
+
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("pacman -S --noconfirm --needed {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("pacman -R --noconfirm {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool> {
+        // e.g. "IgnorePkg = nginx linux"
+        let cmd = Command::new("grep -E '^\\s*IgnorePkg\\s*=' /etc/pacman.conf");
+        let result = try!(cmd.exec(host));
+
+        Ok(result.stdout.lines()
+                        .filter_map(|l| l.splitn(2, '=').nth(1))
+                        .any(|pkgs| pkgs.split_whitespace().any(|p| p == name)))
+    }
+
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("sed -i '/^\\[options\\]/a IgnorePkg = {}' /etc/pacman.conf", name));
+        cmd.exec(host)
+    }
+
+    // Only removes the lines that `hold` adds, rather than packages
+    // listed alongside others.
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("sed -i '/^IgnorePkg = {}$/d' /etc/pacman.conf", name));
+        cmd.exec(host)
+    }
+
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool> {
+        file_exists(host, &repo_path(name))
+    }
+
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool> {
+        if !try!(file_matches(host, &repo_path(&repo.name), &try!(repo_conf(repo)))) {
+            return Ok(false);
+        }
+
+        let cmd = Command::new(&format!("grep -Fxq {} /etc/pacman.conf", quote(&include(&repo.name))));
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        let include = quote(&include(&repo.name));
+
+        exec_all(host, &[
+            write_file(&repo_path(&repo.name), &try!(repo_conf(repo))),
+            format!("grep -Fxq {0} /etc/pacman.conf || printf '%s\\n' {0} >> /etc/pacman.conf", include),
+            "pacman -Sy".into(),
+        ])
+    }
+
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        exec_all(host, &[
+            format!("sed -i '\\|^{}$|d' /etc/pacman.conf", include(&repo.name)),
+            format!("rm -f {}", quote(&repo_path(&repo.name))),
+            "pacman -Sy".into(),
+        ])
+    }
+
+    // Only syncs the database, leaving installed packages as they are
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult> {
+        let cmd = Command::new("pacman -Sy");
+        cmd.exec(host)
+    }
+
+    // Pacman keeps a sync database for each repository.
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>> {
+        stat_age(host, "stat -c %Y /var/lib/pacman/sync/*.db")
+    }
+}
+
+fn repo_path(name: &str) -> String {
+    format!("/etc/pacman.d/{}.conf", name)
+}
+
+// Get the line that includes a repository's configuration in
+// pacman.conf.
+fn include(name: &str) -> String {
+    format!("Include = {}", repo_path(name))
+}
+
+// Get the repository configuration, e.g.
+// [nginx]
+// Server = https://repo.example.com/$arch
+fn repo_conf(repo: &Repository) -> Result<String> {
+    if repo.key.is_some() {
+        return Err(Error::Generic(format!("Pacman repository {} cannot have a key URL. Add the key with pacman-key instead.", repo.name)));
+    }
+
+    Ok(format!("[{}]\nServer = {}", repo.name, try!(repository_url(repo))))
+}
+
+// Get the versions of a package in the package cache, from file names
+// such as "nginx-1.12.1-1-x86_64.pkg.tar.xz".
+fn cached_versions(host: &mut Host, name: &str) -> Result<Vec<String>> {
+    let cmd = Command::new("ls /var/cache/pacman/pkg");
+    let result = try!(cmd.exec(host));
+    if result.exit_code != 0 {
+        return Ok(Vec::new());
+    }
+
+    let prefix = format!("{}-", name);
+    Ok(result.stdout.lines().filter(|f| f.starts_with(&prefix) && !f.ends_with(".sig")).filter_map(|f| {
+        let parts: Vec<&str> = f[prefix.len()..].splitn(3, '-').collect();
+        // Skip packages whose names extend this one, e.g. "nginx-mainline"
+        if parts.len() == 3 && parts[0].starts_with(|c: char| c.is_digit(10)) {
+            Some(format!("{}-{}", parts[0], parts[1]))
+        } else {
+            None
+        }
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use Host;
+    use host::transport::MemoryTransport;
+    use super::*;
+
+    #[test]
+    fn test_available_versions() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "nginx-1.12.0-1-x86_64.pkg.tar.xz\nnginx-1.12.0-1-x86_64.pkg.tar.xz.sig\nnginx-1.12.1-1-x86_64.pkg.tar.xz\nnginx-mainline-1.13.0-1-x86_64.pkg.tar.xz", ""]);
+        transport.reply(&["Ok", "0", "Repository      : community\nName            : nginx\nVersion         : 1.12.2-1", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        assert_eq!(Pacman.available_versions(&mut host, "nginx").unwrap(), vec!["1.12.0-1", "1.12.1-1", "1.12.2-1"]);
+    }
+
+    #[test]
+    fn test_install() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        Pacman.install(&mut host, "nginx").unwrap();
+        assert_eq!(transport.requests()[0], vec!["command::exec", "pacman -S --noconfirm --needed nginx"]);
+    }
+}
@@ -8,9 +8,10 @@
 
 //! Pkg package provider
 
-use command::{Command, CommandResult};
-use error::Result;
+use command::{quote, Command, CommandResult};
+use error::{Error, Result};
 use host::Host;
+use package::version;
 use super::*;
 
 pub struct Pkg;
@@ -34,13 +35,159 @@ impl Provider for Pkg {
         Ok(result.exit_code == 0)
     }
 
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let cmd = Command::new(&format!("pkg query \"%v\" {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(result.stdout))
+    }
+
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let versions = try!(self.available_versions(host, name));
+        Ok(versions.into_iter().max_by(|a, b| version::compare(a, b)))
+    }
+
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>> {
+        // Lists one version for each repository
+        let cmd = Command::new(&format!("pkg rquery \"%v\" {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(Vec::new());
+        }
+
+        Ok(result.stdout.lines().map(|v| v.to_string()).collect())
+    }
+
     fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
         let cmd = Command::new(&format!("env ASSUME_ALWAYS_YES=YES pkg install {}", name));
         cmd.exec(host)
     }
 
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("env ASSUME_ALWAYS_YES=YES pkg install {}-{}", name, version));
+        cmd.exec(host)
+    }
+
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("env ASSUME_ALWAYS_YES=YES pkg upgrade {}", name));
+        cmd.exec(host)
+    }
+
     fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
         let cmd = Command::new(&format!("env ASSUME_ALWAYS_YES=YES pkg delete {}", name));
         cmd.exec(host)
     }
+
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>> {
+        let cmd = Command::new("pkg query -a \"%n\"");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        let installed: Vec<&str> = result.stdout.lines().collect();
+        Ok(names.iter().filter(|n| installed.contains(*n)).map(|n| n.to_string()).collect())
+    }
+
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("env ASSUME_ALWAYS_YES=YES pkg install {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("env ASSUME_ALWAYS_YES=YES pkg delete {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new(&format!("pkg query \"%k\" {}", name));
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0 && result.stdout == "1")
+    }
+
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("pkg lock -y {}", name));
+        cmd.exec(host)
+    }
+
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("pkg unlock -y {}", name));
+        cmd.exec(host)
+    }
+
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool> {
+        file_exists(host, &conf_path(name))
+    }
+
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool> {
+        if !try!(file_matches(host, &conf_path(&repo.name), &try!(repo_conf(repo)))) {
+            return Ok(false);
+        }
+
+        match repo.key {
+            Some(_) => file_exists(host, &key_path(&repo.name)),
+            None => Ok(true),
+        }
+    }
+
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        let mut cmds = Vec::new();
+        if let Some(ref key) = repo.key {
+            cmds.push(format!("mkdir -p '/usr/local/etc/pkg/keys' && fetch -o {} {}", quote(&key_path(&repo.name)), quote(key)));
+        }
+        cmds.push(write_file(&conf_path(&repo.name), &try!(repo_conf(repo))));
+        cmds.push("pkg update -f".into());
+
+        exec_all(host, &cmds)
+    }
+
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        exec_all(host, &[
+            format!("rm -f {} {}", quote(&conf_path(&repo.name)), quote(&key_path(&repo.name))),
+            "pkg update -f".into(),
+        ])
+    }
+
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult> {
+        let cmd = Command::new("pkg update -f");
+        cmd.exec(host)
+    }
+
+    // Pkg keeps a catalogue for each repository.
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>> {
+        stat_age(host, "stat -f %m /var/db/pkg/repo-*.sqlite")
+    }
+}
+
+fn conf_path(name: &str) -> String {
+    format!("/usr/local/etc/pkg/repos/{}.conf", name)
+}
+
+fn key_path(name: &str) -> String {
+    format!("/usr/local/etc/pkg/keys/{}.pub", name)
+}
+
+// Get the repository configuration, e.g.
+// nginx: {
+//   url: "pkg+http://pkg.example.com/${ABI}",
+//   enabled: yes
+// }
+fn repo_conf(repo: &Repository) -> Result<String> {
+    let mut lines = vec![
+        format!("{}: {{", repo.name),
+        format!("  url: \"{}\",", try!(repository_url(repo))),
+    ];
+
+    if repo.key.is_some() {
+        lines.push("  signature_type: \"pubkey\",".into());
+        lines.push(format!("  pubkey: \"{}\",", key_path(&repo.name)));
+    }
+
+    lines.push("  enabled: yes".into());
+    lines.push("}".into());
+    Ok(lines.join("\n"))
 }
@@ -7,9 +7,12 @@
 // modified, or distributed except according to those terms.
 
 //! Ports package provider
+//!
+//! Ports are built from the ports tree in /usr/ports and registered
+//! with pkg, so installed ports are queried with `pkg query`.
 
-use command::CommandResult;
-use error::Result;
+use command::{quote, Command, CommandResult};
+use error::{Error, Result};
 use host::Host;
 use super::*;
 
@@ -20,23 +23,177 @@ impl Provider for Ports {
         Providers::Ports
     }
 
-    #[allow(unused_variables)]
     fn is_active(&self, host: &mut Host) -> Result<bool> {
-        Ok(false)
+        let cmd = Command::new("test -f /usr/ports/Makefile");
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
     }
 
-    #[allow(unused_variables)]
     fn is_installed(&self, host: &mut Host, name: &str) -> Result<bool> {
-        unimplemented!();
+        let cmd = Command::new(&format!("pkg query \"%n\" {}", name));
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let cmd = Command::new(&format!("pkg query \"%v\" {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(result.stdout))
+    }
+
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let dir = match try!(port_dir(host, name)) {
+            Some(dir) => dir,
+            None => return Ok(None),
+        };
+
+        let cmd = Command::new(&format!("make -C {} -V PKGVERSION", quote(&dir)));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        if result.stdout.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(result.stdout))
+        }
+    }
+
+    // The ports tree only offers one version of a port.
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>> {
+        Ok(try!(self.candidate_version(host, name)).into_iter().collect())
     }
 
-    #[allow(unused_variables)]
     fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
-        unimplemented!();
+        let dir = try!(need_port_dir(host, name));
+        let cmd = Command::new(&format!("make -C {} BATCH=yes install clean", quote(&dir)));
+        cmd.exec(host)
+    }
+
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult> {
+        if try!(self.candidate_version(host, name)).map_or(true, |v| v != version) {
+            return Err(Error::Generic(format!("Ports can only install the version of {} in the ports tree", name)));
+        }
+
+        if try!(self.is_installed(host, name)) {
+            self.upgrade(host, name)
+        } else {
+            self.install(host, name)
+        }
+    }
+
+    // Rebuilds the port from the ports tree in place of the installed
+    // package.
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let dir = try!(need_port_dir(host, name));
+        let cmd = Command::new(&format!("make -C {} BATCH=yes reinstall clean", quote(&dir)));
+        cmd.exec(host)
     }
 
-    #[allow(unused_variables)]
     fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
-        unimplemented!();
+        let cmd = Command::new(&format!("pkg delete -y {}", name));
+        cmd.exec(host)
+    }
+
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>> {
+        let cmd = Command::new("pkg query -a \"%n\"");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        let installed: Vec<&str> = result.stdout.lines().collect();
+        Ok(names.iter().filter(|n| installed.contains(*n)).map(|n| n.to_string()).collect())
+    }
+
+    // Each port is built in turn, stopping at the first that fails.
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let mut cmds = Vec::new();
+        for name in names {
+            let dir = try!(need_port_dir(host, name));
+            cmds.push(format!("make -C {} BATCH=yes install clean", quote(&dir)));
+        }
+
+        exec_all(host, &cmds)
+    }
+
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("pkg delete -y {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new(&format!("pkg query \"%k\" {}", name));
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0 && result.stdout == "1")
+    }
+
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("pkg lock -y {}", name));
+        cmd.exec(host)
+    }
+
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("pkg unlock -y {}", name));
+        cmd.exec(host)
+    }
+
+    #[allow(unused_variables)]
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool> {
+        Err(Error::Generic("Repositories are not supported by Ports".into()))
+    }
+
+    #[allow(unused_variables)]
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool> {
+        Err(Error::Generic("Repositories are not supported by Ports".into()))
+    }
+
+    #[allow(unused_variables)]
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        Err(Error::Generic("Repositories are not supported by Ports".into()))
+    }
+
+    #[allow(unused_variables)]
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        Err(Error::Generic("Repositories are not supported by Ports".into()))
+    }
+
+    // Portsnap refuses to fetch without a terminal unless it is told
+    // that it is running interactively.
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult> {
+        let cmd = Command::new("portsnap --interactive fetch update");
+        cmd.exec(host)
+    }
+
+    // Portsnap rebuilds the ports tree's INDEX on each update.
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>> {
+        stat_age(host, "stat -f %m /usr/ports/INDEX-*")
+    }
+}
+
+// Find the directory of a port in the ports tree, e.g.
+// "/usr/ports/www/nginx".
+fn port_dir(host: &mut Host, name: &str) -> Result<Option<String>> {
+    let cmd = Command::new(&format!("whereis -sq {}", name));
+    let result = try!(cmd.exec(host));
+    if result.exit_code != 0 {
+        return Ok(None);
+    }
+
+    Ok(result.stdout.lines().map(|l| l.trim().to_string()).find(|l| l.starts_with("/usr/ports/")))
+}
+
+fn need_port_dir(host: &mut Host, name: &str) -> Result<String> {
+    match try!(port_dir(host, name)) {
+        Some(dir) => Ok(dir),
+        None => Err(Error::Generic(format!("Could not find port {} in /usr/ports", name))),
     }
 }
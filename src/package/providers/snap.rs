@@ -0,0 +1,235 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Snap package provider
+//!
+//! Snaps are installed from channels rather than by version, so
+//! installing a version selects the channel that offers it.
+
+use command::{Command, CommandResult};
+use error::{Error, Result};
+use host::Host;
+use super::*;
+
+pub struct Snap;
+
+impl Provider for Snap {
+    fn get_providers(&self) -> Providers {
+        Providers::Snap
+    }
+
+    fn is_active(&self, host: &mut Host) -> Result<bool> {
+        let cmd = Command::new("which snap");
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn is_installed(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new(&format!("snap list {}", name));
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        Ok(try!(list(host, name)).map(|cols| cols[1].clone()))
+    }
+
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let channels = try!(channels(host, name));
+        Ok(channels.into_iter().find(|&(ref c, _)| c == "latest/stable").map(|(_, v)| v))
+    }
+
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>> {
+        let mut versions: Vec<String> = try!(channels(host, name)).into_iter().map(|(_, v)| v).collect();
+        versions.sort();
+        versions.dedup();
+        Ok(versions)
+    }
+
+    fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("snap install {}", name));
+        cmd.exec(host)
+    }
+
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult> {
+        let channel = match try!(channels(host, name)).into_iter().find(|&(_, ref v)| v == version) {
+            Some((c, _)) => c,
+            None => return Err(Error::Generic(format!("No channel of {} offers version {}", name, version))),
+        };
+
+        let action = if try!(self.is_installed(host, name)) { "refresh" } else { "install" };
+        let cmd = Command::new(&format!("snap {} {} --channel={}", action, name, channel));
+        cmd.exec(host)
+    }
+
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("snap refresh {}", name));
+        cmd.exec(host)
+    }
+
+    fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("snap remove {}", name));
+        cmd.exec(host)
+    }
+
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>> {
+        let cmd = Command::new("snap list");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        let installed: Vec<&str> = result.stdout.lines().skip(1).filter_map(|l| l.split_whitespace().next()).collect();
+        Ok(names.iter().filter(|n| installed.contains(*n)).map(|n| n.to_string()).collect())
+    }
+
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("snap install {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("snap remove {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool> {
+        // The notes column lists "held" for a held snap
+        Ok(try!(list(host, name)).map_or(false, |cols| cols.last().map_or(false, |n| n.split(',').any(|n| n == "held"))))
+    }
+
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("snap refresh --hold {}", name));
+        cmd.exec(host)
+    }
+
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("snap refresh --unhold {}", name));
+        cmd.exec(host)
+    }
+
+    // Snaps always come from the Snap Store, so there are no
+    // repositories to manage.
+    #[allow(unused_variables)]
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    #[allow(unused_variables)]
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool> {
+        Ok(false)
+    }
+
+    #[allow(unused_variables)]
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        Err(Error::Generic("Snap does not support repositories".into()))
+    }
+
+    #[allow(unused_variables)]
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        Err(Error::Generic("Snap does not support repositories".into()))
+    }
+
+    // Snap queries the store directly rather than keeping a metadata
+    // cache, so refreshing checks the store for updates to installed
+    // snaps.
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult> {
+        let cmd = Command::new("snap refresh --list");
+        cmd.exec(host)
+    }
+
+    // With no cache there is no age to report, so a stale-cache
+    // policy always refreshes.
+    #[allow(unused_variables)]
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>> {
+        Ok(None)
+    }
+}
+
+// Get the columns of `snap list` for a snap: name, version, revision,
+// tracking, publisher and notes.
+fn list(host: &mut Host, name: &str) -> Result<Option<Vec<String>>> {
+    let cmd = Command::new(&format!("snap list {}", name));
+    let result = try!(cmd.exec(host));
+    if result.exit_code != 0 {
+        return Ok(None);
+    }
+
+    Ok(result.stdout.lines()
+                    .skip(1)
+                    .map(|l| l.split_whitespace().map(|c| c.to_string()).collect::<Vec<_>>())
+                    .find(|cols| cols.len() > 1 && cols[0] == name))
+}
+
+// Get the channels that offer a snap and their versions from `snap
+// info`, e.g. "  latest/stable:    1.2.3 2018-01-01 (12) 40MB -".
+// Closed channels are listed as "--" or "^" and are skipped.
+fn channels(host: &mut Host, name: &str) -> Result<Vec<(String, String)>> {
+    let cmd = Command::new(&format!("snap info {}", name));
+    let result = try!(cmd.exec(host));
+    if result.exit_code != 0 {
+        return Err(Error::Agent(result.stderr));
+    }
+
+    Ok(result.stdout.lines()
+                    .skip_while(|l| !l.starts_with("channels:"))
+                    .skip(1)
+                    .take_while(|l| l.starts_with(' '))
+                    .filter_map(|l| {
+                        let mut cols = l.split_whitespace();
+                        match (cols.next(), cols.next()) {
+                            (Some(channel), Some(version)) if channel.ends_with(':') && version != "--" && version != "^" => {
+                                Some((channel.trim_right_matches(':').to_string(), version.to_string()))
+                            },
+                            _ => None,
+                        }
+                    })
+                    .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use Host;
+    use host::transport::MemoryTransport;
+    use super::*;
+
+    #[test]
+    fn test_versions() {
+        let info = "name:      hello\n\
+                    summary:   GNU Hello\n\
+                    tracking:  latest/stable\n\
+                    channels:\n  \
+                      latest/stable:    2.10   2019-04-17 (38) 20kB -\n  \
+                      latest/candidate: ^\n  \
+                      latest/beta:      2.10.1 2019-05-01 (40) 20kB -\n  \
+                      latest/edge:      --\n\
+                    installed:          2.10              (38) 20kB -";
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", info, ""]);
+        transport.reply(&["Ok", "0", info, ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        assert_eq!(Snap.candidate_version(&mut host, "hello").unwrap().unwrap(), "2.10");
+        assert_eq!(Snap.available_versions(&mut host, "hello").unwrap(), vec!["2.10", "2.10.1"]);
+    }
+
+    #[test]
+    fn test_list() {
+        let list = "Name   Version  Rev  Tracking       Publisher  Notes\n\
+                    hello  2.10     38   latest/stable  canonical  held";
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", list, ""]);
+        transport.reply(&["Ok", "0", list, ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        assert_eq!(Snap.installed_version(&mut host, "hello").unwrap().unwrap(), "2.10");
+        assert!(Snap.is_held(&mut host, "hello").unwrap());
+    }
+}
@@ -8,10 +8,12 @@
 
 //! Yum package provider
 
-use command::{Command, CommandResult};
+use command::{quote, Command, CommandResult};
 use error::{Error, Result};
 use host::Host;
-use regex::Regex;
+use package::version;
+use regex::{self, Regex};
+use std::cmp::Ordering;
 use super::*;
 
 pub struct Yum;
@@ -41,13 +43,144 @@ impl Provider for Yum {
         Ok(re.is_match(&result.stdout))
     }
 
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let cmd = Command::new(&format!("rpm -q --qf '%{{VERSION}}-%{{RELEASE}}\\n' {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        Ok(result.stdout.lines().next().map(|v| v.to_string()))
+    }
+
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        // Lists the installed version, followed by any newer version
+        let cmd = Command::new(&format!("yum -q list {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        Ok(rpm_list_versions(&result.stdout, name).pop())
+    }
+
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>> {
+        let cmd = Command::new(&format!("yum -q list --showduplicates {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut versions = rpm_list_versions(&result.stdout, name);
+        versions.dedup();
+        Ok(versions)
+    }
+
     fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
         let cmd = Command::new(&format!("yum -y install {}", name));
         cmd.exec(host)
     }
 
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult> {
+        let action = match try!(self.installed_version(host, name)) {
+            Some(ref installed) if version::compare(version, installed) == Ordering::Less => "downgrade",
+            _ => "install",
+        };
+        let cmd = Command::new(&format!("yum -y {} {}-{}", action, name, version));
+        cmd.exec(host)
+    }
+
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("yum -y upgrade {}", name));
+        cmd.exec(host)
+    }
+
     fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
         let cmd = Command::new(&format!("yum -y remove {}", name));
         cmd.exec(host)
     }
+
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>> {
+        let cmd = Command::new("yum list installed");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        let arch = try!(needstr!(host.data() => "/_telemetry/os/arch"));
+
+        let mut installed = Vec::new();
+        for name in names {
+            let re = try!(Regex::new(&format!("(?m)^{}\\.({}|noarch)\\s+", regex::quote(name), arch)));
+            if re.is_match(&result.stdout) {
+                installed.push(name.to_string());
+            }
+        }
+        Ok(installed)
+    }
+
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("yum -y install {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("yum -y remove {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new("yum versionlock list");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        // Locks are listed with an epoch, e.g. "0:nginx-1.12.2-2.el7.*"
+        let re = try!(Regex::new(&format!("(?m)^(\\d+:)?{}-(\\d+:)?\\d", regex::quote(name))));
+        Ok(re.is_match(&result.stdout))
+    }
+
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("yum versionlock add {}", name));
+        cmd.exec(host)
+    }
+
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("yum versionlock delete '*:{}-[0-9]*'", name));
+        cmd.exec(host)
+    }
+
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool> {
+        file_exists(host, &format!("/etc/yum.repos.d/{}.repo", name))
+    }
+
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool> {
+        file_matches(host, &format!("/etc/yum.repos.d/{}.repo", repo.name), &try!(rpm_repo_file(repo)))
+    }
+
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        exec_all(host, &[
+            write_file(&format!("/etc/yum.repos.d/{}.repo", repo.name), &try!(rpm_repo_file(repo))),
+            "yum -y makecache".into(),
+        ])
+    }
+
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        exec_all(host, &[
+            format!("rm -f {}", quote(&format!("/etc/yum.repos.d/{}.repo", repo.name))),
+            "yum -y makecache".into(),
+        ])
+    }
+
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult> {
+        let cmd = Command::new("yum -y makecache");
+        cmd.exec(host)
+    }
+
+    // Yum writes a cookie for each repository when it refreshes its
+    // metadata.
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>> {
+        stat_age(host, "stat -c %Y /var/cache/yum/*/*/*/cachecookie")
+    }
 }
@@ -0,0 +1,207 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Zypper package provider
+
+use command::{quote, Command, CommandResult};
+use error::{Error, Result};
+use host::Host;
+use super::*;
+
+pub struct Zypper;
+
+impl Provider for Zypper {
+    fn get_providers(&self) -> Providers {
+        Providers::Zypper
+    }
+
+    fn is_active(&self, host: &mut Host) -> Result<bool> {
+        let cmd = Command::new("which zypper");
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn is_installed(&self, host: &mut Host, name: &str) -> Result<bool> {
+        let cmd = Command::new(&format!("rpm -q {}", name));
+        let result = try!(cmd.exec(host));
+
+        Ok(result.exit_code == 0)
+    }
+
+    fn installed_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        let cmd = Command::new(&format!("rpm -q --qf '%{{VERSION}}-%{{RELEASE}}\\n' {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        Ok(result.stdout.lines().next().map(|v| v.to_string()))
+    }
+
+    fn candidate_version(&self, host: &mut Host, name: &str) -> Result<Option<String>> {
+        // e.g. "Version        : 1.14.2-1.1"
+        let cmd = Command::new(&format!("zypper --non-interactive -q info {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        Ok(result.stdout.lines()
+                        .find(|l| l.starts_with("Version"))
+                        .and_then(|l| l.splitn(2, ':').nth(1))
+                        .map(|v| v.trim().to_string()))
+    }
+
+    fn available_versions(&self, host: &mut Host, name: &str) -> Result<Vec<String>> {
+        // e.g. "v | nginx | package | 1.14.2-1.1 | x86_64 | Main Repository"
+        let cmd = Command::new(&format!("zypper --non-interactive -q search -s --match-exact -t package {}", name));
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut versions: Vec<String> = result.stdout.lines().filter_map(|line| {
+            let cols: Vec<&str> = line.split('|').map(|c| c.trim()).collect();
+            if cols.len() > 3 && cols[1] == name {
+                Some(cols[3].to_string())
+            } else {
+                None
+            }
+        }).collect();
+        versions.dedup();
+        Ok(versions)
+    }
+
+    fn install(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("zypper --non-interactive install {}", name));
+        cmd.exec(host)
+    }
+
+    fn install_version(&self, host: &mut Host, name: &str, version: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("zypper --non-interactive install --oldpackage {}={}", name, version));
+        cmd.exec(host)
+    }
+
+    fn upgrade(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("zypper --non-interactive update {}", name));
+        cmd.exec(host)
+    }
+
+    fn uninstall(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("zypper --non-interactive remove {}", name));
+        cmd.exec(host)
+    }
+
+    fn which_installed(&self, host: &mut Host, names: &[&str]) -> Result<Vec<String>> {
+        // Exits non-zero if any package is missing, printing "package
+        // <name> is not installed" in place of its name.
+        let cmd = Command::new(&format!("rpm -q --qf '%{{NAME}}\\n' {}", names.join(" ")));
+        let result = try!(cmd.exec(host));
+
+        let installed: Vec<&str> = result.stdout.lines().collect();
+        Ok(names.iter().filter(|n| installed.contains(*n)).map(|n| n.to_string()).collect())
+    }
+
+    fn install_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("zypper --non-interactive install {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn uninstall_all(&self, host: &mut Host, names: &[&str]) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("zypper --non-interactive remove {}", names.join(" ")));
+        cmd.exec(host)
+    }
+
+    fn is_held(&self, host: &mut Host, name: &str) -> Result<bool> {
+        // e.g. "1 | nginx | package | (any)"
+        let cmd = Command::new("zypper --non-interactive -q locks");
+        let result = try!(cmd.exec(host));
+        if result.exit_code != 0 {
+            return Err(Error::Agent(result.stderr));
+        }
+
+        Ok(result.stdout.lines().any(|l| l.split('|').nth(1).map_or(false, |c| c.trim() == name)))
+    }
+
+    fn hold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("zypper --non-interactive addlock {}", name));
+        cmd.exec(host)
+    }
+
+    fn unhold(&self, host: &mut Host, name: &str) -> Result<CommandResult> {
+        let cmd = Command::new(&format!("zypper --non-interactive removelock {}", name));
+        cmd.exec(host)
+    }
+
+    fn has_repository(&self, host: &mut Host, name: &str) -> Result<bool> {
+        file_exists(host, &format!("/etc/zypp/repos.d/{}.repo", name))
+    }
+
+    fn repository_matches(&self, host: &mut Host, repo: &Repository) -> Result<bool> {
+        file_matches(host, &format!("/etc/zypp/repos.d/{}.repo", repo.name), &try!(rpm_repo_file(repo)))
+    }
+
+    fn add_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        exec_all(host, &[
+            write_file(&format!("/etc/zypp/repos.d/{}.repo", repo.name), &try!(rpm_repo_file(repo))),
+            "zypper --non-interactive --gpg-auto-import-keys refresh".into(),
+        ])
+    }
+
+    fn remove_repository(&self, host: &mut Host, repo: &Repository) -> Result<CommandResult> {
+        exec_all(host, &[
+            format!("rm -f {}", quote(&format!("/etc/zypp/repos.d/{}.repo", repo.name))),
+            "zypper --non-interactive refresh".into(),
+        ])
+    }
+
+    fn refresh_cache(&self, host: &mut Host) -> Result<CommandResult> {
+        let cmd = Command::new("zypper --non-interactive refresh");
+        cmd.exec(host)
+    }
+
+    // Zypper keeps the metadata of each repository in its raw cache.
+    fn cache_age(&self, host: &mut Host) -> Result<Option<u64>> {
+        stat_age(host, "stat -c %Y /var/cache/zypp/raw/*/repodata/repomd.xml")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Host;
+    use host::transport::MemoryTransport;
+    use super::*;
+
+    #[test]
+    fn test_available_versions() {
+        let search = "S  | Name  | Type    | Version    | Arch   | Repository\n\
+                      ---+-------+---------+------------+--------+-----------\n\
+                      v  | nginx | package | 1.16.1-1.1 | x86_64 | Update\n\
+                      i+ | nginx | package | 1.14.2-1.1 | x86_64 | Main";
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", search, ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        assert_eq!(Zypper.available_versions(&mut host, "nginx").unwrap(), vec!["1.16.1-1.1", "1.14.2-1.1"]);
+    }
+
+    #[test]
+    fn test_is_held() {
+        let locks = "# | Name  | Type    | Repository\n\
+                     --+-------+---------+-----------\n\
+                     1 | nginx | package | (any)";
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", locks, ""]);
+        transport.reply(&["Ok", "0", locks, ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        assert!(Zypper.is_held(&mut host, "nginx").unwrap());
+        assert!(!Zypper.is_held(&mut host, "curl").unwrap());
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The primitive for adding and removing package repositories.
+
+use error::Result;
+use host::Host;
+use outcome::Outcome;
+use super::providers::*;
+use super::run;
+
+/// A package repository and the key used to sign it.
+///
+/// How a Repository is configured depends on the provider:
+///
+/// - Apt: a source list in `/etc/apt/sources.list.d/<name>.list`,
+///   with the key in `/etc/apt/keyrings/<name>.asc`
+/// - Dnf and Yum: a `/etc/yum.repos.d/<name>.repo` file
+/// - Pkg: a `/usr/local/etc/pkg/repos/<name>.conf` file, with the key
+///   in `/usr/local/etc/pkg/keys/<name>.pub`
+/// - Homebrew: a tap, where `name` is the tap, e.g. `homebrew/cask`,
+///   and `url` is optional
+///
+/// The provider's metadata cache is refreshed after a Repository is
+/// added or removed.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use inapi::{Host, Repository};
+/// let mut host = Host::connect("hosts/myhost.json").unwrap();
+/// let repo = Repository::new("nginx")
+///     .url("http://nginx.org/packages/debian")
+///     .suite("stretch")
+///     .components(&["nginx"])
+///     .key("https://nginx.org/keys/nginx_signing.key");
+/// repo.add(&mut host, None).unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Repository {
+    /// Name of the repository, e.g. `nginx`
+    pub name: String,
+    /// URL of the repository
+    pub url: Option<String>,
+    /// URL of the key the repository is signed with
+    pub key: Option<String>,
+    /// Apt distribution, e.g. `stretch`
+    pub suite: Option<String>,
+    /// Apt components. Defaults to `main`.
+    pub components: Vec<String>,
+}
+
+impl Repository {
+    /// Create a new Repository.
+    pub fn new(name: &str) -> Repository {
+        Repository {
+            name: name.into(),
+            url: None,
+            key: None,
+            suite: None,
+            components: Vec::new(),
+        }
+    }
+
+    /// Set the URL of the repository.
+    pub fn url(mut self, url: &str) -> Repository {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the URL of the repository's signing key.
+    pub fn key(mut self, url: &str) -> Repository {
+        self.key = Some(url.into());
+        self
+    }
+
+    /// Set the Apt distribution.
+    pub fn suite(mut self, suite: &str) -> Repository {
+        self.suite = Some(suite.into());
+        self
+    }
+
+    /// Set the Apt components.
+    pub fn components(mut self, components: &[&str]) -> Repository {
+        self.components = components.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    /// Check whether the repository is configured exactly as given.
+    ///
+    /// If you have multiple package providers, you can specify one
+    /// or allow Intecture to select a default based on the OS.
+    pub fn is_added(&self, host: &mut Host, providers: Option<Providers>) -> Result<bool> {
+        let provider = try!(ProviderFactory::create(host, providers));
+        provider.repository_matches(host, self)
+    }
+
+    /// Add the repository, or update it if its configuration has
+    /// changed.
+    pub fn add(&self, host: &mut Host, providers: Option<Providers>) -> Result<Outcome> {
+        let provider = try!(ProviderFactory::create(host, providers));
+
+        let outcome = if try!(provider.repository_matches(host, self)) {
+            Outcome::unchanged(&self.resource(), "add")
+        } else {
+            Outcome::changed(&self.resource(), "add")
+        };
+
+        run(host, outcome, |h| provider.add_repository(h, self))
+    }
+
+    /// Remove the repository.
+    pub fn remove(&self, host: &mut Host, providers: Option<Providers>) -> Result<Outcome> {
+        let provider = try!(ProviderFactory::create(host, providers));
+
+        let outcome = if try!(provider.has_repository(host, &self.name)) {
+            Outcome::changed(&self.resource(), "remove")
+        } else {
+            Outcome::unchanged(&self.resource(), "remove")
+        };
+
+        run(host, outcome, |h| provider.remove_repository(h, self))
+    }
+
+    fn resource(&self) -> String {
+        format!("repository:{}", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Host;
+    use host::transport::MemoryTransport;
+    use super::*;
+
+    #[test]
+    fn test_add_apt() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "/usr/bin/apt-get", ""]);
+        transport.reply(&["Ok", "1", "", "cat: /etc/apt/sources.list.d/nginx.list: No such file or directory"]);
+        transport.reply(&["Ok", "0", "", ""]);
+        transport.reply(&["Ok", "0", "", ""]);
+        transport.reply(&["Ok", "0", "", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        let repo = Repository::new("nginx")
+            .url("http://nginx.org/packages/debian")
+            .suite("stretch")
+            .components(&["nginx"])
+            .key("https://nginx.org/keys/nginx_signing.key");
+        assert!(repo.add(&mut host, Some(Providers::Apt)).unwrap().is_changed());
+
+        let requests = transport.requests();
+        assert_eq!(requests[1], vec!["command::exec", "cat '/etc/apt/sources.list.d/nginx.list'"]);
+        assert_eq!(requests[2], vec!["command::exec", "mkdir -p '/etc/apt/keyrings' && curl -fsSL 'https://nginx.org/keys/nginx_signing.key' -o '/etc/apt/keyrings/nginx.asc'"]);
+        assert_eq!(requests[3], vec!["command::exec", "mkdir -p '/etc/apt/sources.list.d' && printf '%s\\n' 'deb [signed-by=/etc/apt/keyrings/nginx.asc] http://nginx.org/packages/debian stretch nginx' > '/etc/apt/sources.list.d/nginx.list'"]);
+        assert_eq!(requests[4], vec!["command::exec", "apt-get update"]);
+    }
+
+    #[test]
+    fn test_remove_unchanged() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "/usr/local/bin/brew", ""]);
+        transport.reply(&["Ok", "0", "homebrew/core", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        let repo = Repository::new("homebrew/cask");
+        assert!(!repo.remove(&mut host, Some(Providers::Homebrew)).unwrap().is_changed());
+        assert_eq!(transport.requests().len(), 2);
+    }
+}
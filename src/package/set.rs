@@ -0,0 +1,252 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Operate on several packages with a single provider invocation.
+
+use command::CommandResult;
+use error::{Error, Result};
+use host::Host;
+use outcome::Outcome;
+use super::{exec, refresh_cache, refresh_stale_cache, CachePolicy};
+use super::providers::*;
+
+/// Container for operating on a set of packages.
+///
+/// Unlike creating a [`Package`](struct.Package.html) for each
+/// name, a PackageSet checks the provider once and queries every
+/// package in one command, then installs or uninstalls the packages
+/// that need changing in a single transaction.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use inapi::{Host, PackageSet};
+/// let mut host = Host::connect("hosts/myhost.json").unwrap();
+/// let mut packages = PackageSet::new(&mut host, &["nginx", "git", "curl"], None).unwrap();
+/// for outcome in packages.install(&mut host).unwrap() {
+///     println!("{} {:?}", outcome.resource, outcome.status);
+/// }
+/// ```
+pub struct PackageSet {
+    /// The names of the packages, e.g. `nginx`
+    names: Vec<String>,
+    /// The package source
+    provider: Box<Provider + 'static>,
+    /// The names of the packages that are installed
+    installed: Vec<String>,
+    /// When to refresh the provider's metadata cache
+    cache_policy: CachePolicy,
+}
+
+impl PackageSet {
+    /// Create a new PackageSet.
+    ///
+    /// If you have multiple package providers, you can specify one
+    /// or allow Intecture to select a default based on the OS.
+    pub fn new(host: &mut Host, names: &[&str], providers: Option<Providers>) -> Result<PackageSet> {
+        let provider = try!(ProviderFactory::create(host, providers));
+        let installed = try!(provider.which_installed(host, names));
+
+        Ok(PackageSet {
+            names: names.iter().map(|n| n.to_string()).collect(),
+            provider: provider,
+            installed: installed,
+            cache_policy: CachePolicy::Never,
+        })
+    }
+
+    /// Check if a package in the set is installed.
+    pub fn is_installed(&self, name: &str) -> bool {
+        self.installed.iter().any(|n| n == name)
+    }
+
+    /// Set when to refresh the provider's metadata cache before
+    /// installing packages. Defaults to `CachePolicy::Never`.
+    pub fn set_cache_policy(&mut self, policy: CachePolicy) {
+        self.cache_policy = policy;
+    }
+
+    /// Refresh the provider's metadata cache.
+    pub fn refresh_cache(&self, host: &mut Host) -> Result<Outcome> {
+        refresh_cache(&*self.provider, host, &self.resource())
+    }
+
+    /// Install every package in the set that is not installed.
+    ///
+    /// Returns an Outcome for each package, in the order they were
+    /// given. The Outcomes of packages that were installed share the
+    /// result of the provider command. If the command fails, packages
+    /// that are still not installed are marked as failed.
+    pub fn install(&mut self, host: &mut Host) -> Result<Vec<Outcome>> {
+        let missing: Vec<String> = self.names.iter().filter(|n| !self.is_installed(n)).cloned().collect();
+        if !missing.is_empty() {
+            try!(refresh_stale_cache(&*self.provider, host, self.cache_policy, &self.resource()));
+        }
+        let outcomes = try!(self.apply(host, "install", &missing, true, |p, h, names| p.install_all(h, names)));
+        let installed = self.applied(&outcomes);
+        self.installed.extend(installed);
+
+        Ok(outcomes)
+    }
+
+    /// Uninstall every package in the set that is installed.
+    ///
+    /// Returns an Outcome for each package, in the order they were
+    /// given. The Outcomes of packages that were uninstalled share the
+    /// result of the provider command. If the command fails, packages
+    /// that are still installed are marked as failed.
+    pub fn uninstall(&mut self, host: &mut Host) -> Result<Vec<Outcome>> {
+        let present: Vec<String> = self.names.iter().filter(|n| self.is_installed(n)).cloned().collect();
+        let outcomes = try!(self.apply(host, "uninstall", &present, false, |p, h, names| p.uninstall_all(h, names)));
+        let uninstalled = self.applied(&outcomes);
+        self.installed.retain(|n| !uninstalled.contains(n));
+
+        Ok(outcomes)
+    }
+
+    // Run one provider command for the packages that need changing,
+    // unless the Host is in check mode, then record an Outcome for
+    // every package in the set. `install` is whether the packages
+    // should end up installed.
+    fn apply<F>(&self, host: &mut Host, action: &str, changing: &[String], install: bool, f: F) -> Result<Vec<Outcome>>
+        where F: FnOnce(&Provider, &mut Host, &[&str]) -> Result<CommandResult>
+    {
+        let names: Vec<&str> = changing.iter().map(|n| n.as_str()).collect();
+        let result = match exec(host, !changing.is_empty(), |h| f(&*self.provider, h, &names)) {
+            Ok(r) => r,
+            Err(e) => {
+                // Record the failure against every package that was
+                // changing, so that the report shows each of them.
+                let (last, rest) = match changing.split_last() {
+                    Some(split) => split,
+                    None => return Err(e),
+                };
+                for name in rest {
+                    let _ = host.record(Err(Error::Generic(e.to_string())), Outcome::changed(&resource(name), action));
+                }
+                return host.record(Err(e), Outcome::changed(&resource(last), action)).map(|o| vec![o]);
+            },
+        };
+
+        // A failed command may still have changed some of the packages,
+        // so ask the provider which ones reached the desired state.
+        let changed: Vec<String> = match result {
+            Some(ref r) if r.exit_code != 0 => {
+                let installed = try!(self.provider.which_installed(host, &names));
+                changing.iter().filter(|n| installed.contains(*n) == install).cloned().collect()
+            },
+            _ => changing.to_vec(),
+        };
+
+        let mut outcomes = Vec::new();
+        for name in &self.names {
+            let outcome = if changing.contains(name) {
+                let outcome = Outcome::changed(&resource(name), action);
+                match result {
+                    Some(ref r) if changed.contains(name) => Outcome { result: Some(r.clone()), ..outcome },
+                    Some(ref r) => outcome.command_result(r.clone()),
+                    None => outcome,
+                }
+            } else {
+                Outcome::unchanged(&resource(name), action)
+            };
+            outcomes.push(try!(host.record(Ok(()), outcome)));
+        }
+
+        Ok(outcomes)
+    }
+
+    // Get the names of the packages that a provider command changed.
+    // In check mode nothing is run, so nothing has changed.
+    fn applied(&self, outcomes: &[Outcome]) -> Vec<String> {
+        self.names.iter()
+                  .zip(outcomes)
+                  .filter(|&(_, o)| o.is_changed() && o.result.is_some())
+                  .map(|(n, _)| n.clone())
+                  .collect()
+    }
+
+    fn resource(&self) -> String {
+        resource(&self.names.join(","))
+    }
+}
+
+fn resource(name: &str) -> String {
+    format!("package:{}", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use Host;
+    use host::transport::MemoryTransport;
+    use outcome::Status;
+    use super::*;
+
+    #[test]
+    fn test_install() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "/usr/bin/apt-get", ""]);
+        transport.reply(&["Ok", "0", "curl\t\t\t\t\tinstall\nlibc6:amd64\t\t\t\tinstall", ""]);
+        transport.reply(&["Ok", "0", "", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        let mut packages = PackageSet::new(&mut host, &["nginx", "curl", "git"], Some(Providers::Apt)).unwrap();
+        assert!(packages.is_installed("curl"));
+        assert!(!packages.is_installed("nginx"));
+
+        let outcomes = packages.install(&mut host).unwrap();
+        let status: Vec<Status> = outcomes.iter().map(|o| o.status).collect();
+        assert_eq!(status, vec![Status::Changed, Status::Unchanged, Status::Changed]);
+        assert!(packages.is_installed("nginx"));
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2], vec!["command::exec", "apt-get -y install nginx git"]);
+
+        // Nothing left to install, so no further commands are run
+        let outcomes = packages.install(&mut host).unwrap();
+        assert!(outcomes.iter().all(|o| !o.is_changed()));
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    #[test]
+    fn test_install_failed() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "/usr/bin/apt-get", ""]);
+        transport.reply(&["Ok", "0", "curl\t\t\t\t\tinstall", ""]);
+        transport.reply(&["Ok", "100", "", "E: Unable to locate package nginx"]);
+        transport.reply(&["Ok", "0", "curl\t\t\t\t\tinstall\ngit\t\t\t\t\tinstall", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        let mut packages = PackageSet::new(&mut host, &["nginx", "curl", "git"], Some(Providers::Apt)).unwrap();
+
+        let outcomes = packages.install(&mut host).unwrap();
+        let status: Vec<Status> = outcomes.iter().map(|o| o.status).collect();
+        assert_eq!(status, vec![Status::Failed, Status::Unchanged, Status::Changed]);
+        assert!(!packages.is_installed("nginx"));
+        assert!(packages.is_installed("git"));
+    }
+
+    #[test]
+    fn test_install_error() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "/usr/bin/apt-get", ""]);
+        transport.reply(&["Ok", "0", "curl\t\t\t\t\tinstall", ""]);
+        transport.reply(&["Err", "Connection lost"]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        let mut packages = PackageSet::new(&mut host, &["nginx", "curl", "git"], Some(Providers::Apt)).unwrap();
+        assert!(packages.install(&mut host).is_err());
+
+        let failed: Vec<String> = host.report().outcomes.into_iter()
+                                      .filter(|o| o.status == Status::Failed)
+                                      .map(|o| o.resource)
+                                      .collect();
+        assert_eq!(failed, vec!["package:nginx", "package:git"]);
+    }
+}
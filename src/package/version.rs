@@ -0,0 +1,209 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Package version comparison and requirements.
+//!
+//! A requirement is either an exact version, e.g. `1.10.3-1`, or a
+//! comma separated list of constraints that must all match, e.g.
+//! `>= 1.10, < 1.12`. Constraints use the operators `=`, `>`, `>=`,
+//! `<` and `<=`, and a version ending in `.*`, e.g. `1.10.*`,
+//! matches any version that starts with it. The version's epoch is
+//! ignored unless the requirement gives one, and `.*` cannot be
+//! combined with another operator.
+
+use error::{Error, Result};
+use std::cmp::{self, Ordering};
+
+/// Compare two version strings.
+///
+/// An epoch prefix, e.g. the `1:` in `1:1.18.0`, is compared first,
+/// and a version without one has an epoch of 0. The rest of each
+/// version is split into runs of digits and letters, which are
+/// compared in turn. Digits compare numerically and sort after
+/// letters, and a version that runs out of parts first is the lower
+/// one, so `1.10` > `1.9rc1` > `1.9`. As with dpkg and rpm, a `~`
+/// sorts before everything, including the end of the version, so
+/// `1.0~rc1` < `1.0`.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let (a_epoch, a) = epoch(a);
+    let (b_epoch, b) = epoch(b);
+    if a_epoch != b_epoch {
+        return a_epoch.cmp(&b_epoch);
+    }
+
+    let a = parts(a);
+    let b = parts(b);
+
+    for i in 0..cmp::max(a.len(), b.len()) {
+        let ord = match (a.get(i).map(|p| p.as_str()), b.get(i).map(|p| p.as_str())) {
+            (Some("~"), Some("~")) => Ordering::Equal,
+            (Some("~"), _) => Ordering::Less,
+            (_, Some("~")) => Ordering::Greater,
+            (None, _) => Ordering::Less,
+            (_, None) => Ordering::Greater,
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Greater,
+                (Err(_), Ok(_)) => Ordering::Less,
+                (Err(_), Err(_)) => x.cmp(y),
+            },
+        };
+
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+
+    Ordering::Equal
+}
+
+/// Check whether a requirement is a range rather than an exact
+/// version.
+pub fn is_range(req: &str) -> bool {
+    req.contains(',') || req.ends_with(".*") || req.trim_left().starts_with(|c: char| c == '<' || c == '>' || c == '=')
+}
+
+/// Check whether `version` satisfies a requirement.
+pub fn matches(req: &str, version: &str) -> Result<bool> {
+    for constraint in req.split(',') {
+        let constraint = constraint.trim();
+        let (op, target) = match constraint.find(|c: char| c != '<' && c != '>' && c != '=') {
+            Some(i) => (&constraint[..i], constraint[i..].trim()),
+            None => return Err(Error::Generic(format!("Invalid version requirement \"{}\"", req))),
+        };
+
+        if target.ends_with(".*") {
+            if op != "" && op != "=" && op != "==" {
+                return Err(Error::Generic(format!("Invalid version requirement \"{}\": .* only works with =", req)));
+            }
+
+            // Only compare epochs if the requirement gives one
+            let (prefix_epoch, prefix) = epoch(&target[..target.len() - 2]);
+            let (version_epoch, rest) = epoch(version);
+            let epoch_matches = !target.contains(':') || prefix_epoch == version_epoch;
+            if !epoch_matches || !(rest == prefix || rest.starts_with(&format!("{}.", prefix))) {
+                return Ok(false);
+            }
+            continue;
+        }
+
+        let ord = compare(version, target);
+        let is_match = match op {
+            "" | "=" | "==" => ord == Ordering::Equal,
+            ">" => ord == Ordering::Greater,
+            ">=" => ord != Ordering::Less,
+            "<" => ord == Ordering::Less,
+            "<=" => ord != Ordering::Greater,
+            _ => return Err(Error::Generic(format!("Invalid version operator \"{}\"", op))),
+        };
+
+        if !is_match {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Get the newest version that satisfies a requirement.
+pub fn best_match<'a>(req: &str, versions: &'a [String]) -> Result<Option<&'a String>> {
+    let mut best: Option<&String> = None;
+    for version in versions {
+        if try!(matches(req, version)) && best.map_or(true, |b| compare(version, b) == Ordering::Greater) {
+            best = Some(version);
+        }
+    }
+    Ok(best)
+}
+
+// Split a version into its epoch and the rest of the version.
+fn epoch(version: &str) -> (u64, &str) {
+    match version.find(':') {
+        Some(i) => match version[..i].parse::<u64>() {
+            Ok(epoch) => (epoch, &version[i + 1..]),
+            Err(_) => (0, version),
+        },
+        None => (0, version),
+    }
+}
+
+// Split a version into runs of digits and letters. Each `~` is kept
+// as a part of its own.
+fn parts(version: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+
+    for c in version.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                parts.push(current);
+                current = String::new();
+            }
+            if c == '~' {
+                parts.push("~".into());
+            }
+            continue;
+        }
+
+        if let Some(last) = current.chars().last() {
+            if last.is_digit(10) != c.is_digit(10) {
+                parts.push(current);
+                current = String::new();
+            }
+        }
+        current.push(c);
+    }
+
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare("1.9", "1.9rc1"), Ordering::Less);
+        assert_eq!(compare("1.10.3-1", "1.10.3-1"), Ordering::Equal);
+        assert_eq!(compare("2.0", "10.0"), Ordering::Less);
+        assert_eq!(compare("1:1.18.0", "1.10"), Ordering::Greater);
+        assert_eq!(compare("1:1.0", "2:0.1"), Ordering::Less);
+        assert_eq!(compare("1.0~rc1", "1.0"), Ordering::Less);
+        assert_eq!(compare("1.0~rc1", "1.0~rc2"), Ordering::Less);
+        assert_eq!(compare("1.0~~", "1.0~"), Ordering::Less);
+        assert_eq!(compare("1.0", "1.0~rc1"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches(">= 1.10, < 1.12", "1.11.2").unwrap());
+        assert!(!matches(">= 1.10, < 1.12", "1.12.0").unwrap());
+        assert!(matches("1.10.*", "1.10.3-1").unwrap());
+        assert!(!matches("1.10.*", "1.100").unwrap());
+        assert!(matches("1.10.*", "1:1.10.3-1").unwrap());
+        assert!(matches("= 1:1.10.*", "1:1.10.3-1").unwrap());
+        assert!(!matches("2:1.10.*", "1:1.10.3-1").unwrap());
+        assert!(matches(">= 1.*", "1.2").is_err());
+        assert!(matches("1.10.3", "1.10.3").unwrap());
+        assert!(matches("<>1", "1").is_err());
+    }
+
+    #[test]
+    fn test_best_match() {
+        let versions = vec!["1.9.1".to_string(), "1.11.0".into(), "1.10.3".into(), "1.12.1".into()];
+        assert_eq!(best_match("< 1.12", &versions).unwrap(), Some(&"1.11.0".to_string()));
+        assert_eq!(best_match("> 2", &versions).unwrap(), None);
+        assert!(is_range("< 1.12"));
+        assert!(!is_range("1.12.1"));
+    }
+}
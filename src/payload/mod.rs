@@ -20,8 +20,7 @@
 //!
 //! ```no_run
 //! # use inapi::{Host, Payload};
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(Some(\"hosts/myhost.json\")).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let payload = Payload::new("nginx::install").unwrap(); // format is "payload::executable"
 //! payload.run(&mut host, None).unwrap();
 //! ```
@@ -32,6 +31,7 @@ pub mod ffi;
 use czmq::{ZMsg, ZPoller, ZSock, SocketType, ZSys};
 use error::{Error, Result};
 use host::{Host,HostSendRecv};
+use host::transport::zmq;
 use project::Language;
 use self::config::Config;
 use serde_json;
@@ -151,8 +151,7 @@ impl Payload {
     ///
     /// ```no_run
     /// # use inapi::{Host, Payload};
-    #[cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(Some(\"hosts/myhost.json\")).unwrap();")]
-    #[cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+    /// # let mut host = Host::connect("hosts/myhost.json").unwrap();
     /// let payload = Payload::new("iptables::configure").unwrap();
     /// payload.run(&mut host, Some(vec![
     ///     "add_rule",
@@ -297,17 +296,17 @@ impl Payload {
             if let Some(mut s) = sock {
                 if s == api_pipe {
                     let req = try!(ZMsg::recv(&mut s));
-                    try!(host.send(req));
+                    try!(host.send(zmq::from_zmsg(req)));
 
                     let reply = try!(host.recv_raw());
-                    try!(reply.send(&mut s));
+                    try!(try!(zmq::to_zmsg(reply)).send(&mut s));
                 }
                 else if s == file_pipe {
                     let req = try!(ZMsg::recv(&mut s));
-                    try!(host.send_file(req));
+                    try!(host.send_file(zmq::from_zmsg(req)));
 
                     let reply = try!(host.recv_file_raw());
-                    try!(reply.send(&mut s));
+                    try!(try!(zmq::to_zmsg(reply)).send(&mut s));
                 }
                 else if s == parent {
                     break;
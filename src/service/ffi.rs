@@ -126,7 +126,7 @@ pub extern "C" fn service_action(service_ptr: *mut Service, host_ptr: *const Hos
     let mut host = Leaky::new(trynull!(readptr!(host_ptr, "Host pointer")));
     let action = trynull!(ptrtostr!(action_ptr, "action string"));
 
-    match trynull!(service.action(&mut host, action)) {
+    match trynull!(service.action(&mut host, action)).result {
         Some(result) => {
             let ffi_r = trynull!(catch_unwind(|| result.into()));
             Box::into_raw(Box::new(ffi_r))
@@ -144,19 +144,14 @@ pub extern "C" fn service_free(service_ptr: *mut Service) -> uint8_t {
 #[cfg(test)]
 mod tests {
     use ffi_helpers::Ffi__Array;
-    #[cfg(feature = "remote-run")]
     use Host;
-    #[cfg(feature = "remote-run")]
     use czmq::{ZMsg, ZSys};
-    #[cfg(feature = "remote-run")]
     use host::ffi::host_close;
     use service::{ServiceRunnable, ServiceRunnableOwned};
     use std::collections::HashMap;
-    #[cfg(feature = "remote-run")]
     use std::ffi::CStr;
     use std::ffi::CString;
     use std::{ptr, str};
-    #[cfg(feature = "remote-run")]
     use std::thread;
     use super::*;
 
@@ -264,7 +259,6 @@ mod tests {
         assert!(service.mapped_actions.is_none());
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_service_action() {
         ZSys::init();
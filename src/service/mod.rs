@@ -15,18 +15,14 @@
 //!
 //! ```no_run
 //! # use inapi::Host;
-#![cfg_attr(feature = "local-run", doc = "let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! ```
 //!
 //! Create a new Service to manage your daemon:
 //!
 //! ```no_run
 //! # use inapi::{Host, Service, ServiceRunnable};
-#![cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let service = Service::new_service(ServiceRunnable::Service("nginx"), None);
 //! ```
 //!
@@ -35,24 +31,20 @@
 //!
 //! ```no_run
 //! # use inapi::{Host, Service, ServiceRunnable};
-#![cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let service = Service::new_service(ServiceRunnable::Command("/usr/bin/apachectl"), None);
 //! ```
 //!
-//! Now you can run an action against the Service. This will
-//! optionally return a CommandResult if the action was run, or None
-//! if the service was already in the desired state.
+//! Now you can run an action against the Service. This will return
+//! an Outcome, which holds the CommandResult if the action was run,
+//! or is unchanged if the service was already in the desired state.
 //!
 //! ```no_run
 //! # use inapi::{Host, Service, ServiceRunnable};
-#![cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! # let service = Service::new_service(ServiceRunnable::Service(""), None);
-//! let result = service.action(&mut host, "start").unwrap();
-//! if let Some(r) = result {
+//! let outcome = service.action(&mut host, "start").unwrap();
+//! if let Some(r) = outcome.result {
 //!     assert_eq!(r.exit_code, 0);
 //! }
 //! ```
@@ -81,9 +73,7 @@
 //! ```no_run
 //! # use inapi::{Host, Service, ServiceRunnable};
 //! # use std::collections::HashMap;
-#![cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let mut map = HashMap::new();
 //! map.insert("start", ServiceRunnable::Command("/usr/local/bin/svc_start"));
 //! map.insert("stop", ServiceRunnable::Command("/usr/local/bin/svc_stop"));
@@ -99,9 +89,7 @@
 //! ```no_run
 //! # use inapi::{Host, Service, ServiceRunnable};
 //! # use std::collections::HashMap;
-#![cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let mut map = HashMap::new();
 //! map.insert("_", ServiceRunnable::Service("my_svc")); // <-- Note that "_" (underscore) is the default key used by the Service map
 //! map.insert("status", ServiceRunnable::Command("/usr/bin/my_svc_status"));
@@ -115,9 +103,7 @@
 //! ```no_run
 //! # use inapi::{Host, Service, ServiceRunnable};
 //! # use std::collections::HashMap;
-#![cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let mut map = HashMap::new();
 //! map.insert("start", ServiceRunnable::Command("/usr/bin/start_svc"));
 //! map.insert("kill", ServiceRunnable::Command("killall my_svc"));
@@ -140,9 +126,7 @@
 //! ```no_run
 //! # use inapi::{Host, Service, ServiceRunnable};
 //! # use std::collections::HashMap;
-#![cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let mut map = HashMap::new();
 //! map.insert("start", "-s"); // <-- Map action "start" to "-s"
 //! map.insert("stop", "-t");
@@ -156,9 +140,7 @@
 //! ```no_run
 //! # use inapi::{Host, Service, ServiceRunnable};
 //! # use std::collections::HashMap;
-#![cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-#![cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-#![cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
 //! let mut map = HashMap::new();
 //! map.insert("start", "-c /usr/local/etc/my_svc.conf");
 //! let service = Service::new_service(ServiceRunnable::Command("/usr/local/bin/my_svc"), Some(map));
@@ -168,9 +150,10 @@
 
 pub mod ffi;
 
-use command::{CommandResult, CommandTarget};
+use command::{Command, CommandResult, CommandTarget};
 use error::{Error, Result};
 use host::Host;
+use outcome::Outcome;
 use std::collections::HashMap;
 use std::convert::Into;
 use target::Target;
@@ -267,13 +250,11 @@ impl Service {
     ///
     /// ```no_run
     /// # use inapi::{Host, Service, ServiceRunnable};
-    #[cfg_attr(feature = "local-run", doc = "# let path: Option<String> = None;")]
-    #[cfg_attr(feature = "local-run", doc = "# let mut host = Host::local(path).unwrap();")]
-    #[cfg_attr(feature = "remote-run", doc = "# let mut host = Host::connect(\"hosts/myhost.json\").unwrap();")]
+    /// # let mut host = Host::connect("hosts/myhost.json").unwrap();
     /// let service = Service::new_service(ServiceRunnable::Command("/usr/bin/nginx"), None);
     /// service.action(&mut host, "start").unwrap();
     /// ```
-    pub fn action(&self, host: &mut Host, action: &str) -> Result<Option<CommandResult>> {
+    pub fn action(&self, host: &mut Host, action: &str) -> Result<Outcome> {
         let mut action = action;
 
         // Exchange this action with a mapped action if possible
@@ -292,14 +273,45 @@ impl Service {
         }
     }
 
-    fn run(&self, host: &mut Host, action: &str, runnable: &ServiceRunnableOwned, default: bool) -> Result<Option<CommandResult>> {
-        match *runnable {
+    fn run(&self, host: &mut Host, action: &str, runnable: &ServiceRunnableOwned, default: bool) -> Result<Outcome> {
+        let outcome = match *runnable {
+            ServiceRunnableOwned::Service(ref name) => Outcome::changed(&format!("service:{}", name), action),
+            ServiceRunnableOwned::Command(ref cmd) => Outcome::changed(&format!("command:{}", cmd), action),
+        };
+
+        // Services report their status, so check mode can tell whether
+        // they need starting or stopping. Other actions and commands
+        // are reported as changes.
+        if host.check_mode() {
+            if let ServiceRunnableOwned::Service(ref name) = *runnable {
+                if action == "start" || action == "stop" {
+                    let running = match Target::service_action(host, name, "status") {
+                        Ok(status) => status.map_or(false, |r| r.exit_code == 0),
+                        Err(e) => return host.record(Err(e), outcome),
+                    };
+
+                    if running == (action == "start") {
+                        return host.record(Ok(()), Outcome::unchanged(&outcome.resource, action));
+                    }
+                }
+            }
+
+            return host.record(Ok(()), outcome);
+        }
+
+        let result = match *runnable {
             ServiceRunnableOwned::Service(ref name) => Target::service_action(host, name, action),
             ServiceRunnableOwned::Command(ref cmd) => if default {
-                Ok(Some(try!(Target::exec(host, &format!("{} {}", cmd, action)))))
+                Target::exec(host, &Command::new(&format!("{} {}", cmd, action))).map(|r| Some(r))
             } else {
-                Ok(Some(try!(Target::exec(host, cmd))))
+                Target::exec(host, &Command::new(cmd)).map(|r| Some(r))
             },
+        };
+
+        match result {
+            Ok(Some(r)) => host.record(Ok(()), outcome.command_result(r)),
+            Ok(None) => host.record(Ok(()), Outcome::unchanged(&outcome.resource, action)),
+            Err(e) => host.record(Err(e), outcome),
         }
     }
 }
@@ -310,24 +322,18 @@ pub trait ServiceTarget {
 
 #[cfg(test)]
 mod tests {
-    #[cfg(feature = "remote-run")]
     use Host;
-    #[cfg(feature = "remote-run")]
     use czmq::{ZMsg, ZSys};
-    #[cfg(feature = "remote-run")]
+    use host::transport::MemoryTransport;
     use super::*;
-    #[cfg(feature = "remote-run")]
     use std::collections::HashMap;
-    #[cfg(feature = "remote-run")]
     use std::thread;
 
     // XXX This requires mocking the shell or Command struct
-    // #[cfg(feature = "local-run")]
     // #[test]
     // fn test_action() {
     // }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_action_default() {
         ZSys::init();
@@ -361,18 +367,18 @@ mod tests {
 
         let service = Service::new_service(ServiceRunnable::Service("nginx"), None);
 
-        let result = service.action(&mut host, "start").unwrap().unwrap();
+        let result = service.action(&mut host, "start").unwrap().result.unwrap();
         assert_eq!(result.exit_code, 0);
         assert_eq!(result.stdout, "Service started...");
         assert_eq!(result.stderr, "");
 
-        let result = service.action(&mut host, "start").unwrap();
-        assert!(result.is_none());
+        let outcome = service.action(&mut host, "start").unwrap();
+        assert!(outcome.result.is_none());
+        assert!(!outcome.is_changed());
 
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_action_map() {
         ZSys::init();
@@ -398,7 +404,7 @@ mod tests {
         let mut map = HashMap::new();
         map.insert("start", ServiceRunnable::Service("nginx"));
         let service = Service::new_map(map, None);
-        let result = service.action(&mut host, "start").unwrap().unwrap();
+        let result = service.action(&mut host, "start").unwrap().result.unwrap();
 
         assert_eq!(result.exit_code, 0);
         assert_eq!(result.stdout, "Service started...");
@@ -407,7 +413,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_action_mapped() {
         ZSys::init();
@@ -433,7 +438,7 @@ mod tests {
         let mut map = HashMap::new();
         map.insert("start", "load");
         let service = Service::new_service(ServiceRunnable::Service("nginx"), Some(map));
-        let result = service.action(&mut host, "start").unwrap().unwrap();
+        let result = service.action(&mut host, "start").unwrap().result.unwrap();
 
         assert_eq!(result.exit_code, 0);
         assert_eq!(result.stdout, "Service started...");
@@ -442,7 +447,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_action_error() {
         let mut host = Host::test_new(None, None, None, None);
@@ -453,7 +457,6 @@ mod tests {
         assert!(service.action(&mut host, "nonexistent").is_err());
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_action_command() {
         ZSys::init();
@@ -478,7 +481,7 @@ mod tests {
         let mut map = HashMap::new();
         map.insert("start", ServiceRunnable::Command("/usr/local/bin/nginx"));
         let service = Service::new_map(map, None);
-        let result = service.action(&mut host, "start").unwrap().unwrap();
+        let result = service.action(&mut host, "start").unwrap().result.unwrap();
 
         assert_eq!(result.exit_code, 0);
         assert_eq!(result.stdout, "Service started...");
@@ -487,7 +490,6 @@ mod tests {
         agent_mock.join().unwrap();
     }
 
-    #[cfg(feature = "remote-run")]
     #[test]
     fn test_action_command_mapped() {
         ZSys::init();
@@ -512,7 +514,7 @@ mod tests {
         let mut map = HashMap::new();
         map.insert("start", "-s");
         let service = Service::new_service(ServiceRunnable::Command("/usr/local/bin/nginx"), Some(map));
-        let result = service.action(&mut host, "start").unwrap().unwrap();
+        let result = service.action(&mut host, "start").unwrap().result.unwrap();
 
         assert_eq!(result.exit_code, 0);
         assert_eq!(result.stdout, "Service started...");
@@ -520,4 +522,29 @@ mod tests {
 
         agent_mock.join().unwrap();
     }
+
+    #[test]
+    fn test_action_check_mode() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "3", "", ""]);
+        transport.reply(&["Ok", "0", "", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        host.set_check_mode(true);
+
+        let service = Service::new_service(ServiceRunnable::Service("nginx"), None);
+        let outcome = service.action(&mut host, "start").unwrap();
+        assert!(outcome.is_changed());
+        assert!(outcome.result.is_none());
+        assert_eq!(host.report().outcomes[0].resource, "service:nginx");
+
+        // Only the status is queried, and a running service is left be
+        assert!(!service.action(&mut host, "start").unwrap().is_changed());
+        assert_eq!(transport.requests(), vec![vec!["service::action", "nginx", "status"]; 2]);
+
+        // Commands cannot be queried
+        let service = Service::new_service(ServiceRunnable::Command("/usr/bin/nginx"), None);
+        assert!(service.action(&mut host, "stop").unwrap().is_changed());
+        assert_eq!(transport.requests().len(), 2);
+    }
 }
@@ -0,0 +1,676 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use command::{Command, CommandResult};
+use directory::EntryType;
+use error::{Error, Result};
+use file::{self, FileEdit, FileMetadata};
+use group::GroupInfo;
+use host::Host;
+use host::telemetry::{FsMount, Netif, NetifIPv4, NetifIPv6, NetifStatus};
+use libc;
+use package::providers::{ProviderFactory, Providers};
+use regex::Regex;
+use std::{fs, io, process, str, thread};
+use std::ffi::CString;
+use std::io::{Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{self as unix_fs, MetadataExt};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use target::bin_resolver::BinResolver;
+use user::UserOpts;
+use zfilexfer::FileOptions;
+
+pub fn default_provider(host: &mut Host, providers: Vec<Providers>) -> Result<Providers> {
+    for p in providers {
+        let provider = ProviderFactory::create(host, Some(p));
+
+        if provider.is_ok() {
+            return Ok(provider.unwrap().get_providers());
+        }
+    }
+
+    Err(Error::Generic("No package providers are available".to_string()))
+}
+
+pub fn command_exec(cmd: &str) -> Result<CommandResult> {
+    command_exec_with(&Command::new(cmd))
+}
+
+pub fn command_exec_with(cmd: &Command) -> Result<CommandResult> {
+    let mut command = process::Command::new(&try!(BinResolver::resolve("sh")));
+    command.arg("-c").arg(&cmd.cmd);
+
+    for (key, value) in &cmd.env {
+        command.env(key, value);
+    }
+    if let Some(ref cwd) = cmd.cwd {
+        command.current_dir(cwd);
+    }
+    if let Some(ref user) = cmd.user {
+        let (uid, gid) = try!(user_ids(user));
+        command.uid(uid).gid(gid);
+    }
+    if cmd.timeout.is_some() {
+        // Run the command in its own process group, so that any
+        // processes it starts are killed along with it.
+        // Only async-signal-safe calls may be made between fork and
+        // exec.
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setpgid(0, 0) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    let mut child = try!(command.stdin(if cmd.stdin.is_some() { process::Stdio::piped() } else { process::Stdio::null() })
+                                .stdout(process::Stdio::piped())
+                                .stderr(process::Stdio::piped())
+                                .spawn());
+
+    // Write stdin from another thread, as the command may not read
+    // all of its input before filling its stdout pipe.
+    if let Some(ref input) = cmd.stdin {
+        let mut pipe = child.stdin.take().unwrap();
+        let input = input.clone();
+        thread::spawn(move || pipe.write_all(input.as_bytes()));
+    }
+
+    let done = Arc::new(AtomicBool::new(false));
+    let watchdog = cmd.timeout.map(|secs| {
+        let done = done.clone();
+        let pgid = child.id() as libc::pid_t;
+        thread::spawn(move || kill_after(pgid, secs, &done))
+    });
+
+    let output = child.wait_with_output();
+    done.store(true, Ordering::SeqCst);
+    let timed_out = match watchdog {
+        Some(handle) => handle.join().unwrap_or(false),
+        None => false,
+    };
+    let output = try!(output);
+
+    Ok(CommandResult {
+        exit_code: if timed_out { 124 } else { output.status.code().unwrap_or(-1) },
+        stdout: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    })
+}
+
+// Wait `secs` seconds for `done`, then send SIGTERM to the process
+// group, followed by SIGKILL if it is still running 5 seconds later.
+// Returns whether the process group was killed.
+fn kill_after(pgid: libc::pid_t, secs: u32, done: &AtomicBool) -> bool {
+    let wait = |duration: Duration| {
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            if done.load(Ordering::SeqCst) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        done.load(Ordering::SeqCst)
+    };
+
+    if wait(Duration::from_secs(secs as u64)) {
+        return false;
+    }
+    unsafe { libc::kill(-pgid, libc::SIGTERM); }
+
+    if !wait(Duration::from_secs(5)) {
+        unsafe { libc::kill(-pgid, libc::SIGKILL); }
+    }
+    true
+}
+
+// Look up the UID and primary GID of a user.
+fn user_ids(name: &str) -> Result<(u32, u32)> {
+    let c_name = try!(CString::new(name));
+    let passwd = unsafe { libc::getpwnam(c_name.as_ptr()) };
+    if passwd.is_null() {
+        return Err(Error::Generic(format!("User {} does not exist", name)));
+    }
+
+    unsafe { Ok(((*passwd).pw_uid, (*passwd).pw_gid)) }
+}
+
+pub fn directory_is_directory<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let meta = fs::metadata(path);
+    Ok(meta.is_err() || meta.unwrap().is_dir())
+}
+
+pub fn directory_create<P: AsRef<Path>>(path: P, recursive: bool) -> Result<()> {
+    if recursive {
+        try!(fs::create_dir_all(path));
+    } else {
+        try!(fs::create_dir(path));
+    }
+
+    Ok(())
+}
+
+pub fn directory_delete<P: AsRef<Path>>(path: P, recursive: bool) -> Result<()> {
+    if recursive {
+        try!(fs::remove_dir_all(path));
+    } else {
+        try!(fs::remove_dir(path));
+    }
+
+    Ok(())
+}
+
+pub fn file_is_file<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let meta = fs::metadata(path);
+    Ok(meta.is_err() || meta.unwrap().is_file())
+}
+
+pub fn file_exists<P: AsRef<Path>>(path: P) -> Result<bool> {
+    Ok(fs::metadata(path).is_ok())
+}
+
+pub fn file_delete<P: AsRef<Path>>(path: P) -> Result<()> {
+    try!(fs::remove_file(path));
+    Ok(())
+}
+
+pub fn file_mv<P: AsRef<Path>>(path: P, new_path: P) -> Result<()> {
+    Ok(try!(fs::rename(path, new_path)))
+}
+
+pub fn file_copy<P: AsRef<Path>>(path: P, new_path: P) -> Result<()> {
+    try!(fs::copy(path, new_path));
+    Ok(())
+}
+
+pub fn file_set_owner<P: AsRef<Path>>(path: P, user: &str, group: &str) -> Result<()> {
+    let user_group = format!("{}:{}", user, group);
+    let args: Vec<&str> = vec![&user_group, path.as_ref().to_str().unwrap()];
+    let output = process::Command::new(&try!(BinResolver::resolve("chown"))).args(&args).output().unwrap();
+
+    if !output.status.success() {
+        return Err(Error::Generic(format!("Could not chown file with error: {}", str::from_utf8(&output.stderr).unwrap())));
+    }
+
+    Ok(())
+}
+
+pub fn file_stat<'a, P: AsRef<Path>>(path: P, args: Vec<&'a str>) -> Result<String> {
+    let mut args = args;
+    args.push(path.as_ref().to_str().unwrap());
+    let output = process::Command::new(&try!(BinResolver::resolve("stat"))).args(&args).output().unwrap();
+
+    if !output.status.success() {
+        return Err(Error::Generic(format!("Could not stat file with error: {}", str::from_utf8(&output.stderr).unwrap())));
+    }
+
+    Ok(try!(str::from_utf8(&output.stdout)).trim().to_string())
+}
+
+pub fn file_set_mode<P: AsRef<Path>>(path: P, mode: u16) -> Result<()> {
+    let mode_s: &str = &mode.to_string();
+    let output = process::Command::new(&try!(BinResolver::resolve("chmod"))).args(&vec![mode_s, path.as_ref().to_str().unwrap()]).output().unwrap();
+
+    if !output.status.success() {
+        return Err(Error::Generic(format!("Could not chmod file with error: {}", str::from_utf8(&output.stderr).unwrap())));
+    }
+
+    Ok(())
+}
+
+pub fn file_hash<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    match fs::File::open(path) {
+        Ok(mut fh) => Ok(Some(try!(file::checksum(&mut fh)))),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn file_upload_file<P: AsRef<Path>>(path: P, mut file: fs::File, options: Option<&[FileOptions]>) -> Result<()> {
+    if let Some(opts) = options {
+        for opt in opts {
+            if let FileOptions::BackupExisting(ref suffix) = *opt {
+                if try!(file_exists(path.as_ref())) {
+                    let mut backup = path.as_ref().as_os_str().to_owned();
+                    backup.push(suffix);
+                    try!(fs::rename(path.as_ref(), backup));
+                }
+            }
+        }
+    }
+
+    let mut fh = try!(fs::File::create(path));
+    try!(io::copy(&mut file, &mut fh));
+    Ok(())
+}
+
+pub fn file_download<P: AsRef<Path>>(path: P, writer: &mut Write) -> Result<()> {
+    let mut fh = try!(fs::File::open(path));
+    try!(io::copy(&mut fh, writer));
+    Ok(())
+}
+
+pub fn file_edit<P: AsRef<Path>>(path: P, edit: &FileEdit) -> Result<bool> {
+    let mut content = String::new();
+    match fs::File::open(path.as_ref()) {
+        Ok(mut fh) => { try!(fh.read_to_string(&mut content)); },
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => return Err(e.into()),
+    }
+
+    match try!(file::edit::apply(&content, edit)) {
+        Some(new_content) => {
+            // Write the edit beside the file and rename it into place,
+            // so that the file is never left partially written. Edit
+            // the target of a symlink rather than replacing the link.
+            let path = match fs::canonicalize(path.as_ref()) {
+                Ok(p) => p,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => path.as_ref().to_owned(),
+                Err(e) => return Err(e.into()),
+            };
+            let tmp_path = tmp_path(&path);
+            let result = write_tmp(&tmp_path, &path, &new_content);
+            if result.is_err() {
+                let _ = fs::remove_file(&tmp_path);
+            }
+            try!(result);
+            Ok(true)
+        },
+        None => Ok(false),
+    }
+}
+
+pub fn file_metadata<P: AsRef<Path>>(path: P) -> Result<FileMetadata> {
+    let meta = try!(fs::symlink_metadata(path.as_ref()));
+    let file_type = meta.file_type();
+    let entry_type = if file_type.is_symlink() {
+        EntryType::Symlink
+    } else if file_type.is_dir() {
+        EntryType::Directory
+    } else if file_type.is_file() {
+        EntryType::File
+    } else {
+        EntryType::Other
+    };
+
+    let checksum = if entry_type == EntryType::File {
+        try!(file_hash(path))
+    } else {
+        None
+    };
+
+    Ok(FileMetadata {
+        file_type: entry_type,
+        size: meta.len(),
+        // Mode is represented in octal digits, e.g. 0o644 => 644
+        mode: try!(format!("{:o}", meta.mode() & 0o7777).parse::<u16>()),
+        uid: meta.uid() as u64,
+        gid: meta.gid() as u64,
+        atime: meta.atime(),
+        mtime: meta.mtime(),
+        ctime: meta.ctime(),
+        inode: meta.ino(),
+        nlink: meta.nlink() as u64,
+        selinux_context: None,
+        checksum: checksum,
+    })
+}
+
+pub fn link_read<P: AsRef<Path>>(path: P) -> Result<Option<PathBuf>> {
+    match fs::symlink_metadata(path.as_ref()) {
+        Ok(ref meta) if meta.file_type().is_symlink() => Ok(Some(try!(fs::read_link(path)))),
+        Ok(_) => Ok(None),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn link_create<P: AsRef<Path>>(path: P, target: P) -> Result<()> {
+    // Create the new link beside the old one and rename it into
+    // place, so that the path never disappears during a retarget.
+    let tmp_path = tmp_path(path.as_ref());
+    try!(unix_fs::symlink(target, &tmp_path));
+    rename_tmp(&tmp_path, path.as_ref())
+}
+
+pub fn link_create_hard<P: AsRef<Path>>(path: P, target: P) -> Result<()> {
+    let tmp_path = tmp_path(path.as_ref());
+    try!(fs::hard_link(target, &tmp_path));
+    rename_tmp(&tmp_path, path.as_ref())
+}
+
+pub fn link_is_hard<P: AsRef<Path>>(path: P, target: P) -> Result<bool> {
+    let path_meta = match fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    let target_meta = try!(fs::metadata(target));
+
+    Ok(path_meta.dev() == target_meta.dev() && path_meta.ino() == target_meta.ino())
+}
+
+pub fn link_delete<P: AsRef<Path>>(path: P) -> Result<()> {
+    try!(fs::remove_file(path));
+    Ok(())
+}
+
+// Write `content` to `tmp_path` with the same mode and owner as
+// `path` (if it exists), then rename it over `path`.
+fn write_tmp(tmp_path: &Path, path: &Path, content: &str) -> Result<()> {
+    let mut fh = try!(fs::File::create(tmp_path));
+    try!(fh.write_all(content.as_bytes()));
+
+    if let Ok(meta) = fs::metadata(path) {
+        try!(fs::set_permissions(tmp_path, meta.permissions()));
+
+        let tmp_meta = try!(fh.metadata());
+        if tmp_meta.uid() != meta.uid() || tmp_meta.gid() != meta.gid() {
+            let c_path = try!(CString::new(tmp_path.as_os_str().as_bytes()));
+            if unsafe { libc::chown(c_path.as_ptr(), meta.uid(), meta.gid()) } < 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+        }
+    }
+
+    try!(fs::rename(tmp_path, path));
+    Ok(())
+}
+
+// Move a temporary file into place, removing it if that fails.
+fn rename_tmp(tmp_path: &Path, path: &Path) -> Result<()> {
+    if let Err(e) = fs::rename(tmp_path, path) {
+        let _ = fs::remove_file(tmp_path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Number of temporary paths handed out, which keeps them unique
+/// across threads
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(format!(".{}.{}.tmp", process::id(), TMP_COUNTER.fetch_add(1, Ordering::SeqCst)));
+    PathBuf::from(tmp_path)
+}
+
+pub fn service_action(name: &str, action: &str) -> Result<Option<CommandResult>> {
+    let service = BinResolver::resolve("service")?;
+    if action == "start" || action == "stop" {
+        let status = command_exec(&format!("{} {} status", service.to_str().unwrap(), name))?;
+        // XXX Non-zero exit code may not necessarily indicate that the
+        // service is stopped?
+        if (status.exit_code == 0 && action == "start") || (status.exit_code != 0 && action == "stop") {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(try!(command_exec(&format!("{} {} {}", service.to_str().unwrap(), name, action)))))
+}
+
+/// Run an account management tool, returning None if it exits with
+/// a non-zero status (e.g. because the account does not exist).
+pub fn account_query(bin: &str, args: &[&str]) -> Result<Option<String>> {
+    let output = try!(process::Command::new(&try!(BinResolver::resolve(bin))).args(args).output());
+
+    if output.status.success() {
+        Ok(Some(try!(str::from_utf8(&output.stdout)).trim().to_string()))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn account_exec(bin: &str, args: &[String]) -> Result<()> {
+    let output = try!(process::Command::new(&try!(BinResolver::resolve(bin))).args(args).output());
+
+    if !output.status.success() {
+        return Err(Error::Generic(format!("Could not run {} with error: {}", bin, str::from_utf8(&output.stderr).unwrap().trim())));
+    }
+
+    Ok(())
+}
+
+/// Build the arguments shared by `useradd`/`usermod` and `pw`.
+pub fn user_args(opts: &UserOpts) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(uid) = opts.uid {
+        args.push("-u".to_string());
+        args.push(uid.to_string());
+    }
+    if let Some(gid) = opts.gid {
+        args.push("-g".to_string());
+        args.push(gid.to_string());
+    }
+    if let Some(ref shell) = opts.shell {
+        args.push("-s".to_string());
+        args.push(shell.clone());
+    }
+    if let Some(ref home) = opts.home {
+        args.push("-d".to_string());
+        args.push(home.clone());
+        args.push("-m".to_string());
+    }
+    if let Some(ref groups) = opts.groups {
+        args.push("-G".to_string());
+        args.push(groups.join(","));
+    }
+    args
+}
+
+/// Parse a group entry in the `name:password:gid:members` format.
+pub fn parse_group_entry(name: &str, entry: &str) -> Result<GroupInfo> {
+    let fields: Vec<&str> = entry.split(':').collect();
+    if fields.len() < 4 {
+        return Err(Error::Generic(format!("Invalid group entry for group {}", name)));
+    }
+
+    Ok(GroupInfo {
+        name: name.to_string(),
+        gid: try!(fields[2].parse::<u64>()),
+        members: fields[3].split(',').filter(|m| !m.is_empty()).map(|m| m.to_string()).collect(),
+    })
+}
+
+/// Get the names of a user's supplementary groups.
+pub fn user_groups(name: &str) -> Result<Vec<String>> {
+    match try!(account_query("id", &["-Gn", name])) {
+        // The first group is the user's primary group
+        Some(groups) => Ok(groups.split_whitespace().skip(1).map(|g| g.to_string()).collect()),
+        None => Ok(Vec::new()),
+    }
+}
+
+pub fn hostname() -> Result<String> {
+    let output = try!(process::Command::new(&try!(BinResolver::resolve("hostname"))).arg("-f").output());
+
+    if output.status.success() == false {
+        return Err(Error::Generic(format!("Could not determine hostname with error: {}", str::from_utf8(&output.stderr).unwrap())));
+    }
+
+    Ok(try!(str::from_utf8(&output.stdout)).trim().to_string())
+}
+
+pub enum FsFieldOrder {
+    Filesystem,
+    Size,
+    Used,
+    Available,
+    Capacity,
+    Mount,
+    Blank,
+}
+
+pub fn fs() -> Result<Vec<FsMount>> {
+    self::parse_fs(vec![
+        self::FsFieldOrder::Filesystem,
+        self::FsFieldOrder::Size,
+        self::FsFieldOrder::Used,
+        self::FsFieldOrder::Available,
+        self::FsFieldOrder::Capacity,
+        self::FsFieldOrder::Mount,
+    ])
+}
+
+pub fn parse_fs(fields: Vec<FsFieldOrder>) -> Result<Vec<FsMount>> {
+    let mount_out = try!(process::Command::new(&try!(BinResolver::resolve("df"))).arg("-Pk").output());
+    let mount = try!(String::from_utf8(mount_out.stdout));
+
+    let mut pattern = "(?m)^".to_string();
+
+    for field in fields {
+        match field {
+            FsFieldOrder::Filesystem => pattern.push_str("(?P<fs>.+?)"),
+            FsFieldOrder::Size => pattern.push_str("(?P<size>[0-9]+)"),
+            FsFieldOrder::Used => pattern.push_str("(?P<used>[0-9]+)"),
+            FsFieldOrder::Available => pattern.push_str("(?P<available>[0-9]+)"),
+            FsFieldOrder::Capacity => pattern.push_str("(?P<capacity>[0-9]{1,3})%"),
+            FsFieldOrder::Mount => pattern.push_str("(?P<mount>/.*)"),
+            FsFieldOrder::Blank => pattern.push_str(r"[^\s]+"),
+        }
+
+        pattern.push_str(r"[\s]*");
+    }
+
+    pattern.push_str("$");
+
+    let regex = Regex::new(&pattern).unwrap();
+    let mut fs = vec!();
+
+    let lines: Vec<&str> = mount.lines().collect();
+    for line in lines {
+        if let Some(cap) = regex.captures(line) {
+            fs.push(FsMount {
+                filesystem: cap.name("fs").unwrap().to_string(),
+                mountpoint: cap.name("mount").unwrap().to_string(),
+                size: try!(cap.name("size").unwrap().parse::<u64>()),
+                used: try!(cap.name("used").unwrap().parse::<u64>()),
+                available: try!(cap.name("available").unwrap().parse::<u64>()),
+                capacity: try!(cap.name("capacity").unwrap().parse::<f32>())/100.0,
+            });
+        }
+    };
+
+    Ok(fs)
+}
+
+pub fn parse_nettools_net(if_pattern: &str, kv_pattern: &str, ipv4_pattern: &str, ipv6_pattern: &str) -> Result<Vec<Netif>> {
+    let ifconfig_out = try!(process::Command::new(&try!(BinResolver::resolve("ifconfig"))).output());
+    let ifconfig = try!(str::from_utf8(&ifconfig_out.stdout));
+
+    // Rust Regex doesn't support negative lookahead, so we are
+    // forced to pass this line by line.
+    // Match interfaces:
+    // (?m:^([a-z]+[0-9]+):((?:(?!^[a-z]+[0-9]+:).)+))
+    // Match options:
+    // (?m:^\s*([a-z0-9]+)(?:\s|:\s|=)(.+))
+    let if_regex = Regex::new(if_pattern).unwrap();
+
+    let mut net = vec!();
+
+    for cap in if_regex.captures_iter(ifconfig) {
+        net.push(try!(parse_nettools_netif(cap.name("if").unwrap(), cap.name("content").unwrap(), kv_pattern, ipv4_pattern, ipv6_pattern)));
+    }
+
+    Ok(net)
+}
+
+fn parse_nettools_netif(iface: &str, content: &str, kv_pattern: &str, ipv4_pattern: &str, ipv6_pattern: &str) -> Result<Netif> {
+    let mut netif = Netif {
+        interface: iface.to_string(),
+        mac: None,
+        inet: None,
+        inet6: None,
+        status: None,
+    };
+
+    let kv_regex = Regex::new(kv_pattern).unwrap();
+    let ipv4_regex = Regex::new(ipv4_pattern).unwrap();
+    let ipv6_regex = Regex::new(ipv6_pattern).unwrap();
+
+    let lines: Vec<&str> = content.lines().collect();
+    for line in lines {
+        if let Some(kv_capture) = kv_regex.captures(line) {
+            let value = kv_capture.name("value").unwrap().trim();
+
+            match kv_capture.name("key").unwrap() {
+                "ether" | "HWaddr" => netif.mac = Some(value.to_string()),
+                "inet" => {
+                    if let Some(ipv4_capture) = ipv4_regex.captures(value) {
+                        netif.inet = Some(NetifIPv4 {
+                            address: ipv4_capture.name("ip").unwrap().to_string(),
+                            netmask: ipv4_capture.name("mask").unwrap().to_string(),
+                        });
+                    }
+                },
+                "inet6" => {
+                    if let Some(ipv6_capture) = ipv6_regex.captures(value) {
+                        netif.inet6 = Some(NetifIPv6 {
+                            address: ipv6_capture.name("ip").unwrap().to_string(),
+                            prefixlen: try!(ipv6_capture.name("prefix").unwrap().parse::<u8>()),
+                            scopeid: if ipv6_capture.name("scope").is_some() {
+                                Some(ipv6_capture.name("scope").unwrap().to_string())
+                            } else {
+                                None
+                            },
+                        });
+                    }
+                },
+                "status" => netif.status = match value {
+                    "active" => Some(NetifStatus::Active),
+                    "inactive" => Some(NetifStatus::Inactive),
+                    _ => unreachable!(),
+                },
+                _ => (),
+            }
+        }
+    }
+
+    Ok(netif)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_hostname() {
+        // XXX Not a proper test. Requires mocking.
+        assert!(hostname().is_ok());
+    }
+
+    #[test]
+    fn test_link_create_failed() {
+        let tempdir = TempDir::new("test_link_create_failed").unwrap();
+        let path = tempdir.path().join("dir");
+        let target = tempdir.path().join("file");
+        fs::create_dir(&path).unwrap();
+        fs::File::create(&target).unwrap();
+
+        // A link cannot replace a directory, and no temporary link is
+        // left behind.
+        assert!(link_create(path.as_path(), target.as_path()).is_err());
+        assert!(link_create_hard(path.as_path(), target.as_path()).is_err());
+        assert_eq!(fs::read_dir(tempdir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_tmp_path() {
+        let path = Path::new("/tmp/file");
+        assert!(tmp_path(path) != tmp_path(path));
+    }
+}
@@ -0,0 +1,490 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Primitives run as another user. Each operation is performed by a
+//! shell command wrapped with sudo, doas or su, which works the same
+//! way for local and remote Hosts. Requests that have no primitive
+//! here are answered by the shell transports' commands, run as the
+//! target user.
+//!
+//! A password prompt can only be answered on a local Host, as the
+//! remote transports do not allocate a pty. Remote Hosts must allow
+//! the connecting user to switch user without a password.
+
+use command::{quote, Command, CommandResult, CommandTarget};
+use error::{Error, Result};
+use file::{FileEdit, FileTarget};
+use host::{Become, BecomeMethod, Host};
+use host::transport::shell::{self, Shell};
+use libc;
+use std::{fs, io, process, ptr, thread};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use super::{LocalTarget, RemoteTarget};
+use super::bin_resolver::BinResolver;
+use tempfile::tempfile;
+use user::UserOpts;
+use zfilexfer::FileOptions;
+
+pub fn exec(host: &mut Host, escalation: &Become, cmd: &Command) -> Result<CommandResult> {
+    // A user set on the Command replaces the Host's target user,
+    // while the other options are applied inside the wrapped command.
+    let mut inner = cmd.clone();
+    let escalation = match inner.user.take() {
+        Some(user) => escalation.clone().user(&user),
+        None => escalation.clone(),
+    };
+    let cmd = Command::new(&escalation.wrap(&inner.script()));
+
+    match escalation.password {
+        // su has no option to fail rather than prompt
+        None if escalation.method == BecomeMethod::Su => Err(Error::Generic("su cannot switch user without a password. Set a password, or use sudo or doas instead.".into())),
+        None if host.is_local() => LocalTarget::exec(host, &cmd),
+        None => RemoteTarget::exec(host, &cmd),
+        Some(ref password) if host.is_local() => exec_pty(&cmd.cmd, password),
+        Some(_) => Err(Error::Generic("Become passwords are only supported for local hosts, as remote transports have no pty to answer the prompt on. Allow the remote user to switch user without a password instead.".into())),
+    }
+}
+
+pub fn directory_checksums<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P) -> Result<BTreeMap<String, Option<String>>> {
+    let frames = try!(request(host, escalation, &["directory::checksums", try!(path_str(path.as_ref()))]));
+
+    // Frames are pairs of relative path and checksum, where
+    // directories have an empty checksum.
+    if frames.len() % 2 != 0 {
+        return Err(Error::HostResponse);
+    }
+    Ok(frames.chunks(2).map(|pair| {
+        let hash = if pair[1].is_empty() { None } else { Some(pair[1].clone()) };
+        (pair[0].clone(), hash)
+    }).collect())
+}
+
+pub fn directory_create<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, recursive: bool) -> Result<()> {
+    run(host, escalation, &format!("mkdir{} {}", if recursive { " -p" } else { "" }, quote_path(path.as_ref())))
+}
+
+pub fn directory_delete<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, recursive: bool) -> Result<()> {
+    run(host, escalation, &format!("{} {}", if recursive { "rm -rf" } else { "rmdir" }, quote_path(path.as_ref())))
+}
+
+pub fn delete<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P) -> Result<()> {
+    run(host, escalation, &format!("rm {}", quote_path(path.as_ref())))
+}
+
+pub fn mv<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, new_path: P) -> Result<()> {
+    run(host, escalation, &format!("mv {} {}", quote_path(path.as_ref()), quote_path(new_path.as_ref())))
+}
+
+pub fn copy<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, new_path: P) -> Result<()> {
+    run(host, escalation, &format!("cp -p {} {}", quote_path(path.as_ref()), quote_path(new_path.as_ref())))
+}
+
+pub fn set_owner<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, user: &str, group: &str) -> Result<()> {
+    run(host, escalation, &format!("chown {}:{} {}", quote(user), quote(group), quote_path(path.as_ref())))
+}
+
+pub fn set_mode<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, mode: u16) -> Result<()> {
+    run(host, escalation, &format!("chmod {} {}", mode, quote_path(path.as_ref())))
+}
+
+pub fn file_hash<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P) -> Result<Option<String>> {
+    let frames = try!(request(host, escalation, &["file::hash", try!(path_str(path.as_ref()))]));
+    Ok(frames.into_iter().next())
+}
+
+pub fn file_edit<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, edit: &FileEdit) -> Result<bool> {
+    shell::edit_file(&mut EscalatedShell { host: host, escalation: escalation }, try!(path_str(path.as_ref())), edit)
+}
+
+pub fn download<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, writer: &mut Write) -> Result<()> {
+    match try!(EscalatedShell { host: host, escalation: escalation }.read(path.as_ref())) {
+        Some(contents) => Ok(try!(writer.write_all(&contents))),
+        None => Err(Error::Generic(format!("{} does not exist", path.as_ref().display()))),
+    }
+}
+
+pub fn link_read<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P) -> Result<Option<PathBuf>> {
+    let frames = try!(request(host, escalation, &["link::read", try!(path_str(path.as_ref()))]));
+    Ok(frames.into_iter().next().map(PathBuf::from))
+}
+
+pub fn link_create<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, target: P) -> Result<()> {
+    run(host, escalation, &format!("ln -sfn {} {}", quote_path(target.as_ref()), quote_path(path.as_ref())))
+}
+
+pub fn link_create_hard<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, target: P) -> Result<()> {
+    run(host, escalation, &format!("ln -f {} {}", quote_path(target.as_ref()), quote_path(path.as_ref())))
+}
+
+pub fn link_is_hard<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, target: P) -> Result<bool> {
+    let frames = try!(request(host, escalation, &["link::is_hard", try!(path_str(path.as_ref())), try!(path_str(target.as_ref()))]));
+    Ok(frames.first().map(|f| f == "1").unwrap_or(false))
+}
+
+pub fn group_create(host: &mut Host, escalation: &Become, name: &str, gid: Option<u64>) -> Result<()> {
+    let gid = gid.map(|g| g.to_string()).unwrap_or(String::new());
+    request(host, escalation, &["group::create", name, &gid]).map(|_| ())
+}
+
+pub fn group_modify(host: &mut Host, escalation: &Become, name: &str, gid: u64) -> Result<()> {
+    request(host, escalation, &["group::modify", name, &gid.to_string()]).map(|_| ())
+}
+
+pub fn group_delete(host: &mut Host, escalation: &Become, name: &str) -> Result<()> {
+    request(host, escalation, &["group::delete", name]).map(|_| ())
+}
+
+pub fn service_action(host: &mut Host, escalation: &Become, name: &str, action: &str) -> Result<Option<CommandResult>> {
+    let frames = try!(request(host, escalation, &["service::action", name, action]));
+
+    match frames.len() {
+        0 => Ok(None),
+        3 => Ok(Some(CommandResult {
+            exit_code: try!(frames[0].parse::<i32>()),
+            stdout: frames[1].clone(),
+            stderr: frames[2].clone(),
+        })),
+        _ => Err(Error::HostResponse),
+    }
+}
+
+pub fn user_create(host: &mut Host, escalation: &Become, name: &str, opts: &UserOpts) -> Result<()> {
+    user_request(host, escalation, "user::create", name, opts)
+}
+
+pub fn user_modify(host: &mut Host, escalation: &Become, name: &str, opts: &UserOpts) -> Result<()> {
+    user_request(host, escalation, "user::modify", name, opts)
+}
+
+pub fn user_delete(host: &mut Host, escalation: &Become, name: &str) -> Result<()> {
+    request(host, escalation, &["user::delete", name]).map(|_| ())
+}
+
+/// Upload a file as the connecting user to a private temporary
+/// directory, then copy it into place as the target user. Copying
+/// over an existing file keeps its owner and mode. As only the
+/// connecting user and root can enter the directory, the target user
+/// is usually root.
+pub fn upload<P: AsRef<Path>>(host: &mut Host, escalation: &Become, path: P, file: fs::File, options: Option<&[FileOptions]>) -> Result<()> {
+    let mut temp_opts = Vec::new();
+    if let Some(opts) = options {
+        for opt in opts {
+            if let FileOptions::BackupExisting(ref suffix) = *opt {
+                let mut backup = path.as_ref().as_os_str().to_owned();
+                backup.push(suffix);
+                let path = quote_path(path.as_ref());
+                try!(run(host, escalation, &format!("if [ -e {0} ]; then mv {0} {1}; fi", path, quote_path(Path::new(&backup)))));
+            } else if let FileOptions::ChunkSize(size) = *opt {
+                temp_opts.push(FileOptions::ChunkSize(size));
+            }
+        }
+    }
+
+    let temp_dir = try!(create_temp_dir(host));
+    let temp = format!("{}/contents", temp_dir);
+
+    let uploaded = if host.is_local() {
+        LocalTarget::file_upload_file(host, temp.as_str(), file, Some(&temp_opts))
+    } else {
+        RemoteTarget::file_upload_file(host, temp.as_str(), file, Some(&temp_opts))
+    };
+    let result = uploaded.and_then(|_| run(host, escalation, &format!("cp {} {}", quote(&temp), quote_path(path.as_ref()))));
+
+    try!(remove_temp_dir(host, &temp_dir));
+    result
+}
+
+// Run a command as the target user, turning a non-zero exit code
+// into an error.
+fn run(host: &mut Host, escalation: &Become, cmd: &str) -> Result<()> {
+    let result = try!(exec(host, escalation, &Command::new(cmd)));
+
+    if result.exit_code != 0 {
+        return Err(Error::Generic(format!("Could not run `{}` as {} with error: {}", cmd, escalation.user, result.stderr)));
+    }
+
+    Ok(())
+}
+
+// Answer a request with the shell transports' commands, run as the
+// target user.
+fn request(host: &mut Host, escalation: &Become, frames: &[&str]) -> Result<Vec<String>> {
+    shell::request(&mut EscalatedShell { host: host, escalation: escalation }, frames)
+}
+
+// Send the attributes that are set as key/value frame pairs, like
+// a remote user request.
+fn user_request(host: &mut Host, escalation: &Become, action: &str, name: &str, opts: &UserOpts) -> Result<()> {
+    let mut frames = vec![action.to_string(), name.to_string()];
+    if let Some(uid) = opts.uid {
+        frames.push("uid".into());
+        frames.push(uid.to_string());
+    }
+    if let Some(gid) = opts.gid {
+        frames.push("gid".into());
+        frames.push(gid.to_string());
+    }
+    if let Some(ref shell) = opts.shell {
+        frames.push("shell".into());
+        frames.push(shell.clone());
+    }
+    if let Some(ref home) = opts.home {
+        frames.push("home".into());
+        frames.push(home.clone());
+    }
+    if let Some(ref groups) = opts.groups {
+        frames.push("groups".into());
+        frames.push(groups.join(","));
+    }
+
+    let frames: Vec<&str> = frames.iter().map(|f| f.as_str()).collect();
+    request(host, escalation, &frames).map(|_| ())
+}
+
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str().ok_or(Error::Generic(format!("{} is not valid UTF-8", path.display())))
+}
+
+fn quote_path(path: &Path) -> String {
+    quote(&path.to_string_lossy())
+}
+
+// Run an exec on the Host as the connecting user.
+fn exec_unescalated(host: &mut Host, cmd: &str) -> Result<CommandResult> {
+    if host.is_local() {
+        LocalTarget::exec(host, &Command::new(cmd))
+    } else {
+        RemoteTarget::exec(host, &Command::new(cmd))
+    }
+}
+
+// Create a temporary directory on the Host that only the connecting
+// user (and root) can enter.
+fn create_temp_dir(host: &mut Host) -> Result<String> {
+    let result = try!(exec_unescalated(host, "mktemp -d"));
+    if result.exit_code != 0 {
+        return Err(Error::Generic(format!("Could not create temporary directory: {}", result.stderr)));
+    }
+    Ok(result.stdout)
+}
+
+// The directory belongs to the connecting user, so it does not need
+// escalating to remove.
+fn remove_temp_dir(host: &mut Host, dir: &str) -> Result<()> {
+    let result = try!(exec_unescalated(host, &format!("rm -rf {}", quote(dir))));
+    if result.exit_code != 0 {
+        return Err(Error::Generic(format!("Could not remove temporary directory {}: {}", dir, result.stderr)));
+    }
+    Ok(())
+}
+
+// Answers shell transport requests with commands run as the target
+// user. Files are read and written through a temporary file that the
+// connecting user can access.
+struct EscalatedShell<'a> {
+    host: &'a mut Host,
+    escalation: &'a Become,
+}
+
+impl<'a> Shell for EscalatedShell<'a> {
+    fn exec(&mut self, cmd: &str) -> Result<(i32, String, String)> {
+        let result = try!(exec(self.host, self.escalation, &Command::new(cmd)));
+        Ok((result.exit_code, result.stdout, result.stderr))
+    }
+
+    fn stat(&mut self, path: &Path) -> Result<Option<bool>> {
+        let cmd = format!("if [ -d {0} ]; then echo d; elif [ -e {0} ]; then echo f; fi", quote_path(path));
+        match try!(self.exec(&cmd)) {
+            (0, ref stdout, _) if stdout == "d" => Ok(Some(true)),
+            (0, ref stdout, _) if stdout == "f" => Ok(Some(false)),
+            (0, _, _) => Ok(None),
+            (_, _, stderr) => Err(Error::Generic(stderr)),
+        }
+    }
+
+    fn rename(&mut self, path: &Path, new_path: &Path) -> Result<()> {
+        mv(self.host, self.escalation, path, new_path)
+    }
+
+    fn unlink(&mut self, path: &Path) -> Result<()> {
+        delete(self.host, self.escalation, path)
+    }
+
+    fn read(&mut self, path: &Path) -> Result<Option<Vec<u8>>> {
+        // Only the connecting user can enter the temporary directory,
+        // so its contents need not be private.
+        let temp_dir = try!(create_temp_dir(self.host));
+        let temp_file = format!("{}/contents", temp_dir);
+
+        let cmd = format!("[ -e {0} ] || exit 3; cat {0} > {1} && chmod 644 {1}", quote_path(path), quote(&temp_file));
+        let result = match self.exec(&cmd) {
+            Ok((0, _, _)) => {
+                let mut contents = Vec::new();
+                let downloaded = if self.host.is_local() {
+                    LocalTarget::file_download(self.host, temp_file.as_str(), &mut contents)
+                } else {
+                    RemoteTarget::file_download(self.host, temp_file.as_str(), &mut contents)
+                };
+                downloaded.map(|_| Some(contents))
+            },
+            Ok((3, _, _)) => Ok(None),
+            Ok((_, _, stderr)) => Err(Error::Generic(format!("Could not read {} as {} with error: {}", path.display(), self.escalation.user, stderr))),
+            Err(e) => Err(e),
+        };
+
+        try!(remove_temp_dir(self.host, &temp_dir));
+        result
+    }
+
+    fn write(&mut self, path: &Path, contents: &mut Read) -> Result<()> {
+        let mut file = try!(tempfile());
+        try!(io::copy(contents, &mut file));
+        try!(file.seek(SeekFrom::Start(0)));
+        upload(self.host, self.escalation, path, file, None)
+    }
+}
+
+// Run a command on the local machine with a pty as its controlling
+// terminal, which is where sudo, doas and su read passwords from.
+// Password prompts are answered by a thread that watches the pty.
+fn exec_pty(cmd: &str, password: &str) -> Result<CommandResult> {
+    let mut master_fd = 0;
+    let mut slave_fd = 0;
+    if unsafe { libc::openpty(&mut master_fd, &mut slave_fd, ptr::null_mut(), ptr::null_mut(), ptr::null_mut()) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    let master = unsafe { fs::File::from_raw_fd(master_fd) };
+    let slave = unsafe { fs::File::from_raw_fd(slave_fd) };
+
+    // The command must not inherit the master side, while the slave
+    // side stays open in the command until it exits.
+    unsafe { libc::fcntl(master_fd, libc::F_SETFD, libc::FD_CLOEXEC); }
+
+    let mut command = process::Command::new(&try!(BinResolver::resolve("sh")));
+    command.arg("-c")
+           .arg(cmd)
+           .stdin(process::Stdio::null())
+           .stdout(process::Stdio::piped())
+           .stderr(process::Stdio::piped());
+
+    // Only async-signal-safe calls may be made between fork and exec
+    unsafe {
+        command.pre_exec(move || {
+            if libc::setsid() < 0 || libc::ioctl(slave_fd, libc::TIOCSCTTY as _, 0) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let child = try!(command.spawn());
+    drop(slave);
+
+    let done = Arc::new(AtomicBool::new(false));
+    let responder = {
+        let done = done.clone();
+        let password = password.to_owned();
+        thread::spawn(move || answer_prompts(master, &password, &done))
+    };
+
+    let output = child.wait_with_output();
+    done.store(true, Ordering::SeqCst);
+    let _ = responder.join();
+    let output = try!(output);
+
+    Ok(CommandResult {
+        exit_code: output.status.code().unwrap_or(-1),
+        stdout: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    })
+}
+
+// Write the password to the pty each time a prompt appears. A second
+// prompt means the password was rejected, so the pty is closed rather
+// than leaving the command waiting for another attempt.
+fn answer_prompts(mut master: fs::File, password: &str, done: &AtomicBool) {
+    let mut pollfd = libc::pollfd { fd: master.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+    let mut buf = [0; 1024];
+    let mut seen = String::new();
+    let mut answered = false;
+
+    // Poll rather than block, as the command may leave children
+    // running that keep the pty open after it exits.
+    while !done.load(Ordering::SeqCst) {
+        if unsafe { libc::poll(&mut pollfd, 1, 100) } <= 0 {
+            continue;
+        }
+
+        match master.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => seen.push_str(&String::from_utf8_lossy(&buf[..n])),
+        }
+
+        if seen.to_lowercase().contains("password") {
+            if answered || master.write_all(format!("{}\n", password).as_bytes()).is_err() {
+                break;
+            }
+            answered = true;
+            seen.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use group::GroupTarget;
+    use host::{Become, Host};
+    use host::transport::MemoryTransport;
+    use link::LinkTarget;
+    use std::path::{Path, PathBuf};
+    use super::*;
+    use super::super::Target;
+
+    #[test]
+    fn test_exec_remote_password() {
+        let mut host = Host::test_new(None, None, None, None);
+        assert!(exec(&mut host, &Become::sudo().password("secret"), &Command::new("whoami")).is_err());
+    }
+
+    #[test]
+    fn test_exec_su_without_password() {
+        let transport = MemoryTransport::new();
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        assert!(exec(&mut host, &Become::su(), &Command::new("whoami")).is_err());
+        assert!(transport.requests().is_empty());
+    }
+
+    #[test]
+    fn test_target_escalates() {
+        let transport = MemoryTransport::new();
+        transport.reply(&["Ok", "0", "", ""]);
+        transport.reply(&["Ok", "0", "/tmp/target", ""]);
+        transport.reply(&["Ok", "0", "", ""]);
+
+        let mut host = Host::from_transport("test", Box::new(transport.clone()));
+        host.set_become(Some(Become::sudo()));
+        Target::link_create(&mut host, "/tmp/link", "/tmp/target").unwrap();
+        assert_eq!(Target::link_read(&mut host, "/tmp/link").unwrap(), Some(PathBuf::from("/tmp/target")));
+        Target::group_delete(&mut host, "www").unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests[0], vec!["command::exec".to_string(), Become::sudo().wrap("ln -sfn '/tmp/target' '/tmp/link'")]);
+        assert_eq!(requests[1][1], Become::sudo().wrap("[ -L '/tmp/link' ] || exit 0; readlink '/tmp/link'"));
+        assert_eq!(requests[2][1], Become::sudo().wrap("if command -v groupdel >/dev/null; then groupdel 'www'; else pw groupdel 'www'; fi"));
+    }
+
+    #[test]
+    fn test_quote_path() {
+        assert_eq!(quote_path(Path::new("/tmp/it's")), "'/tmp/it'\\''s'");
+    }
+}
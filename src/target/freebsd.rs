@@ -0,0 +1,420 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use command::{Command, CommandResult, CommandTarget};
+use directory::{self, DirectoryEntry, DirectoryTarget};
+use error::{Error, Result};
+use file::{FileEdit, FileMetadata, FileTarget, FileOwner};
+use group::{GroupInfo, GroupTarget};
+use host::Host;
+use host::telemetry::{Cpu, Os, Telemetry, TelemetryTarget};
+use link::LinkTarget;
+use package::PackageTarget;
+use package::providers::Providers;
+use regex::Regex;
+use serde_json::Value;
+use service::ServiceTarget;
+use std::env;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use super::{default_base as default, LocalTarget, unix_base as unix};
+use user::{UserInfo, UserOpts, UserTarget};
+use zfilexfer::FileOptions;
+
+//
+// Command
+//
+
+impl CommandTarget for LocalTarget {
+    #[allow(unused_variables)]
+    fn exec(host: &mut Host, cmd: &Command) -> Result<CommandResult> {
+        default::command_exec_with(cmd)
+    }
+}
+
+//
+// Directory
+//
+
+impl<P: AsRef<Path>> DirectoryTarget<P> for LocalTarget {
+    #[allow(unused_variables)]
+    fn directory_is_directory(host: &mut Host, path: P) -> Result<bool> {
+        default::directory_is_directory(path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_exists(host: &mut Host, path: P) -> Result<bool> {
+        default::file_exists(path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_create(host: &mut Host, path: P, recursive: bool) -> Result<()> {
+        default::directory_create(path, recursive)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_delete(host: &mut Host, path: P, recursive: bool) -> Result<()> {
+        default::directory_delete(path, recursive)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_mv(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        default::file_mv(path, new_path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_get_owner(host: &mut Host, path: P) -> Result<FileOwner> {
+        unix::file_get_owner(path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()> {
+        default::file_set_owner(path, user, group)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_get_mode(host: &mut Host, path: P) -> Result<u16> {
+        unix::file_get_mode(path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()> {
+        default::file_set_mode(path, mode)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_checksums(host: &mut Host, path: P) -> Result<BTreeMap<String, Option<String>>> {
+        directory::checksums(path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_list(host: &mut Host, path: P, max_depth: Option<u32>) -> Result<Vec<DirectoryEntry>> {
+        directory::list(path, max_depth)
+    }
+}
+
+//
+// File
+//
+
+impl<P: AsRef<Path>> FileTarget<P> for LocalTarget {
+    #[allow(unused_variables)]
+    fn file_is_file(host: &mut Host, path: P) -> Result<bool> {
+        default::file_is_file(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_exists(host: &mut Host, path: P) -> Result<bool> {
+        default::file_exists(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_delete(host: &mut Host, path: P) -> Result<()> {
+        default::file_delete(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_mv(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        default::file_mv(path, new_path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_copy(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        default::file_copy(path, new_path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_get_owner(host: &mut Host, path: P) -> Result<FileOwner> {
+        unix::file_get_owner(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()> {
+        default::file_set_owner(path, user, group)
+    }
+
+    #[allow(unused_variables)]
+    fn file_get_mode(host: &mut Host, path: P) -> Result<u16> {
+        unix::file_get_mode(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()> {
+        default::file_set_mode(path, mode)
+    }
+
+    #[allow(unused_variables)]
+    fn file_hash(host: &mut Host, path: P) -> Result<Option<String>> {
+        default::file_hash(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_upload_file(host: &mut Host, path: P, file: fs::File, options: Option<&[FileOptions]>) -> Result<()> {
+        default::file_upload_file(path, file, options)
+    }
+
+    #[allow(unused_variables)]
+    fn file_download(host: &mut Host, path: P, writer: &mut Write) -> Result<()> {
+        default::file_download(path, writer)
+    }
+
+    #[allow(unused_variables)]
+    fn file_edit(host: &mut Host, path: P, edit: &FileEdit) -> Result<bool> {
+        default::file_edit(path, edit)
+    }
+
+    #[allow(unused_variables)]
+    fn file_metadata(host: &mut Host, path: P) -> Result<FileMetadata> {
+        default::file_metadata(path)
+    }
+}
+
+//
+// Group
+//
+
+impl GroupTarget for LocalTarget {
+    #[allow(unused_variables)]
+    fn group_get(host: &mut Host, name: &str) -> Result<Option<GroupInfo>> {
+        match try!(default::account_query("pw", &["groupshow", name])) {
+            Some(group) => Ok(Some(try!(default::parse_group_entry(name, &group)))),
+            None => Ok(None),
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn group_create(host: &mut Host, name: &str, gid: Option<u64>) -> Result<()> {
+        let mut args = vec!["groupadd".to_string(), name.to_string()];
+        if let Some(gid) = gid {
+            args.push("-g".to_string());
+            args.push(gid.to_string());
+        }
+        default::account_exec("pw", &args)
+    }
+
+    #[allow(unused_variables)]
+    fn group_modify(host: &mut Host, name: &str, gid: u64) -> Result<()> {
+        default::account_exec("pw", &["groupmod".to_string(), name.to_string(), "-g".to_string(), gid.to_string()])
+    }
+
+    #[allow(unused_variables)]
+    fn group_delete(host: &mut Host, name: &str) -> Result<()> {
+        default::account_exec("pw", &["groupdel".to_string(), name.to_string()])
+    }
+}
+
+//
+// Link
+//
+
+impl<P: AsRef<Path>> LinkTarget<P> for LocalTarget {
+    #[allow(unused_variables)]
+    fn link_read(host: &mut Host, path: P) -> Result<Option<PathBuf>> {
+        default::link_read(path)
+    }
+
+    #[allow(unused_variables)]
+    fn link_create(host: &mut Host, path: P, target: P) -> Result<()> {
+        default::link_create(path, target)
+    }
+
+    #[allow(unused_variables)]
+    fn link_create_hard(host: &mut Host, path: P, target: P) -> Result<()> {
+        default::link_create_hard(path, target)
+    }
+
+    #[allow(unused_variables)]
+    fn link_is_hard(host: &mut Host, path: P, target: P) -> Result<bool> {
+        default::link_is_hard(path, target)
+    }
+
+    #[allow(unused_variables)]
+    fn link_delete(host: &mut Host, path: P) -> Result<()> {
+        default::link_delete(path)
+    }
+}
+
+//
+// Package
+//
+
+impl PackageTarget for LocalTarget {
+    fn default_provider(host: &mut Host) -> Result<Providers> {
+        default::default_provider(host, vec![Providers::Pkg, Providers::Ports])
+    }
+}
+
+//
+// Service
+//
+
+impl ServiceTarget for LocalTarget {
+    #[allow(unused_variables)]
+    fn service_action(host: &mut Host, name: &str, action: &str) -> Result<Option<CommandResult>> {
+        let mut rc_conf = try!(OpenOptions::new().read(true).write(true).open("/etc/rc.conf"));
+        let mut rc = String::new();
+        try!(rc_conf.read_to_string(&mut rc));
+
+        let match_daemon = Regex::new(&format!("(?m)^\\s*{}_enable\\s*=\\s*[\"']{{0,1}}(?:YES|yes)[\"']{{0,1}}\n?", name)).unwrap();
+
+        match action {
+            "enable" => {
+                if ! match_daemon.is_match(&rc) {
+                    let newline = if rc.ends_with("\n") { "" } else { "\n" };
+                    try!(rc_conf.write_all(&format!("{}{}_enable=\"YES\"\n", newline, name).into_bytes()));
+                    try!(rc_conf.sync_data());
+
+                    Ok(Some(CommandResult {
+                        exit_code: 0,
+                        stdout: String::new(),
+                        stderr: String::new(),
+                    }))
+                } else {
+                    Ok(None)
+                }
+            },
+            "disable" => {
+                if match_daemon.is_match(&rc) {
+                    let replace = match_daemon.replace(&rc, "").trim().to_string();
+                    try!(rc_conf.seek(SeekFrom::Start(0)));
+                    try!(rc_conf.set_len(replace.len() as u64));
+                    try!(rc_conf.write_all(replace.as_bytes()));
+                    try!(rc_conf.sync_data());
+
+                    Ok(Some(CommandResult {
+                        exit_code: 0,
+                        stdout: String::new(),
+                        stderr: String::new(),
+                    }))
+                } else {
+                    Ok(None)
+                }
+            },
+            "start" | "stop" | "restart" | "status" if ! match_daemon.is_match(&rc) => {
+                default::service_action(name, &format!("one{}", action))
+            },
+            _ => default::service_action(name, action),
+        }
+    }
+}
+
+//
+// Telemetry
+//
+
+impl TelemetryTarget for LocalTarget {
+    #[allow(unused_variables)]
+    fn telemetry_init(host: &mut Host) -> Result<Value> {
+        let cpu_vendor = try!(telemetry_cpu_vendor());
+        let cpu_brand = try!(unix::get_sysctl_item("hw\\.model"));
+        let hostname = try!(default::hostname());
+        let (version_str, version_maj, version_min) = try!(unix::version());
+
+        let telemetry = Telemetry::new(
+            Cpu::new(
+                &cpu_vendor,
+                &cpu_brand,
+                try!(try!(unix::get_sysctl_item("hw\\.ncpu")).parse::<u32>()),
+            ),
+            try!(default::fs()),
+            &hostname,
+            try!(try!(unix::get_sysctl_item("hw\\.physmem")).parse::<u64>()),
+            try!(unix::net()),
+            Os::new(env::consts::ARCH, "unix", "freebsd", &version_str, version_maj, version_min, 0),
+        );
+
+        Ok(telemetry.into_value())
+    }
+}
+
+//
+// User
+//
+
+impl UserTarget for LocalTarget {
+    #[allow(unused_variables)]
+    fn user_get(host: &mut Host, name: &str) -> Result<Option<UserInfo>> {
+        let passwd = match try!(default::account_query("pw", &["usershow", name])) {
+            Some(passwd) => passwd,
+            None => return Ok(None),
+        };
+
+        // name:password:uid:gid:class:change:expire:gecos:home:shell
+        let fields: Vec<&str> = passwd.split(':').collect();
+        if fields.len() < 10 {
+            return Err(Error::Generic(format!("Invalid passwd entry for user {}", name)));
+        }
+
+        Ok(Some(UserInfo {
+            name: name.to_string(),
+            uid: try!(fields[2].parse::<u64>()),
+            gid: try!(fields[3].parse::<u64>()),
+            home: fields[8].to_string(),
+            shell: fields[9].to_string(),
+            groups: try!(default::user_groups(name)),
+        }))
+    }
+
+    #[allow(unused_variables)]
+    fn user_create(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()> {
+        let mut args = vec!["useradd".to_string(), name.to_string()];
+        args.extend(default::user_args(opts));
+        default::account_exec("pw", &args)
+    }
+
+    #[allow(unused_variables)]
+    fn user_modify(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()> {
+        let mut args = vec!["usermod".to_string(), name.to_string()];
+        args.extend(default::user_args(opts));
+        default::account_exec("pw", &args)
+    }
+
+    #[allow(unused_variables)]
+    fn user_delete(host: &mut Host, name: &str) -> Result<()> {
+        default::account_exec("pw", &["userdel".to_string(), name.to_string()])
+    }
+}
+
+fn telemetry_cpu_vendor() -> Result<String> {
+    let mut fh = try!(File::open("/var/run/dmesg.boot"));
+    let mut fc = String::new();
+    fh.read_to_string(&mut fc).unwrap();
+
+    let regex = Regex::new(r#"(?m)^CPU:.+$\n\s+Origin="([A-Za-z]+)""#).unwrap();
+    if let Some(cap) = regex.captures(&fc) {
+        Ok(cap.at(1).unwrap().to_string())
+    } else {
+        Err(Error::Generic("Could not match CPU vendor".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Host;
+    use package::PackageTarget;
+    use target::LocalTarget;
+    use host::telemetry::TelemetryTarget;
+
+    #[test]
+    fn test_package_default_provider() {
+        let path: Option<String> = None;
+        let mut host = Host::local(path).unwrap();
+        let result = LocalTarget::default_provider(&mut host);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_telemetry_init() {
+        let path: Option<String> = None;
+        let mut host = Host::local(path).unwrap();
+        let result = LocalTarget::telemetry_init(&mut host);
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,626 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use command::{Command, CommandResult, CommandTarget};
+use directory::{DirectoryEntry, DirectoryTarget};
+use error::{Error, Result};
+use file::{FileEdit, FileMetadata, FileTarget, FileOwner};
+use group::{GroupInfo, GroupTarget};
+use host::Host;
+use link::LinkTarget;
+use package::PackageTarget;
+use package::providers::Providers;
+use serde_json::Value;
+use service::ServiceTarget;
+use std::fs;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Once, ONCE_INIT};
+use super::LocalTarget;
+use super::centos::CentosTarget;
+use super::debian::DebianTarget;
+use super::fedora::FedoraTarget;
+use super::generic::GenericTarget;
+use super::redhat::RedhatTarget;
+use super::ubuntu::UbuntuTarget;
+use host::telemetry::TelemetryTarget;
+use user::{UserInfo, UserOpts, UserTarget};
+use zfilexfer::FileOptions;
+
+static mut LINUX_PLATFORM: Option<LinuxPlatform> = None;
+static INIT_FINGERPRINT: Once = ONCE_INIT;
+
+enum LinuxPlatform {
+    Centos,
+    Debian,
+    Fedora,
+    Generic,
+    Redhat,
+    Ubuntu,
+}
+
+//
+// Command
+//
+
+impl CommandTarget for LocalTarget {
+    fn exec(host: &mut Host, cmd: &Command) -> Result<CommandResult> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::exec(host, cmd),
+            &LinuxPlatform::Debian => DebianTarget::exec(host, cmd),
+            &LinuxPlatform::Fedora => FedoraTarget::exec(host, cmd),
+            &LinuxPlatform::Generic => GenericTarget::exec(host, cmd),
+            &LinuxPlatform::Redhat => RedhatTarget::exec(host, cmd),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::exec(host, cmd),
+        }
+    }
+}
+
+//
+// Directory
+//
+
+impl<P: AsRef<Path>> DirectoryTarget<P> for LocalTarget {
+    fn directory_is_directory(host: &mut Host, path: P) -> Result<bool> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::directory_is_directory(host, path),
+            &LinuxPlatform::Debian => DebianTarget::directory_is_directory(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::directory_is_directory(host, path),
+            &LinuxPlatform::Generic => GenericTarget::directory_is_directory(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::directory_is_directory(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::directory_is_directory(host, path),
+        }
+    }
+
+    fn directory_exists(host: &mut Host, path: P) -> Result<bool> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::directory_exists(host, path),
+            &LinuxPlatform::Debian => DebianTarget::directory_exists(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::directory_exists(host, path),
+            &LinuxPlatform::Generic => GenericTarget::directory_exists(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::directory_exists(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::directory_exists(host, path),
+        }
+    }
+
+    fn directory_create(host: &mut Host, path: P, recursive: bool) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::directory_create(host, path, recursive),
+            &LinuxPlatform::Debian => DebianTarget::directory_create(host, path, recursive),
+            &LinuxPlatform::Fedora => FedoraTarget::directory_create(host, path, recursive),
+            &LinuxPlatform::Generic => GenericTarget::directory_create(host, path, recursive),
+            &LinuxPlatform::Redhat => RedhatTarget::directory_create(host, path, recursive),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::directory_create(host, path, recursive),
+        }
+    }
+
+    fn directory_delete(host: &mut Host, path: P, recursive: bool) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::directory_delete(host, path, recursive),
+            &LinuxPlatform::Debian => DebianTarget::directory_delete(host, path, recursive),
+            &LinuxPlatform::Fedora => FedoraTarget::directory_delete(host, path, recursive),
+            &LinuxPlatform::Generic => GenericTarget::directory_delete(host, path, recursive),
+            &LinuxPlatform::Redhat => RedhatTarget::directory_delete(host, path, recursive),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::directory_delete(host, path, recursive),
+        }
+    }
+
+    fn directory_mv(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::directory_mv(host, path, new_path),
+            &LinuxPlatform::Debian => DebianTarget::directory_mv(host, path, new_path),
+            &LinuxPlatform::Fedora => FedoraTarget::directory_mv(host, path, new_path),
+            &LinuxPlatform::Generic => GenericTarget::directory_mv(host, path, new_path),
+            &LinuxPlatform::Redhat => RedhatTarget::directory_mv(host, path, new_path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::directory_mv(host, path, new_path),
+        }
+    }
+
+    fn directory_get_owner(host: &mut Host, path: P) -> Result<FileOwner> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::directory_get_owner(host, path),
+            &LinuxPlatform::Debian => DebianTarget::directory_get_owner(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::directory_get_owner(host, path),
+            &LinuxPlatform::Generic => GenericTarget::directory_get_owner(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::directory_get_owner(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::directory_get_owner(host, path),
+        }
+    }
+
+    fn directory_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::directory_set_owner(host, path, user, group),
+            &LinuxPlatform::Debian => DebianTarget::directory_set_owner(host, path, user, group),
+            &LinuxPlatform::Fedora => FedoraTarget::directory_set_owner(host, path, user, group),
+            &LinuxPlatform::Generic => GenericTarget::directory_set_owner(host, path, user, group),
+            &LinuxPlatform::Redhat => RedhatTarget::directory_set_owner(host, path, user, group),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::directory_set_owner(host, path, user, group),
+        }
+    }
+
+    fn directory_get_mode(host: &mut Host, path: P) -> Result<u16> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::directory_get_mode(host, path),
+            &LinuxPlatform::Debian => DebianTarget::directory_get_mode(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::directory_get_mode(host, path),
+            &LinuxPlatform::Generic => GenericTarget::directory_get_mode(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::directory_get_mode(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::directory_get_mode(host, path),
+        }
+    }
+
+    fn directory_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::directory_set_mode(host, path, mode),
+            &LinuxPlatform::Debian => DebianTarget::directory_set_mode(host, path, mode),
+            &LinuxPlatform::Fedora => FedoraTarget::directory_set_mode(host, path, mode),
+            &LinuxPlatform::Generic => GenericTarget::directory_set_mode(host, path, mode),
+            &LinuxPlatform::Redhat => RedhatTarget::directory_set_mode(host, path, mode),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::directory_set_mode(host, path, mode),
+        }
+    }
+
+    fn directory_checksums(host: &mut Host, path: P) -> Result<BTreeMap<String, Option<String>>> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::directory_checksums(host, path),
+            &LinuxPlatform::Debian => DebianTarget::directory_checksums(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::directory_checksums(host, path),
+            &LinuxPlatform::Generic => GenericTarget::directory_checksums(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::directory_checksums(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::directory_checksums(host, path),
+        }
+    }
+
+    fn directory_list(host: &mut Host, path: P, max_depth: Option<u32>) -> Result<Vec<DirectoryEntry>> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::directory_list(host, path, max_depth),
+            &LinuxPlatform::Debian => DebianTarget::directory_list(host, path, max_depth),
+            &LinuxPlatform::Fedora => FedoraTarget::directory_list(host, path, max_depth),
+            &LinuxPlatform::Generic => GenericTarget::directory_list(host, path, max_depth),
+            &LinuxPlatform::Redhat => RedhatTarget::directory_list(host, path, max_depth),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::directory_list(host, path, max_depth),
+        }
+    }
+}
+
+//
+// File
+//
+
+impl<P: AsRef<Path>> FileTarget<P> for LocalTarget {
+    fn file_is_file(host: &mut Host, path: P) -> Result<bool> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_is_file(host, path),
+            &LinuxPlatform::Debian => DebianTarget::file_is_file(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::file_is_file(host, path),
+            &LinuxPlatform::Generic => GenericTarget::file_is_file(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::file_is_file(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_is_file(host, path),
+        }
+    }
+
+    fn file_exists(host: &mut Host, path: P) -> Result<bool> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_exists(host, path),
+            &LinuxPlatform::Debian => DebianTarget::file_exists(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::file_exists(host, path),
+            &LinuxPlatform::Generic => GenericTarget::file_exists(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::file_exists(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_exists(host, path),
+        }
+    }
+
+    fn file_delete(host: &mut Host, path: P) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_delete(host, path),
+            &LinuxPlatform::Debian => DebianTarget::file_delete(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::file_delete(host, path),
+            &LinuxPlatform::Generic => GenericTarget::file_delete(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::file_delete(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_delete(host, path),
+        }
+    }
+
+    fn file_mv(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_mv(host, path, new_path),
+            &LinuxPlatform::Debian => DebianTarget::file_mv(host, path, new_path),
+            &LinuxPlatform::Fedora => FedoraTarget::file_mv(host, path, new_path),
+            &LinuxPlatform::Generic => GenericTarget::file_mv(host, path, new_path),
+            &LinuxPlatform::Redhat => RedhatTarget::file_mv(host, path, new_path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_mv(host, path, new_path),
+        }
+    }
+
+    fn file_copy(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_copy(host, path, new_path),
+            &LinuxPlatform::Debian => DebianTarget::file_copy(host, path, new_path),
+            &LinuxPlatform::Fedora => FedoraTarget::file_copy(host, path, new_path),
+            &LinuxPlatform::Generic => GenericTarget::file_copy(host, path, new_path),
+            &LinuxPlatform::Redhat => RedhatTarget::file_copy(host, path, new_path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_copy(host, path, new_path),
+        }
+    }
+
+    fn file_get_owner(host: &mut Host, path: P) -> Result<FileOwner> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_get_owner(host, path),
+            &LinuxPlatform::Debian => DebianTarget::file_get_owner(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::file_get_owner(host, path),
+            &LinuxPlatform::Generic => GenericTarget::file_get_owner(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::file_get_owner(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_get_owner(host, path),
+        }
+    }
+
+    fn file_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_set_owner(host, path, user, group),
+            &LinuxPlatform::Debian => DebianTarget::file_set_owner(host, path, user, group),
+            &LinuxPlatform::Fedora => FedoraTarget::file_set_owner(host, path, user, group),
+            &LinuxPlatform::Generic => GenericTarget::file_set_owner(host, path, user, group),
+            &LinuxPlatform::Redhat => RedhatTarget::file_set_owner(host, path, user, group),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_set_owner(host, path, user, group),
+        }
+    }
+
+    fn file_get_mode(host: &mut Host, path: P) -> Result<u16> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_get_mode(host, path),
+            &LinuxPlatform::Debian => DebianTarget::file_get_mode(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::file_get_mode(host, path),
+            &LinuxPlatform::Generic => GenericTarget::file_get_mode(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::file_get_mode(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_get_mode(host, path),
+        }
+    }
+
+    fn file_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_set_mode(host, path, mode),
+            &LinuxPlatform::Debian => DebianTarget::file_set_mode(host, path, mode),
+            &LinuxPlatform::Fedora => FedoraTarget::file_set_mode(host, path, mode),
+            &LinuxPlatform::Generic => GenericTarget::file_set_mode(host, path, mode),
+            &LinuxPlatform::Redhat => RedhatTarget::file_set_mode(host, path, mode),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_set_mode(host, path, mode),
+        }
+    }
+
+    fn file_hash(host: &mut Host, path: P) -> Result<Option<String>> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_hash(host, path),
+            &LinuxPlatform::Debian => DebianTarget::file_hash(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::file_hash(host, path),
+            &LinuxPlatform::Generic => GenericTarget::file_hash(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::file_hash(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_hash(host, path),
+        }
+    }
+
+    fn file_upload_file(host: &mut Host, path: P, file: fs::File, options: Option<&[FileOptions]>) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_upload_file(host, path, file, options),
+            &LinuxPlatform::Debian => DebianTarget::file_upload_file(host, path, file, options),
+            &LinuxPlatform::Fedora => FedoraTarget::file_upload_file(host, path, file, options),
+            &LinuxPlatform::Generic => GenericTarget::file_upload_file(host, path, file, options),
+            &LinuxPlatform::Redhat => RedhatTarget::file_upload_file(host, path, file, options),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_upload_file(host, path, file, options),
+        }
+    }
+
+    fn file_download(host: &mut Host, path: P, writer: &mut Write) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_download(host, path, writer),
+            &LinuxPlatform::Debian => DebianTarget::file_download(host, path, writer),
+            &LinuxPlatform::Fedora => FedoraTarget::file_download(host, path, writer),
+            &LinuxPlatform::Generic => GenericTarget::file_download(host, path, writer),
+            &LinuxPlatform::Redhat => RedhatTarget::file_download(host, path, writer),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_download(host, path, writer),
+        }
+    }
+
+    fn file_edit(host: &mut Host, path: P, edit: &FileEdit) -> Result<bool> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_edit(host, path, edit),
+            &LinuxPlatform::Debian => DebianTarget::file_edit(host, path, edit),
+            &LinuxPlatform::Fedora => FedoraTarget::file_edit(host, path, edit),
+            &LinuxPlatform::Generic => GenericTarget::file_edit(host, path, edit),
+            &LinuxPlatform::Redhat => RedhatTarget::file_edit(host, path, edit),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_edit(host, path, edit),
+        }
+    }
+
+    fn file_metadata(host: &mut Host, path: P) -> Result<FileMetadata> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::file_metadata(host, path),
+            &LinuxPlatform::Debian => DebianTarget::file_metadata(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::file_metadata(host, path),
+            &LinuxPlatform::Generic => GenericTarget::file_metadata(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::file_metadata(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::file_metadata(host, path),
+        }
+    }
+}
+
+//
+// Group
+//
+
+impl GroupTarget for LocalTarget {
+    fn group_get(host: &mut Host, name: &str) -> Result<Option<GroupInfo>> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::group_get(host, name),
+            &LinuxPlatform::Debian => DebianTarget::group_get(host, name),
+            &LinuxPlatform::Fedora => FedoraTarget::group_get(host, name),
+            &LinuxPlatform::Generic => GenericTarget::group_get(host, name),
+            &LinuxPlatform::Redhat => RedhatTarget::group_get(host, name),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::group_get(host, name),
+        }
+    }
+
+    fn group_create(host: &mut Host, name: &str, gid: Option<u64>) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::group_create(host, name, gid),
+            &LinuxPlatform::Debian => DebianTarget::group_create(host, name, gid),
+            &LinuxPlatform::Fedora => FedoraTarget::group_create(host, name, gid),
+            &LinuxPlatform::Generic => GenericTarget::group_create(host, name, gid),
+            &LinuxPlatform::Redhat => RedhatTarget::group_create(host, name, gid),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::group_create(host, name, gid),
+        }
+    }
+
+    fn group_modify(host: &mut Host, name: &str, gid: u64) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::group_modify(host, name, gid),
+            &LinuxPlatform::Debian => DebianTarget::group_modify(host, name, gid),
+            &LinuxPlatform::Fedora => FedoraTarget::group_modify(host, name, gid),
+            &LinuxPlatform::Generic => GenericTarget::group_modify(host, name, gid),
+            &LinuxPlatform::Redhat => RedhatTarget::group_modify(host, name, gid),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::group_modify(host, name, gid),
+        }
+    }
+
+    fn group_delete(host: &mut Host, name: &str) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::group_delete(host, name),
+            &LinuxPlatform::Debian => DebianTarget::group_delete(host, name),
+            &LinuxPlatform::Fedora => FedoraTarget::group_delete(host, name),
+            &LinuxPlatform::Generic => GenericTarget::group_delete(host, name),
+            &LinuxPlatform::Redhat => RedhatTarget::group_delete(host, name),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::group_delete(host, name),
+        }
+    }
+}
+
+//
+// Link
+//
+
+impl<P: AsRef<Path>> LinkTarget<P> for LocalTarget {
+    fn link_read(host: &mut Host, path: P) -> Result<Option<PathBuf>> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::link_read(host, path),
+            &LinuxPlatform::Debian => DebianTarget::link_read(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::link_read(host, path),
+            &LinuxPlatform::Generic => GenericTarget::link_read(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::link_read(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::link_read(host, path),
+        }
+    }
+
+    fn link_create(host: &mut Host, path: P, target: P) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::link_create(host, path, target),
+            &LinuxPlatform::Debian => DebianTarget::link_create(host, path, target),
+            &LinuxPlatform::Fedora => FedoraTarget::link_create(host, path, target),
+            &LinuxPlatform::Generic => GenericTarget::link_create(host, path, target),
+            &LinuxPlatform::Redhat => RedhatTarget::link_create(host, path, target),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::link_create(host, path, target),
+        }
+    }
+
+    fn link_create_hard(host: &mut Host, path: P, target: P) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::link_create_hard(host, path, target),
+            &LinuxPlatform::Debian => DebianTarget::link_create_hard(host, path, target),
+            &LinuxPlatform::Fedora => FedoraTarget::link_create_hard(host, path, target),
+            &LinuxPlatform::Generic => GenericTarget::link_create_hard(host, path, target),
+            &LinuxPlatform::Redhat => RedhatTarget::link_create_hard(host, path, target),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::link_create_hard(host, path, target),
+        }
+    }
+
+    fn link_is_hard(host: &mut Host, path: P, target: P) -> Result<bool> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::link_is_hard(host, path, target),
+            &LinuxPlatform::Debian => DebianTarget::link_is_hard(host, path, target),
+            &LinuxPlatform::Fedora => FedoraTarget::link_is_hard(host, path, target),
+            &LinuxPlatform::Generic => GenericTarget::link_is_hard(host, path, target),
+            &LinuxPlatform::Redhat => RedhatTarget::link_is_hard(host, path, target),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::link_is_hard(host, path, target),
+        }
+    }
+
+    fn link_delete(host: &mut Host, path: P) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::link_delete(host, path),
+            &LinuxPlatform::Debian => DebianTarget::link_delete(host, path),
+            &LinuxPlatform::Fedora => FedoraTarget::link_delete(host, path),
+            &LinuxPlatform::Generic => GenericTarget::link_delete(host, path),
+            &LinuxPlatform::Redhat => RedhatTarget::link_delete(host, path),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::link_delete(host, path),
+        }
+    }
+}
+
+//
+// Package
+//
+
+impl PackageTarget for LocalTarget {
+    fn default_provider(host: &mut Host) -> Result<Providers> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::default_provider(host),
+            &LinuxPlatform::Debian => DebianTarget::default_provider(host),
+            &LinuxPlatform::Fedora => FedoraTarget::default_provider(host),
+            &LinuxPlatform::Generic => GenericTarget::default_provider(host),
+            &LinuxPlatform::Redhat => RedhatTarget::default_provider(host),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::default_provider(host),
+        }
+    }
+}
+
+//
+// Service
+//
+
+impl ServiceTarget for LocalTarget {
+    fn service_action(host: &mut Host, name: &str, action: &str) -> Result<Option<CommandResult>> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::service_action(host, name, action),
+            &LinuxPlatform::Debian => DebianTarget::service_action(host, name, action),
+            &LinuxPlatform::Fedora => FedoraTarget::service_action(host, name, action),
+            &LinuxPlatform::Generic => GenericTarget::service_action(host, name, action),
+            &LinuxPlatform::Redhat => RedhatTarget::service_action(host, name, action),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::service_action(host, name, action),
+        }
+    }
+}
+
+//
+// Telemetry
+//
+
+impl TelemetryTarget for LocalTarget {
+    fn telemetry_init(host: &mut Host) -> Result<Value> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::telemetry_init(host),
+            &LinuxPlatform::Debian => DebianTarget::telemetry_init(host),
+            &LinuxPlatform::Fedora => FedoraTarget::telemetry_init(host),
+            &LinuxPlatform::Generic => GenericTarget::telemetry_init(host),
+            &LinuxPlatform::Redhat => RedhatTarget::telemetry_init(host),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::telemetry_init(host),
+        }
+    }
+}
+
+//
+// User
+//
+
+impl UserTarget for LocalTarget {
+    fn user_get(host: &mut Host, name: &str) -> Result<Option<UserInfo>> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::user_get(host, name),
+            &LinuxPlatform::Debian => DebianTarget::user_get(host, name),
+            &LinuxPlatform::Fedora => FedoraTarget::user_get(host, name),
+            &LinuxPlatform::Generic => GenericTarget::user_get(host, name),
+            &LinuxPlatform::Redhat => RedhatTarget::user_get(host, name),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::user_get(host, name),
+        }
+    }
+
+    fn user_create(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::user_create(host, name, opts),
+            &LinuxPlatform::Debian => DebianTarget::user_create(host, name, opts),
+            &LinuxPlatform::Fedora => FedoraTarget::user_create(host, name, opts),
+            &LinuxPlatform::Generic => GenericTarget::user_create(host, name, opts),
+            &LinuxPlatform::Redhat => RedhatTarget::user_create(host, name, opts),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::user_create(host, name, opts),
+        }
+    }
+
+    fn user_modify(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::user_modify(host, name, opts),
+            &LinuxPlatform::Debian => DebianTarget::user_modify(host, name, opts),
+            &LinuxPlatform::Fedora => FedoraTarget::user_modify(host, name, opts),
+            &LinuxPlatform::Generic => GenericTarget::user_modify(host, name, opts),
+            &LinuxPlatform::Redhat => RedhatTarget::user_modify(host, name, opts),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::user_modify(host, name, opts),
+        }
+    }
+
+    fn user_delete(host: &mut Host, name: &str) -> Result<()> {
+        match try!(fingerprint_os()) {
+            &LinuxPlatform::Centos => CentosTarget::user_delete(host, name),
+            &LinuxPlatform::Debian => DebianTarget::user_delete(host, name),
+            &LinuxPlatform::Fedora => FedoraTarget::user_delete(host, name),
+            &LinuxPlatform::Generic => GenericTarget::user_delete(host, name),
+            &LinuxPlatform::Redhat => RedhatTarget::user_delete(host, name),
+            &LinuxPlatform::Ubuntu => UbuntuTarget::user_delete(host, name),
+        }
+    }
+}
+
+fn fingerprint_os() -> Result<&'static LinuxPlatform> {
+    INIT_FINGERPRINT.call_once(|| {
+        let platform = if fs::metadata("/etc/centos-release").is_ok() {
+            LinuxPlatform::Centos
+        } else if fs::metadata("/etc/lsb-release").is_ok() {
+            LinuxPlatform::Ubuntu
+        } else if fs::metadata("/etc/debian_version").is_ok() {
+            LinuxPlatform::Debian
+        } else if fs::metadata("/etc/fedora-release").is_ok() {
+            LinuxPlatform::Fedora
+        } else if fs::metadata("/etc/redhat-release").is_ok() {
+            LinuxPlatform::Redhat
+        }
+        // Other distros, e.g. Alpine, Arch and openSUSE
+        else if fs::metadata("/etc/os-release").is_ok() {
+            LinuxPlatform::Generic
+        } else {
+            return;
+        };
+        unsafe { LINUX_PLATFORM = Some(platform); }
+    });
+
+    unsafe { LINUX_PLATFORM.as_ref() }.ok_or(Error::Generic("Unknown Linux distro".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use Host;
+    use package::PackageTarget;
+    use target::LocalTarget;
+    use host::telemetry::TelemetryTarget;
+    use serde_json::Value;
+    use super::super::generic::GenericTarget;
+
+    #[test]
+    fn test_package_default_provider() {
+        let path: Option<String> = None;
+        let mut host = Host::local(path).unwrap();
+        let result = LocalTarget::default_provider(&mut host);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_telemetry_init() {
+        let path: Option<String> = None;
+        let mut host = Host::local(path).unwrap();
+        let result = LocalTarget::telemetry_init(&mut host);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_generic_telemetry_init() {
+        // Any distro with /etc/os-release can fall back to the
+        // generic target, including the one running the tests.
+        let path: Option<String> = None;
+        let mut host = Host::local(path).unwrap();
+        let telemetry = GenericTarget::telemetry_init(&mut host).unwrap();
+        match telemetry.pointer("/os/platform") {
+            Some(&Value::String(ref platform)) => assert!(!platform.is_empty()),
+            p => panic!("Unexpected platform {:?}", p),
+        }
+    }
+}
@@ -0,0 +1,528 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use command::{Command, CommandResult, CommandTarget};
+use directory::{self, DirectoryEntry, DirectoryTarget};
+use error::{Error, Result};
+use file::{FileEdit, FileMetadata, FileTarget, FileOwner};
+use group::{GroupInfo, GroupTarget};
+use host::Host;
+use host::telemetry::{Cpu, Os, Telemetry, TelemetryTarget};
+use link::LinkTarget;
+use package::PackageTarget;
+use package::providers::Providers;
+use serde_json::Value;
+use service::ServiceTarget;
+use std::{env, fs, process, str};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use super::{default_base as default, LocalTarget, unix_base as unix};
+use target::bin_resolver::BinResolver;
+use user::{UserInfo, UserOpts, UserTarget};
+use zfilexfer::FileOptions;
+
+// This implementation is legacy. More work is required to support
+// modern launchd implementations.
+//
+// const LD_PATHS: [&'static str; 2] = [
+//     "/Libarary/LaunchDaemons",
+//     "/System/Library/LaunchDaemons"
+// ];
+
+//
+// Command
+//
+
+impl CommandTarget for LocalTarget {
+    #[allow(unused_variables)]
+    fn exec(host: &mut Host, cmd: &Command) -> Result<CommandResult> {
+        default::command_exec_with(cmd)
+    }
+}
+
+//
+// Directory
+//
+
+impl<P: AsRef<Path>> DirectoryTarget<P> for LocalTarget {
+    #[allow(unused_variables)]
+    fn directory_is_directory(host: &mut Host, path: P) -> Result<bool> {
+        default::directory_is_directory(path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_exists(host: &mut Host, path: P) -> Result<bool> {
+        default::file_exists(path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_create(host: &mut Host, path: P, recursive: bool) -> Result<()> {
+        default::directory_create(path, recursive)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_delete(host: &mut Host, path: P, recursive: bool) -> Result<()> {
+        default::directory_delete(path, recursive)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_mv(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        default::file_mv(path, new_path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_get_owner(host: &mut Host, path: P) -> Result<FileOwner> {
+        unix::file_get_owner(path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()> {
+        default::file_set_owner(path, user, group)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_get_mode(host: &mut Host, path: P) -> Result<u16> {
+        unix::file_get_mode(path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()> {
+        default::file_set_mode(path, mode)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_checksums(host: &mut Host, path: P) -> Result<BTreeMap<String, Option<String>>> {
+        directory::checksums(path)
+    }
+
+    #[allow(unused_variables)]
+    fn directory_list(host: &mut Host, path: P, max_depth: Option<u32>) -> Result<Vec<DirectoryEntry>> {
+        directory::list(path, max_depth)
+    }
+}
+
+//
+// File
+//
+
+impl<P: AsRef<Path>> FileTarget<P> for LocalTarget {
+    #[allow(unused_variables)]
+    fn file_is_file(host: &mut Host, path: P) -> Result<bool> {
+        default::file_is_file(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_exists(host: &mut Host, path: P) -> Result<bool> {
+        default::file_exists(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_delete(host: &mut Host, path: P) -> Result<()> {
+        default::file_delete(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_mv(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        default::file_mv(path, new_path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_copy(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        default::file_copy(path, new_path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_get_owner(host: &mut Host, path: P) -> Result<FileOwner> {
+        unix::file_get_owner(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()> {
+        default::file_set_owner(path, user, group)
+    }
+
+    #[allow(unused_variables)]
+    fn file_get_mode(host: &mut Host, path: P) -> Result<u16> {
+        unix::file_get_mode(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()> {
+        default::file_set_mode(path, mode)
+    }
+
+    #[allow(unused_variables)]
+    fn file_hash(host: &mut Host, path: P) -> Result<Option<String>> {
+        default::file_hash(path)
+    }
+
+    #[allow(unused_variables)]
+    fn file_upload_file(host: &mut Host, path: P, file: fs::File, options: Option<&[FileOptions]>) -> Result<()> {
+        default::file_upload_file(path, file, options)
+    }
+
+    #[allow(unused_variables)]
+    fn file_download(host: &mut Host, path: P, writer: &mut Write) -> Result<()> {
+        default::file_download(path, writer)
+    }
+
+    #[allow(unused_variables)]
+    fn file_edit(host: &mut Host, path: P, edit: &FileEdit) -> Result<bool> {
+        default::file_edit(path, edit)
+    }
+
+    #[allow(unused_variables)]
+    fn file_metadata(host: &mut Host, path: P) -> Result<FileMetadata> {
+        default::file_metadata(path)
+    }
+}
+
+//
+// Group
+//
+
+impl GroupTarget for LocalTarget {
+    #[allow(unused_variables)]
+    fn group_get(host: &mut Host, name: &str) -> Result<Option<GroupInfo>> {
+        let path = format!("/Groups/{}", name);
+        let record = match try!(dscl_read(&path, &["PrimaryGroupID", "GroupMembership"])) {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+
+        Ok(Some(GroupInfo {
+            name: name.to_string(),
+            gid: try!(try!(dscl_value(&record, "PrimaryGroupID")).parse::<u64>()),
+            members: record.get("GroupMembership").map(|m| m.split_whitespace().map(|m| m.to_string()).collect()).unwrap_or(Vec::new()),
+        }))
+    }
+
+    #[allow(unused_variables)]
+    fn group_create(host: &mut Host, name: &str, gid: Option<u64>) -> Result<()> {
+        let path = format!("/Groups/{}", name);
+        let gid = match gid {
+            Some(gid) => gid,
+            None => try!(dscl_next_id("/Groups", "PrimaryGroupID")),
+        };
+        try!(dscl(&["-create", &path]));
+        dscl(&["-create", &path, "PrimaryGroupID", &gid.to_string()])
+    }
+
+    #[allow(unused_variables)]
+    fn group_modify(host: &mut Host, name: &str, gid: u64) -> Result<()> {
+        dscl(&["-create", &format!("/Groups/{}", name), "PrimaryGroupID", &gid.to_string()])
+    }
+
+    #[allow(unused_variables)]
+    fn group_delete(host: &mut Host, name: &str) -> Result<()> {
+        dscl(&["-delete", &format!("/Groups/{}", name)])
+    }
+}
+
+//
+// Link
+//
+
+impl<P: AsRef<Path>> LinkTarget<P> for LocalTarget {
+    #[allow(unused_variables)]
+    fn link_read(host: &mut Host, path: P) -> Result<Option<PathBuf>> {
+        default::link_read(path)
+    }
+
+    #[allow(unused_variables)]
+    fn link_create(host: &mut Host, path: P, target: P) -> Result<()> {
+        default::link_create(path, target)
+    }
+
+    #[allow(unused_variables)]
+    fn link_create_hard(host: &mut Host, path: P, target: P) -> Result<()> {
+        default::link_create_hard(path, target)
+    }
+
+    #[allow(unused_variables)]
+    fn link_is_hard(host: &mut Host, path: P, target: P) -> Result<bool> {
+        default::link_is_hard(path, target)
+    }
+
+    #[allow(unused_variables)]
+    fn link_delete(host: &mut Host, path: P) -> Result<()> {
+        default::link_delete(path)
+    }
+}
+
+//
+// Package
+//
+
+impl PackageTarget for LocalTarget {
+    fn default_provider(host: &mut Host) -> Result<Providers> {
+        default::default_provider(host, vec![Providers::Homebrew, Providers::Macports])
+    }
+}
+
+//
+// Service
+//
+
+impl ServiceTarget for LocalTarget {
+    #[allow(unused_variables)]
+    fn service_action(host: &mut Host, name: &str, action: &str) -> Result<Option<CommandResult>> {
+        // This implementation is legacy. More work is required to
+        // support modern launchd implementations.
+        //
+        // let action = match action {
+        //     "start" => "load",
+        //     "stop" => "unload",
+        //     _ => action,
+        // };
+        //
+        // // If name is relative path, search known LaunchDaemon paths
+        // // for name's path.
+        // let mut name = name;
+        // let name_path = Path::new(name);
+        // if name_path.is_relative() {
+        //     for path in LD_PATHS.into_iter() {
+        //         let mut buf = PathBuf::from(path);
+        //
+        //         if buf.is_dir() {
+        //             buf.push(name);
+        //
+        //             if buf.is_file() {
+        //                 name = buf.to_str().unwrap();
+        //                 break;
+        //             }
+        //         }
+        //     }
+        // }
+        //
+        // command_exec(&format!("{} {} {}", &try!(BinResolver::resolve("launchctl")), action, name))
+        unimplemented!()
+    }
+}
+
+//
+// Telemetry
+//
+
+impl TelemetryTarget for LocalTarget {
+    #[allow(unused_variables)]
+    fn telemetry_init(host: &mut Host) -> Result<Value> {
+        let cpu_vendor = try!(unix::get_sysctl_item("machdep\\.cpu\\.vendor"));
+        let cpu_brand = try!(unix::get_sysctl_item("machdep\\.cpu\\.brand_string"));
+        let hostname = try!(default::hostname());
+        let (version_str, version_maj, version_min, version_patch) = try!(version());
+
+        let telemetry = Telemetry::new(
+            Cpu::new(
+                &cpu_vendor,
+                &cpu_brand,
+                try!(try!(unix::get_sysctl_item("hw\\.physicalcpu")).parse::<u32>())
+            ),
+            try!(default::parse_fs(vec![
+                default::FsFieldOrder::Filesystem,
+                default::FsFieldOrder::Size,
+                default::FsFieldOrder::Used,
+                default::FsFieldOrder::Available,
+                default::FsFieldOrder::Capacity,
+                default::FsFieldOrder::Blank,
+                default::FsFieldOrder::Blank,
+                default::FsFieldOrder::Blank,
+                default::FsFieldOrder::Mount,
+            ])),
+            &hostname,
+            try!(try!(unix::get_sysctl_item("hw\\.memsize")).parse::<u64>()),
+            try!(unix::net()),
+            Os::new(env::consts::ARCH, "unix", "macos", &version_str, version_maj, version_min, version_patch),
+        );
+
+        Ok(telemetry.into_value())
+    }
+}
+
+//
+// User
+//
+
+impl UserTarget for LocalTarget {
+    #[allow(unused_variables)]
+    fn user_get(host: &mut Host, name: &str) -> Result<Option<UserInfo>> {
+        let path = format!("/Users/{}", name);
+        let record = match try!(dscl_read(&path, &["UniqueID", "PrimaryGroupID", "NFSHomeDirectory", "UserShell"])) {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+
+        Ok(Some(UserInfo {
+            name: name.to_string(),
+            uid: try!(try!(dscl_value(&record, "UniqueID")).parse::<u64>()),
+            gid: try!(try!(dscl_value(&record, "PrimaryGroupID")).parse::<u64>()),
+            home: record.get("NFSHomeDirectory").cloned().unwrap_or(String::new()),
+            shell: record.get("UserShell").cloned().unwrap_or(String::new()),
+            groups: try!(default::user_groups(name)),
+        }))
+    }
+
+    #[allow(unused_variables)]
+    fn user_create(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()> {
+        let path = format!("/Users/{}", name);
+        let uid = match opts.uid {
+            Some(uid) => uid,
+            None => try!(dscl_next_id("/Users", "UniqueID")),
+        };
+        let home = opts.home.clone().unwrap_or(path.clone());
+
+        try!(dscl(&["-create", &path]));
+        try!(dscl(&["-create", &path, "UniqueID", &uid.to_string()]));
+        // Default to the "staff" group, as System Preferences does
+        try!(dscl(&["-create", &path, "PrimaryGroupID", &opts.gid.unwrap_or(20).to_string()]));
+        try!(dscl(&["-create", &path, "UserShell", opts.shell.as_ref().map(|s| s.as_str()).unwrap_or("/bin/bash")]));
+        try!(dscl(&["-create", &path, "NFSHomeDirectory", &home]));
+        try!(default::account_exec("createhomedir", &["-c".to_string(), "-u".to_string(), name.to_string()]));
+
+        if let Some(ref groups) = opts.groups {
+            try!(user_set_groups(name, groups));
+        }
+
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    fn user_modify(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()> {
+        let path = format!("/Users/{}", name);
+        if let Some(uid) = opts.uid {
+            try!(dscl(&["-create", &path, "UniqueID", &uid.to_string()]));
+        }
+        if let Some(gid) = opts.gid {
+            try!(dscl(&["-create", &path, "PrimaryGroupID", &gid.to_string()]));
+        }
+        if let Some(ref shell) = opts.shell {
+            try!(dscl(&["-create", &path, "UserShell", shell]));
+        }
+        if let Some(ref home) = opts.home {
+            try!(dscl(&["-create", &path, "NFSHomeDirectory", home]));
+        }
+        if let Some(ref groups) = opts.groups {
+            try!(user_set_groups(name, groups));
+        }
+
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    fn user_delete(host: &mut Host, name: &str) -> Result<()> {
+        dscl(&["-delete", &format!("/Users/{}", name)])
+    }
+}
+
+fn version() -> Result<(String, u32, u32, u32)> {
+    let out = process::Command::new(BinResolver::resolve("sw_vers")?).arg("-productVersion").output()?;
+    let version_str = str::from_utf8(&out.stdout).or(Err(Error::Generic("Could not read OS version".into())))?.trim().to_owned();
+    let (maj, min, patch) = {
+        let mut parts = version_str.split('.');
+        (parts.next().ok_or(Error::Generic(format!("Expected OS version format `u32.u32.u32`. Got: {}", version_str)))?.parse()?,
+         parts.next().ok_or(Error::Generic(format!("Expected OS version format `u32.u32.u32`. Got: {}", version_str)))?.parse()?,
+         parts.next().ok_or(Error::Generic(format!("Expected OS version format `u32.u32.u32`. Got: {}", version_str)))?.parse()?)
+    };
+    Ok((version_str, maj, min, patch))
+}
+
+fn dscl(args: &[&str]) -> Result<()> {
+    let mut dscl_args = vec![".".to_string()];
+    dscl_args.extend(args.iter().map(|a| a.to_string()));
+    default::account_exec("dscl", &dscl_args)
+}
+
+/// Read the given keys from a Directory Service record, or None if
+/// the record does not exist.
+fn dscl_read(path: &str, keys: &[&str]) -> Result<Option<HashMap<String, String>>> {
+    let mut args = vec![".", "-read", path];
+    args.extend_from_slice(keys);
+
+    let output = match try!(default::account_query("dscl", &args)) {
+        Some(output) => output,
+        None => return Ok(None),
+    };
+
+    // Values are printed as "Key: value", or on the following
+    // indented line(s) if they are long.
+    let mut record = HashMap::new();
+    let mut key: Option<String> = None;
+    for line in output.lines() {
+        if line.starts_with(' ') {
+            if let Some(ref k) = key {
+                let value = record.entry(k.clone()).or_insert(String::new());
+                if !value.is_empty() {
+                    value.push(' ');
+                }
+                value.push_str(line.trim());
+            }
+        } else if let Some(i) = line.find(':') {
+            let k = line[..i].to_string();
+            record.insert(k.clone(), line[i + 1..].trim().to_string());
+            key = Some(k);
+        }
+    }
+
+    Ok(Some(record))
+}
+
+fn dscl_value<'a>(record: &'a HashMap<String, String>, key: &str) -> Result<&'a str> {
+    record.get(key).map(|v| v.as_str()).ok_or(Error::Generic(format!("Directory Service record is missing {}", key)))
+}
+
+/// Find the next free ID above the range reserved for system accounts.
+fn dscl_next_id(path: &str, key: &str) -> Result<u64> {
+    let output = try!(default::account_query("dscl", &[".", "-list", path, key])).unwrap_or(String::new());
+    let max = output.lines()
+                    .filter_map(|l| l.split_whitespace().last())
+                    .filter_map(|id| id.parse::<u64>().ok())
+                    .max()
+                    .unwrap_or(0);
+
+    Ok(if max < 500 { 501 } else { max + 1 })
+}
+
+/// Replace a user's supplementary groups.
+fn user_set_groups(name: &str, groups: &[String]) -> Result<()> {
+    let current = try!(default::user_groups(name));
+
+    for group in current.iter().filter(|g| !groups.contains(g)) {
+        try!(default::account_exec("dseditgroup", &["-o".to_string(), "edit".to_string(), "-d".to_string(), name.to_string(), "-t".to_string(), "user".to_string(), group.clone()]));
+    }
+
+    for group in groups.iter().filter(|g| !current.contains(g)) {
+        try!(default::account_exec("dseditgroup", &["-o".to_string(), "edit".to_string(), "-a".to_string(), name.to_string(), "-t".to_string(), "user".to_string(), group.clone()]));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use Host;
+    use package::PackageTarget;
+    use target::LocalTarget;
+    use host::telemetry::TelemetryTarget;
+
+    #[test]
+    fn test_package_default_provider() {
+        let path: Option<String> = None;
+        let mut host = Host::local(path).unwrap();
+        let result = LocalTarget::default_provider(&mut host);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_telemetry_init() {
+        let path: Option<String> = None;
+        let mut host = Host::local(path).unwrap();
+        let result = LocalTarget::telemetry_init(&mut host);
+        assert!(result.is_ok());
+    }
+}
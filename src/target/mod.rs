@@ -0,0 +1,537 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+pub mod bin_resolver;
+
+#[cfg(target_os = "linux")]
+#[allow(dead_code)]
+pub mod debian_base;
+
+#[allow(dead_code)]
+pub mod default_base;
+
+#[cfg(target_os = "linux")]
+#[allow(dead_code)]
+pub mod linux_base;
+
+#[cfg(target_os = "linux")]
+#[allow(dead_code)]
+pub mod redhat_base;
+
+#[cfg(any(target_os = "freebsd", target_os = "macos"))]
+#[allow(dead_code)]
+pub mod unix_base;
+
+#[cfg(target_os = "linux")]
+pub mod debian;
+
+#[cfg(target_os = "linux")]
+pub mod centos;
+
+#[cfg(target_os = "linux")]
+pub mod fedora;
+
+#[cfg(target_os = "freebsd")]
+pub mod freebsd;
+
+#[cfg(target_os = "linux")]
+pub mod generic;
+
+#[cfg(target_os = "linux")]
+#[allow(dead_code)]
+pub mod linux;
+
+#[cfg(target_os = "macos")]
+pub mod macos;
+
+#[cfg(target_os = "linux")]
+pub mod redhat;
+
+#[cfg(target_os = "linux")]
+pub mod ubuntu;
+
+pub mod remote;
+
+mod escalate;
+
+use command::{Command, CommandResult, CommandTarget};
+use directory::{DirectoryEntry, DirectoryTarget};
+use error::Result;
+use file::{FileEdit, FileMetadata, FileOwner, FileTarget};
+use group::{GroupInfo, GroupTarget};
+use host::Host;
+use host::telemetry::TelemetryTarget;
+use link::LinkTarget;
+use package::PackageTarget;
+use package::providers::Providers;
+use serde_json::Value;
+use service::ServiceTarget;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use user::{UserInfo, UserOpts, UserTarget};
+use zfilexfer::FileOptions;
+
+/// Runs primitives against a Host, using the local machine or the
+/// Host's transport depending on how the Host was created.
+/// Operations that change the host are run as another user if the
+/// Host has one set.
+pub struct Target;
+
+/// Runs primitives against the local machine.
+pub struct LocalTarget;
+
+/// Runs primitives against a remote host over its transport.
+pub struct RemoteTarget;
+
+//
+// Command
+//
+
+impl CommandTarget for Target {
+    fn exec(host: &mut Host, cmd: &Command) -> Result<CommandResult> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::exec(host, &escalation, cmd)
+        } else if host.is_local() {
+            LocalTarget::exec(host, cmd)
+        } else {
+            RemoteTarget::exec(host, cmd)
+        }
+    }
+}
+
+//
+// Directory
+//
+
+impl<P: AsRef<Path>> DirectoryTarget<P> for Target {
+    fn directory_is_directory(host: &mut Host, path: P) -> Result<bool> {
+        if host.is_local() {
+            LocalTarget::directory_is_directory(host, path)
+        } else {
+            RemoteTarget::directory_is_directory(host, path)
+        }
+    }
+
+    fn directory_exists(host: &mut Host, path: P) -> Result<bool> {
+        if host.is_local() {
+            LocalTarget::directory_exists(host, path)
+        } else {
+            RemoteTarget::directory_exists(host, path)
+        }
+    }
+
+    fn directory_create(host: &mut Host, path: P, recursive: bool) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::directory_create(host, &escalation, path, recursive)
+        } else if host.is_local() {
+            LocalTarget::directory_create(host, path, recursive)
+        } else {
+            RemoteTarget::directory_create(host, path, recursive)
+        }
+    }
+
+    fn directory_delete(host: &mut Host, path: P, recursive: bool) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::directory_delete(host, &escalation, path, recursive)
+        } else if host.is_local() {
+            LocalTarget::directory_delete(host, path, recursive)
+        } else {
+            RemoteTarget::directory_delete(host, path, recursive)
+        }
+    }
+
+    fn directory_mv(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::mv(host, &escalation, path, new_path)
+        } else if host.is_local() {
+            LocalTarget::directory_mv(host, path, new_path)
+        } else {
+            RemoteTarget::directory_mv(host, path, new_path)
+        }
+    }
+
+    fn directory_get_owner(host: &mut Host, path: P) -> Result<FileOwner> {
+        if host.is_local() {
+            LocalTarget::directory_get_owner(host, path)
+        } else {
+            RemoteTarget::directory_get_owner(host, path)
+        }
+    }
+
+    fn directory_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::set_owner(host, &escalation, path, user, group)
+        } else if host.is_local() {
+            LocalTarget::directory_set_owner(host, path, user, group)
+        } else {
+            RemoteTarget::directory_set_owner(host, path, user, group)
+        }
+    }
+
+    fn directory_get_mode(host: &mut Host, path: P) -> Result<u16> {
+        if host.is_local() {
+            LocalTarget::directory_get_mode(host, path)
+        } else {
+            RemoteTarget::directory_get_mode(host, path)
+        }
+    }
+
+    fn directory_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::set_mode(host, &escalation, path, mode)
+        } else if host.is_local() {
+            LocalTarget::directory_set_mode(host, path, mode)
+        } else {
+            RemoteTarget::directory_set_mode(host, path, mode)
+        }
+    }
+
+    fn directory_checksums(host: &mut Host, path: P) -> Result<BTreeMap<String, Option<String>>> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::directory_checksums(host, &escalation, path)
+        } else if host.is_local() {
+            LocalTarget::directory_checksums(host, path)
+        } else {
+            RemoteTarget::directory_checksums(host, path)
+        }
+    }
+
+    fn directory_list(host: &mut Host, path: P, max_depth: Option<u32>) -> Result<Vec<DirectoryEntry>> {
+        if host.is_local() {
+            LocalTarget::directory_list(host, path, max_depth)
+        } else {
+            RemoteTarget::directory_list(host, path, max_depth)
+        }
+    }
+}
+
+//
+// File
+//
+
+impl<P: AsRef<Path>> FileTarget<P> for Target {
+    fn file_is_file(host: &mut Host, path: P) -> Result<bool> {
+        if host.is_local() {
+            LocalTarget::file_is_file(host, path)
+        } else {
+            RemoteTarget::file_is_file(host, path)
+        }
+    }
+
+    fn file_exists(host: &mut Host, path: P) -> Result<bool> {
+        if host.is_local() {
+            LocalTarget::file_exists(host, path)
+        } else {
+            RemoteTarget::file_exists(host, path)
+        }
+    }
+
+    fn file_delete(host: &mut Host, path: P) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::delete(host, &escalation, path)
+        } else if host.is_local() {
+            LocalTarget::file_delete(host, path)
+        } else {
+            RemoteTarget::file_delete(host, path)
+        }
+    }
+
+    fn file_mv(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::mv(host, &escalation, path, new_path)
+        } else if host.is_local() {
+            LocalTarget::file_mv(host, path, new_path)
+        } else {
+            RemoteTarget::file_mv(host, path, new_path)
+        }
+    }
+
+    fn file_copy(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::copy(host, &escalation, path, new_path)
+        } else if host.is_local() {
+            LocalTarget::file_copy(host, path, new_path)
+        } else {
+            RemoteTarget::file_copy(host, path, new_path)
+        }
+    }
+
+    fn file_get_owner(host: &mut Host, path: P) -> Result<FileOwner> {
+        if host.is_local() {
+            LocalTarget::file_get_owner(host, path)
+        } else {
+            RemoteTarget::file_get_owner(host, path)
+        }
+    }
+
+    fn file_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::set_owner(host, &escalation, path, user, group)
+        } else if host.is_local() {
+            LocalTarget::file_set_owner(host, path, user, group)
+        } else {
+            RemoteTarget::file_set_owner(host, path, user, group)
+        }
+    }
+
+    fn file_get_mode(host: &mut Host, path: P) -> Result<u16> {
+        if host.is_local() {
+            LocalTarget::file_get_mode(host, path)
+        } else {
+            RemoteTarget::file_get_mode(host, path)
+        }
+    }
+
+    fn file_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::set_mode(host, &escalation, path, mode)
+        } else if host.is_local() {
+            LocalTarget::file_set_mode(host, path, mode)
+        } else {
+            RemoteTarget::file_set_mode(host, path, mode)
+        }
+    }
+
+    fn file_hash(host: &mut Host, path: P) -> Result<Option<String>> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::file_hash(host, &escalation, path)
+        } else if host.is_local() {
+            LocalTarget::file_hash(host, path)
+        } else {
+            RemoteTarget::file_hash(host, path)
+        }
+    }
+
+    fn file_upload_file(host: &mut Host, path: P, file: fs::File, options: Option<&[FileOptions]>) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::upload(host, &escalation, path, file, options)
+        } else if host.is_local() {
+            LocalTarget::file_upload_file(host, path, file, options)
+        } else {
+            RemoteTarget::file_upload_file(host, path, file, options)
+        }
+    }
+
+    fn file_download(host: &mut Host, path: P, writer: &mut Write) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::download(host, &escalation, path, writer)
+        } else if host.is_local() {
+            LocalTarget::file_download(host, path, writer)
+        } else {
+            RemoteTarget::file_download(host, path, writer)
+        }
+    }
+
+    fn file_edit(host: &mut Host, path: P, edit: &FileEdit) -> Result<bool> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::file_edit(host, &escalation, path, edit)
+        } else if host.is_local() {
+            LocalTarget::file_edit(host, path, edit)
+        } else {
+            RemoteTarget::file_edit(host, path, edit)
+        }
+    }
+
+    fn file_metadata(host: &mut Host, path: P) -> Result<FileMetadata> {
+        if host.is_local() {
+            LocalTarget::file_metadata(host, path)
+        } else {
+            RemoteTarget::file_metadata(host, path)
+        }
+    }
+}
+
+//
+// Group
+//
+
+impl GroupTarget for Target {
+    fn group_get(host: &mut Host, name: &str) -> Result<Option<GroupInfo>> {
+        if host.is_local() {
+            LocalTarget::group_get(host, name)
+        } else {
+            RemoteTarget::group_get(host, name)
+        }
+    }
+
+    fn group_create(host: &mut Host, name: &str, gid: Option<u64>) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::group_create(host, &escalation, name, gid)
+        } else if host.is_local() {
+            LocalTarget::group_create(host, name, gid)
+        } else {
+            RemoteTarget::group_create(host, name, gid)
+        }
+    }
+
+    fn group_modify(host: &mut Host, name: &str, gid: u64) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::group_modify(host, &escalation, name, gid)
+        } else if host.is_local() {
+            LocalTarget::group_modify(host, name, gid)
+        } else {
+            RemoteTarget::group_modify(host, name, gid)
+        }
+    }
+
+    fn group_delete(host: &mut Host, name: &str) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::group_delete(host, &escalation, name)
+        } else if host.is_local() {
+            LocalTarget::group_delete(host, name)
+        } else {
+            RemoteTarget::group_delete(host, name)
+        }
+    }
+}
+
+//
+// Link
+//
+
+impl<P: AsRef<Path>> LinkTarget<P> for Target {
+    fn link_read(host: &mut Host, path: P) -> Result<Option<PathBuf>> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::link_read(host, &escalation, path)
+        } else if host.is_local() {
+            LocalTarget::link_read(host, path)
+        } else {
+            RemoteTarget::link_read(host, path)
+        }
+    }
+
+    fn link_create(host: &mut Host, path: P, target: P) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::link_create(host, &escalation, path, target)
+        } else if host.is_local() {
+            LocalTarget::link_create(host, path, target)
+        } else {
+            RemoteTarget::link_create(host, path, target)
+        }
+    }
+
+    fn link_create_hard(host: &mut Host, path: P, target: P) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::link_create_hard(host, &escalation, path, target)
+        } else if host.is_local() {
+            LocalTarget::link_create_hard(host, path, target)
+        } else {
+            RemoteTarget::link_create_hard(host, path, target)
+        }
+    }
+
+    fn link_is_hard(host: &mut Host, path: P, target: P) -> Result<bool> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::link_is_hard(host, &escalation, path, target)
+        } else if host.is_local() {
+            LocalTarget::link_is_hard(host, path, target)
+        } else {
+            RemoteTarget::link_is_hard(host, path, target)
+        }
+    }
+
+    fn link_delete(host: &mut Host, path: P) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::delete(host, &escalation, path)
+        } else if host.is_local() {
+            LocalTarget::link_delete(host, path)
+        } else {
+            RemoteTarget::link_delete(host, path)
+        }
+    }
+}
+
+//
+// Package
+//
+
+impl PackageTarget for Target {
+    fn default_provider(host: &mut Host) -> Result<Providers> {
+        if host.is_local() {
+            LocalTarget::default_provider(host)
+        } else {
+            RemoteTarget::default_provider(host)
+        }
+    }
+}
+
+//
+// Service
+//
+
+impl ServiceTarget for Target {
+    fn service_action(host: &mut Host, name: &str, action: &str) -> Result<Option<CommandResult>> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::service_action(host, &escalation, name, action)
+        } else if host.is_local() {
+            LocalTarget::service_action(host, name, action)
+        } else {
+            RemoteTarget::service_action(host, name, action)
+        }
+    }
+}
+
+//
+// Telemetry
+//
+
+impl TelemetryTarget for Target {
+    fn telemetry_init(host: &mut Host) -> Result<Value> {
+        if host.is_local() {
+            LocalTarget::telemetry_init(host)
+        } else {
+            RemoteTarget::telemetry_init(host)
+        }
+    }
+}
+
+//
+// User
+//
+
+impl UserTarget for Target {
+    fn user_get(host: &mut Host, name: &str) -> Result<Option<UserInfo>> {
+        if host.is_local() {
+            LocalTarget::user_get(host, name)
+        } else {
+            RemoteTarget::user_get(host, name)
+        }
+    }
+
+    fn user_create(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::user_create(host, &escalation, name, opts)
+        } else if host.is_local() {
+            LocalTarget::user_create(host, name, opts)
+        } else {
+            RemoteTarget::user_create(host, name, opts)
+        }
+    }
+
+    fn user_modify(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::user_modify(host, &escalation, name, opts)
+        } else if host.is_local() {
+            LocalTarget::user_modify(host, name, opts)
+        } else {
+            RemoteTarget::user_modify(host, name, opts)
+        }
+    }
+
+    fn user_delete(host: &mut Host, name: &str) -> Result<()> {
+        if let Some(escalation) = host.escalation().cloned() {
+            escalate::user_delete(host, &escalation, name)
+        } else if host.is_local() {
+            LocalTarget::user_delete(host, name)
+        } else {
+            RemoteTarget::user_delete(host, name)
+        }
+    }
+}
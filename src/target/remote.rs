@@ -0,0 +1,655 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use command::{Command, CommandResult, CommandTarget};
+use directory::{DirectoryEntry, DirectoryTarget, EntryType};
+use error::{Error, Result};
+use file::{FileEdit, FileMetadata, FileTarget, FileOwner};
+use group::{GroupInfo, GroupTarget};
+use host::{Host, HostSendRecv, Message, TelemetryTarget};
+use link::LinkTarget;
+use package::PackageTarget;
+use package::providers::Providers;
+use serde_json::{self, Value};
+use service::ServiceTarget;
+use std::fs;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use super::RemoteTarget;
+use user::{UserInfo, UserOpts, UserTarget};
+use zfilexfer::FileOptions;
+
+//
+// Command
+//
+
+impl CommandTarget for RemoteTarget {
+    fn exec(host: &mut Host, cmd: &Command) -> Result<CommandResult> {
+        let msg = Message::new();
+        try!(msg.addstr("command::exec"));
+        try!(msg.addstr(&cmd.cmd));
+        if let Some(opts) = cmd.opts() {
+            try!(msg.addstr(&try!(serde_json::to_string(&opts))));
+        }
+        try!(host.send(msg));
+
+        let msg = try!(host.recv(3, Some(3)));
+
+        let exit_code = try!(msg.popstr().unwrap().or(Err(Error::HostResponse))).parse::<i32>().unwrap();
+        let stdout = try!(msg.popstr().unwrap().or(Err(Error::HostResponse)));
+        let stderr = try!(msg.popstr().unwrap().or(Err(Error::HostResponse)));
+
+        Ok(CommandResult {
+            exit_code: exit_code,
+            stdout: stdout,
+            stderr: stderr,
+        })
+    }
+}
+
+//
+// Directory
+//
+
+impl <P: AsRef<Path>> DirectoryTarget<P> for RemoteTarget {
+    fn directory_is_directory(host: &mut Host, path: P) -> Result<bool> {
+        let msg = Message::new();
+        try!(msg.addstr("directory::is_directory"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(1, Some(1)));
+        Ok(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))) == "1")
+    }
+
+    fn directory_exists(host: &mut Host, path: P) -> Result<bool> {
+        let msg = Message::new();
+        try!(msg.addstr("directory::exists"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(1, Some(1)));
+        Ok(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))) == "1")
+    }
+
+    fn directory_create(host: &mut Host, path: P, recursive: bool) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("directory::create"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(if recursive { "1" } else { "0" }));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn directory_delete(host: &mut Host, path: P, recursive: bool) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("directory::delete"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(if recursive { "1" } else { "0" }));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn directory_mv(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("directory::mv"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(new_path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn directory_get_owner(host: &mut Host, path: P) -> Result<FileOwner> {
+        let msg = Message::new();
+        try!(msg.addstr("directory::get_owner"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(4, Some(4)));
+
+        Ok(FileOwner {
+            user_name: try!(reply.popstr().unwrap().or(Err(Error::HostResponse))),
+            user_uid: try!(reply.popstr().unwrap().or(Err(Error::HostResponse))).parse::<u64>().unwrap(),
+            group_name: try!(reply.popstr().unwrap().or(Err(Error::HostResponse))),
+            group_gid: try!(reply.popstr().unwrap().or(Err(Error::HostResponse))).parse::<u64>().unwrap()
+        })
+    }
+
+    fn directory_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("directory::set_owner"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(user));
+        try!(msg.addstr(group));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn directory_get_mode(host: &mut Host, path: P) -> Result<u16> {
+        let msg = Message::new();
+        try!(msg.addstr("directory::get_mode"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(0, None));
+        Ok(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))).parse::<u16>().unwrap())
+    }
+
+    fn directory_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("directory::set_mode"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(&mode.to_string()));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn directory_checksums(host: &mut Host, path: P) -> Result<BTreeMap<String, Option<String>>> {
+        let msg = Message::new();
+        try!(msg.addstr("directory::checksums"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        // Reply frames are pairs of relative path and checksum, where
+        // directories have an empty checksum.
+        let reply = try!(host.recv(0, None));
+        let mut checksums = BTreeMap::new();
+        while reply.size() > 0 {
+            let rel_path = try!(reply.popstr().unwrap().or(Err(Error::HostResponse)));
+            let hash = try!(reply.popstr().ok_or(Error::HostResponse).and_then(|h| h.or(Err(Error::HostResponse))));
+            checksums.insert(rel_path, if hash.is_empty() { None } else { Some(hash) });
+        }
+        Ok(checksums)
+    }
+
+    fn directory_list(host: &mut Host, path: P, max_depth: Option<u32>) -> Result<Vec<DirectoryEntry>> {
+        let msg = Message::new();
+        try!(msg.addstr("directory::list"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(&max_depth.map(|d| d.to_string()).unwrap_or(String::new())));
+        try!(host.send(msg));
+
+        // Reply frames are groups of path, type, size and mtime
+        let reply = try!(host.recv(0, None));
+        if reply.size() % 4 != 0 {
+            return Err(Error::HostResponse);
+        }
+
+        let mut entries = Vec::new();
+        while reply.size() > 0 {
+            let path = try!(reply.popstr().unwrap().or(Err(Error::HostResponse)));
+            let entry_type = try!(reply.popstr().unwrap().or(Err(Error::HostResponse)));
+            let size = try!(reply.popstr().unwrap().or(Err(Error::HostResponse)));
+            let mtime = try!(reply.popstr().unwrap().or(Err(Error::HostResponse)));
+
+            entries.push(DirectoryEntry {
+                path: PathBuf::from(path),
+                entry_type: EntryType::from(entry_type.as_str()),
+                size: try!(size.parse::<u64>()),
+                mtime: try!(mtime.parse::<i64>()),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+//
+// File
+//
+
+impl <P: AsRef<Path>> FileTarget<P> for RemoteTarget {
+    fn file_is_file(host: &mut Host, path: P) -> Result<bool> {
+        let msg = Message::new();
+        try!(msg.addstr("file::is_file"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(0, None));
+        Ok(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))) == "1")
+    }
+
+    fn file_exists(host: &mut Host, path: P) -> Result<bool> {
+        let msg = Message::new();
+        try!(msg.addstr("file::exists"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(1, Some(1)));
+        Ok(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))) == "1")
+    }
+
+    fn file_delete(host: &mut Host, path: P) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("file::delete"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn file_mv(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("file::mv"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(new_path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn file_copy(host: &mut Host, path: P, new_path: P) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("file::copy"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(new_path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn file_get_owner(host: &mut Host, path: P) -> Result<FileOwner> {
+        let msg = Message::new();
+        try!(msg.addstr("file::get_owner"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(4, Some(4)));
+
+        Ok(FileOwner {
+            user_name: try!(reply.popstr().unwrap().or(Err(Error::HostResponse))),
+            user_uid: try!(reply.popstr().unwrap().or(Err(Error::HostResponse))).parse::<u64>().unwrap(),
+            group_name: try!(reply.popstr().unwrap().or(Err(Error::HostResponse))),
+            group_gid: try!(reply.popstr().unwrap().or(Err(Error::HostResponse))).parse::<u64>().unwrap()
+        })
+    }
+
+    fn file_set_owner(host: &mut Host, path: P, user: &str, group: &str) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("file::set_owner"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(user));
+        try!(msg.addstr(group));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn file_get_mode(host: &mut Host, path: P) -> Result<u16> {
+        let msg = Message::new();
+        try!(msg.addstr("file::get_mode"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(0, None));
+        Ok(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))).parse::<u16>().unwrap())
+    }
+
+    fn file_set_mode(host: &mut Host, path: P, mode: u16) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("file::set_mode"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(&mode.to_string()));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn file_hash(host: &mut Host, path: P) -> Result<Option<String>> {
+        let msg = Message::new();
+        try!(msg.addstr("file::hash"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(0, Some(1)));
+        if reply.size() == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(try!(reply.popstr().unwrap().or(Err(Error::HostResponse)))))
+        }
+    }
+
+    fn file_upload_file(host: &mut Host, path: P, file: fs::File, options: Option<&[FileOptions]>) -> Result<()> {
+        host.upload_file(file, path, options)
+    }
+
+    fn file_download(host: &mut Host, path: P, writer: &mut Write) -> Result<()> {
+        host.recv_fs_file(path, writer)
+    }
+
+    fn file_edit(host: &mut Host, path: P, edit: &FileEdit) -> Result<bool> {
+        let msg = Message::new();
+        try!(msg.addstr("file::edit"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        match *edit {
+            FileEdit::LinePresent(ref line) => {
+                try!(msg.addstr("line_present"));
+                try!(msg.addstr(line));
+            },
+            FileEdit::LineAbsent(ref line) => {
+                try!(msg.addstr("line_absent"));
+                try!(msg.addstr(line));
+            },
+            FileEdit::Regex(ref pattern, ref line) => {
+                try!(msg.addstr("regex"));
+                try!(msg.addstr(pattern));
+                try!(msg.addstr(line));
+            },
+            FileEdit::RegexAbsent(ref pattern) => {
+                try!(msg.addstr("regex_absent"));
+                try!(msg.addstr(pattern));
+            },
+            FileEdit::Block(ref name, ref block) => {
+                try!(msg.addstr("block"));
+                try!(msg.addstr(name));
+                try!(msg.addstr(block));
+            },
+            FileEdit::BlockAbsent(ref name) => {
+                try!(msg.addstr("block_absent"));
+                try!(msg.addstr(name));
+            },
+        }
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(1, Some(1)));
+        Ok(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))) == "1")
+    }
+
+    fn file_metadata(host: &mut Host, path: P) -> Result<FileMetadata> {
+        let msg = Message::new();
+        try!(msg.addstr("file::metadata"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(12, Some(12)));
+        let mut frames = Vec::new();
+        for _ in 0..12 {
+            frames.push(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))));
+        }
+
+        Ok(FileMetadata {
+            file_type: EntryType::from(frames[0].as_str()),
+            size: try!(frames[1].parse::<u64>()),
+            mode: try!(frames[2].parse::<u16>()),
+            uid: try!(frames[3].parse::<u64>()),
+            gid: try!(frames[4].parse::<u64>()),
+            atime: try!(frames[5].parse::<i64>()),
+            mtime: try!(frames[6].parse::<i64>()),
+            ctime: try!(frames[7].parse::<i64>()),
+            inode: try!(frames[8].parse::<u64>()),
+            nlink: try!(frames[9].parse::<u64>()),
+            selinux_context: if frames[10].is_empty() { None } else { Some(frames[10].clone()) },
+            checksum: if frames[11].is_empty() { None } else { Some(frames[11].clone()) },
+        })
+    }
+}
+
+//
+// Group
+//
+
+impl GroupTarget for RemoteTarget {
+    fn group_get(host: &mut Host, name: &str) -> Result<Option<GroupInfo>> {
+        let msg = Message::new();
+        try!(msg.addstr("group::get"));
+        try!(msg.addstr(name));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(0, Some(2)));
+        if reply.size() == 0 {
+            Ok(None)
+        } else {
+            let gid = try!(reply.popstr().unwrap().or(Err(Error::HostResponse)));
+            let members = try!(reply.popstr().unwrap().or(Err(Error::HostResponse)));
+
+            Ok(Some(GroupInfo {
+                name: name.to_string(),
+                gid: try!(gid.parse::<u64>()),
+                members: members.split(',').filter(|m| !m.is_empty()).map(|m| m.to_string()).collect(),
+            }))
+        }
+    }
+
+    fn group_create(host: &mut Host, name: &str, gid: Option<u64>) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("group::create"));
+        try!(msg.addstr(name));
+        try!(msg.addstr(&gid.map(|g| g.to_string()).unwrap_or(String::new())));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn group_modify(host: &mut Host, name: &str, gid: u64) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("group::modify"));
+        try!(msg.addstr(name));
+        try!(msg.addstr(&gid.to_string()));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn group_delete(host: &mut Host, name: &str) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("group::delete"));
+        try!(msg.addstr(name));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+}
+
+//
+// Link
+//
+
+impl <P: AsRef<Path>> LinkTarget<P> for RemoteTarget {
+    fn link_read(host: &mut Host, path: P) -> Result<Option<PathBuf>> {
+        let msg = Message::new();
+        try!(msg.addstr("link::read"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(0, Some(1)));
+        if reply.size() == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(PathBuf::from(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))))))
+        }
+    }
+
+    fn link_create(host: &mut Host, path: P, target: P) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("link::create"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(target.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn link_create_hard(host: &mut Host, path: P, target: P) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("link::create_hard"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(target.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn link_is_hard(host: &mut Host, path: P, target: P) -> Result<bool> {
+        let msg = Message::new();
+        try!(msg.addstr("link::is_hard"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(msg.addstr(target.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(1, Some(1)));
+        Ok(try!(reply.popstr().unwrap().or(Err(Error::HostResponse))) == "1")
+    }
+
+    fn link_delete(host: &mut Host, path: P) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("link::delete"));
+        try!(msg.addstr(path.as_ref().to_str().unwrap()));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+}
+
+//
+// Package
+//
+
+impl PackageTarget for RemoteTarget {
+    fn default_provider(host: &mut Host) -> Result<Providers> {
+        let msg = Message::new();
+        try!(msg.addstr("package::default_provider"));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(1, Some(1)));
+        Ok(Providers::from(try!(reply.popstr().unwrap().or(Err(Error::HostResponse)))))
+    }
+}
+
+//
+// Service
+//
+
+impl ServiceTarget for RemoteTarget {
+    fn service_action(host: &mut Host, name: &str, action: &str) -> Result<Option<CommandResult>> {
+        let msg = Message::new();
+        try!(msg.addstr("service::action"));
+        try!(msg.addstr(name));
+        try!(msg.addstr(action));
+        try!(host.send(msg));
+
+        let msg = try!(host.recv(0, Some(3)));
+
+        if msg.size() == 0 {
+            Ok(None)
+        }
+        else if msg.size() == 3 {
+            Ok(Some(CommandResult {
+                exit_code: try!(msg.popstr().unwrap().or(Err(Error::HostResponse))).parse::<i32>().unwrap(),
+                stdout: try!(msg.popstr().unwrap().or(Err(Error::HostResponse))),
+                stderr: try!(msg.popstr().unwrap().or(Err(Error::HostResponse))),
+            }))
+        } else {
+            Err(Error::HostResponse)
+        }
+    }
+}
+
+//
+// Telemetry
+//
+
+impl TelemetryTarget for RemoteTarget {
+    fn telemetry_init(host: &mut Host) -> Result<Value> {
+        let msg = Message::new();
+        try!(msg.addstr("telemetry"));
+        try!(host.send(msg));
+
+        let msg = try!(host.recv(1, Some(1)));
+        let telemetry = try!(msg.popstr().unwrap().or(Err(Error::HostResponse)));
+        Ok(try!(serde_json::from_str(&telemetry)))
+    }
+}
+
+//
+// User
+//
+
+impl UserTarget for RemoteTarget {
+    fn user_get(host: &mut Host, name: &str) -> Result<Option<UserInfo>> {
+        let msg = Message::new();
+        try!(msg.addstr("user::get"));
+        try!(msg.addstr(name));
+        try!(host.send(msg));
+
+        let reply = try!(host.recv(0, Some(5)));
+        if reply.size() == 0 {
+            Ok(None)
+        } else if reply.size() == 5 {
+            let uid = try!(reply.popstr().unwrap().or(Err(Error::HostResponse)));
+            let gid = try!(reply.popstr().unwrap().or(Err(Error::HostResponse)));
+
+            Ok(Some(UserInfo {
+                name: name.to_string(),
+                uid: try!(uid.parse::<u64>()),
+                gid: try!(gid.parse::<u64>()),
+                home: try!(reply.popstr().unwrap().or(Err(Error::HostResponse))),
+                shell: try!(reply.popstr().unwrap().or(Err(Error::HostResponse))),
+                groups: try!(reply.popstr().unwrap().or(Err(Error::HostResponse))).split(',').filter(|g| !g.is_empty()).map(|g| g.to_string()).collect(),
+            }))
+        } else {
+            Err(Error::HostResponse)
+        }
+    }
+
+    fn user_create(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()> {
+        let msg = try!(user_opts_msg("user::create", name, opts));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn user_modify(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()> {
+        let msg = try!(user_opts_msg("user::modify", name, opts));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+
+    fn user_delete(host: &mut Host, name: &str) -> Result<()> {
+        let msg = Message::new();
+        try!(msg.addstr("user::delete"));
+        try!(msg.addstr(name));
+        try!(host.send(msg));
+        try!(host.recv(0, None));
+        Ok(())
+    }
+}
+
+/// Build a user message with the attributes that are set, as
+/// key/value frame pairs.
+fn user_opts_msg(action: &str, name: &str, opts: &UserOpts) -> Result<Message> {
+    let msg = Message::new();
+    try!(msg.addstr(action));
+    try!(msg.addstr(name));
+    if let Some(uid) = opts.uid {
+        try!(msg.addstr("uid"));
+        try!(msg.addstr(&uid.to_string()));
+    }
+    if let Some(gid) = opts.gid {
+        try!(msg.addstr("gid"));
+        try!(msg.addstr(&gid.to_string()));
+    }
+    if let Some(ref shell) = opts.shell {
+        try!(msg.addstr("shell"));
+        try!(msg.addstr(shell));
+    }
+    if let Some(ref home) = opts.home {
+        try!(msg.addstr("home"));
+        try!(msg.addstr(home));
+    }
+    if let Some(ref groups) = opts.groups {
+        try!(msg.addstr("groups"));
+        try!(msg.addstr(&groups.join(",")));
+    }
+    Ok(msg)
+}
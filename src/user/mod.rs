@@ -0,0 +1,294 @@
+// Copyright 2015-2017 Intecture Developers. See the COPYRIGHT file at the
+// top-level directory of this distribution and at
+// https://intecture.io/COPYRIGHT.
+//
+// Licensed under the Mozilla Public License 2.0 <LICENSE or
+// https://www.tldrlegal.com/l/mpl-2.0>. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The primitive for managing user accounts on a managed host.
+//!
+//! # Examples
+//!
+//! Initialise a new Host using your managed host's IP address and
+//! port number:
+//!
+//! ```no_run
+//! # use inapi::Host;
+//! let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! ```
+//!
+//! Now create a user, then change its shell:
+//!
+//! ```no_run
+//! # use inapi::{Host, User, UserOpts};
+//! # let mut host = Host::connect("hosts/myhost.json").unwrap();
+//! let user = User::new("deploy");
+//! user.create(&mut host, &UserOpts {
+//!     home: Some("/srv/deploy".into()),
+//!     groups: Some(vec!["www".into()]),
+//!     ..Default::default()
+//! }).unwrap();
+//!
+//! user.modify(&mut host, &UserOpts {
+//!     shell: Some("/bin/zsh".into()),
+//!     ..Default::default()
+//! }).unwrap();
+//! ```
+
+use error::{Error, Result};
+use host::Host;
+use outcome::Outcome;
+use target::Target;
+
+/// Attributes of an existing user account.
+#[derive(Debug, PartialEq)]
+pub struct UserInfo {
+    /// User name
+    pub name: String,
+    /// User UID
+    pub uid: u64,
+    /// Primary group GID
+    pub gid: u64,
+    /// Home directory
+    pub home: String,
+    /// Login shell
+    pub shell: String,
+    /// Names of supplementary groups
+    pub groups: Vec<String>,
+}
+
+/// Attributes to set on a user account.
+///
+/// Attributes that are `None` are left to the OS defaults when
+/// creating a user, and left untouched when modifying one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UserOpts {
+    /// User UID
+    pub uid: Option<u64>,
+    /// Primary group GID
+    pub gid: Option<u64>,
+    /// Login shell
+    pub shell: Option<String>,
+    /// Home directory
+    pub home: Option<String>,
+    /// Names of supplementary groups, replacing any existing ones
+    pub groups: Option<Vec<String>>,
+}
+
+impl UserOpts {
+    /// Get the attributes that differ from an existing user.
+    fn diff(&self, info: &UserInfo) -> UserOpts {
+        let mut groups = info.groups.clone();
+        groups.sort();
+
+        UserOpts {
+            uid: self.uid.and_then(|v| if v == info.uid { None } else { Some(v) }),
+            gid: self.gid.and_then(|v| if v == info.gid { None } else { Some(v) }),
+            shell: self.shell.clone().and_then(|v| if v == info.shell { None } else { Some(v) }),
+            home: self.home.clone().and_then(|v| if v == info.home { None } else { Some(v) }),
+            groups: self.groups.clone().and_then(|mut v| {
+                v.sort();
+                if v == groups { None } else { Some(v) }
+            }),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == UserOpts::default()
+    }
+
+    /// Describe the attributes that are set, e.g. `uid=1001 shell=/bin/zsh`.
+    fn describe(&self) -> String {
+        let mut attrs = Vec::new();
+        if let Some(uid) = self.uid {
+            attrs.push(format!("uid={}", uid));
+        }
+        if let Some(gid) = self.gid {
+            attrs.push(format!("gid={}", gid));
+        }
+        if let Some(ref shell) = self.shell {
+            attrs.push(format!("shell={}", shell));
+        }
+        if let Some(ref home) = self.home {
+            attrs.push(format!("home={}", home));
+        }
+        if let Some(ref groups) = self.groups {
+            attrs.push(format!("groups={}", groups.join(",")));
+        }
+        attrs.join(" ")
+    }
+}
+
+/// Container for operating on a user account.
+pub struct User {
+    /// User name
+    name: String,
+}
+
+impl User {
+    /// Create a new User struct.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use inapi::User;
+    /// let user = User::new("deploy");
+    /// ```
+    pub fn new(name: &str) -> User {
+        User {
+            name: name.to_string(),
+        }
+    }
+
+    /// Check if the user exists.
+    pub fn exists(&self, host: &mut Host) -> Result<bool> {
+        Ok(try!(self.info(host)).is_some())
+    }
+
+    /// Get the user's attributes, or None if the user does not exist.
+    pub fn info(&self, host: &mut Host) -> Result<Option<UserInfo>> {
+        Target::user_get(host, &self.name)
+    }
+
+    /// Create the user if it does not exist.
+    pub fn create(&self, host: &mut Host, opts: &UserOpts) -> Result<Outcome> {
+        let outcome = if try!(self.exists(host)) {
+            Outcome::unchanged(&self.resource(), "create")
+        } else {
+            let mut outcome = Outcome::changed(&self.resource(), "create");
+            outcome.after = Some(opts.describe());
+            outcome
+        };
+
+        host.apply(outcome, |h| Target::user_create(h, &self.name, opts))
+    }
+
+    /// Change the user's attributes. Only attributes that differ from
+    /// the user's current attributes are applied.
+    pub fn modify(&self, host: &mut Host, opts: &UserOpts) -> Result<Outcome> {
+        let info = match try!(self.info(host)) {
+            Some(info) => info,
+            None => return Err(Error::Generic(format!("User {} does not exist", self.name))),
+        };
+
+        let diff = opts.diff(&info);
+        if diff.is_empty() {
+            return host.record(Ok(()), Outcome::unchanged(&self.resource(), "modify"));
+        }
+
+        let current = UserOpts {
+            uid: diff.uid.and(Some(info.uid)),
+            gid: diff.gid.and(Some(info.gid)),
+            shell: diff.shell.as_ref().and(Some(info.shell)),
+            home: diff.home.as_ref().and(Some(info.home)),
+            groups: diff.groups.as_ref().and(Some(info.groups)),
+        };
+        let outcome = Outcome::changed(&self.resource(), "modify").values(current.describe(), diff.describe());
+
+        host.apply(outcome, |h| Target::user_modify(h, &self.name, &diff))
+    }
+
+    /// Delete the user if it exists.
+    pub fn delete(&self, host: &mut Host) -> Result<Outcome> {
+        let outcome = if try!(self.exists(host)) {
+            Outcome::changed(&self.resource(), "delete")
+        } else {
+            Outcome::unchanged(&self.resource(), "delete")
+        };
+
+        host.apply(outcome, |h| Target::user_delete(h, &self.name))
+    }
+
+    fn resource(&self) -> String {
+        format!("user:{}", self.name)
+    }
+}
+
+pub trait UserTarget {
+    fn user_get(host: &mut Host, name: &str) -> Result<Option<UserInfo>>;
+    fn user_create(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()>;
+    fn user_modify(host: &mut Host, name: &str, opts: &UserOpts) -> Result<()>;
+    fn user_delete(host: &mut Host, name: &str) -> Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use czmq::{ZMsg, ZSys};
+    use host::Host;
+    use std::thread;
+    use super::*;
+
+    fn info() -> UserInfo {
+        UserInfo {
+            name: "deploy".into(),
+            uid: 1001,
+            gid: 1001,
+            home: "/home/deploy".into(),
+            shell: "/bin/sh".into(),
+            groups: vec!["www".into(), "adm".into()],
+        }
+    }
+
+    #[test]
+    fn test_opts_diff() {
+        let opts = UserOpts {
+            uid: Some(1001),
+            shell: Some("/bin/zsh".into()),
+            groups: Some(vec!["adm".into(), "www".into()]),
+            ..Default::default()
+        };
+
+        let diff = opts.diff(&info());
+        assert_eq!(diff, UserOpts {
+            shell: Some("/bin/zsh".into()),
+            ..Default::default()
+        });
+        assert_eq!(diff.describe(), "shell=/bin/zsh");
+        assert!(UserOpts::default().diff(&info()).is_empty());
+    }
+
+    #[test]
+    fn test_modify() {
+        ZSys::init();
+
+        let (client, mut server) = ZSys::create_pipe().unwrap();
+
+        let agent_mock = thread::spawn(move || {
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("user::get", msg.popstr().unwrap().unwrap());
+            assert_eq!("deploy", msg.popstr().unwrap().unwrap());
+
+            let reply = ZMsg::new();
+            reply.addstr("Ok").unwrap();
+            reply.addstr("1001").unwrap();
+            reply.addstr("1001").unwrap();
+            reply.addstr("/home/deploy").unwrap();
+            reply.addstr("/bin/sh").unwrap();
+            reply.addstr("www,adm").unwrap();
+            reply.send(&mut server).unwrap();
+
+            let msg = ZMsg::recv(&mut server).unwrap();
+            assert_eq!("user::modify", msg.popstr().unwrap().unwrap());
+            assert_eq!("deploy", msg.popstr().unwrap().unwrap());
+            assert_eq!("shell", msg.popstr().unwrap().unwrap());
+            assert_eq!("/bin/zsh", msg.popstr().unwrap().unwrap());
+            assert_eq!(msg.size(), 0);
+
+            server.send_str("Ok").unwrap();
+        });
+
+        let mut host = Host::test_new(None, Some(client), None, None);
+
+        let user = User::new("deploy");
+        let outcome = user.modify(&mut host, &UserOpts {
+            uid: Some(1001),
+            shell: Some("/bin/zsh".into()),
+            ..Default::default()
+        }).unwrap();
+        assert!(outcome.is_changed());
+        assert_eq!(outcome.before.unwrap(), "shell=/bin/sh");
+
+        agent_mock.join().unwrap();
+    }
+}